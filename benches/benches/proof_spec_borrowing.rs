@@ -0,0 +1,250 @@
+use ark_bls12_381::{Bls12_381, G1Affine};
+use ark_ec::pairing::Pairing;
+use ark_std::{
+    collections::{BTreeMap, BTreeSet},
+    rand::{rngs::StdRng, SeedableRng},
+    UniformRand,
+};
+use bbs_plus::{
+    setup::{KeypairG2, SignatureParamsG1},
+    signature::SignatureG1,
+};
+use blake2::Blake2b512;
+use criterion::{criterion_group, criterion_main, Criterion};
+use proof_system::{
+    prelude::{
+        EqualWitnesses, MetaStatement, MetaStatements, PreparedProofSpec, Proof, ProofSpec,
+        Statements, Witness, WitnessRef, Witnesses,
+    },
+    statement::{
+        bbs_plus::PoKBBSSignatureG1 as PoKSignatureBBSG1Stmt,
+        ped_comm::PedersenCommitment as PedersenCommitmentStmt,
+    },
+    witness::PoKBBSSignatureG1 as PoKSignatureBBSG1Wit,
+};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+type Fr = <Bls12_381 as Pairing>::ScalarField;
+type ProofG1 = Proof<Bls12_381, G1Affine>;
+
+/// Counts allocations so that creating and verifying a proof by cloning the `ProofSpec` can be compared
+/// with borrowing it
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Run `f` and return the number of allocations and allocated bytes
+fn count_allocations<F: FnOnce()>(f: F) -> (usize, usize) {
+    let (a, b) = (
+        ALLOCATIONS.load(Ordering::Relaxed),
+        ALLOCATED_BYTES.load(Ordering::Relaxed),
+    );
+    f();
+    (
+        ALLOCATIONS.load(Ordering::Relaxed) - a,
+        ALLOCATED_BYTES.load(Ordering::Relaxed) - b,
+    )
+}
+
+/// Proof of knowledge of a BBS+ signature over 10 messages, 2 of which are revealed, and a Pedersen
+/// commitment to 2 of the hidden messages
+fn setup() -> (ProofSpec<Bls12_381, G1Affine>, Witnesses<Bls12_381>) {
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let message_count = 10;
+    let messages = (0..message_count)
+        .map(|_| Fr::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let params = SignatureParamsG1::<Bls12_381>::generate_using_rng(&mut rng, message_count);
+    let keypair = KeypairG2::<Bls12_381>::generate_using_rng(&mut rng, &params);
+    let sig =
+        SignatureG1::<Bls12_381>::new(&mut rng, &messages, &keypair.secret_key, &params).unwrap();
+
+    let revealed_indices = BTreeSet::from([0, 1]);
+    let mut revealed_msgs = BTreeMap::new();
+    let mut unrevealed_msgs = BTreeMap::new();
+    for (i, m) in messages.iter().enumerate() {
+        if revealed_indices.contains(&i) {
+            revealed_msgs.insert(i, *m);
+        } else {
+            unrevealed_msgs.insert(i, *m);
+        }
+    }
+
+    let bases = (0..3).map(|_| G1Affine::rand(&mut rng)).collect::<Vec<_>>();
+    let blinding = Fr::rand(&mut rng);
+    let comm_wit = vec![messages[2], messages[3], blinding];
+    let comm = (bases[0] * comm_wit[0] + bases[1] * comm_wit[1] + bases[2] * comm_wit[2]).into();
+
+    let mut statements = Statements::new();
+    statements.add(PoKSignatureBBSG1Stmt::new_statement_from_params(
+        params,
+        keypair.public_key.clone(),
+        revealed_msgs,
+    ));
+    statements.add(PedersenCommitmentStmt::new_statement_from_params(
+        bases, comm,
+    ));
+
+    let mut meta_statements = MetaStatements::new();
+    for i in 0..2 {
+        meta_statements.add(MetaStatement::WitnessEquality(EqualWitnesses(
+            vec![(0, i + 2), (1, i)]
+                .into_iter()
+                .collect::<BTreeSet<WitnessRef>>(),
+        )));
+    }
+    let proof_spec = ProofSpec::new(statements, meta_statements, vec![], None);
+
+    let mut witnesses = Witnesses::new();
+    witnesses.add(PoKSignatureBBSG1Wit::new_as_witness(sig, unrevealed_msgs));
+    witnesses.add(Witness::PedersenCommitment(comm_wit));
+    (proof_spec, witnesses)
+}
+
+fn prove_and_verify_cloning(
+    rng: &mut StdRng,
+    proof_spec: &ProofSpec<Bls12_381, G1Affine>,
+    witnesses: &Witnesses<Bls12_381>,
+) {
+    let proof = ProofG1::new::<StdRng, Blake2b512>(
+        rng,
+        proof_spec.clone(),
+        witnesses.clone(),
+        None,
+        Default::default(),
+    )
+    .unwrap()
+    .0;
+    proof
+        .verify::<StdRng, Blake2b512>(rng, proof_spec.clone(), None, Default::default())
+        .unwrap();
+}
+
+fn prove_and_verify_borrowing(
+    rng: &mut StdRng,
+    proof_spec: &ProofSpec<Bls12_381, G1Affine>,
+    witnesses: Witnesses<Bls12_381>,
+) {
+    let proof = ProofG1::new_with_spec_ref::<StdRng, Blake2b512>(
+        rng,
+        proof_spec,
+        witnesses,
+        None,
+        Default::default(),
+    )
+    .unwrap()
+    .0;
+    proof
+        .verify_with_spec_ref::<StdRng, Blake2b512>(rng, proof_spec, None, Default::default())
+        .unwrap();
+}
+
+fn proof_spec_borrowing(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(1u64);
+    let (proof_spec, witnesses) = setup();
+
+    let (cloning_allocs, cloning_bytes) =
+        count_allocations(|| prove_and_verify_cloning(&mut rng, &proof_spec, &witnesses));
+    let wits = witnesses.clone();
+    let (borrowing_allocs, borrowing_bytes) =
+        count_allocations(|| prove_and_verify_borrowing(&mut rng, &proof_spec, wits));
+    println!(
+        "Proof with 10 messages: cloning ProofSpec and witnesses does {} allocations of {} bytes, borrowing the ProofSpec does {} allocations of {} bytes",
+        cloning_allocs, cloning_bytes, borrowing_allocs, borrowing_bytes
+    );
+
+    let mut group = c.benchmark_group("Create and verify proof with 10 messages");
+    group.bench_function("cloning ProofSpec", |b| {
+        b.iter(|| prove_and_verify_cloning(&mut rng, &proof_spec, &witnesses))
+    });
+    group.bench_function("borrowing ProofSpec", |b| {
+        b.iter_batched(
+            || witnesses.clone(),
+            |w| prove_and_verify_borrowing(&mut rng, &proof_spec, w),
+            criterion::BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+/// Verify 100 proofs for the same `ProofSpec`, deriving the verification parameters for each proof vs
+/// once with a `PreparedProofSpec`
+fn prepared_proof_spec(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(2u64);
+    let (proof_spec, witnesses) = setup();
+    let proofs = (0..100)
+        .map(|_| {
+            ProofG1::new_with_spec_ref::<StdRng, Blake2b512>(
+                &mut rng,
+                &proof_spec,
+                witnesses.clone(),
+                None,
+                Default::default(),
+            )
+            .unwrap()
+            .0
+        })
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("Verify 100 proofs with 10 messages");
+    group.bench_function("with ProofSpec", |b| {
+        b.iter_batched(
+            || proofs.clone(),
+            |proofs| {
+                for proof in proofs {
+                    proof
+                        .verify_with_spec_ref::<StdRng, Blake2b512>(
+                            &mut rng,
+                            &proof_spec,
+                            None,
+                            Default::default(),
+                        )
+                        .unwrap();
+                }
+            },
+            criterion::BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("with PreparedProofSpec", |b| {
+        b.iter_batched(
+            || proofs.clone(),
+            |proofs| {
+                let prepared = PreparedProofSpec::new(proof_spec.clone()).unwrap();
+                for proof in proofs {
+                    proof
+                        .verify_with_prepared_spec::<StdRng, Blake2b512>(
+                            &mut rng,
+                            &prepared,
+                            None,
+                            Default::default(),
+                        )
+                        .unwrap();
+                }
+            },
+            criterion::BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, proof_spec_borrowing, prepared_proof_spec);
+criterion_main!(benches);
@@ -0,0 +1,345 @@
+use ark_bls12_381::Bls12_381;
+use ark_ec::pairing::Pairing;
+use ark_serialize::CanonicalSerialize;
+use ark_std::{
+    rand::{rngs::StdRng, SeedableRng},
+    UniformRand,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use saver::{
+    circuit::BitsizeCheckCircuit,
+    encryption::{Ciphertext, DecryptScratch, Encryption, EncryptionContext},
+    keygen::{keygen, EncryptionKey, PreparedDecryptionKey},
+    saver_groth16::{
+        create_proof, get_gs_for_encryption, prepare_verifying_key, verify_proof, CachedProvingKey,
+    },
+    setup::{setup_for_groth16, EncryptionGens},
+    utils::decompose,
+};
+
+type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+fn deserialization(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let chunk_bit_size = 8;
+    let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+    let message = Fr::rand(&mut rng);
+    let (ct, _) = Encryption::encrypt_given_snark_vk(
+        &mut rng,
+        &message,
+        &ek,
+        &snark_pk.pk.vk,
+        chunk_bit_size,
+    )
+    .unwrap();
+
+    let mut ek_bytes = vec![];
+    ek.serialize_compressed(&mut ek_bytes).unwrap();
+    let mut ct_bytes = vec![];
+    ct.serialize_compressed(&mut ct_bytes).unwrap();
+
+    let mut ek_group = c.benchmark_group(format!(
+        "Deserialize encryption key for chunk_bit_size {}",
+        chunk_bit_size
+    ));
+    ek_group.bench_function("checked", |b| {
+        b.iter(|| EncryptionKey::<Bls12_381>::from_bytes(black_box(&ek_bytes)).unwrap())
+    });
+    ek_group.bench_function("unchecked", |b| {
+        b.iter(|| EncryptionKey::<Bls12_381>::from_bytes_unchecked(black_box(&ek_bytes)).unwrap())
+    });
+    ek_group.finish();
+
+    let mut ct_group = c.benchmark_group(format!(
+        "Deserialize ciphertext for chunk_bit_size {}",
+        chunk_bit_size
+    ));
+    ct_group.bench_function("checked", |b| {
+        b.iter(|| Ciphertext::<Bls12_381>::from_bytes(black_box(&ct_bytes)).unwrap())
+    });
+    ct_group.bench_function("unchecked", |b| {
+        b.iter(|| Ciphertext::<Bls12_381>::from_bytes_unchecked(black_box(&ct_bytes)).unwrap())
+    });
+    ct_group.finish();
+}
+
+/// Decrypting and verifying decryption of several ciphertexts with the decryption key prepared once vs
+/// preparing it for each ciphertext
+fn decryption_with_prepared_key(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let chunk_bit_size = 8;
+    let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let (snark_pk, sk, ek, dk) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+    let vk = &snark_pk.pk.vk;
+    let ciphertexts = (0..5)
+        .map(|_| {
+            let m = Fr::rand(&mut rng);
+            Encryption::encrypt_given_snark_vk(&mut rng, &m, &ek, vk, chunk_bit_size)
+                .unwrap()
+                .0
+        })
+        .collect::<Vec<_>>();
+    let prepared_dk = PreparedDecryptionKey::from(dk.clone());
+
+    let mut group = c.benchmark_group(format!(
+        "Decrypt and verify decryption of {} ciphertexts for chunk_bit_size {}",
+        ciphertexts.len(),
+        chunk_bit_size
+    ));
+    group.sample_size(10);
+    group.bench_function("preparing decryption key for each ciphertext", |b| {
+        b.iter(|| {
+            for ct in &ciphertexts {
+                let (m, nu) = ct
+                    .decrypt_given_groth16_vk(&sk, dk.clone(), vk, chunk_bit_size)
+                    .unwrap();
+                ct.verify_decryption_given_groth16_vk(
+                    &m,
+                    &nu,
+                    chunk_bit_size,
+                    dk.clone(),
+                    vk,
+                    gens.clone(),
+                )
+                .unwrap();
+            }
+        })
+    });
+    group.bench_function("prepared decryption key", |b| {
+        b.iter(|| {
+            for ct in &ciphertexts {
+                let (m, nu) = ct
+                    .decrypt_given_groth16_vk(&sk, prepared_dk.clone(), vk, chunk_bit_size)
+                    .unwrap();
+                ct.verify_decryption_given_groth16_vk(
+                    &m,
+                    &nu,
+                    chunk_bit_size,
+                    prepared_dk.clone(),
+                    vk,
+                    gens.clone(),
+                )
+                .unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+/// Encrypting many messages with the same key with and without an `EncryptionContext`. Creating the
+/// context is part of the measured time.
+fn encryption_with_context(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let chunk_bit_size = 8;
+    let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+    let g_i = get_gs_for_encryption(&snark_pk.pk.vk);
+    let messages = (0..1000).map(|_| Fr::rand(&mut rng)).collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group(format!(
+        "Encrypt {} messages for chunk_bit_size {}",
+        messages.len(),
+        chunk_bit_size
+    ));
+    group.sample_size(10);
+    group.bench_function("without context", |b| {
+        b.iter(|| {
+            for m in &messages {
+                black_box(Encryption::encrypt(&mut rng, m, &ek, g_i, chunk_bit_size).unwrap());
+            }
+        })
+    });
+    group.bench_function("with context", |b| {
+        b.iter(|| {
+            let ctx = EncryptionContext::new(&ek, g_i, chunk_bit_size, messages.len()).unwrap();
+            for m in &messages {
+                black_box(Encryption::encrypt_given_context(&mut rng, m, &ctx).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+/// Encrypting and then creating the proof vs `Encryption::encrypt_with_proof` which, with the `parallel`
+/// feature, does both at the same time
+fn encryption_with_proof(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let chunk_bit_size = 8;
+    let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+    let g_i = get_gs_for_encryption(&snark_pk.pk.vk);
+    let message = Fr::rand(&mut rng);
+
+    let mut group = c.benchmark_group(format!(
+        "Encrypt with proof for chunk_bit_size {}",
+        chunk_bit_size
+    ));
+    group.sample_size(10);
+    group.bench_function("sequential", |b| {
+        b.iter(|| {
+            let (ct, r) =
+                Encryption::encrypt(&mut rng, &message, &ek, g_i, chunk_bit_size).unwrap();
+            let decomposed = decompose(&message, chunk_bit_size)
+                .unwrap()
+                .into_iter()
+                .map(Fr::from)
+                .collect();
+            let circuit = BitsizeCheckCircuit::new(chunk_bit_size, None, Some(decomposed), true);
+            let proof = create_proof(circuit, &r, &snark_pk, &ek, &mut rng).unwrap();
+            black_box((ct, proof))
+        })
+    });
+    group.bench_function("encrypt_with_proof", |b| {
+        b.iter(|| {
+            black_box(
+                Encryption::encrypt_with_proof(&mut rng, &message, &ek, &snark_pk, chunk_bit_size)
+                    .unwrap(),
+            )
+        })
+    });
+    group.finish();
+}
+
+/// Verifying many proofs while preparing the verification key for each proof vs preparing it once with
+/// `CachedProvingKey::prepared_vk`
+fn verification_with_prepared_vk(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let chunk_bit_size = 8;
+    let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+    let items = (0..100)
+        .map(|_| {
+            let m = Fr::rand(&mut rng);
+            let (ct, _, proof) =
+                Encryption::encrypt_with_proof(&mut rng, &m, &ek, &snark_pk, chunk_bit_size)
+                    .unwrap();
+            (ct, proof)
+        })
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group(format!(
+        "Verify {} proofs for chunk_bit_size {}",
+        items.len(),
+        chunk_bit_size
+    ));
+    group.sample_size(10);
+    let snark_pk = CachedProvingKey::new(snark_pk);
+    group.bench_function("preparing verification key for each proof", |b| {
+        b.iter(|| {
+            for (ct, proof) in &items {
+                let pvk = prepare_verifying_key(&snark_pk.pk.vk);
+                verify_proof(&pvk, proof, ct).unwrap();
+            }
+        })
+    });
+    group.bench_function("prepared verification key", |b| {
+        b.iter(|| {
+            let pvk = snark_pk.prepared_vk();
+            for (ct, proof) in &items {
+                verify_proof(pvk, proof, ct).unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+/// Encryption and key generation for chunk bit sizes on either side of `utils::PARALLEL_MIN_CHUNKS`. With the
+/// `parallel` feature, the 16 chunks of 16-bit chunk size are processed serially and the 64 chunks of 4-bit
+/// chunk size in parallel. Compare with a run without the `parallel` feature to see where parallelism pays off.
+fn per_chunk_parallelism(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let message = Fr::rand(&mut rng);
+    for chunk_bit_size in [4, 16] {
+        let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        let g_i = get_gs_for_encryption(&snark_pk.pk.vk);
+
+        let mut group = c.benchmark_group(format!(
+            "{} chunks for chunk_bit_size {}",
+            ek.X.len(),
+            chunk_bit_size
+        ));
+        group.bench_function("encrypt", |b| {
+            b.iter(|| {
+                black_box(
+                    Encryption::encrypt(&mut rng, &message, &ek, g_i, chunk_bit_size).unwrap(),
+                )
+            })
+        });
+        group.bench_function("keygen", |b| {
+            b.iter(|| {
+                black_box(
+                    keygen(
+                        &mut rng,
+                        chunk_bit_size,
+                        &gens,
+                        g_i,
+                        &snark_pk.pk.delta_g1,
+                        &snark_pk.gamma_g1,
+                    )
+                    .unwrap(),
+                )
+            })
+        });
+        group.finish();
+    }
+}
+
+/// Decrypting many ciphertexts with and without reusing a `DecryptScratch`. The decryption key is prepared once
+/// in both cases.
+fn decryption_with_scratch(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let chunk_bit_size = 8;
+    let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let (snark_pk, sk, ek, dk) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+    let g_i = get_gs_for_encryption(&snark_pk.pk.vk);
+    let ciphertexts = (0..100)
+        .map(|_| {
+            let m = Fr::rand(&mut rng);
+            Encryption::encrypt(&mut rng, &m, &ek, g_i, chunk_bit_size)
+                .unwrap()
+                .0
+        })
+        .collect::<Vec<_>>();
+    let dk = PreparedDecryptionKey::from(dk);
+
+    let mut group = c.benchmark_group(format!(
+        "Decrypt {} ciphertexts for chunk_bit_size {}",
+        ciphertexts.len(),
+        chunk_bit_size
+    ));
+    group.sample_size(10);
+    group.bench_function("without scratch", |b| {
+        b.iter(|| {
+            for ct in &ciphertexts {
+                black_box(ct.decrypt(&sk, dk.clone(), g_i, chunk_bit_size).unwrap().0);
+            }
+        })
+    });
+    group.bench_function("with scratch", |b| {
+        b.iter(|| {
+            let mut scratch = DecryptScratch::new(ek.X.len());
+            for ct in &ciphertexts {
+                black_box(
+                    ct.decrypt_with_scratch(&sk, &dk, g_i, chunk_bit_size, &mut scratch)
+                        .unwrap()
+                        .0,
+                );
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    deserialization,
+    decryption_with_prepared_key,
+    encryption_with_context,
+    encryption_with_proof,
+    verification_with_prepared_vk,
+    per_chunk_parallelism,
+    decryption_with_scratch
+);
+criterion_main!(benches);
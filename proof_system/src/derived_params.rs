@@ -134,7 +134,9 @@ impl<'a, E: Pairing>
     fn new_derived(
         (comm_gens, chunk_bit_size): &(&ChunkedCommitmentGens<E::G1Affine>, u8),
     ) -> (Vec<E::G1Affine>, Vec<E::G1Affine>) {
+        // `ProofSpec::derive_commitment_keys` checks the chunk bit size before deriving the keys
         SaverProtocol::<E>::chunked_comm_keys(comm_gens, *chunk_bit_size)
+            .expect("chunk bit size should have been checked")
     }
 }
 
@@ -59,6 +59,12 @@ pub enum ProofSystemError {
     SaverInequalChunkedCommitmentResponse,
     SaverSnarkProvingKeyNotProvided,
     SaverSnarkVerifyingKeyNotProvided,
+    /// The message can't be proven to differ from the forbidden value as it is equal to it
+    SaverMessageEqualsForbiddenValue,
+    /// The range `[a, b]` is not the set of messages whose top chunks are fixed and the other chunks are free
+    SaverRangeNotAlignedToChunks(u64, u64),
+    /// The message can't be proven to be in the range as it is outside of it
+    SaverMessageOutOfRange,
     LegoGroth16Error(LegoGroth16Error),
     LegoGroth16InequalResponse,
     LegoGroth16ProvingKeyNotProvided,
@@ -131,3 +137,17 @@ impl From<coconut_crypto::SignaturePoKError> for ProofSystemError {
         Self::PSSignaturePoKError(e)
     }
 }
+
+/// Returned by `verify_encrypted_credential` and indicates the check that failed
+#[derive(Debug)]
+pub enum EncryptedCredentialVerificationError {
+    /// The proof spec has no Pedersen commitment statement for the ciphertext's commitment under the
+    /// encryption key's commitment key
+    CiphertextCommitmentNotInProofSpec,
+    /// Ciphertext does not commit to the encrypted message
+    CiphertextCommitment(SaverError),
+    /// The SNARK proof that each encrypted chunk is of the correct size is invalid
+    SnarkProof(SaverError),
+    /// The composite proof is invalid
+    Proof(ProofSystemError),
+}
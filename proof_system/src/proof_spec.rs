@@ -1,7 +1,7 @@
 use crate::{
     derived_params::{DerivedParamsTracker, StatementDerivedParams},
     error::ProofSystemError,
-    meta_statement::{MetaStatement, MetaStatements},
+    meta_statement::{MetaStatement, MetaStatements, WitnessRef},
     setup_params::SetupParams,
     statement::{Statement, Statements},
 };
@@ -257,6 +257,7 @@ where
                         ),
                         _ => panic!("This should never happen"),
                     };
+                    saver::utils::check_chunk_bit_size(chunk_bit_size)?;
                     tuple_map.insert(s_idx, (comm_gens, chunk_bit_size));
                 }
                 _ => (),
@@ -432,6 +433,98 @@ where
     }
 }
 
+/// Commitment keys returned by `ProofSpec::derive_commitment_keys`
+pub(crate) type DerivedCommitmentKeys<E> = (
+    StatementDerivedParams<Vec<<E as Pairing>::G1Affine>>,
+    StatementDerivedParams<Vec<<E as Pairing>::G1Affine>>,
+    StatementDerivedParams<(Vec<<E as Pairing>::G1Affine>, Vec<<E as Pairing>::G1Affine>)>,
+    StatementDerivedParams<Vec<<E as Pairing>::G1Affine>>,
+);
+
+/// Prepared parameters returned by `ProofSpec::derive_prepared_parameters`
+pub(crate) type DerivedPreparedParameters<E> = (
+    StatementDerivedParams<LegoPreparedVerifyingKey<E>>,
+    StatementDerivedParams<PreparedEncryptionGens<E>>,
+    StatementDerivedParams<PreparedEncryptionKey<E>>,
+    StatementDerivedParams<SaverPreparedVerifyingKey<E>>,
+    StatementDerivedParams<PreparedBBSPlusSigParams<E>>,
+    StatementDerivedParams<PreparedBBSPlusPk<E>>,
+    StatementDerivedParams<PreparedAccumParams<E>>,
+    StatementDerivedParams<PreparedAccumPk<E>>,
+    StatementDerivedParams<PreparedPSSigParams<E>>,
+    StatementDerivedParams<PreparedPSPk<E>>,
+    StatementDerivedParams<PreparedBBSSigParams23<E>>,
+);
+
+/// Everything the verifier derives from a `ProofSpec` before looking at the proof
+pub(crate) struct VerificationParams<E: Pairing> {
+    pub(crate) commitment_keys: DerivedCommitmentKeys<E>,
+    pub(crate) prepared_parameters: DerivedPreparedParameters<E>,
+    /// All the distinct witness equalities
+    pub(crate) witness_equalities: Vec<BTreeSet<WitnessRef>>,
+}
+
+impl<E, G> ProofSpec<E, G>
+where
+    E: Pairing,
+    G: AffineRepr,
+{
+    /// Validate the proof spec and derive the parameters needed to verify a proof for it
+    pub(crate) fn derive_verification_params(
+        &self,
+    ) -> Result<VerificationParams<E>, ProofSystemError> {
+        self.validate()?;
+        let commitment_keys = self.derive_commitment_keys()?;
+        let prepared_parameters = self.derive_prepared_parameters()?;
+        let witness_equalities = if self.meta_statements.is_empty() {
+            Vec::new()
+        } else {
+            self.meta_statements
+                .disjoint_witness_equalities()
+                .into_iter()
+                .map(|eq_wits| eq_wits.0)
+                .collect()
+        };
+        Ok(VerificationParams {
+            commitment_keys,
+            prepared_parameters,
+            witness_equalities,
+        })
+    }
+}
+
+/// A `ProofSpec` that has been validated along with the commitment keys, prepared parameters and witness
+/// equalities derived from it. A verifier verifying many proofs for the same `ProofSpec` can create this
+/// once and verify each proof with `Proof::verify_with_prepared_spec` instead of deriving these for each
+/// proof.
+pub struct PreparedProofSpec<E: Pairing, G: AffineRepr> {
+    spec: ProofSpec<E, G>,
+    params: VerificationParams<E>,
+}
+
+impl<E, G> PreparedProofSpec<E, G>
+where
+    E: Pairing,
+    G: AffineRepr,
+{
+    pub fn new(spec: ProofSpec<E, G>) -> Result<Self, ProofSystemError> {
+        let params = spec.derive_verification_params()?;
+        Ok(Self { spec, params })
+    }
+
+    pub fn spec(&self) -> &ProofSpec<E, G> {
+        &self.spec
+    }
+
+    pub fn into_spec(self) -> ProofSpec<E, G> {
+        self.spec
+    }
+
+    pub(crate) fn params(&self) -> &VerificationParams<E> {
+        &self.params
+    }
+}
+
 impl<E, G> Default for ProofSpec<E, G>
 where
     E: Pairing,
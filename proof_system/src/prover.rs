@@ -97,6 +97,20 @@ where
         proof_spec: ProofSpec<E, G>,
         witnesses: Witnesses<E>,
         nonce: Option<Vec<u8>>,
+        config: ProverConfig<E>,
+    ) -> Result<(Self, BTreeMap<usize, E::ScalarField>), ProofSystemError> {
+        Self::new_with_spec_ref::<R, D>(rng, &proof_spec, witnesses, nonce, config)
+    }
+
+    /// Same as `Self::new` but borrows the `ProofSpec` so that the same one can be used to verify the proof
+    /// without cloning it. The witnesses are still consumed as they are moved into the sub-protocols so
+    /// they need to be cloned only if used again. When aggregating SNARK proofs, the aggregation SRS and
+    /// the statement indices to aggregate are cloned from the `ProofSpec`.
+    pub fn new_with_spec_ref<R: RngCore, D: Digest>(
+        rng: &mut R,
+        proof_spec: &ProofSpec<E, G>,
+        witnesses: Witnesses<E>,
+        nonce: Option<Vec<u8>>,
         mut config: ProverConfig<E>,
     ) -> Result<(Self, BTreeMap<usize, E::ScalarField>), ProofSystemError> {
         proof_spec.validate()?;
@@ -489,8 +503,8 @@ where
         if aggregate_snarks {
             // The validity of `ProofSpec` ensures that statements are not being repeated
 
-            let srs = match proof_spec.snark_aggregation_srs {
-                Some(SnarkpackSRS::ProverSrs(srs)) => srs,
+            let srs = match &proof_spec.snark_aggregation_srs {
+                Some(SnarkpackSRS::ProverSrs(srs)) => srs.clone(),
                 _ => return Err(ProofSystemError::SnarckpackSrsNotProvided),
             };
             let prepared_srs = PreparedProverSRS::from(srs);
@@ -499,7 +513,7 @@ where
             transcript.append(b"challenge", &challenge);
 
             if proof_spec.aggregate_groth16.is_some() {
-                let to_aggr = proof_spec.aggregate_groth16.unwrap();
+                let to_aggr = proof_spec.aggregate_groth16.clone().unwrap();
                 let mut proofs = vec![];
                 for a in to_aggr {
                     for i in &a {
@@ -523,7 +537,7 @@ where
             }
 
             if proof_spec.aggregate_legogroth16.is_some() {
-                let to_aggr = proof_spec.aggregate_legogroth16.unwrap();
+                let to_aggr = proof_spec.aggregate_legogroth16.clone().unwrap();
                 let mut proofs = vec![];
                 for a in to_aggr {
                     for i in &a {
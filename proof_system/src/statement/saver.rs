@@ -1,16 +1,26 @@
-use ark_ec::{pairing::Pairing, AffineRepr};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::{Field, PrimeField, Zero};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::vec::Vec;
+use ark_std::{rand::RngCore, vec, vec::Vec, UniformRand};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
 use crate::{
-    error::ProofSystemError, setup_params::SetupParams, statement::Statement,
+    error::ProofSystemError,
+    meta_statement::{EqualWitnesses, MetaStatement, MetaStatements},
+    proof::Proof,
+    proof_spec::ProofSpec,
+    setup_params::SetupParams,
+    statement::{ped_comm::PedersenCommitment, Statement, Statements},
     sub_protocols::saver::SaverProtocol,
+    witness::{Witness, Witnesses},
 };
+use digest::Digest;
 use dock_crypto_utils::serde_utils::ArkObjectBytes;
-use saver::prelude::{
-    ChunkedCommitmentGens, EncryptionGens, EncryptionKey, ProvingKey, VerifyingKey,
+use saver::{
+    encryption::{Ciphertext, Encryption},
+    error::SaverError,
+    prelude::{ChunkedCommitmentGens, EncryptionGens, EncryptionKey, ProvingKey, VerifyingKey},
 };
 
 /// Proving knowledge of correctly encrypted message
@@ -254,3 +264,336 @@ impl<E: Pairing> SaverVerifier<E> {
         )
     }
 }
+
+/// Add `bound_check`, a `BoundCheckLegoGroth16Prover` or `BoundCheckLegoGroth16Verifier` statement, to
+/// `statements` along with the witness equality that makes it apply to the message encrypted by the SAVER
+/// statement at `saver_stmt_idx`. This proves `min <= message <= max` for the encrypted message without
+/// revealing it. The prover must add `Witness::BoundCheckLegoGroth16` with the message for the returned
+/// statement index. A prover's SAVER statement needs a prover's bound check statement and likewise for the
+/// verifier.
+pub fn add_bound_check_for_encrypted_message<E: Pairing, G: AffineRepr>(
+    statements: &mut Statements<E, G>,
+    meta_statements: &mut MetaStatements,
+    saver_stmt_idx: usize,
+    bound_check: Statement<E, G>,
+) -> Result<usize, ProofSystemError> {
+    match (statements.0.get(saver_stmt_idx), &bound_check) {
+        (Some(Statement::SaverProver(_)), Statement::BoundCheckLegoGroth16Prover(_))
+        | (Some(Statement::SaverVerifier(_)), Statement::BoundCheckLegoGroth16Verifier(_)) => (),
+        _ => return Err(ProofSystemError::InvalidStatement),
+    }
+    let bound_check_stmt_idx = statements.add(bound_check);
+    // Both statements have a single witness, the message
+    meta_statements.add_witness_equality(EqualWitnesses(
+        [(saver_stmt_idx, 0), (bound_check_stmt_idx, 0)]
+            .into_iter()
+            .collect(),
+    ));
+    Ok(bound_check_stmt_idx)
+}
+
+/// Witness equalities linking the chunks of a message across 2 Pedersen commitment statements, the one at
+/// `chunks_comm_stmt_idx` committing to the chunks with `ChunkedCommitment::commitment_key` and the one at
+/// `ct_comm_stmt_idx` committing to them with `EncryptionKey::commitment_key`. Both have the `chunks_count`
+/// chunks as their first witnesses, in the same order, followed by the randomness which is not linked.
+pub fn link_commitment_chunks(
+    chunks_comm_stmt_idx: usize,
+    ct_comm_stmt_idx: usize,
+    chunks_count: usize,
+) -> Vec<MetaStatement> {
+    (0..chunks_count)
+        .map(|i| {
+            MetaStatement::WitnessEquality(EqualWitnesses(
+                [(chunks_comm_stmt_idx, i), (ct_comm_stmt_idx, i)]
+                    .into_iter()
+                    .collect(),
+            ))
+        })
+        .collect()
+}
+
+/// Add statements to `statements` and `meta_statements` proving that `ct_a`, encrypted with `ek_a`, and
+/// `ct_b`, encrypted with `ek_b`, encrypt the same message without revealing it. These are 2 Pedersen
+/// commitment statements, one opening each ciphertext's commitment with its key's
+/// `EncryptionKey::commitment_key`, and witness equalities between their chunks as in
+/// `link_commitment_chunks`. Returns the indices of the 2 statements. The prover's witness for each is the
+/// message decomposed into `chunk_bit_size` chunks followed by the randomness of the ciphertext's
+/// encryption. The statements only prove that the commitments open to the same chunks, so the verifier
+/// must also check that each ciphertext is consistent with its commitment, eg. with
+/// `Ciphertext::verify_commitment_and_proof`.
+pub fn proof_statements_equal_across_keys<E: Pairing>(
+    statements: &mut Statements<E, E::G1Affine>,
+    meta_statements: &mut MetaStatements,
+    ct_a: &Ciphertext<E>,
+    ek_a: &EncryptionKey<E>,
+    ct_b: &Ciphertext<E>,
+    ek_b: &EncryptionKey<E>,
+    chunk_bit_size: u8,
+) -> Result<[usize; 2], ProofSystemError> {
+    let indices = proof_statements_equal_across_many_keys(
+        statements,
+        meta_statements,
+        &[(ct_a, ek_a), (ct_b, ek_b)],
+        chunk_bit_size,
+    )?;
+    Ok([indices[0], indices[1]])
+}
+
+/// Same as `proof_statements_equal_across_keys` but for any number of ciphertexts, each with the key it was
+/// encrypted with. There is a Pedersen commitment statement for each ciphertext and a witness equality for
+/// each chunk between all the statements. Returns the indices of the statements, in the order of
+/// `ciphertexts`.
+pub fn proof_statements_equal_across_many_keys<E: Pairing>(
+    statements: &mut Statements<E, E::G1Affine>,
+    meta_statements: &mut MetaStatements,
+    ciphertexts: &[(&Ciphertext<E>, &EncryptionKey<E>)],
+    chunk_bit_size: u8,
+) -> Result<Vec<usize>, ProofSystemError> {
+    saver::utils::check_chunk_bit_size(chunk_bit_size)?;
+    let chunks_count = saver::utils::chunks_count::<E::ScalarField>(chunk_bit_size) as usize;
+    for (ct, ek) in ciphertexts {
+        SaverProtocol::validate_encryption_key(chunk_bit_size, ek)?;
+        if ct.encrypted_chunk_count() != chunks_count {
+            return Err(ProofSystemError::SaverError(
+                SaverError::IncompatibleEncryptionKey(ct.encrypted_chunk_count(), chunks_count),
+            ));
+        }
+    }
+    let indices = ciphertexts
+        .iter()
+        .map(|(ct, ek)| {
+            statements.add(PedersenCommitment::new_statement_from_params(
+                Ciphertext::commitment_bases(ek),
+                ct.commitment,
+            ))
+        })
+        .collect::<Vec<_>>();
+    if indices.len() > 1 {
+        for i in 0..chunks_count {
+            meta_statements.add(MetaStatement::WitnessEquality(EqualWitnesses(
+                indices.iter().map(|idx| (*idx, i)).collect(),
+            )));
+        }
+    }
+    Ok(indices)
+}
+
+/// Encrypt `message` for each of the encryption keys `eks`, like `Encryption::encrypt_given_snark_vk`, and
+/// prove that all the ciphertexts encrypt the same message with the statements of
+/// `proof_statements_equal_across_many_keys`, so that any of the key holders can decrypt it. The keys must
+/// have been created with the generators of `snark_vk`, eg. with `saver::keygen::keygen`. Returns
+/// a ciphertext for each key, in the order of `eks`, and the proof to be verified with
+/// `verify_encryption_to_many`.
+#[allow(clippy::type_complexity)]
+pub fn encrypt_to_many<R: RngCore, E: Pairing, D: Digest>(
+    rng: &mut R,
+    message: &E::ScalarField,
+    eks: &[EncryptionKey<E>],
+    snark_vk: &VerifyingKey<E>,
+    chunk_bit_size: u8,
+) -> Result<(Vec<Ciphertext<E>>, Proof<E, E::G1Affine>), ProofSystemError> {
+    if eks.is_empty() {
+        return Err(ProofSystemError::SaverError(
+            SaverError::VectorShorterThanExpected(0, 1),
+        ));
+    }
+    let mut chunks = saver::utils::decompose(message, chunk_bit_size)?
+        .into_iter()
+        .map(E::ScalarField::from)
+        .collect::<Vec<_>>();
+    let mut ciphertexts = Vec::with_capacity(eks.len());
+    let mut witnesses = Witnesses::new();
+    for ek in eks {
+        let (ct, r) =
+            Encryption::encrypt_given_snark_vk(rng, message, ek, snark_vk, chunk_bit_size)?;
+        chunks.push(r);
+        witnesses.add(Witness::PedersenCommitment(chunks.clone()));
+        chunks.pop();
+        ciphertexts.push(ct);
+    }
+    let proof_spec = encryption_to_many_proof_spec(&ciphertexts, eks, chunk_bit_size)?;
+    let proof =
+        Proof::new_with_spec_ref::<R, D>(rng, &proof_spec, witnesses, None, Default::default())?.0;
+    Ok((ciphertexts, proof))
+}
+
+/// Verify the proof created by `encrypt_to_many` that `ciphertexts`, encrypted with `eks` in the same order,
+/// encrypt the same message. Also checks that each ciphertext is consistent with its commitment. There is
+/// no SNARK proof that the chunks are small, so the verifier learns that all the key holders decrypt the same
+/// message but not that the decryption succeeds.
+pub fn verify_encryption_to_many<R: RngCore, E: Pairing, D: Digest>(
+    rng: &mut R,
+    ciphertexts: &[Ciphertext<E>],
+    eks: &[EncryptionKey<E>],
+    proof: Proof<E, E::G1Affine>,
+    gens: &EncryptionGens<E>,
+    chunk_bit_size: u8,
+) -> Result<(), ProofSystemError> {
+    saver::utils::check_chunk_bit_size(chunk_bit_size)?;
+    for (ct, ek) in ciphertexts.iter().zip(eks.iter()) {
+        ct.verify_commitment(ek.clone(), gens.clone())?;
+    }
+    let proof_spec = encryption_to_many_proof_spec(ciphertexts, eks, chunk_bit_size)?;
+    proof.verify_with_spec_ref::<R, D>(rng, &proof_spec, None, Default::default())
+}
+
+fn encryption_to_many_proof_spec<E: Pairing>(
+    ciphertexts: &[Ciphertext<E>],
+    eks: &[EncryptionKey<E>],
+    chunk_bit_size: u8,
+) -> Result<ProofSpec<E, E::G1Affine>, ProofSystemError> {
+    if ciphertexts.len() != eks.len() {
+        return Err(ProofSystemError::SaverError(
+            SaverError::UnequalSizeOfSequence(ciphertexts.len(), eks.len()),
+        ));
+    }
+    let mut statements = Statements::new();
+    let mut meta_statements = MetaStatements::new();
+    proof_statements_equal_across_many_keys(
+        &mut statements,
+        &mut meta_statements,
+        &ciphertexts.iter().zip(eks.iter()).collect::<Vec<_>>(),
+        chunk_bit_size,
+    )?;
+    Ok(ProofSpec::new(statements, meta_statements, vec![], None))
+}
+
+/// Statements proving that a message `m` is not `forbidden_value`, eg. a revoked id, without revealing it.
+/// `commitment` is a commitment `G * (m - forbidden_value) + H * r` created by the prover with
+/// `inequality_commitment_and_witnesses` and `gens` are the Pedersen commitment generators `G` and `H`.
+/// The 1st statement proves knowledge of `m` and `r` in `commitment + G * forbidden_value = G * m + H * r`,
+/// and the 2nd proves knowledge of `w` and `s` in `G = commitment * w + H * s`, which is only possible when
+/// `m - forbidden_value` has an inverse `w`, i.e. is not 0. To apply this to the message of another
+/// statement, like the message encrypted by a SAVER statement, add a witness equality between that
+/// message and the 1st witness of the 1st statement.
+pub fn inequality_statements<E: Pairing, G: AffineRepr>(
+    commitment: G,
+    forbidden_value: &G::ScalarField,
+    gens: &ChunkedCommitmentGens<G>,
+) -> [Statement<E, G>; 2] {
+    [
+        PedersenCommitment::new_statement_from_params(
+            vec![gens.G, gens.H],
+            (commitment + gens.G * forbidden_value).into_affine(),
+        ),
+        PedersenCommitment::new_statement_from_params(vec![commitment, gens.H], gens.G),
+    ]
+}
+
+/// Create the commitment for `inequality_statements` and the witnesses for its 2 statements. Returns an
+/// error if `message` equals `forbidden_value` as no such proof exists then.
+pub fn inequality_commitment_and_witnesses<R: RngCore, E: Pairing, G>(
+    rng: &mut R,
+    message: &E::ScalarField,
+    forbidden_value: &E::ScalarField,
+    gens: &ChunkedCommitmentGens<G>,
+) -> Result<(G, [Witness<E>; 2]), ProofSystemError>
+where
+    G: AffineRepr<ScalarField = E::ScalarField>,
+{
+    let diff = *message - forbidden_value;
+    if diff.is_zero() {
+        return Err(ProofSystemError::SaverMessageEqualsForbiddenValue);
+    }
+    let r = E::ScalarField::rand(rng);
+    let commitment = (gens.G * diff + gens.H * r).into_affine();
+    // G = commitment * w + H * s when w = 1 / diff and s = -r / diff
+    let w = diff.inverse().unwrap();
+    Ok((
+        commitment,
+        [
+            Witness::PedersenCommitment(vec![*message, r]),
+            Witness::PedersenCommitment(vec![w, -(r * w)]),
+        ],
+    ))
+}
+
+/// Statements proving that the message encrypted in a ciphertext is in `[a, b]` without revealing it, by
+/// proving that the top chunks of the message are those shared by all the values of the range. The range
+/// must therefore be aligned to the chunks: `a` must have its `k` lowest chunks 0 and `b` must be `a` with them
+/// all at their max value, for some `k`, eg. `[0, 2^{16} - 1]` or `[3 * 2^8, 4 * 2^8 - 1]` with 8 bit chunks,
+/// otherwise `ProofSystemError::SaverRangeNotAlignedToChunks` is returned. There is no need to bound the `k`
+/// lowest chunks as they are already bounded by the SNARK proof of the ciphertext.
+///
+/// `message_commitment` is the commitment `Ciphertext::commitment` of the ciphertext, encrypted with `ek`.
+/// The only statement is a Pedersen commitment statement opening `message_commitment` minus the top chunks
+/// with the remaining bases of `EncryptionKey::commitment_key`, which are independent so the top chunks of any
+/// opening are the fixed ones. No meta-statements are needed. The prover's witnesses are created with
+/// `range_proof_witnesses`. The verifier must also check that the ciphertext is consistent with its commitment
+/// and that its chunks are bounded, eg. with `Ciphertext::verify_commitment_and_proof`. A ciphertext created
+/// by a SAVER statement of the same proof can't be used as it's only created while proving.
+pub fn range_proof_statements<E: Pairing>(
+    message_commitment: E::G1Affine,
+    ek: &EncryptionKey<E>,
+    a: u64,
+    b: u64,
+    chunk_bit_size: u8,
+) -> Result<Statements<E, E::G1Affine>, ProofSystemError> {
+    SaverProtocol::validate_encryption_key(chunk_bit_size, ek)?;
+    let free_chunks = free_chunks_of_range::<E::ScalarField>(a, b, chunk_bit_size)?;
+    let comm_key = Ciphertext::commitment_bases(ek);
+    let fixed_chunks = comm_key.len() - 1 - free_chunks;
+    let fixed = saver::utils::decompose(&E::ScalarField::from(a), chunk_bit_size)?;
+    let fixed_part = fixed[..fixed_chunks]
+        .iter()
+        .zip(comm_key.iter())
+        .fold(E::G1::zero(), |acc, (c, y)| {
+            acc + *y * E::ScalarField::from(*c)
+        });
+    let mut statements = Statements::new();
+    statements.add(PedersenCommitment::new_statement_from_params(
+        comm_key[fixed_chunks..].to_vec(),
+        (message_commitment.into_group() - fixed_part).into_affine(),
+    ));
+    Ok(statements)
+}
+
+/// Witnesses for the statements of `range_proof_statements`, given the encrypted `message` and the randomness
+/// `r` of its encryption: the chunks of the message that are not fixed by the range followed by `r`. Returns
+/// `ProofSystemError::SaverMessageOutOfRange` if `message` is not in `[a, b]` as no such proof exists then.
+pub fn range_proof_witnesses<E: Pairing>(
+    message: &E::ScalarField,
+    r: E::ScalarField,
+    a: u64,
+    b: u64,
+    chunk_bit_size: u8,
+) -> Result<Witnesses<E>, ProofSystemError> {
+    let free_chunks = free_chunks_of_range::<E::ScalarField>(a, b, chunk_bit_size)?;
+    let chunks = saver::utils::decompose(message, chunk_bit_size)?;
+    let fixed = saver::utils::decompose(&E::ScalarField::from(a), chunk_bit_size)?;
+    let fixed_chunks = chunks.len() - free_chunks;
+    if chunks[..fixed_chunks] != fixed[..fixed_chunks] {
+        return Err(ProofSystemError::SaverMessageOutOfRange);
+    }
+    let mut witness = chunks[fixed_chunks..]
+        .iter()
+        .map(|c| E::ScalarField::from(*c))
+        .collect::<Vec<_>>();
+    witness.push(r);
+    let mut witnesses = Witnesses::new();
+    witnesses.add(Witness::PedersenCommitment(witness));
+    Ok(witnesses)
+}
+
+/// Number of lowest chunks that take every value in `[a, b]`, the top chunks being fixed
+fn free_chunks_of_range<F: PrimeField>(
+    a: u64,
+    b: u64,
+    chunk_bit_size: u8,
+) -> Result<usize, ProofSystemError> {
+    saver::utils::check_chunk_bit_size(chunk_bit_size)?;
+    let chunks_count = saver::utils::chunks_count::<F>(chunk_bit_size) as usize;
+    (0..=chunks_count)
+        .map_while(|k| {
+            let free_bits = k * chunk_bit_size as usize;
+            match free_bits {
+                0 => Some((k, 0)),
+                1..=63 => Some((k, (1u64 << free_bits) - 1)),
+                64 => Some((k, u64::MAX)),
+                _ => None,
+            }
+        })
+        .find(|(_, mask)| a & mask == 0 && b == a | mask)
+        .map(|(k, _)| k)
+        .ok_or(ProofSystemError::SaverRangeNotAlignedToChunks(a, b))
+}
@@ -30,6 +30,10 @@ use saver::{
     utils::decompose,
 };
 
+/// Commitment keys returned by `SaverProtocol::chunked_comm_keys`, the bases for the commitment to the chunks
+/// and the bases `G` and `H` for the commitment to the combined message
+pub type ChunkedCommitmentKeys<G> = (Vec<G>, Vec<G>);
+
 /// Apart from the SAVER protocol (encryption and snark proof), this also runs 3 Schnorr proof of knowledge protocols
 #[derive(Clone, Debug, PartialEq)]
 pub struct SaverProtocol<'a, E: Pairing> {
@@ -403,6 +407,7 @@ impl<'a, E: Pairing> SaverProtocol<'a, E> {
         chunk_bit_size: u8,
         encryption_key: &EncryptionKey<E>,
     ) -> Result<(), ProofSystemError> {
+        saver::utils::check_chunk_bit_size(chunk_bit_size)?;
         if encryption_key.supported_chunks_count()?
             != saver::utils::chunks_count::<E::ScalarField>(chunk_bit_size)
         {
@@ -419,20 +424,18 @@ impl<'a, E: Pairing> SaverProtocol<'a, E> {
 
     /// Commitment key for the commitment in ciphertext
     pub fn encryption_comm_key(encryption_key: &EncryptionKey<E>) -> Vec<E::G1Affine> {
-        encryption_key.commitment_key()
+        Ciphertext::commitment_bases(encryption_key)
     }
 
     /// Commitment key for chunked commitment
     pub fn chunked_comm_keys(
         chunked_commitment_gens: &ChunkedCommitmentGens<E::G1Affine>,
         chunk_bit_size: u8,
-    ) -> (Vec<E::G1Affine>, Vec<E::G1Affine>) {
-        let ck_comm_chunks = ChunkedCommitment::<E::G1Affine>::commitment_key(
-            chunked_commitment_gens,
-            chunk_bit_size,
-        );
+    ) -> Result<ChunkedCommitmentKeys<E::G1Affine>, ProofSystemError> {
+        let ck_comm_chunks =
+            ChunkedCommitment::<E::G1Affine>::bases(chunked_commitment_gens, chunk_bit_size)?;
         let ck_comm_combined = vec![chunked_commitment_gens.G, chunked_commitment_gens.H];
-        (ck_comm_chunks, ck_comm_combined)
+        Ok((ck_comm_chunks, ck_comm_combined))
     }
 
     fn init_schnorr_protocols<R: RngCore>(
@@ -481,7 +484,7 @@ impl<'a, E: Pairing> SaverProtocol<'a, E> {
 
         let message_chunks = decompose(&message, self.chunk_bit_size)?
             .into_iter()
-            .map(|m| E::ScalarField::from(m as u64))
+            .map(E::ScalarField::from)
             .collect::<Vec<_>>();
 
         // NOTE: value of id is dummy
@@ -1,7 +1,8 @@
 use crate::{
-    error::ProofSystemError,
+    error::{EncryptedCredentialVerificationError, ProofSystemError},
+    meta_statement::MetaStatement,
     proof::Proof,
-    proof_spec::{ProofSpec, SnarkpackSRS},
+    proof_spec::{PreparedProofSpec, ProofSpec, SnarkpackSRS, VerificationParams},
     statement::Statement,
     statement_proof::StatementProof,
     sub_protocols::{
@@ -24,7 +25,7 @@ use dock_crypto_utils::{
     randomized_pairing_check::RandomizedPairingChecker,
     transcript::{new_merlin_transcript, Transcript},
 };
-use saver::encryption::Ciphertext;
+use saver::{encryption::Ciphertext, keygen::PreparedEncryptionKey, setup::PreparedEncryptionGens};
 
 /// Passed to the verifier during proof verification
 #[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize, Default)]
@@ -34,6 +35,24 @@ pub struct VerifierConfig {
     pub use_lazy_randomized_pairing_checks: Option<bool>,
 }
 
+/// Part of the `ProofSpec` whose check failed, found by `Proof::verify_and_locate_failure`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailureLocation {
+    /// Index of the statement in `ProofSpec::statements`
+    Statement(usize),
+    /// Index of the meta-statement in `ProofSpec::meta_statements`
+    MetaStatement(usize),
+}
+
+/// Error returned by `Proof::verify_and_locate_failure`. `location` is `None` when the failure isn't due
+/// to a single statement or meta-statement, eg. when there are fewer statement proofs than statements or
+/// an aggregated SNARK proof is invalid.
+#[derive(Debug)]
+pub struct LocatedVerificationError {
+    pub location: Option<FailureLocation>,
+    pub error: ProofSystemError,
+}
+
 impl<E, G> Proof<E, G>
 where
     E: Pairing,
@@ -46,25 +65,127 @@ where
         proof_spec: ProofSpec<E, G>,
         nonce: Option<Vec<u8>>,
         config: VerifierConfig,
+    ) -> Result<(), ProofSystemError> {
+        self.verify_with_spec_ref::<R, D>(rng, &proof_spec, nonce, config)
+    }
+
+    /// Same as `Self::verify` but borrows the `ProofSpec`, like `Proof::new_with_spec_ref`, so it can be
+    /// reused without cloning.
+    pub fn verify_with_spec_ref<R: RngCore, D: Digest>(
+        self,
+        rng: &mut R,
+        proof_spec: &ProofSpec<E, G>,
+        nonce: Option<Vec<u8>>,
+        config: VerifierConfig,
+    ) -> Result<(), ProofSystemError> {
+        let params = proof_spec.derive_verification_params()?;
+        self.verify_given_params::<R, D>(rng, proof_spec, &params, nonce, config)
+    }
+
+    /// Same as `Self::verify` but uses the parameters already derived from the `ProofSpec` in
+    /// `PreparedProofSpec`, so verifying many proofs for the same `ProofSpec` doesn't derive them each time.
+    pub fn verify_with_prepared_spec<R: RngCore, D: Digest>(
+        self,
+        rng: &mut R,
+        prepared_spec: &PreparedProofSpec<E, G>,
+        nonce: Option<Vec<u8>>,
+        config: VerifierConfig,
+    ) -> Result<(), ProofSystemError> {
+        self.verify_given_params::<R, D>(
+            rng,
+            prepared_spec.spec(),
+            prepared_spec.params(),
+            nonce,
+            config,
+        )
+    }
+
+    /// Same as `Self::verify_with_spec_ref` but on failure also returns which statement or meta-statement
+    /// of the `ProofSpec` failed, to debug a `ProofSpec` with many statements and witness equalities. Meant
+    /// for the prover's side, eg. in tests, as it is slower: pairings are checked for each statement instead
+    /// of all at once so that the failing one is known. The Fiat-Shamir challenge covers all statements so
+    /// if the verifier's `ProofSpec` differs from the prover's, all statements fail and the 1st one is
+    /// reported. The statement reported is then the 1st one that depends on the challenge, not necessarily
+    /// the one that differs.
+    pub fn verify_and_locate_failure<R: RngCore, D: Digest>(
+        self,
+        rng: &mut R,
+        proof_spec: &ProofSpec<E, G>,
+        nonce: Option<Vec<u8>>,
+    ) -> Result<(), LocatedVerificationError> {
+        let params =
+            proof_spec
+                .derive_verification_params()
+                .map_err(|error| LocatedVerificationError {
+                    location: None,
+                    error,
+                })?;
+        let mut current_statement = None;
+        self._verify::<R, D>(
+            rng,
+            proof_spec,
+            &params,
+            nonce,
+            None,
+            &mut current_statement,
+        )
+        .map_err(|error| {
+            // Find the meta-statement containing the witness whose response differs from the others
+            let in_meta_statement = |w: &(usize, usize)| {
+                proof_spec.meta_statements.0.iter().position(|m| match m {
+                    MetaStatement::WitnessEquality(eq) => eq.0.contains(w),
+                })
+            };
+            let location = match &error {
+                ProofSystemError::WitnessResponseNotEqual(s, w) => {
+                    in_meta_statement(&(*s, *w)).map(FailureLocation::MetaStatement)
+                }
+                ProofSystemError::UnsatisfiedWitnessEqualities(eqs) => eqs
+                    .iter()
+                    .flatten()
+                    .find_map(in_meta_statement)
+                    .map(FailureLocation::MetaStatement),
+                _ => current_statement.map(FailureLocation::Statement),
+            };
+            LocatedVerificationError { location, error }
+        })
+    }
+
+    fn verify_given_params<R: RngCore, D: Digest>(
+        self,
+        rng: &mut R,
+        proof_spec: &ProofSpec<E, G>,
+        params: &VerificationParams<E>,
+        nonce: Option<Vec<u8>>,
+        config: VerifierConfig,
     ) -> Result<(), ProofSystemError> {
         match config.use_lazy_randomized_pairing_checks {
             Some(b) => {
                 let pairing_checker = RandomizedPairingChecker::new_using_rng(rng, b);
-                self._verify::<R, D>(rng, proof_spec, nonce, Some(pairing_checker))
+                self._verify::<R, D>(
+                    rng,
+                    proof_spec,
+                    params,
+                    nonce,
+                    Some(pairing_checker),
+                    &mut None,
+                )
             }
-            None => self._verify::<R, D>(rng, proof_spec, nonce, None),
+            None => self._verify::<R, D>(rng, proof_spec, params, nonce, None, &mut None),
         }
     }
 
+    /// `params` must be derived from `proof_spec` which is validated while deriving them. While checking
+    /// a statement, its index is in `current_statement` so on failure it's the index of the failed statement.
     fn _verify<R: RngCore, D: Digest>(
         self,
         rng: &mut R,
-        proof_spec: ProofSpec<E, G>,
+        proof_spec: &ProofSpec<E, G>,
+        params: &VerificationParams<E>,
         nonce: Option<Vec<u8>>,
         mut pairing_checker: Option<RandomizedPairingChecker<E>>,
+        current_statement: &mut Option<usize>,
     ) -> Result<(), ProofSystemError> {
-        proof_spec.validate()?;
-
         // Number of statement proofs is less than number of statements which means some statements
         // are not satisfied.
         if proof_spec.statements.len() > self.statement_proofs.len() {
@@ -104,34 +225,26 @@ where
             }
         }
 
-        // Prepare commitment keys for running Schnorr protocols of all statements.
-        let (bound_check_comm, ek_comm, chunked_comm, r1cs_comm_keys) =
-            proof_spec.derive_commitment_keys()?;
-
-        // Prepare required parameters for pairings
-        let (
-            derived_lego_vk,
-            derived_gens,
-            derived_ek,
-            derived_saver_vk,
-            derived_bbs_plus_param,
-            derived_bbs_pk,
-            derived_accum_param,
-            derived_accum_pk,
-            derived_ps_param,
-            derived_ps_pk,
-            derived_bbs_param,
-        ) = proof_spec.derive_prepared_parameters()?;
-
-        // All the distinct equalities in `ProofSpec`
-        let mut witness_equalities = vec![];
-
-        if !proof_spec.meta_statements.is_empty() {
-            let disjoint_equalities = proof_spec.meta_statements.disjoint_witness_equalities();
-            for eq_wits in disjoint_equalities {
-                witness_equalities.push(eq_wits.0);
-            }
-        }
+        // Commitment keys for running Schnorr protocols of all statements, prepared parameters for
+        // pairings and all the distinct equalities in `ProofSpec`
+        let VerificationParams {
+            commitment_keys: (bound_check_comm, ek_comm, chunked_comm, r1cs_comm_keys),
+            prepared_parameters:
+                (
+                    derived_lego_vk,
+                    derived_gens,
+                    derived_ek,
+                    derived_saver_vk,
+                    derived_bbs_plus_param,
+                    derived_bbs_pk,
+                    derived_accum_param,
+                    derived_accum_pk,
+                    derived_ps_param,
+                    derived_ps_pk,
+                    derived_bbs_param,
+                ),
+            witness_equalities,
+        } = params;
 
         // This will hold the response for each witness equality. If there is no response for some witness
         // equality, it will contain `None` corresponding to that.
@@ -155,6 +268,7 @@ where
             .zip(self.statement_proofs.iter())
             .enumerate()
         {
+            *current_statement = Some(s_idx);
             match statement {
                 Statement::PoKBBSSignatureG1(s) => match proof {
                     StatementProof::PoKBBSSignatureG1(p) => {
@@ -529,6 +643,8 @@ where
             }
         }
 
+        *current_statement = None;
+
         // If even one of witness equality had no corresponding response, it means that wasn't satisfied
         // and proof should not verify
         if responses_for_equalities.iter().any(|r| r.is_none()) {
@@ -555,6 +671,7 @@ where
             .zip(self.statement_proofs.into_iter())
             .enumerate()
         {
+            *current_statement = Some(s_idx);
             match statement {
                 Statement::PoKBBSSignatureG1(s) => match proof {
                     StatementProof::PoKBBSSignatureG1(ref p) => {
@@ -829,10 +946,12 @@ where
             }
         }
 
+        *current_statement = None;
+
         if aggregate_snarks {
             // The validity of `ProofSpec` ensures that statements are not being repeated
 
-            let srs = match proof_spec.snark_aggregation_srs {
+            let srs = match &proof_spec.snark_aggregation_srs {
                 Some(SnarkpackSRS::VerifierSrs(srs)) => srs,
                 _ => return Err(ProofSystemError::SnarckpackSrsNotProvided),
             };
@@ -840,7 +959,7 @@ where
             let mut transcript = new_merlin_transcript(b"aggregation");
             transcript.append(b"challenge", &challenge);
 
-            if let Some(to_aggregate) = proof_spec.aggregate_groth16 {
+            if let Some(to_aggregate) = &proof_spec.aggregate_groth16 {
                 if let Some(aggr_proofs) = self.aggregated_groth16 {
                     if to_aggregate.len() != aggr_proofs.len() {
                         return Err(ProofSystemError::InvalidNumberOfAggregateGroth16Proofs(
@@ -868,7 +987,7 @@ where
                             &mut pairing_checker,
                         )?;
                         saver::saver_groth16::verify_aggregate_proof(
-                            &srs,
+                            srs,
                             pvk,
                             &a.proof,
                             ciphertexts,
@@ -882,7 +1001,7 @@ where
                 }
             }
 
-            if let Some(to_aggregate) = proof_spec.aggregate_legogroth16 {
+            if let Some(to_aggregate) = &proof_spec.aggregate_legogroth16 {
                 if let Some(aggr_proofs) = self.aggregated_legogroth16 {
                     if to_aggregate.len() != aggr_proofs.len() {
                         return Err(ProofSystemError::InvalidNumberOfAggregateLegoGroth16Proofs(
@@ -897,7 +1016,7 @@ where
                         let s_id = a.statements.into_iter().next().unwrap();
                         let pvk = derived_lego_vk.get(s_id).unwrap();
                         legogroth16::aggregation::legogroth16::using_groth16::verify_aggregate_proof(
-                            &srs,
+                            srs,
                             pvk,
                             &agg_lego[i].1,
                             &a.proof,
@@ -940,3 +1059,84 @@ where
         Ok(())
     }
 }
+
+/// Ciphertext, its SNARK proof and the public parameters needed to verify them in
+/// `verify_encrypted_credential`
+#[derive(Clone, Debug)]
+pub struct SaverArtifacts<'a, E: Pairing> {
+    pub ciphertext: &'a Ciphertext<E>,
+    pub snark_proof: &'a saver::saver_groth16::Proof<E>,
+    pub snark_pvk: &'a saver::saver_groth16::PreparedVerifyingKey<E>,
+    pub ek: PreparedEncryptionKey<E>,
+    pub enc_gens: PreparedEncryptionGens<E>,
+}
+
+impl<'a, E: Pairing> SaverArtifacts<'a, E> {
+    pub fn new(
+        ciphertext: &'a Ciphertext<E>,
+        snark_proof: &'a saver::saver_groth16::Proof<E>,
+        snark_pvk: &'a saver::saver_groth16::PreparedVerifyingKey<E>,
+        ek: impl Into<PreparedEncryptionKey<E>>,
+        enc_gens: impl Into<PreparedEncryptionGens<E>>,
+    ) -> Self {
+        Self {
+            ciphertext,
+            snark_proof,
+            snark_pvk,
+            ek: ek.into(),
+            enc_gens: enc_gens.into(),
+        }
+    }
+}
+
+/// Verify a proof about a credential with one of its messages verifiably encrypted using SAVER with
+/// Groth16 where the ciphertext is linked to the proof via a Pedersen commitment statement over the
+/// ciphertext's commitment (rather than the `Saver` statement). Does all the checks that the verifier
+/// needs to do in this case and returns the first failure:
+///  1. The proof spec contains a Pedersen commitment statement for `ciphertext.commitment` with the
+///     encryption key's commitment key as bases
+///  2. The ciphertext commits to the encrypted message
+///  3. The SNARK proof for the ciphertext is valid
+///  4. The composite proof is valid
+pub fn verify_encrypted_credential<R: RngCore, D: Digest, E: Pairing>(
+    rng: &mut R,
+    proof: Proof<E, E::G1Affine>,
+    proof_spec: ProofSpec<E, E::G1Affine>,
+    saver: SaverArtifacts<E>,
+    nonce: Option<Vec<u8>>,
+    config: VerifierConfig,
+) -> Result<(), EncryptedCredentialVerificationError> {
+    let SaverArtifacts {
+        ciphertext,
+        snark_proof,
+        snark_pvk,
+        ek,
+        enc_gens,
+    } = saver;
+    let comm_key = ek.commitment_key();
+    let found = proof_spec
+        .statements
+        .0
+        .iter()
+        .enumerate()
+        .any(|(i, s)| match s {
+            Statement::PedersenCommitment(s) => {
+                s.commitment == ciphertext.commitment
+                    && s.get_commitment_key(&proof_spec.setup_params, i)
+                        .map(|k| *k == comm_key)
+                        .unwrap_or(false)
+            }
+            _ => false,
+        });
+    if !found {
+        return Err(EncryptedCredentialVerificationError::CiphertextCommitmentNotInProofSpec);
+    }
+    ciphertext
+        .verify_commitment(ek, enc_gens)
+        .map_err(EncryptedCredentialVerificationError::CiphertextCommitment)?;
+    saver::saver_groth16::verify_proof(snark_pvk, snark_proof, ciphertext)
+        .map_err(EncryptedCredentialVerificationError::SnarkProof)?;
+    proof
+        .verify::<R, D>(rng, proof_spec, nonce, config)
+        .map_err(EncryptedCredentialVerificationError::Proof)
+}
@@ -9,7 +9,10 @@ use ark_std::{
 };
 use blake2::Blake2b512;
 use proof_system::{
-    prelude::{EqualWitnesses, MetaStatement, MetaStatements, Witness, WitnessRef, Witnesses},
+    prelude::{
+        EqualWitnesses, FailureLocation, MetaStatement, MetaStatements, Witness, WitnessRef,
+        Witnesses,
+    },
     proof_spec::ProofSpec,
     setup_params::SetupParams,
     statement::{ped_comm::PedersenCommitment as PedersenCommitmentStmt, Statements},
@@ -290,3 +293,76 @@ fn pok_of_knowledge_in_pedersen_commitment_and_equality_with_commitment_key_reus
         .verify::<StdRng, Blake2b512>(&mut rng, proof_spec, nonce, Default::default())
         .unwrap();
 }
+
+#[test]
+fn locate_failing_statement_and_meta_statement() {
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let count = 5;
+    let bases = (0..3)
+        .map(|_| {
+            (0..count)
+                .map(|_| G1Projective::rand(&mut rng).into_affine())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut scalars = (0..3)
+        .map(|_| (0..count).map(|_| Fr::rand(&mut rng)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    scalars[1][2] = scalars[0][0];
+    scalars[2][4] = scalars[1][3];
+    let commitments = bases
+        .iter()
+        .zip(scalars.iter())
+        .map(|(b, s)| {
+            G1Projective::msm_bigint(b, &s.iter().map(|s| s.into_bigint()).collect::<Vec<_>>())
+                .into_affine()
+        })
+        .collect::<Vec<_>>();
+
+    let create_and_verify =
+        |rng: &mut StdRng, commitments: &[G1Affine], equalities: Vec<Vec<WitnessRef>>| {
+            let mut statements = Statements::new();
+            for (b, c) in bases.iter().zip(commitments.iter()) {
+                statements.add(PedersenCommitmentStmt::new_statement_from_params(
+                    b.clone(),
+                    *c,
+                ));
+            }
+            let mut meta_statements = MetaStatements::new();
+            for e in equalities {
+                meta_statements.add(MetaStatement::WitnessEquality(EqualWitnesses(
+                    e.into_iter().collect::<BTreeSet<WitnessRef>>(),
+                )));
+            }
+            let mut witnesses = Witnesses::new();
+            for s in scalars.iter() {
+                witnesses.add(Witness::PedersenCommitment(s.clone()));
+            }
+            let proof_spec = ProofSpec::new(statements, meta_statements, vec![], None);
+            let proof = ProofG1::new::<StdRng, Blake2b512>(
+                rng,
+                proof_spec.clone(),
+                witnesses,
+                None,
+                Default::default(),
+            )
+            .unwrap()
+            .0;
+            proof.verify_and_locate_failure::<StdRng, Blake2b512>(rng, &proof_spec, None)
+        };
+
+    let equalities = vec![vec![(0, 0), (1, 2)], vec![(1, 3), (2, 4)]];
+    create_and_verify(&mut rng, &commitments, equalities.clone()).unwrap();
+
+    // The commitment of statement 1 isn't to the witnesses
+    let mut wrong_commitments = commitments.clone();
+    wrong_commitments[1] = (wrong_commitments[1] + bases[1][0]).into_affine();
+    let err = create_and_verify(&mut rng, &wrong_commitments, equalities.clone()).unwrap_err();
+    assert_eq!(err.location, Some(FailureLocation::Statement(1)));
+
+    // Witnesses of meta-statement 1 aren't equal
+    let wrong_equalities = vec![vec![(0, 0), (1, 2)], vec![(1, 3), (2, 1)]];
+    let err = create_and_verify(&mut rng, &commitments, wrong_equalities).unwrap_err();
+    assert_eq!(err.location, Some(FailureLocation::MetaStatement(1)));
+}
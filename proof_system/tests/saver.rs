@@ -7,9 +7,10 @@ use ark_std::{
 };
 use blake2::Blake2b512;
 use proof_system::{
+    error::{EncryptedCredentialVerificationError, ProofSystemError},
     prelude::{
-        generate_snark_srs_bound_check, EqualWitnesses, MetaStatements, ProofSpec, ProverConfig,
-        VerifierConfig, Witness, WitnessRef, Witnesses,
+        generate_snark_srs_bound_check, EqualWitnesses, MetaStatements, PreparedProofSpec,
+        ProofSpec, ProverConfig, VerifierConfig, Witness, WitnessRef, Witnesses,
     },
     prover::{OldLegoGroth16Proof, OldSaverProof},
     setup_params::SetupParams,
@@ -20,18 +21,29 @@ use proof_system::{
             BoundCheckLegoGroth16Prover as BoundCheckProverStmt,
             BoundCheckLegoGroth16Verifier as BoundCheckVerifierStmt,
         },
-        saver::{SaverProver as SaverProverStmt, SaverVerifier as SaverVerifierStmt},
+        ped_comm::PedersenCommitment as PedersenCommitmentStmt,
+        saver::{
+            add_bound_check_for_encrypted_message, encrypt_to_many,
+            inequality_commitment_and_witnesses, inequality_statements, link_commitment_chunks,
+            proof_statements_equal_across_keys, range_proof_statements, range_proof_witnesses,
+            verify_encryption_to_many, SaverProver as SaverProverStmt,
+            SaverVerifier as SaverVerifierStmt,
+        },
         Statements,
     },
+    verifier::{verify_encrypted_credential, SaverArtifacts},
     witness::{
         PoKBBSSignature23G1 as PoKSignatureBBS23G1Wit, PoKBBSSignatureG1 as PoKSignatureBBSG1Wit,
     },
 };
 use saver::{
-    keygen::{DecryptionKey, EncryptionKey, PreparedDecryptionKey, SecretKey},
+    commitment::ChunkedCommitment,
+    encryption::{Ciphertext, Encryption},
+    keygen::{keygen, DecryptionKey, EncryptionKey, PreparedDecryptionKey, SecretKey},
     prelude::VerifyingKey,
-    saver_groth16::ProvingKey,
+    saver_groth16::{self, ProvingKey},
     setup::{setup_for_groth16, ChunkedCommitmentGens, EncryptionGens, PreparedEncryptionGens},
+    utils::{chunked_commitment_witness, ciphertext_commitment_witness, decompose},
 };
 use std::time::Instant;
 
@@ -1425,3 +1437,817 @@ fn pok_of_bbs_plus_sig_and_bounded_message_and_verifiable_encryption() {
         )
         .unwrap();
 }
+
+#[test]
+fn verify_encrypted_credential_reports_failing_stage() {
+    // Message of a BBS+ signature is encrypted and the ciphertext's commitment is linked to the signature
+    // using Pedersen commitment statements instead of the Saver statement
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let chunk_bit_size = 8;
+    let msg_count = 5;
+    let (msgs, sig_params, sig_keypair, sig) = bbs_plus_sig_setup(&mut rng, msg_count);
+    let enc_msg_idx = 1;
+    let enc_msg = msgs[enc_msg_idx];
+
+    let enc_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let chunked_comm_gens = ChunkedCommitmentGens::<G1Affine>::new_using_rng(&mut rng);
+    let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens).unwrap();
+    let pvk = saver_groth16::prepare_verifying_key(&snark_pk.pk.vk);
+
+    let (ct, r, snark_proof) =
+        Encryption::encrypt_with_proof(&mut rng, &enc_msg, &ek, &snark_pk, chunk_bit_size).unwrap();
+    let (other_ct, _, other_snark_proof) =
+        Encryption::encrypt_with_proof(&mut rng, &enc_msg, &ek, &snark_pk, chunk_bit_size).unwrap();
+
+    let decomposed = decompose(&enc_msg, chunk_bit_size)
+        .unwrap()
+        .into_iter()
+        .map(|m| Fr::from(m as u64))
+        .collect::<Vec<_>>();
+    let chunks_count = decomposed.len();
+    let blinding = Fr::rand(&mut rng);
+    let comm_chunks =
+        ChunkedCommitment::<G1Affine>::new(&enc_msg, &blinding, chunk_bit_size, &chunked_comm_gens)
+            .unwrap()
+            .0;
+
+    let mut statements = Statements::new();
+    statements.add(PoKSignatureBBSG1Stmt::new_statement_from_params(
+        sig_params.clone(),
+        sig_keypair.public_key.clone(),
+        BTreeMap::new(),
+    ));
+    statements.add(PedersenCommitmentStmt::new_statement_from_params(
+        vec![chunked_comm_gens.G, chunked_comm_gens.H],
+        comm_chunks,
+    ));
+    statements.add(PedersenCommitmentStmt::new_statement_from_params(
+        ChunkedCommitment::<G1Affine>::bases(&chunked_comm_gens, chunk_bit_size).unwrap(),
+        comm_chunks,
+    ));
+    statements.add(PedersenCommitmentStmt::new_statement_from_params(
+        Ciphertext::commitment_bases(&ek),
+        ct.commitment,
+    ));
+
+    let mut meta_statements = MetaStatements::new();
+    meta_statements.add_witness_equality(EqualWitnesses(
+        vec![(0, enc_msg_idx), (1, 0)]
+            .into_iter()
+            .collect::<BTreeSet<WitnessRef>>(),
+    ));
+    for m in link_commitment_chunks(2, 3, chunks_count) {
+        meta_statements.add(m);
+    }
+    let proof_spec = ProofSpec::new(statements, meta_statements, vec![], None);
+
+    let wit_comm_chunks = chunked_commitment_witness(&decomposed, blinding);
+    let wit_comm_ct = ciphertext_commitment_witness(&decomposed, r);
+    let mut witnesses = Witnesses::new();
+    witnesses.add(PoKSignatureBBSG1Wit::new_as_witness(
+        sig,
+        msgs.into_iter().enumerate().collect(),
+    ));
+    witnesses.add(Witness::PedersenCommitment(vec![enc_msg, blinding]));
+    witnesses.add(Witness::PedersenCommitment(wit_comm_chunks));
+    witnesses.add(Witness::PedersenCommitment(wit_comm_ct));
+
+    let proof = ProofG1::new::<StdRng, Blake2b512>(
+        &mut rng,
+        proof_spec.clone(),
+        witnesses,
+        None,
+        Default::default(),
+    )
+    .unwrap()
+    .0;
+
+    verify_encrypted_credential::<StdRng, Blake2b512, Bls12_381>(
+        &mut rng,
+        proof.clone(),
+        proof_spec.clone(),
+        SaverArtifacts::new(&ct, &snark_proof, &pvk, ek.clone(), enc_gens.clone()),
+        None,
+        Default::default(),
+    )
+    .unwrap();
+
+    // Ciphertext not referenced by the proof spec
+    assert!(matches!(
+        verify_encrypted_credential::<StdRng, Blake2b512, Bls12_381>(
+            &mut rng,
+            proof.clone(),
+            proof_spec.clone(),
+            SaverArtifacts::new(
+                &other_ct,
+                &other_snark_proof,
+                &pvk,
+                ek.clone(),
+                enc_gens.clone()
+            ),
+            None,
+            Default::default(),
+        ),
+        Err(EncryptedCredentialVerificationError::CiphertextCommitmentNotInProofSpec)
+    ));
+
+    // Ciphertext's commitment is in the proof spec but the ciphertext was tampered
+    let mut tampered_ct = ct.clone();
+    tampered_ct.enc_chunks.swap(0, 1);
+    assert!(matches!(
+        verify_encrypted_credential::<StdRng, Blake2b512, Bls12_381>(
+            &mut rng,
+            proof.clone(),
+            proof_spec.clone(),
+            SaverArtifacts::new(
+                &tampered_ct,
+                &snark_proof,
+                &pvk,
+                ek.clone(),
+                enc_gens.clone()
+            ),
+            None,
+            Default::default(),
+        ),
+        Err(EncryptedCredentialVerificationError::CiphertextCommitment(
+            _
+        ))
+    ));
+
+    // SNARK proof is for another ciphertext
+    assert!(matches!(
+        verify_encrypted_credential::<StdRng, Blake2b512, Bls12_381>(
+            &mut rng,
+            proof.clone(),
+            proof_spec.clone(),
+            SaverArtifacts::new(&ct, &other_snark_proof, &pvk, ek.clone(), enc_gens.clone()),
+            None,
+            Default::default(),
+        ),
+        Err(EncryptedCredentialVerificationError::SnarkProof(_))
+    ));
+
+    // Composite proof fails with a nonce it wasn't created with
+    assert!(matches!(
+        verify_encrypted_credential::<StdRng, Blake2b512, Bls12_381>(
+            &mut rng,
+            proof,
+            proof_spec,
+            SaverArtifacts::new(&ct, &snark_proof, &pvk, ek, enc_gens),
+            Some(b"nonce".to_vec()),
+            Default::default(),
+        ),
+        Err(EncryptedCredentialVerificationError::Proof(_))
+    ));
+}
+
+#[test]
+fn verifiably_encrypted_message_in_range() {
+    // Prove that the verifiably encrypted message satisfies min <= message <= max without revealing it
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let min = 100;
+    let max = 200;
+    let bound_snark_pk = generate_snark_srs_bound_check::<Bls12_381, _>(&mut rng).unwrap();
+
+    let enc_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let chunk_bit_size = 16;
+    let chunked_comm_gens = ChunkedCommitmentGens::<G1Affine>::new_using_rng(&mut rng);
+    let (snark_pk, sk, ek, dk) = setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens).unwrap();
+
+    let mut prover_statements = Statements::new();
+    let saver_stmt_idx = prover_statements.add(
+        SaverProverStmt::new_statement_from_params(
+            chunk_bit_size,
+            enc_gens.clone(),
+            chunked_comm_gens.clone(),
+            ek.clone(),
+            snark_pk.clone(),
+        )
+        .unwrap(),
+    );
+    let mut prover_meta_statements = MetaStatements::new();
+    let bound_stmt_idx = add_bound_check_for_encrypted_message(
+        &mut prover_statements,
+        &mut prover_meta_statements,
+        saver_stmt_idx,
+        BoundCheckProverStmt::new_statement_from_params(min, max, bound_snark_pk.clone()).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(bound_stmt_idx, 1);
+    let prover_proof_spec = ProofSpec::new(
+        prover_statements.clone(),
+        prover_meta_statements.clone(),
+        vec![],
+        None,
+    );
+    prover_proof_spec.validate().unwrap();
+
+    let mut verifier_statements = Statements::new();
+    let saver_stmt_idx = verifier_statements.add(
+        SaverVerifierStmt::new_statement_from_params(
+            chunk_bit_size,
+            enc_gens.clone(),
+            chunked_comm_gens,
+            ek,
+            snark_pk.pk.vk.clone(),
+        )
+        .unwrap(),
+    );
+    let mut verifier_meta_statements = MetaStatements::new();
+    add_bound_check_for_encrypted_message(
+        &mut verifier_statements,
+        &mut verifier_meta_statements,
+        saver_stmt_idx,
+        BoundCheckVerifierStmt::new_statement_from_params(min, max, bound_snark_pk.vk.clone())
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(verifier_meta_statements, prover_meta_statements);
+    let verifier_proof_spec =
+        ProofSpec::new(verifier_statements, verifier_meta_statements, vec![], None);
+    verifier_proof_spec.validate().unwrap();
+
+    let witnesses_for = |m: Fr| {
+        let mut witnesses = Witnesses::new();
+        witnesses.add(Witness::Saver(m));
+        witnesses.add(Witness::BoundCheckLegoGroth16(m));
+        witnesses
+    };
+
+    for m in [min, 150, max] {
+        let m = Fr::from(m);
+        let proof = ProofG1::new::<StdRng, Blake2b512>(
+            &mut rng,
+            prover_proof_spec.clone(),
+            witnesses_for(m),
+            None,
+            Default::default(),
+        )
+        .unwrap()
+        .0;
+        proof
+            .clone()
+            .verify::<StdRng, Blake2b512>(
+                &mut rng,
+                verifier_proof_spec.clone(),
+                None,
+                Default::default(),
+            )
+            .unwrap();
+        decrypt_and_verify(
+            &proof,
+            saver_stmt_idx,
+            &snark_pk.pk.vk,
+            m,
+            &sk,
+            dk.clone(),
+            enc_gens.clone(),
+            chunk_bit_size,
+        );
+    }
+
+    // Message out of range can't be proven
+    for m in [min - 1, max + 1] {
+        assert!(ProofG1::new::<StdRng, Blake2b512>(
+            &mut rng,
+            prover_proof_spec.clone(),
+            witnesses_for(Fr::from(m)),
+            None,
+            Default::default(),
+        )
+        .is_err());
+    }
+
+    // Bound check must be of the same kind, prover or verifier, as the SAVER statement
+    let mut statements = prover_statements.clone();
+    assert!(add_bound_check_for_encrypted_message(
+        &mut statements,
+        &mut MetaStatements::new(),
+        0,
+        BoundCheckVerifierStmt::new_statement_from_params(min, max, bound_snark_pk.vk.clone())
+            .unwrap(),
+    )
+    .is_err());
+    // and must refer to a SAVER statement
+    assert!(add_bound_check_for_encrypted_message(
+        &mut statements,
+        &mut MetaStatements::new(),
+        1,
+        BoundCheckProverStmt::new_statement_from_params(min, max, bound_snark_pk).unwrap(),
+    )
+    .is_err());
+    assert_eq!(statements.len(), 2);
+}
+
+#[test]
+fn verifiably_encrypted_message_not_forbidden() {
+    // Prove that the verifiably encrypted message is not a forbidden value, like a revoked id, without
+    // revealing it
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let enc_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let chunk_bit_size = 16;
+    let chunked_comm_gens = ChunkedCommitmentGens::<G1Affine>::new_using_rng(&mut rng);
+    let ineq_gens = ChunkedCommitmentGens::<G1Affine>::new_using_rng(&mut rng);
+    let (snark_pk, sk, ek, dk) = setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens).unwrap();
+    let forbidden = Fr::rand(&mut rng);
+
+    let proof_specs = |commitment: G1Affine| {
+        let mut prover_statements = Statements::new();
+        prover_statements.add(
+            SaverProverStmt::new_statement_from_params(
+                chunk_bit_size,
+                enc_gens.clone(),
+                chunked_comm_gens.clone(),
+                ek.clone(),
+                snark_pk.clone(),
+            )
+            .unwrap(),
+        );
+        let mut verifier_statements = Statements::new();
+        verifier_statements.add(
+            SaverVerifierStmt::new_statement_from_params(
+                chunk_bit_size,
+                enc_gens.clone(),
+                chunked_comm_gens.clone(),
+                ek.clone(),
+                snark_pk.pk.vk.clone(),
+            )
+            .unwrap(),
+        );
+        for stmts in [&mut prover_statements, &mut verifier_statements] {
+            for stmt in inequality_statements(commitment, &forbidden, &ineq_gens) {
+                stmts.add(stmt);
+            }
+        }
+        let mut meta_statements = MetaStatements::new();
+        meta_statements.add_witness_equality(EqualWitnesses(
+            [(0, 0), (1, 0)].into_iter().collect::<BTreeSet<_>>(),
+        ));
+        let prover_proof_spec =
+            ProofSpec::new(prover_statements, meta_statements.clone(), vec![], None);
+        prover_proof_spec.validate().unwrap();
+        let verifier_proof_spec =
+            ProofSpec::new(verifier_statements, meta_statements, vec![], None);
+        verifier_proof_spec.validate().unwrap();
+        (prover_proof_spec, verifier_proof_spec)
+    };
+
+    let m = Fr::rand(&mut rng);
+    let (commitment, [wit_1, wit_2]) =
+        inequality_commitment_and_witnesses(&mut rng, &m, &forbidden, &ineq_gens).unwrap();
+    let (prover_proof_spec, verifier_proof_spec) = proof_specs(commitment);
+    let mut witnesses = Witnesses::new();
+    witnesses.add(Witness::Saver(m));
+    witnesses.add(wit_1);
+    witnesses.add(wit_2);
+    let proof = ProofG1::new::<StdRng, Blake2b512>(
+        &mut rng,
+        prover_proof_spec,
+        witnesses,
+        None,
+        Default::default(),
+    )
+    .unwrap()
+    .0;
+    proof
+        .clone()
+        .verify::<StdRng, Blake2b512>(&mut rng, verifier_proof_spec, None, Default::default())
+        .unwrap();
+    decrypt_and_verify(
+        &proof,
+        0,
+        &snark_pk.pk.vk,
+        m,
+        &sk,
+        dk,
+        enc_gens.clone(),
+        chunk_bit_size,
+    );
+
+    // The forbidden value can't be proven different from itself
+    assert!(matches!(
+        inequality_commitment_and_witnesses::<_, Bls12_381, _>(
+            &mut rng, &forbidden, &forbidden, &ineq_gens
+        ),
+        Err(ProofSystemError::SaverMessageEqualsForbiddenValue)
+    ));
+
+    // Encrypting the forbidden value while proving the inequality for another message fails
+    let other = forbidden + Fr::from(1u64);
+    let (commitment, [wit_1, wit_2]) =
+        inequality_commitment_and_witnesses(&mut rng, &other, &forbidden, &ineq_gens).unwrap();
+    let (prover_proof_spec, verifier_proof_spec) = proof_specs(commitment);
+    let mut witnesses = Witnesses::new();
+    witnesses.add(Witness::Saver(forbidden));
+    witnesses.add(wit_1);
+    witnesses.add(wit_2);
+    let proof = ProofG1::new::<StdRng, Blake2b512>(
+        &mut rng,
+        prover_proof_spec,
+        witnesses,
+        None,
+        Default::default(),
+    )
+    .unwrap()
+    .0;
+    assert!(proof
+        .verify::<StdRng, Blake2b512>(&mut rng, verifier_proof_spec, None, Default::default())
+        .is_err());
+}
+
+#[test]
+fn verify_many_proofs_with_prepared_proof_spec() {
+    // Verifying several proofs for the same `ProofSpec` with a `PreparedProofSpec` gives the same results as
+    // verifying each with the `ProofSpec`
+    let mut rng = StdRng::seed_from_u64(0u64);
+
+    let enc_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let chunk_bit_size = 16;
+    let chunked_comm_gens = ChunkedCommitmentGens::<G1Affine>::new_using_rng(&mut rng);
+    let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens).unwrap();
+
+    let mut prover_statements = Statements::new();
+    prover_statements.add(
+        SaverProverStmt::new_statement_from_params(
+            chunk_bit_size,
+            enc_gens.clone(),
+            chunked_comm_gens.clone(),
+            ek.clone(),
+            snark_pk.clone(),
+        )
+        .unwrap(),
+    );
+    let prover_proof_spec = ProofSpec::new(prover_statements, MetaStatements::new(), vec![], None);
+
+    let mut verifier_statements = Statements::new();
+    verifier_statements.add(
+        SaverVerifierStmt::new_statement_from_params(
+            chunk_bit_size,
+            enc_gens.clone(),
+            chunked_comm_gens.clone(),
+            ek.clone(),
+            snark_pk.pk.vk.clone(),
+        )
+        .unwrap(),
+    );
+    let verifier_proof_spec =
+        ProofSpec::new(verifier_statements, MetaStatements::new(), vec![], None);
+    let prepared = PreparedProofSpec::new(verifier_proof_spec.clone()).unwrap();
+    assert_eq!(prepared.spec(), &verifier_proof_spec);
+
+    let proofs = (0..3)
+        .map(|_| {
+            let mut witnesses = Witnesses::new();
+            witnesses.add(Witness::Saver(Fr::rand(&mut rng)));
+            ProofG1::new_with_spec_ref::<StdRng, Blake2b512>(
+                &mut rng,
+                &prover_proof_spec,
+                witnesses,
+                None,
+                Default::default(),
+            )
+            .unwrap()
+            .0
+        })
+        .collect::<Vec<_>>();
+    for proof in &proofs {
+        proof
+            .clone()
+            .verify_with_spec_ref::<StdRng, Blake2b512>(
+                &mut rng,
+                &verifier_proof_spec,
+                None,
+                Default::default(),
+            )
+            .unwrap();
+        proof
+            .clone()
+            .verify_with_prepared_spec::<StdRng, Blake2b512>(
+                &mut rng,
+                &prepared,
+                None,
+                Default::default(),
+            )
+            .unwrap();
+        proof
+            .clone()
+            .verify_with_prepared_spec::<StdRng, Blake2b512>(
+                &mut rng,
+                &prepared,
+                None,
+                VerifierConfig {
+                    use_lazy_randomized_pairing_checks: Some(true),
+                },
+            )
+            .unwrap();
+    }
+
+    // A proof created with a different nonce than the verifier's fails
+    assert!(proofs[0]
+        .clone()
+        .verify_with_prepared_spec::<StdRng, Blake2b512>(
+            &mut rng,
+            &prepared,
+            Some(b"some nonce".to_vec()),
+            Default::default(),
+        )
+        .is_err());
+
+    // An invalid `ProofSpec` can't be prepared
+    let mut meta_statements = MetaStatements::new();
+    meta_statements.add_witness_equality(EqualWitnesses(
+        [(0, 0)].into_iter().collect::<BTreeSet<_>>(),
+    ));
+    let invalid_proof_spec = ProofSpec::new(
+        prepared.into_spec().statements,
+        meta_statements,
+        vec![],
+        None,
+    );
+    assert!(matches!(
+        PreparedProofSpec::new(invalid_proof_spec),
+        Err(ProofSystemError::InvalidWitnessEquality)
+    ));
+}
+
+#[test]
+fn verifiably_encrypted_messages_equal_across_keys() {
+    // Prove that ciphertexts for 2 different decryptors encrypt the same message without revealing it
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let chunk_bit_size = 8;
+
+    let enc_gens_a = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let (snark_pk_a, _, ek_a, _) =
+        setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens_a).unwrap();
+    let pvk_a = saver_groth16::prepare_verifying_key(&snark_pk_a.pk.vk);
+    let enc_gens_b = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let (snark_pk_b, _, ek_b, _) =
+        setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens_b).unwrap();
+    let pvk_b = saver_groth16::prepare_verifying_key(&snark_pk_b.pk.vk);
+
+    let chunks_as_witness = |m: &Fr, r: Fr| {
+        let mut wit = decompose(m, chunk_bit_size)
+            .unwrap()
+            .into_iter()
+            .map(|c| Fr::from(c as u64))
+            .collect::<Vec<_>>();
+        wit.push(r);
+        Witness::PedersenCommitment(wit)
+    };
+
+    let prove_and_verify = |rng: &mut StdRng, m_a: Fr, m_b: Fr| {
+        let (ct_a, r_a, proof_a) =
+            Encryption::encrypt_with_proof(rng, &m_a, &ek_a, &snark_pk_a, chunk_bit_size).unwrap();
+        let (ct_b, r_b, proof_b) =
+            Encryption::encrypt_with_proof(rng, &m_b, &ek_b, &snark_pk_b, chunk_bit_size).unwrap();
+        ct_a.verify_commitment_and_proof(&proof_a, &pvk_a, ek_a.clone(), enc_gens_a.clone())
+            .unwrap();
+        ct_b.verify_commitment_and_proof(&proof_b, &pvk_b, ek_b.clone(), enc_gens_b.clone())
+            .unwrap();
+
+        let mut statements = Statements::new();
+        let mut meta_statements = MetaStatements::new();
+        let [idx_a, idx_b] = proof_statements_equal_across_keys(
+            &mut statements,
+            &mut meta_statements,
+            &ct_a,
+            &ek_a,
+            &ct_b,
+            &ek_b,
+            chunk_bit_size,
+        )
+        .unwrap();
+        assert_eq!((idx_a, idx_b), (0, 1));
+        let proof_spec = ProofSpec::new(statements, meta_statements, vec![], None);
+        proof_spec.validate().unwrap();
+
+        let mut witnesses = Witnesses::new();
+        witnesses.add(chunks_as_witness(&m_a, r_a));
+        witnesses.add(chunks_as_witness(&m_b, r_b));
+        let proof = ProofG1::new_with_spec_ref::<StdRng, Blake2b512>(
+            rng,
+            &proof_spec,
+            witnesses,
+            None,
+            Default::default(),
+        )
+        .unwrap()
+        .0;
+        proof.verify_with_spec_ref::<StdRng, Blake2b512>(rng, &proof_spec, None, Default::default())
+    };
+
+    let m = Fr::rand(&mut rng);
+    prove_and_verify(&mut rng, m, m).unwrap();
+    assert!(prove_and_verify(&mut rng, m, m + Fr::from(1u64)).is_err());
+
+    // Keys must be for the chunk bit size
+    let (ct_a, _) = Encryption::encrypt(
+        &mut rng,
+        &m,
+        &ek_a,
+        saver_groth16::get_gs_for_encryption(&snark_pk_a.pk.vk),
+        chunk_bit_size,
+    )
+    .unwrap();
+    assert!(proof_statements_equal_across_keys(
+        &mut Statements::<Bls12_381, G1Affine>::new(),
+        &mut MetaStatements::new(),
+        &ct_a,
+        &ek_a,
+        &ct_a,
+        &ek_a,
+        4,
+    )
+    .is_err());
+}
+
+#[test]
+fn encrypt_to_many_recipients() {
+    // Encrypt one message for 2 decryptors whose keys share the SNARK SRS
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let chunk_bit_size = 16;
+
+    let enc_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let (snark_pk, sk_a, ek_a, dk_a) =
+        setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens).unwrap();
+    let g_i = saver_groth16::get_gs_for_encryption(&snark_pk.pk.vk);
+    let (sk_b, ek_b, dk_b) = keygen(
+        &mut rng,
+        chunk_bit_size,
+        &enc_gens,
+        g_i,
+        &snark_pk.pk.delta_g1,
+        &snark_pk.gamma_g1,
+    )
+    .unwrap();
+    let eks = vec![ek_a, ek_b];
+
+    let m = Fr::rand(&mut rng);
+    let (cts, proof) =
+        encrypt_to_many::<_, _, Blake2b512>(&mut rng, &m, &eks, &snark_pk.pk.vk, chunk_bit_size)
+            .unwrap();
+    assert_eq!(cts.len(), 2);
+    assert_ne!(cts[0], cts[1]);
+
+    // Each decryptor decrypts the same message on its own
+    let (m_a, _) = cts[0].decrypt(&sk_a, dk_a, g_i, chunk_bit_size).unwrap();
+    let (m_b, _) = cts[1].decrypt(&sk_b, dk_b, g_i, chunk_bit_size).unwrap();
+    assert_eq!(m_a, m);
+    assert_eq!(m_b, m);
+
+    verify_encryption_to_many::<_, _, Blake2b512>(
+        &mut rng,
+        &cts,
+        &eks,
+        proof.clone(),
+        &enc_gens,
+        chunk_bit_size,
+    )
+    .unwrap();
+
+    // Replacing a ciphertext with the encryption of another message fails
+    let (other_ct, _) = Encryption::encrypt(
+        &mut rng,
+        &(m + Fr::from(1u64)),
+        &eks[1],
+        g_i,
+        chunk_bit_size,
+    )
+    .unwrap();
+    assert!(verify_encryption_to_many::<_, _, Blake2b512>(
+        &mut rng,
+        &[cts[0].clone(), other_ct],
+        &eks,
+        proof.clone(),
+        &enc_gens,
+        chunk_bit_size,
+    )
+    .is_err());
+    // Ciphertexts given in the wrong order
+    assert!(verify_encryption_to_many::<_, _, Blake2b512>(
+        &mut rng,
+        &[cts[1].clone(), cts[0].clone()],
+        &eks,
+        proof.clone(),
+        &enc_gens,
+        chunk_bit_size,
+    )
+    .is_err());
+    // Invalid chunk bit size is an error
+    assert!(verify_encryption_to_many::<_, _, Blake2b512>(
+        &mut rng, &cts, &eks, proof, &enc_gens, 0,
+    )
+    .is_err());
+}
+
+#[test]
+fn verifiably_encrypted_message_in_chunk_aligned_range() {
+    // Prove that the message of a verified ciphertext is in a range by fixing its top chunks
+    let mut rng = StdRng::seed_from_u64(0u64);
+    let chunk_bit_size = 8;
+
+    let enc_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let (snark_pk, sk, ek, dk) = setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens).unwrap();
+    let pvk = saver_groth16::prepare_verifying_key(&snark_pk.pk.vk);
+
+    let prove_and_verify = |rng: &mut StdRng, m: u64, a: u64, b: u64| {
+        let m = Fr::from(m);
+        let (ct, r, proof) =
+            Encryption::encrypt_with_proof(rng, &m, &ek, &snark_pk, chunk_bit_size).unwrap();
+        ct.verify_commitment_and_proof(&proof, &pvk, ek.clone(), enc_gens.clone())
+            .unwrap();
+
+        let statements = range_proof_statements(ct.commitment, &ek, a, b, chunk_bit_size)?;
+        let proof_spec = ProofSpec::new(statements, MetaStatements::new(), vec![], None);
+        proof_spec.validate().unwrap();
+        let witnesses = range_proof_witnesses::<Bls12_381>(&m, r, a, b, chunk_bit_size)?;
+        let proof = ProofG1::new_with_spec_ref::<StdRng, Blake2b512>(
+            rng,
+            &proof_spec,
+            witnesses,
+            None,
+            Default::default(),
+        )
+        .unwrap()
+        .0;
+        proof.verify_with_spec_ref::<StdRng, Blake2b512>(
+            rng,
+            &proof_spec,
+            None,
+            Default::default(),
+        )?;
+
+        let (decrypted, _) = ct
+            .decrypt_given_groth16_vk(&sk, dk.clone(), &snark_pk.pk.vk, chunk_bit_size)
+            .unwrap();
+        assert_eq!(decrypted, m);
+        Ok::<_, ProofSystemError>(())
+    };
+
+    // Messages of 2 chunks, and of 1 chunk with the next chunk being 3
+    for m in [0, 1000, (1 << 16) - 1] {
+        prove_and_verify(&mut rng, m, 0, (1 << 16) - 1).unwrap();
+    }
+    for m in [3 << 8, (3 << 8) + 100, (4 << 8) - 1] {
+        prove_and_verify(&mut rng, m, 3 << 8, (4 << 8) - 1).unwrap();
+    }
+    // Single value and the whole u64 range
+    prove_and_verify(&mut rng, 42, 42, 42).unwrap();
+    prove_and_verify(&mut rng, u64::MAX, 0, u64::MAX).unwrap();
+
+    // Message out of range can't be proven
+    for m in [1 << 16, u64::MAX] {
+        assert!(matches!(
+            prove_and_verify(&mut rng, m, 0, (1 << 16) - 1),
+            Err(ProofSystemError::SaverMessageOutOfRange)
+        ));
+    }
+    assert!(matches!(
+        prove_and_verify(&mut rng, (4 << 8) + 1, 3 << 8, (4 << 8) - 1),
+        Err(ProofSystemError::SaverMessageOutOfRange)
+    ));
+
+    // A witness with the out of range message's chunks doesn't verify
+    let m = Fr::from((4u64 << 8) + 1);
+    let (ct, r) = Encryption::encrypt(
+        &mut rng,
+        &m,
+        &ek,
+        saver_groth16::get_gs_for_encryption(&snark_pk.pk.vk),
+        chunk_bit_size,
+    )
+    .unwrap();
+    let statements =
+        range_proof_statements(ct.commitment, &ek, 3 << 8, (4 << 8) - 1, chunk_bit_size).unwrap();
+    let proof_spec = ProofSpec::new(statements, MetaStatements::new(), vec![], None);
+    let low_chunk = *decompose(&m, chunk_bit_size).unwrap().last().unwrap();
+    let mut witnesses = Witnesses::new();
+    witnesses.add(Witness::PedersenCommitment(vec![
+        Fr::from(low_chunk as u64),
+        r,
+    ]));
+    let proof = ProofG1::new_with_spec_ref::<StdRng, Blake2b512>(
+        &mut rng,
+        &proof_spec,
+        witnesses,
+        None,
+        Default::default(),
+    )
+    .unwrap()
+    .0;
+    assert!(proof
+        .verify_with_spec_ref::<StdRng, Blake2b512>(&mut rng, &proof_spec, None, Default::default())
+        .is_err());
+
+    // Ranges not aligned to the chunks are rejected
+    for (a, b) in [(0, 1000), (1, 1 << 8), (100, 50)] {
+        assert!(matches!(
+            range_proof_statements(ct.commitment, &ek, a, b, chunk_bit_size),
+            Err(ProofSystemError::SaverRangeNotAlignedToChunks(..))
+        ));
+    }
+    // Key must be for the chunk bit size
+    assert!(range_proof_statements(ct.commitment, &ek, 0, 15, 4).is_err());
+}
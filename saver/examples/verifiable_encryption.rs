@@ -0,0 +1,78 @@
+//! Verifiable encryption of a random message with SAVER over BLS12-381 using Groth16.
+//!
+//! The encryptor encrypts the message and proves that the ciphertext is correct, the verifier checks the
+//! proof without learning the message and the decryptor decrypts and proves that the decryption is
+//! correct. The chunk bit size can be given as the first argument, 8 by default, eg.
+//!
+//! `cargo run --release --example verifiable_encryption -- 8`
+
+use ark_bls12_381::Bls12_381;
+use ark_ec::pairing::Pairing;
+use ark_std::{
+    rand::{rngs::StdRng, SeedableRng},
+    UniformRand,
+};
+use saver::{
+    encryption::Encryption,
+    saver_groth16,
+    setup::{setup_for_groth16, EncryptionGens},
+};
+use std::{env, process, time::Instant};
+
+type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+fn main() {
+    let chunk_bit_size = match env::args().nth(1) {
+        Some(arg) => match arg.parse::<u8>() {
+            Ok(b) if matches!(b, 1 | 2 | 4 | 8 | 16) => b,
+            _ => {
+                eprintln!(
+                    "chunk bit size should be one of 1, 2, 4, 8 or 16 but was {}",
+                    arg
+                );
+                process::exit(1);
+            }
+        },
+        None => 8,
+    };
+    println!("Using chunks of {} bits", chunk_bit_size);
+
+    let mut rng = StdRng::from_entropy();
+
+    // Done once by the decryptor. The encryption key and SNARK keys are public while the secret key and
+    // decryption key are kept by the decryptor.
+    let start = Instant::now();
+    let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let (snark_pk, sk, ek, dk) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+    let snark_vk = &snark_pk.pk.vk;
+    let pvk = saver_groth16::prepare_verifying_key(snark_vk);
+    println!("Keygen and SNARK setup: {:?}", start.elapsed());
+
+    // The encryptor encrypts and proves that each chunk of the message is of `chunk_bit_size` bits
+    let message = Fr::rand(&mut rng);
+    let start = Instant::now();
+    let (ct, _, proof) =
+        Encryption::encrypt_with_proof(&mut rng, &message, &ek, &snark_pk, chunk_bit_size).unwrap();
+    println!("Encryption with proof: {:?}", start.elapsed());
+
+    // The verifier checks the ciphertext without being able to decrypt it
+    let start = Instant::now();
+    ct.verify_commitment_and_proof(&proof, &pvk, ek.clone(), gens.clone())
+        .unwrap();
+    println!("Verifying ciphertext and proof: {:?}", start.elapsed());
+
+    // The decryptor decrypts and creates `nu` which lets anyone verify the decryption
+    let start = Instant::now();
+    let (decrypted, nu) = ct
+        .decrypt_given_groth16_vk(&sk, dk.clone(), snark_vk, chunk_bit_size)
+        .unwrap();
+    println!("Decryption: {:?}", start.elapsed());
+    assert_eq!(decrypted, message);
+
+    let start = Instant::now();
+    ct.verify_decryption_given_groth16_vk(&decrypted, &nu, chunk_bit_size, dk, snark_vk, gens)
+        .unwrap();
+    println!("Verifying decryption: {:?}", start.elapsed());
+
+    println!("Decrypted the encrypted message");
+}
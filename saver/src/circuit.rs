@@ -1,5 +1,5 @@
-use crate::utils::chunks_count;
-use ark_ff::PrimeField;
+use crate::{error::SaverError, utils::chunks_count};
+use ark_ff::{BigInteger, PrimeField};
 use ark_r1cs_std::{
     fields::fp::FpVar,
     prelude::{AllocVar, AllocationMode, Boolean, EqGadget},
@@ -8,23 +8,152 @@ use ark_r1cs_std::{
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
 use ark_std::vec::Vec;
 
-/// Circuit to check that each of `values` has bit size at most `required_bit_size`
+/// Constrains a variable to have at most a given number of bits. `BitsizeCheckCircuit` uses it for each of
+/// its values so a different way of range checking can be used without changing the circuit.
+pub trait RangeCheckGadget<F: PrimeField> {
+    fn enforce_bit_size(&self, v: &FpVar<F>, bit_size: u8) -> Result<(), SynthesisError>;
+}
+
+/// Range check by decomposing the variable into bits and ensuring that the bits beyond `bit_size` are 0.
+/// This is the default gadget of `BitsizeCheckCircuit`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BitDecompositionRangeCheck;
+
+impl<F: PrimeField> RangeCheckGadget<F> for BitDecompositionRangeCheck {
+    fn enforce_bit_size(&self, v: &FpVar<F>, bit_size: u8) -> Result<(), SynthesisError> {
+        // Little endian to keep the least significant bits in the beginning. `to_non_unique_bits_le` is fine
+        // as except only a small number of least significant bits are to be ensured a 0
+        let bits = v.to_non_unique_bits_le()?;
+        // If all bits beyond `bit_size` are 0, then their OR must be 0 as well.
+        let mut or_result = Boolean::constant(false);
+        for b in bits[bit_size as usize..].iter() {
+            or_result = or_result.or(b)?;
+        }
+        or_result.enforce_equal(&Boolean::constant(false))
+    }
+}
+
+/// Circuit to check that each of `values` has bit size at most `required_bit_size`. The check is done by
+/// the `RangeCheckGadget` `G`. There is no `Default` as no `required_bit_size` is right for every use: it
+/// must be the chunk bit size the encryption and SNARK keys are created for, so it is always given to
+/// `Self::new` or `Self::builder`.
 #[derive(Clone)]
-pub struct BitsizeCheckCircuit<F: PrimeField> {
+pub struct BitsizeCheckCircuit<F: PrimeField, G: RangeCheckGadget<F> = BitDecompositionRangeCheck> {
     pub required_bit_size: u8,
     pub num_values: u8,
     pub values: Option<Vec<F>>,
     /// Allocate the value as public input or private, used to switch between Groth16 and LegoGroth16.
     /// For Groth16, its true, for LegoGroth16, its false
     pub alloc_as_public: bool,
+    pub range_check: G,
 }
 
 impl<F: PrimeField> BitsizeCheckCircuit<F> {
+    /// Circuit checking that `num_values` values have at most `required_bit_size` bits, using
+    /// `BitDecompositionRangeCheck`. `BitsizeCheckCircuit::builder` is clearer when setting the arguments.
+    /// - `num_values` as `None` means the number of chunks of a field element, i.e. `chunks_count` for
+    ///   `required_bit_size`.
+    /// - `values` are `None` when creating the SNARK keys and the chunks of the message when proving.
+    /// - `alloc_as_public` is true for Groth16 where the chunks are public inputs and false for LegoGroth16
+    ///   where they are committed witnesses.
     pub fn new(
         required_bit_size: u8,
         num_values: Option<u8>,
         values: Option<Vec<F>>,
         alloc_as_public: bool,
+    ) -> Self {
+        Self::new_with_range_check(
+            required_bit_size,
+            num_values,
+            values,
+            alloc_as_public,
+            BitDecompositionRangeCheck,
+        )
+    }
+
+    /// Builder for a circuit checking values of at most `required_bit_size` bits. Without further calls, it
+    /// builds the circuit for creating Groth16 keys, with as many values as chunks of a field element, no
+    /// values and public inputs.
+    pub fn builder(required_bit_size: u8) -> BitsizeCheckCircuitBuilder<F> {
+        BitsizeCheckCircuitBuilder {
+            required_bit_size,
+            num_values: None,
+            values: None,
+            alloc_as_public: true,
+            range_check: BitDecompositionRangeCheck,
+        }
+    }
+}
+
+/// Builder for `BitsizeCheckCircuit`, created with `BitsizeCheckCircuit::builder`. Like the circuit, it has
+/// no `Default` as the required bit size must always be given.
+#[derive(Clone)]
+pub struct BitsizeCheckCircuitBuilder<
+    F: PrimeField,
+    G: RangeCheckGadget<F> = BitDecompositionRangeCheck,
+> {
+    required_bit_size: u8,
+    num_values: Option<u8>,
+    values: Option<Vec<F>>,
+    alloc_as_public: bool,
+    range_check: G,
+}
+
+impl<F: PrimeField, G: RangeCheckGadget<F>> BitsizeCheckCircuitBuilder<F, G> {
+    /// Number of values to check, when not the number of chunks of a field element
+    pub fn num_values(mut self, num_values: u8) -> Self {
+        self.num_values = Some(num_values);
+        self
+    }
+
+    /// Values to check, i.e. the chunks of the message, needed when proving
+    pub fn with_values(mut self, values: Vec<F>) -> Self {
+        self.values = Some(values);
+        self
+    }
+
+    /// Whether the values are public inputs, as for Groth16, or private, as for LegoGroth16
+    pub fn public_inputs(mut self, alloc_as_public: bool) -> Self {
+        self.alloc_as_public = alloc_as_public;
+        self
+    }
+
+    /// Use the given gadget for range checks instead of `BitDecompositionRangeCheck`
+    pub fn range_check<G2: RangeCheckGadget<F>>(
+        self,
+        range_check: G2,
+    ) -> BitsizeCheckCircuitBuilder<F, G2> {
+        BitsizeCheckCircuitBuilder {
+            required_bit_size: self.required_bit_size,
+            num_values: self.num_values,
+            values: self.values,
+            alloc_as_public: self.alloc_as_public,
+            range_check,
+        }
+    }
+
+    /// Create the circuit. When the number of values isn't set, it's the number of chunks of a field element
+    /// for the required bit size.
+    pub fn build(self) -> BitsizeCheckCircuit<F, G> {
+        BitsizeCheckCircuit::new_with_range_check(
+            self.required_bit_size,
+            self.num_values,
+            self.values,
+            self.alloc_as_public,
+            self.range_check,
+        )
+    }
+}
+
+impl<F: PrimeField, G: RangeCheckGadget<F>> BitsizeCheckCircuit<F, G> {
+    /// Same as `Self::new` but uses the given gadget for range checks. The proving key must be created with
+    /// the same gadget as the proof.
+    pub fn new_with_range_check(
+        required_bit_size: u8,
+        num_values: Option<u8>,
+        values: Option<Vec<F>>,
+        alloc_as_public: bool,
+        range_check: G,
     ) -> Self {
         let num_values = if num_values.is_some() {
             num_values.unwrap()
@@ -36,13 +165,34 @@ impl<F: PrimeField> BitsizeCheckCircuit<F> {
             num_values,
             values,
             alloc_as_public,
+            range_check,
+        }
+    }
+
+    /// Check that each of `values`, if present, has bit size at most `required_bit_size` and return the
+    /// first one that doesn't. Synthesizing the circuit with such a value would only fail later during proving
+    /// or verification without indicating the offending value. The reported value saturates at `u64::MAX`.
+    pub fn check_values(&self) -> crate::Result<()> {
+        if let Some(values) = &self.values {
+            let max = 1u64
+                .checked_shl(self.required_bit_size as u32)
+                .map_or(u64::MAX, |v| v - 1);
+            for (index, v) in values.iter().enumerate() {
+                let v = v.into_bigint();
+                let bits = v.num_bits();
+                if bits > self.required_bit_size as u32 {
+                    let value = if bits <= 64 { v.as_ref()[0] } else { u64::MAX };
+                    return Err(SaverError::ChunkOutOfRange { index, value, max });
+                }
+            }
         }
+        Ok(())
     }
 }
 
 /// Number of constraints is 7920
-impl<ConstraintF: PrimeField> ConstraintSynthesizer<ConstraintF>
-    for BitsizeCheckCircuit<ConstraintF>
+impl<ConstraintF: PrimeField, G: RangeCheckGadget<ConstraintF>> ConstraintSynthesizer<ConstraintF>
+    for BitsizeCheckCircuit<ConstraintF, G>
 {
     fn generate_constraints(
         self,
@@ -69,17 +219,105 @@ impl<ConstraintF: PrimeField> ConstraintSynthesizer<ConstraintF>
 
         // For each variable, ensure that only last `self.required_bit_size` _may_ be set, rest *must* be unset
         for v in vars {
-            // Little endian to keep the least significant bits in the beginning. `to_non_unique_bits_le` is fine
-            // as except only a small number of least significant bits are to be ensured a 0
-            let bits = v.to_non_unique_bits_le()?;
-            // If all bits beyond `self.required_bit_size` are 0, then their OR must be 0 as well.
-            let mut or_result = Boolean::constant(false);
-            for b in bits[self.required_bit_size as usize..].iter() {
-                or_result = or_result.or(b)?;
-            }
-            or_result.enforce_equal(&Boolean::constant(false))?;
+            self.range_check
+                .enforce_bit_size(&v, self.required_bit_size)?;
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_381::Bls12_381;
+    use ark_ec::pairing::Pairing;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+    fn assert_same(a: &BitsizeCheckCircuit<Fr>, b: &BitsizeCheckCircuit<Fr>) {
+        assert_eq!(a.required_bit_size, b.required_bit_size);
+        assert_eq!(a.num_values, b.num_values);
+        assert_eq!(a.values, b.values);
+        assert_eq!(a.alloc_as_public, b.alloc_as_public);
+        assert_eq!(a.range_check, b.range_check);
+    }
+
+    #[test]
+    fn builder_matches_positional_constructor() {
+        let chunk_bit_size = 8;
+        let values = (0..chunks_count::<Fr>(chunk_bit_size))
+            .map(|i| Fr::from(i as u64 * 7))
+            .collect::<Vec<_>>();
+
+        // For creating keys
+        assert_same(
+            &BitsizeCheckCircuit::builder(chunk_bit_size).build(),
+            &BitsizeCheckCircuit::new(chunk_bit_size, None, None, true),
+        );
+        // For proving with Groth16
+        let circuit = BitsizeCheckCircuit::builder(chunk_bit_size)
+            .with_values(values.clone())
+            .public_inputs(true)
+            .build();
+        assert_same(
+            &circuit,
+            &BitsizeCheckCircuit::new(chunk_bit_size, None, Some(values.clone()), true),
+        );
+        // For proving with LegoGroth16 with a given number of values
+        assert_same(
+            &BitsizeCheckCircuit::builder(chunk_bit_size)
+                .num_values(4)
+                .with_values(values[..4].to_vec())
+                .public_inputs(false)
+                .build(),
+            &BitsizeCheckCircuit::new(chunk_bit_size, Some(4), Some(values[..4].to_vec()), false),
+        );
+        // Range check gadget can be set
+        let with_gadget = BitsizeCheckCircuit::<Fr>::builder(chunk_bit_size)
+            .range_check(BitDecompositionRangeCheck)
+            .build();
+        assert_eq!(with_gadget.num_values, chunks_count::<Fr>(chunk_bit_size));
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        // The constant 1 and a public input for each value
+        assert_eq!(cs.num_instance_variables(), values.len() + 1);
+    }
+
+    #[test]
+    fn check_values_reports_first_oversized_value() {
+        let circuit = BitsizeCheckCircuit::new(
+            8,
+            Some(3),
+            Some(vec![Fr::from(255u64), Fr::from(256u64), Fr::from(1000u64)]),
+            true,
+        );
+        assert!(matches!(
+            circuit.check_values(),
+            Err(SaverError::ChunkOutOfRange {
+                index: 1,
+                value: 256,
+                max: 255
+            })
+        ));
+        assert!(BitsizeCheckCircuit::<Fr>::new(8, Some(3), None, true)
+            .check_values()
+            .is_ok());
+
+        // Max saturates rather than overflowing for bit sizes of 64 and more
+        let circuit = BitsizeCheckCircuit::new(64, Some(1), Some(vec![Fr::from(u64::MAX)]), true);
+        assert!(circuit.check_values().is_ok());
+        let circuit = BitsizeCheckCircuit::new(64, Some(1), Some(vec![Fr::from(u128::MAX)]), true);
+        assert!(matches!(
+            circuit.check_values(),
+            Err(SaverError::ChunkOutOfRange {
+                index: 0,
+                value: u64::MAX,
+                max: u64::MAX
+            })
+        ));
+    }
+}
@@ -1,9 +1,10 @@
 use crate::{
+    error::SaverError,
     setup::ChunkedCommitmentGens,
-    utils::{chunks_count, decompose},
+    utils::{check_chunk_bit_size, chunks_count, decompose, CHUNK_TYPE},
 };
 use ark_ec::{AffineRepr, CurveGroup, Group, VariableBaseMSM};
-use ark_ff::{Field, One, PrimeField};
+use ark_ff::{Field, One, PrimeField, Zero};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::{vec, vec::Vec};
 use serde::{Deserialize, Serialize};
@@ -61,14 +62,27 @@ impl<G: AffineRepr> ChunkedCommitment<G> {
         gens: &ChunkedCommitmentGens<G>,
     ) -> crate::Result<Self> {
         let decomposed = Self::get_values_to_commit(message, blinding, chunk_bit_size)?;
-        let gs = Self::commitment_key(gens, chunk_bit_size);
+        let gs = Self::bases(gens, chunk_bit_size)?;
         Ok(Self(
             G::Group::msm_bigint(&gs, &decomposed).into_affine(),
             gs,
         ))
     }
 
+    /// Same as `Self::new` with a blinding of 0, so the commitment is `m * G` and the same message always
+    /// gives the same commitment. Meant for messages that are public but still encrypted, eg. for
+    /// homomorphically adding the ciphertexts. The commitment is NOT hiding: anyone can check a guess of the
+    /// message against it, so it must not be used for a secret message.
+    pub fn new_non_hiding(
+        message: &G::ScalarField,
+        chunk_bit_size: u8,
+        gens: &ChunkedCommitmentGens<G>,
+    ) -> crate::Result<Self> {
+        Self::new(message, &G::ScalarField::zero(), chunk_bit_size, gens)
+    }
+
     /// Similar to `Self::new` but expects the commitment key to be created already. Returns the commitment.
+    /// The commitment key must have been created for `chunk_bit_size`.
     pub fn get_commitment_given_commitment_key(
         message: &G::ScalarField,
         blinding: &G::ScalarField,
@@ -76,12 +90,23 @@ impl<G: AffineRepr> ChunkedCommitment<G> {
         comm_key: &[G],
     ) -> crate::Result<G> {
         let decomposed = Self::get_values_to_commit(message, blinding, chunk_bit_size)?;
+        if comm_key.len() != decomposed.len() {
+            return Err(SaverError::IncompatibleCommitmentBases(
+                comm_key.len(),
+                decomposed.len(),
+            ));
+        }
         Ok(G::Group::msm_bigint(comm_key, &decomposed).into_affine())
     }
 
-    /// Commitment key (vector of all `g`s and `h`) for the chunked commitment
-    /// Given a group element `g`, create `chunks_count` multiples of `g` as `g_n, g_{n-1}, ..., g_2, g_1` where each `g_i = {radix^i} * g` and `radix = 2^chunk_bit_ize`
-    pub fn commitment_key(gens: &ChunkedCommitmentGens<G>, chunk_bit_size: u8) -> Vec<G> {
+    /// Bases of the chunked commitment, in the order that its witness (`utils::chunked_commitment_witness`)
+    /// must follow: a base for each chunk of the big-endian decomposition (`utils::decompose`) followed by
+    /// `gens.H` for the blinding, i.e. `[radix^{n-1} * G, ..., radix * G, G, H]` where `radix = 2^chunk_bit_size`
+    /// and `n` is the number of chunks. Provers and verifiers should take the bases from here rather than
+    /// building them so that they agree on the order.
+    /// Returns an error if the chunk bit size isn't supported.
+    pub fn bases(gens: &ChunkedCommitmentGens<G>, chunk_bit_size: u8) -> crate::Result<Vec<G>> {
+        check_chunk_bit_size(chunk_bit_size)?;
         let radix = (1 << chunk_bit_size) as u64;
         let chunks = chunks_count::<G::ScalarField>(chunk_bit_size);
         let gs = if radix.is_power_of_two() {
@@ -91,7 +116,58 @@ impl<G: AffineRepr> ChunkedCommitment<G> {
         };
         let mut ck = G::Group::normalize_batch(&gs);
         ck.push(gens.H);
-        ck
+        Ok(ck)
+    }
+
+    /// Commitment key (vector of all `g`s and `h`) for the chunked commitment. Same as `Self::bases`
+    pub fn commitment_key(
+        gens: &ChunkedCommitmentGens<G>,
+        chunk_bit_size: u8,
+    ) -> crate::Result<Vec<G>> {
+        Self::bases(gens, chunk_bit_size)
+    }
+
+    /// Check that `commitment` opens to the chunks `decomposed`, in the order of `utils::decompose`, and
+    /// `blinding`, using the same bases as `Self::commitment_key`. Returns `SaverError::ChunkOutOfRange` if a
+    /// chunk doesn't fit in `chunk_bit_size` bits, as the opening would then not be to a decomposition of a
+    /// message, and `SaverError::InvalidOpening` if the commitment doesn't match.
+    #[must_use = "a failed verification is only reported through the returned value"]
+    pub fn verify_opening(
+        commitment: &G,
+        decomposed: &[CHUNK_TYPE],
+        blinding: &G::ScalarField,
+        chunk_bit_size: u8,
+        gens: &ChunkedCommitmentGens<G>,
+    ) -> crate::Result<()> {
+        let bases = Self::bases(gens, chunk_bit_size)?;
+        if bases.len() != decomposed.len() + 1 {
+            return Err(SaverError::IncompatibleCommitmentBases(
+                bases.len(),
+                decomposed.len() + 1,
+            ));
+        }
+        let max = (1u64 << chunk_bit_size) - 1;
+        if let Some((index, value)) = decomposed
+            .iter()
+            .enumerate()
+            .find(|(_, c)| **c as u64 > max)
+        {
+            return Err(SaverError::ChunkOutOfRange {
+                index,
+                value: *value as u64,
+                max,
+            });
+        }
+        let mut values = decomposed
+            .iter()
+            .map(|c| <G::ScalarField as PrimeField>::BigInt::from(*c))
+            .collect::<Vec<_>>();
+        values.push(blinding.into_bigint());
+        if G::Group::msm_bigint(&bases, &values).into_affine() == *commitment {
+            Ok(())
+        } else {
+            Err(SaverError::InvalidOpening)
+        }
     }
 
     fn get_values_to_commit(
@@ -101,7 +177,7 @@ impl<G: AffineRepr> ChunkedCommitment<G> {
     ) -> crate::Result<Vec<<G::ScalarField as PrimeField>::BigInt>> {
         let mut decomposed = decompose(message, chunk_bit_size)?
             .into_iter()
-            .map(|m| <G::ScalarField as PrimeField>::BigInt::from(m as u64))
+            .map(<G::ScalarField as PrimeField>::BigInt::from)
             .collect::<Vec<_>>();
         decomposed.push(blinding.into_bigint());
         Ok(decomposed)
@@ -201,6 +277,183 @@ mod tests {
         check(16);
     }
 
+    #[test]
+    fn bases_order() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let gens = ChunkedCommitmentGens::<G1Affine>::new_using_rng(&mut rng);
+        for chunk_bit_size in [4, 8, 16] {
+            let n = chunks_count::<Fr>(chunk_bit_size) as usize;
+            let radix = Fr::from(1u64 << chunk_bit_size);
+            // Built by hand as `[radix^{n-1} * G, ..., radix * G, G, H]`
+            let mut expected = vec![];
+            let mut g = gens.G.into_group();
+            for _ in 0..n {
+                expected.push(g.into_affine());
+                g *= radix;
+            }
+            expected.reverse();
+            expected.push(gens.H);
+
+            let bases = ChunkedCommitment::<G1Affine>::bases(&gens, chunk_bit_size).unwrap();
+            assert_eq!(bases, expected);
+            assert_eq!(
+                ChunkedCommitment::<G1Affine>::commitment_key(&gens, chunk_bit_size).unwrap(),
+                bases
+            );
+
+            // Committing with the bases and the witness gives the chunked commitment
+            let m = Fr::rand(&mut rng);
+            let blinding = Fr::rand(&mut rng);
+            let decomposed = decompose(&m, chunk_bit_size)
+                .unwrap()
+                .into_iter()
+                .map(Fr::from)
+                .collect::<Vec<_>>();
+            let witness = crate::utils::chunked_commitment_witness(&decomposed, blinding);
+            assert_eq!(
+                <Bls12_381 as Pairing>::G1::msm_unchecked(&bases, &witness).into_affine(),
+                ChunkedCommitment::<G1Affine>::new(&m, &blinding, chunk_bit_size, &gens)
+                    .unwrap()
+                    .0
+            );
+        }
+    }
+
+    #[test]
+    fn non_hiding_commitment() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let gens = ChunkedCommitmentGens::<G1Affine>::new_using_rng(&mut rng);
+        let m = Fr::rand(&mut rng);
+        for chunk_bit_size in [4, 8, 16] {
+            let comm =
+                ChunkedCommitment::<G1Affine>::new_non_hiding(&m, chunk_bit_size, &gens).unwrap();
+            // Reproducible and the same as a commitment with 0 blinding, i.e. `m * G`
+            assert_eq!(
+                ChunkedCommitment::<G1Affine>::new_non_hiding(&m, chunk_bit_size, &gens).unwrap(),
+                comm
+            );
+            assert_eq!(
+                ChunkedCommitment::<G1Affine>::new(&m, &Fr::zero(), chunk_bit_size, &gens).unwrap(),
+                comm
+            );
+            assert_eq!(comm.0, (gens.G * m).into_affine());
+            assert_ne!(
+                ChunkedCommitment::<G1Affine>::new_non_hiding(
+                    &(m + Fr::one()),
+                    chunk_bit_size,
+                    &gens
+                )
+                .unwrap()
+                .0,
+                comm.0
+            );
+        }
+    }
+
+    #[test]
+    fn opening_verification() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let gens = ChunkedCommitmentGens::<G1Affine>::new_using_rng(&mut rng);
+        let m = Fr::rand(&mut rng);
+        let blinding = Fr::rand(&mut rng);
+        for chunk_bit_size in [4, 8, 16] {
+            let comm = ChunkedCommitment::<G1Affine>::new(&m, &blinding, chunk_bit_size, &gens)
+                .unwrap()
+                .0;
+            let decomposed = decompose(&m, chunk_bit_size).unwrap();
+            ChunkedCommitment::verify_opening(&comm, &decomposed, &blinding, chunk_bit_size, &gens)
+                .unwrap();
+
+            assert!(matches!(
+                ChunkedCommitment::verify_opening(
+                    &comm,
+                    &decomposed,
+                    &(blinding + Fr::one()),
+                    chunk_bit_size,
+                    &gens,
+                ),
+                Err(SaverError::InvalidOpening)
+            ));
+            let mut wrong = decomposed.clone();
+            wrong[0] ^= 1;
+            assert!(matches!(
+                ChunkedCommitment::verify_opening(&comm, &wrong, &blinding, chunk_bit_size, &gens),
+                Err(SaverError::InvalidOpening)
+            ));
+            assert!(matches!(
+                ChunkedCommitment::verify_opening(
+                    &comm,
+                    &decomposed[1..],
+                    &blinding,
+                    chunk_bit_size,
+                    &gens,
+                ),
+                Err(SaverError::IncompatibleCommitmentBases(_, _))
+            ));
+        }
+
+        // Carrying into the next chunk gives the same commitment but isn't a decomposition
+        let chunk_bit_size = 4;
+        let comm = ChunkedCommitment::<G1Affine>::new(&m, &blinding, chunk_bit_size, &gens)
+            .unwrap()
+            .0;
+        let mut decomposed = decompose(&m, chunk_bit_size).unwrap();
+        let n = decomposed.len();
+        let i = (0..n - 1).rev().find(|i| decomposed[*i] > 0).unwrap();
+        decomposed[i] -= 1;
+        decomposed[i + 1] += 16;
+        assert!(matches!(
+            ChunkedCommitment::verify_opening(&comm, &decomposed, &blinding, chunk_bit_size, &gens),
+            Err(SaverError::ChunkOutOfRange { index, max: 15, .. }) if index == i + 1
+        ));
+    }
+
+    #[test]
+    fn inconsistent_commitment_params() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let gens = ChunkedCommitmentGens::<G1Affine>::new_using_rng(&mut rng);
+        let m = Fr::rand(&mut rng);
+        let blinding = Fr::rand(&mut rng);
+
+        for chunk_bit_size in [0, 3, 5, 32, 64, 255] {
+            assert!(matches!(
+                ChunkedCommitment::<G1Affine>::commitment_key(&gens, chunk_bit_size),
+                Err(SaverError::UnexpectedBase(b)) if b == chunk_bit_size
+            ));
+            assert!(matches!(
+                ChunkedCommitment::<G1Affine>::new(&m, &blinding, chunk_bit_size, &gens),
+                Err(SaverError::UnexpectedBase(b)) if b == chunk_bit_size
+            ));
+        }
+
+        // Commitment key created for a different chunk bit size has a different number of bases
+        let comm_key = ChunkedCommitment::<G1Affine>::commitment_key(&gens, 8).unwrap();
+        assert_eq!(
+            ChunkedCommitment::<G1Affine>::get_commitment_given_commitment_key(
+                &m, &blinding, 8, &comm_key
+            )
+            .unwrap(),
+            ChunkedCommitment::<G1Affine>::new(&m, &blinding, 8, &gens)
+                .unwrap()
+                .0
+        );
+        assert!(matches!(
+            ChunkedCommitment::<G1Affine>::get_commitment_given_commitment_key(
+                &m, &blinding, 4, &comm_key
+            ),
+            Err(SaverError::IncompatibleCommitmentBases(l, e)) if l == comm_key.len() && e == 65
+        ));
+        assert!(matches!(
+            ChunkedCommitment::<G1Affine>::get_commitment_given_commitment_key(
+                &m,
+                &blinding,
+                8,
+                &comm_key[1..]
+            ),
+            Err(SaverError::IncompatibleCommitmentBases(_, _))
+        ));
+    }
+
     #[test]
     fn commitment_transform_works() {
         fn check(chunk_bit_size: u8) {
@@ -240,12 +493,13 @@ mod tests {
                 let mut decomposed = decompose(&m, chunk_bit_size)
                     .unwrap()
                     .into_iter()
-                    .map(|m| Fr::from(m as u64))
+                    .map(Fr::from)
                     .collect::<Vec<_>>();
                 let gs = ChunkedCommitment::<<Bls12_381 as Pairing>::G1Affine>::commitment_key(
                     &gens,
                     chunk_bit_size,
-                );
+                )
+                .unwrap();
                 decomposed.push(blinding);
 
                 assert_eq!(gs.len(), decomposed.len());
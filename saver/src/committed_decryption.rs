@@ -0,0 +1,395 @@
+//! Proving correct decryption to a committed message.
+//!
+//! `Encryption::verify_decryption` needs the decrypted message. When the decryptor should not reveal it, it
+//! instead publishes `nu`, a chunked commitment `J = m_1*G_1 + m_2*G_2 + ... + m_n*G_n + r*H` to the message
+//! (see [`ChunkedCommitment`]) and a [`CommittedDecryptionProof`]. Decryption is correct when
+//!
+//! ```text
+//! e(c_0, V_0) = e(nu, H) and e(g_i, V_2_i)^{m_i} = e(c_i, V_2_i) * e(-nu, V_1_i) for each chunk i
+//! ```
+//!
+//! so the proof is a Schnorr protocol proving knowledge of `m_1, .., m_n, r` such that `J` opens to them and
+//! the right side of the 2nd equation, `T_i`, is `e(g_i, V_2_i)^{m_i}`. The verifier checks the 1st equation
+//! directly as it does not involve the message. Since `J` is also `m*G + r*H`, its opening can be given to
+//! the commitment-holder or proven equal to other commitments without revealing `m` to anyone else.
+//!
+//! [`ChunkedCommitment`]: crate::commitment::ChunkedCommitment
+
+use crate::{
+    encryption::Ciphertext, error::SaverError, keygen::PreparedDecryptionKey, saver_groth16,
+    setup::PreparedEncryptionGens, utils,
+};
+use ark_ec::{
+    pairing::{Pairing, PairingOutput},
+    AffineRepr, CurveGroup, VariableBaseMSM,
+};
+use ark_ff::{PrimeField, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{ops::Neg, rand::RngCore, vec::Vec, UniformRand};
+use digest::Digest;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use dock_crypto_utils::{hashing_utils::field_elem_from_try_and_incr, serde_utils::*};
+
+/// Proof that a ciphertext decrypts to the message committed in a chunked commitment
+#[serde_as]
+#[derive(
+    Clone, PartialEq, Eq, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize,
+)]
+pub struct CommittedDecryptionProof<E: Pairing> {
+    /// `e(g_i, V_2_i)^{k_i}` for random `k_i` of each chunk
+    #[serde_as(as = "Vec<ArkObjectBytes>")]
+    pub t_comms: Vec<PairingOutput<E>>,
+    /// `k_1*G_1 + k_2*G_2 + ... + k_n*G_n + k_r*H` for random `k_r`
+    #[serde_as(as = "ArkObjectBytes")]
+    pub comm: E::G1Affine,
+    /// `k_i + challenge * m_i` for each chunk
+    #[serde_as(as = "Vec<ArkObjectBytes>")]
+    pub resp_chunks: Vec<E::ScalarField>,
+    /// `k_r + challenge * r`
+    #[serde_as(as = "ArkObjectBytes")]
+    pub resp_blinding: E::ScalarField,
+}
+
+/// Public parameters of a `CommittedDecryptionProof`, same for the prover and verifier
+#[derive(Clone, Debug)]
+pub struct CommittedDecryptionParams<'a, E: Pairing> {
+    /// Key of the chunked commitment, `ChunkedCommitment::commitment_key`
+    pub comm_key: &'a [E::G1Affine],
+    pub dk: PreparedDecryptionKey<E>,
+    pub g_i: &'a [E::G1Affine],
+}
+
+impl<'a, E: Pairing> CommittedDecryptionParams<'a, E> {
+    pub fn new(
+        comm_key: &'a [E::G1Affine],
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        g_i: &'a [E::G1Affine],
+    ) -> Self {
+        Self {
+            comm_key,
+            dk: dk.into(),
+            g_i,
+        }
+    }
+
+    /// Same as `Self::new` but takes the Groth16 verification key instead of `g_i`
+    pub fn new_given_groth16_vk(
+        comm_key: &'a [E::G1Affine],
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        snark_vk: &'a ark_groth16::VerifyingKey<E>,
+    ) -> Self {
+        Self::new(comm_key, dk, saver_groth16::get_gs_for_encryption(snark_vk))
+    }
+
+    /// Returns the number of chunks
+    fn check_sizes(&self, ciphertext: &Ciphertext<E>) -> crate::Result<usize> {
+        let n = ciphertext.enc_chunks.len();
+        let expected = self.dk.supported_chunks_count()? as usize;
+        if n != expected {
+            return Err(SaverError::IncompatibleDecryptionKey(n, expected));
+        }
+        if n > self.g_i.len() {
+            return Err(SaverError::InsufficientGenerators {
+                have: self.g_i.len(),
+                need: n,
+                source: "g_i",
+            });
+        }
+        // A base for each chunk and one for the blinding
+        if self.comm_key.len() != n + 1 {
+            return Err(SaverError::IncompatibleCommitmentBases(
+                self.comm_key.len(),
+                n + 1,
+            ));
+        }
+        Ok(n)
+    }
+}
+
+impl<E: Pairing> CommittedDecryptionProof<E> {
+    /// Create the proof for the message and `nu` returned by decrypting `ciphertext`. The commitment is to
+    /// `message` with `blinding`. Returns the commitment and the proof.
+    pub fn new<R: RngCore, D: Digest>(
+        rng: &mut R,
+        message: &E::ScalarField,
+        blinding: &E::ScalarField,
+        ciphertext: &Ciphertext<E>,
+        nu: &E::G1Affine,
+        chunk_bit_size: u8,
+        params: &CommittedDecryptionParams<E>,
+    ) -> crate::Result<(E::G1Affine, Self)> {
+        let n = params.check_sizes(ciphertext)?;
+        let mut witnesses = utils::decompose(message, chunk_bit_size)?
+            .into_iter()
+            .map(E::ScalarField::from)
+            .collect::<Vec<_>>();
+        if witnesses.len() != n {
+            return Err(SaverError::IncompatibleDecryptionKey(witnesses.len(), n));
+        }
+        witnesses.push(*blinding);
+        let commitment = E::G1::msm_unchecked(params.comm_key, &witnesses).into_affine();
+
+        let randomness = (0..=n)
+            .map(|_| E::ScalarField::rand(rng))
+            .collect::<Vec<_>>();
+        let t_comms = params
+            .g_i
+            .iter()
+            .zip(params.dk.V_2.iter())
+            .zip(randomness.iter())
+            .map(|((g, v_2), k)| E::pairing(*g, v_2.clone()) * k)
+            .collect::<Vec<_>>();
+        let comm = E::G1::msm_unchecked(params.comm_key, &randomness).into_affine();
+
+        let challenge =
+            Self::compute_challenge::<D>(ciphertext, nu, &commitment, &t_comms, &comm, params);
+        let mut responses = randomness
+            .into_iter()
+            .zip(witnesses)
+            .map(|(k, w)| k + challenge * w)
+            .collect::<Vec<_>>();
+        let resp_blinding = responses.pop().unwrap();
+        Ok((
+            commitment,
+            Self {
+                t_comms,
+                comm,
+                resp_chunks: responses,
+                resp_blinding,
+            },
+        ))
+    }
+
+    /// Verify that `ciphertext` decrypts to the message committed in `commitment` and `nu` is the one
+    /// returned by decryption. Does not need the message.
+    #[must_use = "a failed verification is only reported through the returned value"]
+    pub fn verify<D: Digest>(
+        &self,
+        commitment: &E::G1Affine,
+        ciphertext: &Ciphertext<E>,
+        nu: &E::G1Affine,
+        params: &CommittedDecryptionParams<E>,
+        gens: impl Into<PreparedEncryptionGens<E>>,
+    ) -> crate::Result<()> {
+        let gens = gens.into();
+        let dk = &params.dk;
+        let n = params.check_sizes(ciphertext)?;
+        if self.t_comms.len() != n {
+            return Err(SaverError::VectorShorterThanExpected(self.t_comms.len(), n));
+        }
+        if self.resp_chunks.len() != n {
+            return Err(SaverError::VectorShorterThanExpected(
+                self.resp_chunks.len(),
+                n,
+            ));
+        }
+
+        // e(c_0, V_0) = e(nu, H)
+        if !E::multi_pairing(
+            [nu.into_group().neg().into_affine(), ciphertext.X_r],
+            [gens.H, dk.V_0.clone()],
+        )
+        .is_zero()
+        {
+            return Err(SaverError::InvalidDecryption);
+        }
+
+        let challenge = Self::compute_challenge::<D>(
+            ciphertext,
+            nu,
+            commitment,
+            &self.t_comms,
+            &self.comm,
+            params,
+        );
+
+        // e(g_i, V_2_i)^{resp_i} = t_comm_i * T_i^challenge where T_i = e(c_i, V_2_i) * e(-nu, V_1_i)
+        let minus_nu = E::G1Prepared::from(nu.into_group().neg());
+        for (((((c_i, v_1), v_2), g), resp), t_comm) in ciphertext
+            .enc_chunks
+            .iter()
+            .zip(dk.V_1.iter())
+            .zip(dk.V_2.iter())
+            .zip(params.g_i.iter())
+            .zip(self.resp_chunks.iter())
+            .zip(self.t_comms.iter())
+        {
+            let t_i = E::multi_pairing(
+                [E::G1Prepared::from(*c_i), minus_nu.clone()],
+                [v_2.clone(), v_1.clone()],
+            );
+            if E::pairing(*g, v_2.clone()) * resp != *t_comm + t_i * challenge {
+                return Err(SaverError::InvalidDecryption);
+            }
+        }
+
+        // resp_1*G_1 + .. + resp_n*G_n + resp_r*H = comm + commitment * challenge
+        let mut responses = self.resp_chunks.clone();
+        responses.push(self.resp_blinding);
+        if E::G1::msm_unchecked(params.comm_key, &responses)
+            != self.comm.into_group() + commitment.mul_bigint(challenge.into_bigint())
+        {
+            return Err(SaverError::InvalidCommitment);
+        }
+        Ok(())
+    }
+
+    /// Hashes the public parameters as well so that the proof can't be used with other ones
+    fn compute_challenge<D: Digest>(
+        ciphertext: &Ciphertext<E>,
+        nu: &E::G1Affine,
+        commitment: &E::G1Affine,
+        t_comms: &[PairingOutput<E>],
+        comm: &E::G1Affine,
+        params: &CommittedDecryptionParams<E>,
+    ) -> E::ScalarField {
+        let n = ciphertext.enc_chunks.len();
+        let mut bytes = Vec::new();
+        params.comm_key.serialize_compressed(&mut bytes).unwrap();
+        params.dk.V_0.serialize_compressed(&mut bytes).unwrap();
+        params.dk.V_1.serialize_compressed(&mut bytes).unwrap();
+        params.dk.V_2.serialize_compressed(&mut bytes).unwrap();
+        params.g_i[..n].serialize_compressed(&mut bytes).unwrap();
+        ciphertext.serialize_compressed(&mut bytes).unwrap();
+        nu.serialize_compressed(&mut bytes).unwrap();
+        commitment.serialize_compressed(&mut bytes).unwrap();
+        for t in t_comms {
+            t.serialize_compressed(&mut bytes).unwrap();
+        }
+        comm.serialize_compressed(&mut bytes).unwrap();
+        field_elem_from_try_and_incr::<E::ScalarField, D>(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        commitment::ChunkedCommitment,
+        encryption::Encryption,
+        setup::{setup_for_groth16, ChunkedCommitmentGens, EncryptionGens},
+    };
+    use ark_bls12_381::Bls12_381;
+    use ark_ff::One;
+    use ark_std::rand::{prelude::StdRng, SeedableRng};
+    use blake2::Blake2b512;
+
+    type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+    #[test]
+    fn decryption_to_committed_message() {
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let enc_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+            let comm_gens =
+                ChunkedCommitmentGens::<<Bls12_381 as Pairing>::G1Affine>::new_using_rng(&mut rng);
+            let (snark_pk, sk, ek, dk) =
+                setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens).unwrap();
+            let comm_key = ChunkedCommitment::<<Bls12_381 as Pairing>::G1Affine>::commitment_key(
+                &comm_gens,
+                chunk_bit_size,
+            )
+            .unwrap();
+
+            let params = CommittedDecryptionParams::new_given_groth16_vk(
+                &comm_key,
+                dk.clone(),
+                &snark_pk.pk.vk,
+            );
+
+            let m = Fr::rand(&mut rng);
+            let (ct, _) = Encryption::encrypt_given_snark_vk(
+                &mut rng,
+                &m,
+                &ek,
+                &snark_pk.pk.vk,
+                chunk_bit_size,
+            )
+            .unwrap();
+
+            // Decryptor decrypts and commits to the message
+            let (commitment, proof, nu) = {
+                let (m_, nu) = ct
+                    .decrypt_given_groth16_vk(&sk, dk.clone(), &snark_pk.pk.vk, chunk_bit_size)
+                    .unwrap();
+                let blinding = Fr::rand(&mut rng);
+                let (commitment, proof) = CommittedDecryptionProof::new::<_, Blake2b512>(
+                    &mut rng,
+                    &m_,
+                    &blinding,
+                    &ct,
+                    &nu,
+                    chunk_bit_size,
+                    &params,
+                )
+                .unwrap();
+                // The commitment opens to the message with the blinding, which the decryptor can give to the
+                // commitment-holder
+                assert_eq!(
+                    commitment,
+                    (comm_gens.G * m + comm_gens.H * blinding).into_affine()
+                );
+                (commitment, proof, nu)
+            };
+
+            // Verifier only has the ciphertext, `nu`, commitment, proof and public parameters
+            let verify = |commitment: &<Bls12_381 as Pairing>::G1Affine,
+                          ct: &Ciphertext<Bls12_381>,
+                          nu: &<Bls12_381 as Pairing>::G1Affine,
+                          proof: &CommittedDecryptionProof<Bls12_381>| {
+                proof.verify::<Blake2b512>(commitment, ct, nu, &params, enc_gens.clone())
+            };
+            verify(&commitment, &ct, &nu, &proof).unwrap();
+            test_serialization!(CommittedDecryptionProof<Bls12_381>, proof);
+
+            // Commitment to a different message
+            let other_commitment =
+                (comm_gens.G * (m + Fr::one()) + comm_gens.H * Fr::rand(&mut rng)).into_affine();
+            assert!(verify(&other_commitment, &ct, &nu, &proof).is_err());
+
+            // Wrong `nu`
+            let other_nu = (nu.into_group() + enc_gens.G).into_affine();
+            assert!(matches!(
+                verify(&commitment, &ct, &other_nu, &proof),
+                Err(SaverError::InvalidDecryption)
+            ));
+
+            // Proof is bound to the commitment key it was created with
+            let other_comm_key =
+                ChunkedCommitment::<<Bls12_381 as Pairing>::G1Affine>::commitment_key(
+                    &ChunkedCommitmentGens::new_using_rng(&mut rng),
+                    chunk_bit_size,
+                )
+                .unwrap();
+            let other_params = CommittedDecryptionParams::new_given_groth16_vk(
+                &other_comm_key,
+                dk.clone(),
+                &snark_pk.pk.vk,
+            );
+            assert!(proof
+                .verify::<Blake2b512>(&commitment, &ct, &nu, &other_params, enc_gens.clone())
+                .is_err());
+
+            // Proof for a different message can't be created with a correct `nu`
+            let blinding = Fr::rand(&mut rng);
+            let (commitment, proof) = CommittedDecryptionProof::new::<_, Blake2b512>(
+                &mut rng,
+                &(m + Fr::one()),
+                &blinding,
+                &ct,
+                &nu,
+                chunk_bit_size,
+                &params,
+            )
+            .unwrap();
+            assert!(matches!(
+                verify(&commitment, &ct, &nu, &proof),
+                Err(SaverError::InvalidDecryption)
+            ));
+        }
+        check(4);
+        check(8);
+    }
+}
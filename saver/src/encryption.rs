@@ -2,20 +2,24 @@
 
 use crate::{
     circuit::BitsizeCheckCircuit,
+    commitment::ChunkedCommitment,
     error::SaverError,
     keygen::{EncryptionKey, PreparedDecryptionKey, PreparedEncryptionKey, SecretKey},
     saver_groth16, saver_legogroth16,
-    setup::PreparedEncryptionGens,
+    setup::{ChunkedCommitmentGens, PreparedEncryptionGens},
     utils,
 };
 use ark_ec::{
     pairing::{Pairing, PairingOutput},
     AffineRepr, CurveGroup, VariableBaseMSM,
 };
-use ark_ff::{PrimeField, Zero};
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_ff::{One, PrimeField, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
 use ark_std::{
-    cfg_into_iter, cfg_iter,
+    cfg_into_iter, cfg_iter, cfg_iter_mut,
+    cmp::Ordering,
+    fmt,
+    iter::Sum,
     marker::PhantomData,
     ops::{Add, Mul, Neg, Sub},
     rand::RngCore,
@@ -27,7 +31,8 @@ use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
 use crate::utils::CHUNK_TYPE;
-use dock_crypto_utils::{ff::non_zero_random, serde_utils::*};
+use dock_crypto_utils::{ff::non_zero_random, join, msm::WindowTable, serde_utils::*};
+use num_bigint::BigUint;
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
@@ -46,6 +51,49 @@ pub struct Ciphertext<E: Pairing> {
     pub commitment: E::G1Affine,
 }
 
+/// Prints the ciphertext's fingerprint, see `utils::fingerprint`
+impl<E: Pairing> fmt::Display for Ciphertext<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        utils::write_fingerprint(self, f)
+    }
+}
+
+/// Message decrypted from a ciphertext with the `nu` that proves the decryption correct, returned by
+/// `Ciphertext::decrypt_and_prove`. Contains all that the decryptor needs to send to verifiers who have the
+/// ciphertext and the public parameters.
+#[serde_as]
+#[derive(
+    Clone, PartialEq, Eq, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize,
+)]
+pub struct VerifiableDecryption<E: Pairing> {
+    #[serde_as(as = "ArkObjectBytes")]
+    pub message: E::ScalarField,
+    #[serde_as(as = "ArkObjectBytes")]
+    pub nu: E::G1Affine,
+    pub chunk_bit_size: u8,
+}
+
+impl<E: Pairing> VerifiableDecryption<E> {
+    /// Verify that `ciphertext` decrypts to the message, like `Ciphertext::verify_decryption_given_groth16_vk`
+    #[must_use = "a failed verification is only reported through the returned value"]
+    pub fn verify(
+        &self,
+        ciphertext: &Ciphertext<E>,
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        gens: impl Into<PreparedEncryptionGens<E>>,
+    ) -> crate::Result<()> {
+        ciphertext.verify_decryption_given_groth16_vk(
+            &self.message,
+            &self.nu,
+            self.chunk_bit_size,
+            dk,
+            snark_vk,
+            gens,
+        )
+    }
+}
+
 /// Ciphertext used with LegoGroth16 and the slightly modified SAVER protocol. See `saver_legogroth16::protocol_2` for more
 /// details.
 #[derive(Clone, PartialEq, Eq, Debug, CanonicalSerialize, CanonicalDeserialize)]
@@ -56,6 +104,227 @@ pub struct CiphertextAlt<E: Pairing> {
     pub X_r_sum: E::G1Affine,
 }
 
+/// Output of `Encryption::encrypt_committed`. Contains the ciphertext, a Pedersen commitment to the message
+/// and what the prover needs to prove that both have the same message using Pedersen commitment statements,
+/// one for the chunked commitment and one for the ciphertext's commitment, with equal chunks.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CommittedEncryption<E: Pairing> {
+    pub ciphertext: Ciphertext<E>,
+    /// Randomness used in encryption
+    pub r: E::ScalarField,
+    /// Commitment `G * m + H * blinding` to the message `m`. This is also the chunked commitment to `m`
+    /// (see `ChunkedCommitment`) so its opening can be proven with either key.
+    pub commitment: E::G1Affine,
+    pub blinding: E::ScalarField,
+    /// Commitment key of the chunked commitment
+    pub chunked_comm_key: Vec<E::G1Affine>,
+    /// Commitment key of the ciphertext's commitment, i.e. `EncryptionKey::commitment_key`
+    pub ct_comm_key: Vec<E::G1Affine>,
+    /// Chunks of the message as field elements
+    pub decomposed_message: Vec<E::ScalarField>,
+}
+
+impl<E: Pairing> CommittedEncryption<E> {
+    /// Opening of `self.commitment` under `self.chunked_comm_key`
+    pub fn chunked_commitment_witnesses(&self) -> Vec<E::ScalarField> {
+        utils::chunked_commitment_witness(&self.decomposed_message, self.blinding)
+    }
+
+    /// Opening of the ciphertext's commitment under `self.ct_comm_key`
+    pub fn ciphertext_commitment_witnesses(&self) -> Vec<E::ScalarField> {
+        utils::ciphertext_commitment_witness(&self.decomposed_message, self.r)
+    }
+
+    /// Witnesses that must be equal given the index of the statement for the chunked commitment and the
+    /// index of the statement for the ciphertext's commitment. Each item is a pair of `(statement index,
+    /// witness index)`, one for each chunk.
+    pub fn witness_equalities(
+        &self,
+        chunked_comm_stmt_idx: usize,
+        ct_comm_stmt_idx: usize,
+    ) -> Vec<((usize, usize), (usize, usize))> {
+        (0..self.decomposed_message.len())
+            .map(|i| ((chunked_comm_stmt_idx, i), (ct_comm_stmt_idx, i)))
+            .collect()
+    }
+}
+
+/// Work done when encrypting, decrypting and verifying with a given chunk bit size, as returned by
+/// `Encryption::cost_estimate`. Pairings in a multi-pairing are counted individually.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct EncryptionCost {
+    pub chunks_count: usize,
+    /// Scalar multiplications in G1 for encrypting the chunks, i.e. `X_0 * r` and `X_i * r + G_i * m_i`
+    /// for each chunk `i`
+    pub encryption_scalar_mults: usize,
+    /// Terms of the G1 MSM creating the ciphertext's commitment `psi`
+    pub g1_msm_terms: usize,
+    /// Pairings for verifying the ciphertext's commitment
+    pub commitment_verification_pairings: usize,
+    /// Pairings for decrypting, not counting the ones for solving discrete logs when pairing powers
+    /// are not precomputed
+    pub decryption_pairings: usize,
+    /// Largest discrete log solved for each chunk, i.e. the worst case number of steps per chunk
+    pub discrete_log_range: u64,
+    /// Pairings for verifying the decryption
+    pub decryption_verification_pairings: usize,
+}
+
+/// Work done by a verification method for a given number of chunks, as returned by
+/// `Ciphertext::verify_commitment_cost`, `Ciphertext::verify_decryption_cost` and
+/// `saver_groth16::verify_proof_cost`. Pairings in a multi-pairing are counted individually and group
+/// negations are not counted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct VerificationCost {
+    pub pairings: usize,
+    /// Scalar multiplications in G1, including the terms of any MSM
+    pub g1_scalar_mults: usize,
+    /// Additions and subtractions in G1 outside of MSMs
+    pub g1_additions: usize,
+}
+
+/// Buffer for the decrypted chunks that is reused across decryptions with `Ciphertext::decrypt_with_scratch`, so
+/// that a decryptor processing a stream of ciphertexts doesn't allocate it for each ciphertext. Holds the chunks
+/// of the last decryption. It can also hold the pairing powers of the decryption key
+/// (`PreparedDecryptionKey::pairing_powers`) to decrypt faster, and then must only be used with that key.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DecryptScratch<E: Pairing> {
+    chunks: Vec<CHUNK_TYPE>,
+    pairing_powers: Vec<Vec<PairingOutput<E>>>,
+}
+
+impl<E: Pairing> Default for DecryptScratch<E> {
+    fn default() -> Self {
+        Self {
+            chunks: Vec::new(),
+            pairing_powers: Vec::new(),
+        }
+    }
+}
+
+impl<E: Pairing> DecryptScratch<E> {
+    /// Create a buffer for ciphertexts of `chunks_count` chunks
+    pub fn new(chunks_count: usize) -> Self {
+        Self {
+            chunks: Vec::with_capacity(chunks_count),
+            pairing_powers: Vec::new(),
+        }
+    }
+
+    /// Create a buffer that decrypts using the pairing powers of the decryption key
+    pub fn with_pairing_powers(pairing_powers: Vec<Vec<PairingOutput<E>>>) -> Self {
+        Self {
+            chunks: Vec::with_capacity(pairing_powers.len()),
+            pairing_powers,
+        }
+    }
+
+    /// Chunks decrypted by the last decryption
+    pub fn chunks(&self) -> &[CHUNK_TYPE] {
+        &self.chunks
+    }
+}
+
+/// Pairing powers of a decryption key (`PreparedDecryptionKey::pairing_powers`) serialized once for
+/// `Encryption::decrypt_oblivious`, which compares the serialized pairing of each chunk with every entry of
+/// the chunk's row. Row `i` is for the `i`-th chunk and is ordered by value as index `j` has the power `j+1`.
+/// Create once for a decryption key and reuse it for every ciphertext decrypted with that key.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ObliviousTable<E: Pairing> {
+    chunk_bit_size: u8,
+    /// Serialization of the identity, which corresponds to the chunk 0 and isn't part of the rows
+    identity: Vec<u8>,
+    rows: Vec<Vec<Vec<u8>>>,
+    _phantom: PhantomData<E>,
+}
+
+impl<E: Pairing> ObliviousTable<E> {
+    /// Returns `SaverError::InvalidPairingPowers` if a row doesn't have a power for each non-zero chunk. The
+    /// powers aren't checked to be those of a decryption key.
+    pub fn new(
+        pairing_powers: &[Vec<PairingOutput<E>>],
+        chunk_bit_size: u8,
+    ) -> crate::Result<Self> {
+        utils::check_chunk_bit_size(chunk_bit_size)?;
+        let chunk_max_val = (1 << chunk_bit_size) - 1;
+        if pairing_powers.iter().any(|p| p.len() < chunk_max_val) {
+            return Err(SaverError::InvalidPairingPowers);
+        }
+        let rows = cfg_iter!(pairing_powers)
+            .map(|p| {
+                p[..chunk_max_val]
+                    .iter()
+                    .map(Encryption::<E>::pairing_output_bytes)
+                    .collect()
+            })
+            .collect();
+        Ok(Self {
+            chunk_bit_size,
+            identity: Encryption::<E>::pairing_output_bytes(&PairingOutput::<E>::zero()),
+            rows,
+            _phantom: PhantomData,
+        })
+    }
+
+    pub fn chunk_bit_size(&self) -> u8 {
+        self.chunk_bit_size
+    }
+}
+
+/// Bases of an encryption key that are multiplied by the randomness, `X_0`, `X_i` and `P_1`, as window
+/// tables so that encrypting many messages with the same key and `g_i` is faster than with
+/// `Encryption::encrypt`. The other bases are only multiplied by the small chunks and are kept as they are.
+/// Create once with `Self::new` and pass to `Encryption::encrypt_given_context`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EncryptionContext<E: Pairing> {
+    chunk_bit_size: u8,
+    X_0: WindowTable<E::G1>,
+    X: Vec<WindowTable<E::G1>>,
+    P_1: WindowTable<E::G1>,
+    Y: Vec<E::G1Affine>,
+    g_i: Vec<E::G1Affine>,
+}
+
+impl<E: Pairing> EncryptionContext<E> {
+    /// `num_encryptions` is the expected number of messages that will be encrypted and is only used to
+    /// choose the size of the tables. Bigger tables take longer to create and more memory but make each
+    /// multiplication faster.
+    pub fn new(
+        ek: &EncryptionKey<E>,
+        g_i: &[E::G1Affine],
+        chunk_bit_size: u8,
+        num_encryptions: usize,
+    ) -> crate::Result<Self> {
+        utils::check_chunk_bit_size(chunk_bit_size)?;
+        let n = ek.supported_chunks_count()? as usize;
+        let expected = utils::chunks_count::<E::ScalarField>(chunk_bit_size) as usize;
+        if n != expected {
+            return Err(SaverError::IncompatibleEncryptionKey(expected, n));
+        }
+        if g_i.len() < n {
+            return Err(SaverError::InsufficientGenerators {
+                have: g_i.len(),
+                need: n,
+                source: "g_i",
+            });
+        }
+        Ok(Self {
+            chunk_bit_size,
+            X_0: WindowTable::new(num_encryptions, ek.X_0.into_group()),
+            X: cfg_iter!(ek.X)
+                .map(|x| WindowTable::new(num_encryptions, x.into_group()))
+                .collect(),
+            P_1: WindowTable::new(num_encryptions, ek.P_1.into_group()),
+            Y: ek.Y.clone(),
+            g_i: g_i[..n].to_vec(),
+        })
+    }
+
+    pub fn chunk_bit_size(&self) -> u8 {
+        self.chunk_bit_size
+    }
+}
+
 macro_rules! impl_enc_funcs {
     () => {
         /// Decrypt this ciphertext returning the plaintext and commitment to randomness
@@ -66,9 +335,54 @@ macro_rules! impl_enc_funcs {
             g_i: &[E::G1Affine],
             chunk_bit_size: u8,
         ) -> crate::Result<(E::ScalarField, E::G1Affine)> {
+            self.check_chunk_bit_size(chunk_bit_size)?;
             Encryption::decrypt(&self.X_r, &self.enc_chunks, sk, dk, g_i, chunk_bit_size)
         }
 
+        /// Same as `Self::decrypt` but uses the chunk bit size of this ciphertext (`Self::chunk_bit_size`)
+        pub fn decrypt_with_own_chunk_bit_size(
+            &self,
+            sk: &SecretKey<E::ScalarField>,
+            dk: impl Into<PreparedDecryptionKey<E>>,
+            g_i: &[E::G1Affine],
+        ) -> crate::Result<(E::ScalarField, E::G1Affine)> {
+            self.decrypt(sk, dk, g_i, self.chunk_bit_size()?)
+        }
+
+        /// Decrypt only the last chunks of this ciphertext with a key from `DecryptionKey::truncate_to_chunks`.
+        /// The result is the message only if the chunks not decrypted are 0, i.e. the message is less than
+        /// `2^{chunk_bit_size * n}` for a key of `n` chunks. `g_i` are all the generators, as for `Self::decrypt`.
+        pub fn decrypt_last_chunks(
+            &self,
+            sk: &SecretKey<E::ScalarField>,
+            dk: impl Into<PreparedDecryptionKey<E>>,
+            g_i: &[E::G1Affine],
+            chunk_bit_size: u8,
+        ) -> crate::Result<(E::ScalarField, E::G1Affine)> {
+            self.check_chunk_bit_size(chunk_bit_size)?;
+            let dk = dk.into();
+            let n = dk.supported_chunks_count()? as usize;
+            let count = self.enc_chunks.len();
+            if n > count {
+                return Err(SaverError::IncompatibleDecryptionKey(count, n));
+            }
+            if count > g_i.len() {
+                return Err(SaverError::InsufficientGenerators {
+                    have: g_i.len(),
+                    need: count,
+                    source: "g_i",
+                });
+            }
+            Encryption::decrypt(
+                &self.X_r,
+                &self.enc_chunks[count - n..],
+                sk,
+                dk,
+                &g_i[count - n..count],
+                chunk_bit_size,
+            )
+        }
+
         /// Same as `Self::decrypt` but takes pairing powers (see `PreparedDecryptionKey::pairing_powers`)
         /// that can be precomputed for faster decryption
         pub fn decrypt_given_pairing_powers(
@@ -79,6 +393,7 @@ macro_rules! impl_enc_funcs {
             chunk_bit_size: u8,
             pairing_powers: &[Vec<PairingOutput<E>>],
         ) -> crate::Result<(E::ScalarField, E::G1Affine)> {
+            self.check_chunk_bit_size(chunk_bit_size)?;
             Encryption::decrypt_given_pairing_powers(
                 &self.X_r,
                 &self.enc_chunks,
@@ -90,12 +405,72 @@ macro_rules! impl_enc_funcs {
             )
         }
 
-        /// Verify that the ciphertext correctly commits to the message
+        /// Same as `Self::decrypt_given_pairing_powers` but finds the discrete log in constant time. See
+        /// `Encryption::decrypt_oblivious`
+        pub fn decrypt_oblivious(
+            &self,
+            sk: &SecretKey<E::ScalarField>,
+            dk: impl Into<PreparedDecryptionKey<E>>,
+            g_i: &[E::G1Affine],
+            table: &ObliviousTable<E>,
+        ) -> crate::Result<(E::ScalarField, E::G1Affine)> {
+            Encryption::decrypt_oblivious(&self.X_r, &self.enc_chunks, sk, dk, g_i, table)
+        }
+
+        /// Chunk bit size of the message encrypted in this ciphertext, determined by its number of chunks like
+        /// `EncryptionKey::chunk_bit_size`. Returns an error if no supported chunk bit size gives that number.
+        pub fn chunk_bit_size(&self) -> crate::Result<u8> {
+            utils::chunk_bit_size_for_chunks_count::<E::ScalarField>(self.encrypted_chunk_count())
+                .ok_or(SaverError::InvalidDecomposition)
+        }
+
+        /// Returns `SaverError::ChunkBitSizeMismatch` if the message in this ciphertext isn't decomposed in
+        /// chunks of `chunk_bit_size` bits. Decrypting with a bigger chunk bit size than the real one would
+        /// otherwise succeed but compose the chunks into a wrong message.
+        fn check_chunk_bit_size(&self, chunk_bit_size: u8) -> crate::Result<()> {
+            utils::check_chunk_bit_size(chunk_bit_size)?;
+            let chunks_count = self.encrypted_chunk_count();
+            if utils::chunks_count::<E::ScalarField>(chunk_bit_size) as usize != chunks_count {
+                return Err(SaverError::ChunkBitSizeMismatch {
+                    chunk_bit_size,
+                    chunks_count,
+                });
+            }
+            Ok(())
+        }
+
+        /// Number of chunks encrypted in this ciphertext. It must be the `supported_chunks_count` of the
+        /// keys used with it.
+        pub fn encrypted_chunk_count(&self) -> usize {
+            self.enc_chunks.len()
+        }
+
+        /// Encryption `c_i = X_i * r + g_i * m_i` of each chunk `m_i` of the message. Index `i` is the index of the
+        /// chunk in the big-endian decomposition (`utils::decompose`), so index 0 is the most significant chunk,
+        /// and of the elements of the keys (`X`, `Y`, `V_1`, `V_2`) and `g_i` used with that chunk.
+        pub fn chunk_ciphertexts(&self) -> &[E::G1Affine] {
+            &self.enc_chunks
+        }
+
+        /// Number of bases needed to open `self.commitment`, i.e. one for each chunk and one for the randomness.
+        /// The commitment key of a compatible encryption key (`EncryptionKey::commitment_key`) has this many
+        /// elements and can be used as bases in a Pedersen commitment statement about `self.commitment`
+        pub fn expected_commitment_bases_len(&self) -> usize {
+            self.encrypted_chunk_count() + 1
+        }
+
+        /// Verify that the ciphertext correctly commits to the message. `gens` can't be taken from `ek` as
+        /// the check pairs the commitment with `gens.H` which the key doesn't contain. The key's elements in
+        /// G2 are `H * t_i` with secret `t_i` so `H` can't be recovered from them, and the key only has a
+        /// fingerprint of the generators, to check that `gens` are the ones it was created with.
+        #[must_use = "a failed verification is only reported through the returned value"]
         pub fn verify_commitment(
             &self,
             ek: impl Into<PreparedEncryptionKey<E>>,
             gens: impl Into<PreparedEncryptionGens<E>>,
         ) -> crate::Result<()> {
+            let (ek, gens) = (ek.into(), gens.into());
+            self.check_commitment_inputs(&ek, &gens)?;
             Encryption::verify_ciphertext_commitment(
                 &self.X_r,
                 &self.enc_chunks,
@@ -105,7 +480,61 @@ macro_rules! impl_enc_funcs {
             )
         }
 
+        /// Checks of `Self::verify_commitment` that don't need any pairing: `ek` must be for as many chunks as this
+        /// ciphertext and have as many commitment bases, and `gens` must be the generators `ek` was created with.
+        fn check_commitment_inputs(
+            &self,
+            ek: &PreparedEncryptionKey<E>,
+            gens: &PreparedEncryptionGens<E>,
+        ) -> crate::Result<()> {
+            // A malformed key is reported after the size checks
+            if let Ok(n) = ek.supported_chunks_count() {
+                if self.encrypted_chunk_count() != n as usize {
+                    return Err(SaverError::IncompatibleEncryptionKey(
+                        self.encrypted_chunk_count(),
+                        n as usize,
+                    ));
+                }
+            }
+            let bases_len = ek.Y.len() + 1;
+            if bases_len != self.expected_commitment_bases_len() {
+                return Err(SaverError::IncompatibleCommitmentBases(
+                    bases_len,
+                    self.expected_commitment_bases_len(),
+                ));
+            }
+            ek.check_gens_fingerprint(&gens.fingerprint)?;
+            ek.supported_chunks_count()?;
+            Ok(())
+        }
+
+        /// Same as `Self::verify_commitment` but first checks that `bases`, commitment bases kept by the
+        /// caller, are the ones of the encryption key, i.e. `ek.commitment_key()`. Returns
+        /// `SaverError::IncompatibleCommitmentBases` if their number differs and
+        /// `SaverError::MismatchedCommitmentBases` if any base differs.
+        #[must_use = "a failed verification is only reported through the returned value"]
+        pub fn verify_commitment_with_bases(
+            &self,
+            bases: &[E::G1Affine],
+            ek: impl Into<PreparedEncryptionKey<E>>,
+            gens: impl Into<PreparedEncryptionGens<E>>,
+        ) -> crate::Result<()> {
+            let ek = ek.into();
+            let expected = ek.commitment_key();
+            if bases.len() != expected.len() {
+                return Err(SaverError::IncompatibleCommitmentBases(
+                    bases.len(),
+                    expected.len(),
+                ));
+            }
+            if bases != expected {
+                return Err(SaverError::MismatchedCommitmentBases);
+            }
+            self.verify_commitment(ek, gens)
+        }
+
         /// Verify that the decrypted message corresponds to original plaintext in the ciphertext
+        #[must_use = "a failed verification is only reported through the returned value"]
         pub fn verify_decryption(
             &self,
             message: &E::ScalarField,
@@ -129,9 +558,46 @@ macro_rules! impl_enc_funcs {
     };
 }
 
+/// Keys a ciphertext was created with, along with the secret and decryption keys to decrypt it. Used by
+/// `Encryption::migrate_ciphertext`.
+#[derive(Clone, Debug)]
+pub struct MigrationSourceKeys<'a, E: Pairing> {
+    pub sk: &'a SecretKey<E::ScalarField>,
+    pub dk: PreparedDecryptionKey<E>,
+    /// The SNARK verification key the encryption key was created with
+    pub snark_vk: &'a ark_groth16::VerifyingKey<E>,
+    pub chunk_bit_size: u8,
+}
+
+/// Keys a ciphertext is re-encrypted with by `Encryption::migrate_ciphertext`
+#[derive(Clone, Debug)]
+pub struct MigrationTargetKeys<'a, E: Pairing> {
+    pub ek: &'a EncryptionKey<E>,
+    /// The SNARK verification key the encryption key was created with
+    pub snark_vk: &'a ark_groth16::VerifyingKey<E>,
+    pub chunk_bit_size: u8,
+}
+
 pub struct Encryption<E: Pairing>(PhantomData<E>);
 
 impl<E: Pairing> Encryption<E> {
+    /// Estimate the work done for the given chunk bit size without doing any. See `EncryptionCost`
+    pub fn cost_estimate(chunk_bit_size: u8) -> crate::Result<EncryptionCost> {
+        utils::check_chunk_bit_size(chunk_bit_size)?;
+        let n = utils::chunks_count::<E::ScalarField>(chunk_bit_size) as usize;
+        Ok(EncryptionCost {
+            chunks_count: n,
+            encryption_scalar_mults: 2 * n + 1,
+            // A base for each chunk and one for the randomness
+            g1_msm_terms: n + 1,
+            commitment_verification_pairings: Ciphertext::<E>::verify_commitment_cost(n).pairings,
+            // A multi-pairing of size 2 for each chunk
+            decryption_pairings: 2 * n,
+            discrete_log_range: (1u64 << chunk_bit_size) - 1,
+            decryption_verification_pairings: Ciphertext::<E>::verify_decryption_cost(n).pairings,
+        })
+    }
+
     /// Encrypt a message `m` in exponent-Elgamal after breaking it into chunks of `chunk_bit_size` bits.
     /// Returns the ciphertext, commitment and randomness created for encryption. This is "Enc" from algorithm
     /// 2 in the paper
@@ -155,7 +621,74 @@ impl<E: Pairing> Encryption<E> {
         ))
     }
 
-    /// Return the encryption and Groth16 proof
+    /// Same as `Self::encrypt` but uses the chunk bit size the key was created for
+    /// (`EncryptionKey::chunk_bit_size`)
+    pub fn encrypt_with_key_chunk_bit_size<R: RngCore>(
+        rng: &mut R,
+        message: &E::ScalarField,
+        ek: &EncryptionKey<E>,
+        g_i: &[E::G1Affine],
+    ) -> crate::Result<(Ciphertext<E>, E::ScalarField)> {
+        Self::encrypt(rng, message, ek, g_i, ek.chunk_bit_size()?)
+    }
+
+    /// Same as `Self::encrypt` but returns an error if the message is 0 or 1 unless `allow_trivial` is set.
+    /// These are rarely intended, eg. an unset field that defaulted to 0 being encrypted.
+    pub fn encrypt_checked<R: RngCore>(
+        rng: &mut R,
+        message: &E::ScalarField,
+        ek: &EncryptionKey<E>,
+        g_i: &[E::G1Affine],
+        chunk_bit_size: u8,
+        allow_trivial: bool,
+    ) -> crate::Result<(Ciphertext<E>, E::ScalarField)> {
+        if !allow_trivial && (message.is_zero() || message.is_one()) {
+            return Err(SaverError::TrivialMessage);
+        }
+        Self::encrypt(rng, message, ek, g_i, chunk_bit_size)
+    }
+
+    /// Encrypt the message obtained by hashing `data` with `utils::message_from_bytes`. Returns the
+    /// ciphertext and randomness like `Self::encrypt`.
+    pub fn encrypt_bytes_hashed<R: RngCore>(
+        rng: &mut R,
+        data: &[u8],
+        ek: &EncryptionKey<E>,
+        g_i: &[E::G1Affine],
+        chunk_bit_size: u8,
+    ) -> crate::Result<(Ciphertext<E>, E::ScalarField)> {
+        let message = utils::message_from_bytes::<E::ScalarField>(data);
+        Self::encrypt(rng, &message, ek, g_i, chunk_bit_size)
+    }
+
+    /// Same as `Self::encrypt` but the randomness is derived from `shared_secret` and `context` with
+    /// `utils::derive_randomness` so that anyone knowing them and the message can create the same ciphertext.
+    #[cfg(feature = "derived-randomness")]
+    pub fn encrypt_with_derived_randomness(
+        message: &E::ScalarField,
+        ek: &EncryptionKey<E>,
+        g_i: &[E::G1Affine],
+        chunk_bit_size: u8,
+        shared_secret: &[u8],
+        context: &[u8],
+    ) -> crate::Result<(Ciphertext<E>, E::ScalarField)> {
+        let decomposed = utils::decompose(message, chunk_bit_size)?;
+        Self::check_decomposed_message(&decomposed, ek, g_i)?;
+        let r = utils::derive_randomness::<E::ScalarField>(shared_secret, context);
+        let mut ct = Self::encrypt_decomposed_message_given_randomness(decomposed, &r, ek, g_i);
+        Ok((
+            Ciphertext {
+                X_r: ct.remove(0),
+                commitment: ct.remove(ct.len() - 1),
+                enc_chunks: ct,
+            },
+            r,
+        ))
+    }
+
+    /// Return the encryption and Groth16 proof. With the `parallel` feature, the ciphertext and proof are
+    /// created at the same time as the proof doesn't depend on the ciphertext. The result is the same as
+    /// calling `Self::encrypt` and then `saver_groth16::create_proof` with the same RNG.
     pub fn encrypt_with_proof<R: RngCore>(
         rng: &mut R,
         message: &E::ScalarField,
@@ -164,15 +697,43 @@ impl<E: Pairing> Encryption<E> {
         chunk_bit_size: u8,
     ) -> crate::Result<(Ciphertext<E>, E::ScalarField, ark_groth16::Proof<E>)> {
         let g_i = saver_groth16::get_gs_for_encryption(&snark_pk.pk.vk);
-        let (ct, r) = Encryption::encrypt(rng, message, ek, g_i, chunk_bit_size)?;
-        let decomposed_message = utils::decompose(message, chunk_bit_size)?
-            .into_iter()
-            .map(|m| E::ScalarField::from(m as u64))
-            .collect::<Vec<_>>();
-        let circuit =
-            BitsizeCheckCircuit::new(chunk_bit_size, None, Some(decomposed_message), true);
-        let proof = saver_groth16::create_proof(circuit, &r, snark_pk, ek, rng)?;
-        Ok((ct, r, proof))
+        let decomposed = utils::decompose(message, chunk_bit_size)?;
+        Self::check_decomposed_message(&decomposed, ek, g_i)?;
+        let circuit = BitsizeCheckCircuit::new(
+            chunk_bit_size,
+            None,
+            Some(
+                decomposed
+                    .iter()
+                    .map(|c| E::ScalarField::from(*c))
+                    .collect(),
+            ),
+            true,
+        );
+        // Sampled in the order `Self::encrypt` and `saver_groth16::create_proof` sample them
+        let r = E::ScalarField::rand(rng);
+        let t = E::ScalarField::rand(rng);
+        let s = E::ScalarField::rand(rng);
+        // The proof might be created on another thread, which should report its span to the caller's subscriber
+        #[cfg(feature = "tracing")]
+        let dispatch = tracing::dispatcher::get_default(|d| d.clone());
+        let (mut ct, proof) = join!(
+            Self::encrypt_decomposed_message_given_randomness(decomposed, &r, ek, g_i),
+            {
+                #[cfg(feature = "tracing")]
+                let _guard = tracing::dispatcher::set_default(&dispatch);
+                saver_groth16::create_proof_given_randomness(circuit, &r, &t, &s, snark_pk, ek)
+            }
+        );
+        Ok((
+            Ciphertext {
+                X_r: ct.remove(0),
+                commitment: ct.remove(ct.len() - 1),
+                enc_chunks: ct,
+            },
+            r,
+            proof?,
+        ))
     }
 
     pub fn rerandomize_ciphertext_and_proof<R: RngCore>(
@@ -189,7 +750,7 @@ impl<E: Pairing> Encryption<E> {
             .mul_bigint(r_prime_repr)
             .add(&ciphertext.X_r)
             .into_affine();
-        let enc = cfg_into_iter!(ciphertext.enc_chunks)
+        let enc = cfg_into_iter!(ciphertext.enc_chunks, utils::PARALLEL_MIN_CHUNKS)
             .zip(cfg_iter!(ek.X))
             .map(|(c, x)| x.mul_bigint(r_prime_repr).add(&c))
             .collect::<Vec<_>>();
@@ -207,7 +768,10 @@ impl<E: Pairing> Encryption<E> {
         Ok((ct, r_prime, proof))
     }
 
-    /// Same as `Self::encrypt` but takes the SNARK verification key instead of the generators used for Elgamal encryption
+    /// Same as `Self::encrypt` but takes the SNARK verification key instead of the generators used for Elgamal encryption.
+    /// Returns `SaverError::GeneratorCountMismatch` if the commitment bases of the key (`Ciphertext::commitment_bases`)
+    /// aren't one for each chunk of the message and one for the randomness, as the proof for such a ciphertext
+    /// would fail to verify.
     pub fn encrypt_given_snark_vk<R: RngCore>(
         rng: &mut R,
         message: &E::ScalarField,
@@ -215,10 +779,145 @@ impl<E: Pairing> Encryption<E> {
         snark_vk: &ark_groth16::VerifyingKey<E>,
         chunk_bit_size: u8,
     ) -> crate::Result<(Ciphertext<E>, E::ScalarField)> {
+        ek.validate()?;
+        utils::check_chunk_bit_size(chunk_bit_size)?;
+        let key = ek.Y.len() + 1;
+        let message_bases = utils::chunks_count::<E::ScalarField>(chunk_bit_size) as usize + 1;
+        if key != message_bases {
+            return Err(SaverError::GeneratorCountMismatch {
+                key,
+                message: message_bases,
+            });
+        }
         let g_i = saver_groth16::get_gs_for_encryption(snark_vk);
         Self::encrypt(rng, message, ek, g_i, chunk_bit_size)
     }
 
+    /// Same as `Self::encrypt` but uses the tables in `ctx` created for the encryption key and `g_i`. Given
+    /// the same RNG, creates the same ciphertext as `Self::encrypt`.
+    pub fn encrypt_given_context<R: RngCore>(
+        rng: &mut R,
+        message: &E::ScalarField,
+        ctx: &EncryptionContext<E>,
+    ) -> crate::Result<(Ciphertext<E>, E::ScalarField)> {
+        let m = utils::decompose(message, ctx.chunk_bit_size)?
+            .into_iter()
+            .map(<E::ScalarField as PrimeField>::BigInt::from)
+            .collect::<Vec<_>>();
+        let r = E::ScalarField::rand(rng);
+        let enc_chunks = cfg_iter!(ctx.X, utils::PARALLEL_MIN_CHUNKS)
+            .zip(cfg_iter!(ctx.g_i))
+            .zip(cfg_iter!(m))
+            .map(|((x, g), m)| x.multiply(&r).add(g.mul_bigint(*m)))
+            .collect::<Vec<_>>();
+        let psi = E::G1::msm_bigint(&ctx.Y, &m).add(ctx.P_1.multiply(&r));
+        Ok((
+            Ciphertext {
+                X_r: ctx.X_0.multiply(&r).into_affine(),
+                enc_chunks: E::G1::normalize_batch(&enc_chunks),
+                commitment: psi.into_affine(),
+            },
+            r,
+        ))
+    }
+
+    /// Decrypt `ciphertext` created with the keys in `old` and encrypt the message again with the keys in
+    /// `new`, eg. when moving ciphertexts to a different chunk size. Only for a trusted party holding the old
+    /// secret and decryption keys as it learns the message. Returns the new ciphertext and its randomness
+    /// which can be used to create a proof for it.
+    pub fn migrate_ciphertext<R: RngCore>(
+        rng: &mut R,
+        ciphertext: &Ciphertext<E>,
+        old: MigrationSourceKeys<E>,
+        new: &MigrationTargetKeys<E>,
+    ) -> crate::Result<(Ciphertext<E>, E::ScalarField)> {
+        // Decryption succeeds with a bigger chunk bit size than the real one but composes the chunks into
+        // a wrong message, so the old keys' chunk bit size must be the ciphertext's
+        utils::check_chunk_bit_size(old.chunk_bit_size)?;
+        let expected = utils::chunks_count::<E::ScalarField>(old.chunk_bit_size) as usize;
+        if ciphertext.enc_chunks.len() != expected {
+            return Err(SaverError::IncompatibleEncryptionKey(
+                ciphertext.enc_chunks.len(),
+                expected,
+            ));
+        }
+        let (message, _) = ciphertext.decrypt_given_groth16_vk(
+            old.sk,
+            old.dk,
+            old.snark_vk,
+            old.chunk_bit_size,
+        )?;
+        Self::encrypt_given_snark_vk(rng, &message, new.ek, new.snark_vk, new.chunk_bit_size)
+    }
+
+    /// Encrypt arbitrary bytes by encoding them as field elements (see `utils::bytes_to_field_elements`)
+    /// and encrypting each field element separately. Returns a ciphertext and randomness for each field element.
+    pub fn encrypt_bytes<R: RngCore>(
+        rng: &mut R,
+        data: &[u8],
+        ek: &EncryptionKey<E>,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        chunk_bit_size: u8,
+    ) -> crate::Result<Vec<(Ciphertext<E>, E::ScalarField)>> {
+        let g_i = saver_groth16::get_gs_for_encryption(snark_vk);
+        utils::bytes_to_field_elements::<E::ScalarField>(data)?
+            .iter()
+            .map(|m| Self::encrypt(rng, m, ek, g_i, chunk_bit_size))
+            .collect()
+    }
+
+    /// Encrypt the message and create a Pedersen commitment to it with the given blinding. Returns them along
+    /// with the commitment keys and openings needed to prove that the ciphertext and commitment have the
+    /// same message.
+    pub fn encrypt_committed<R: RngCore>(
+        rng: &mut R,
+        message: &E::ScalarField,
+        blinding: &E::ScalarField,
+        chunked_comm_gens: &ChunkedCommitmentGens<E::G1Affine>,
+        ek: &EncryptionKey<E>,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        chunk_bit_size: u8,
+    ) -> crate::Result<CommittedEncryption<E>> {
+        let (ciphertext, r) =
+            Self::encrypt_given_snark_vk(rng, message, ek, snark_vk, chunk_bit_size)?;
+        let ChunkedCommitment(commitment, chunked_comm_key) =
+            ChunkedCommitment::new(message, blinding, chunk_bit_size, chunked_comm_gens)?;
+        let decomposed_message = utils::decompose(message, chunk_bit_size)?
+            .into_iter()
+            .map(E::ScalarField::from)
+            .collect();
+        Ok(CommittedEncryption {
+            ciphertext,
+            r,
+            commitment,
+            blinding: *blinding,
+            chunked_comm_key,
+            ct_comm_key: Ciphertext::commitment_bases(ek),
+            decomposed_message,
+        })
+    }
+
+    /// Encrypt many messages, in parallel when the `parallel` feature is enabled. The randomness for the
+    /// `i`-th message comes from `utils::rng_for_batch_item(seed, i)` so the output depends only on the
+    /// inputs and `seed`, and not on the feature or the number of threads. It's the same as encrypting each
+    /// message in order with `Self::encrypt_given_snark_vk` using that RNG. `seed` must be kept secret
+    /// like any other encryption randomness and never be reused.
+    pub fn encrypt_batch_parallel(
+        messages: &[E::ScalarField],
+        ek: &EncryptionKey<E>,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        chunk_bit_size: u8,
+        seed: &[u8; 32],
+    ) -> crate::Result<Vec<(Ciphertext<E>, E::ScalarField)>> {
+        cfg_iter!(messages)
+            .enumerate()
+            .map(|(i, m)| {
+                let mut rng = utils::rng_for_batch_item(seed, i);
+                Self::encrypt_given_snark_vk(&mut rng, m, ek, snark_vk, chunk_bit_size)
+            })
+            .collect()
+    }
+
     /// Same as `Self::encrypt` but outputs sum `r*X_1 + r*X_2 + .. + r*X_n` as well
     // XXX: Is this secure?
     pub fn encrypt_alt<R: RngCore>(
@@ -291,17 +990,100 @@ impl<E: Pairing> Encryption<E> {
         Ok((utils::compose(&chunks, chunk_bit_size)?, nu))
     }
 
-    /// Same as `Self::decrypt` but takes Groth16's verification key instead of the generators used for Elgamal encryption
-    pub fn decrypt_given_groth16_vk(
+    /// Same as `Self::decrypt_given_pairing_powers` but the discrete log of each chunk is found by scanning
+    /// the complete row of the serialized pairing powers in `table` with constant-time comparisons, so that
+    /// the lookup of a chunk in the table doesn't depend on its value, unlike the hash map or early exit of
+    /// the other decryptions. This is slower than `Self::decrypt_given_pairing_powers` and is meant for
+    /// decryptors concerned about timing side channels. Only the lookup is done this way; the pairings,
+    /// the serialization and composing the chunks into the message are not written to be constant time.
+    /// `table` must have been created from the pairing powers of `dk`, which `ObliviousTable::new` doesn't
+    /// check, and for the chunk bit size of the ciphertext, otherwise `SaverError::ChunkBitSizeMismatch`
+    /// is returned.
+    pub fn decrypt_oblivious(
         c_0: &E::G1Affine,
         c: &[E::G1Affine],
         sk: &SecretKey<E::ScalarField>,
         dk: impl Into<PreparedDecryptionKey<E>>,
-        snark_vk: &ark_groth16::VerifyingKey<E>,
-        chunk_bit_size: u8,
+        g_i: &[E::G1Affine],
+        table: &ObliviousTable<E>,
     ) -> crate::Result<(E::ScalarField, E::G1Affine)> {
-        let g_i = saver_groth16::get_gs_for_encryption(snark_vk);
-        Self::decrypt(c_0, c, sk, dk, g_i, chunk_bit_size)
+        let dk = dk.into();
+        let n = c.len();
+        if n != dk.supported_chunks_count()? as usize {
+            return Err(SaverError::IncompatibleDecryptionKey(
+                n,
+                dk.supported_chunks_count()? as usize,
+            ));
+        }
+        if n > g_i.len() {
+            return Err(SaverError::InsufficientGenerators {
+                have: g_i.len(),
+                need: n,
+                source: "g_i",
+            });
+        }
+        if utils::chunks_count::<E::ScalarField>(table.chunk_bit_size) as usize != n {
+            return Err(SaverError::ChunkBitSizeMismatch {
+                chunk_bit_size: table.chunk_bit_size,
+                chunks_count: n,
+            });
+        }
+        if table.rows.len() < n {
+            return Err(SaverError::InvalidPairingPowers);
+        }
+        // c_0 * -rho
+        let c_0_rho = c_0.mul_bigint((-sk.0).into_bigint());
+        let c_0_rho_prepared = E::G1Prepared::from(c_0_rho.into_affine());
+        let mut decrypted_chunks = Vec::with_capacity(n);
+        for (((c_i, v_1), v_2), row) in c
+            .iter()
+            .zip(dk.V_1.iter())
+            .zip(dk.V_2.iter())
+            .zip(table.rows.iter())
+        {
+            let p = Self::pairing_output_bytes(&E::multi_pairing(
+                [(*c_i).into(), c_0_rho_prepared.clone()],
+                [v_2.clone(), v_1.clone()],
+            ));
+            // Chunk 0 corresponds to the identity which isn't part of the table
+            let mut found = utils::ct_eq_bytes(&p, &table.identity);
+            let mut chunk: CHUNK_TYPE = 0;
+            for (j, power) in row.iter().enumerate() {
+                let eq = utils::ct_eq_bytes(&p, power);
+                // All bits set if equal, none otherwise
+                let mask = (0 as CHUNK_TYPE).wrapping_sub(eq as CHUNK_TYPE);
+                chunk |= mask & (j as CHUNK_TYPE + 1);
+                found |= eq;
+            }
+            if found == 0 {
+                return Err(SaverError::CouldNotFindDiscreteLog);
+            }
+            decrypted_chunks.push(chunk);
+        }
+        Ok((
+            utils::compose(&decrypted_chunks, table.chunk_bit_size)?,
+            (-c_0_rho).into_affine(),
+        ))
+    }
+
+    /// Same as `Self::decrypt` but takes Groth16's verification key instead of the generators used for Elgamal encryption
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(chunks_count = c.len(), chunk_bit_size = chunk_bit_size)
+        )
+    )]
+    pub fn decrypt_given_groth16_vk(
+        c_0: &E::G1Affine,
+        c: &[E::G1Affine],
+        sk: &SecretKey<E::ScalarField>,
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        chunk_bit_size: u8,
+    ) -> crate::Result<(E::ScalarField, E::G1Affine)> {
+        let g_i = saver_groth16::get_gs_for_encryption(snark_vk);
+        Self::decrypt(c_0, c, sk, dk, g_i, chunk_bit_size)
     }
 
     /// Same as `Self::decrypt` but takes Groth16's verification key and the
@@ -319,6 +1101,25 @@ impl<E: Pairing> Encryption<E> {
         Self::decrypt_given_pairing_powers(c_0, c, sk, dk, g_i, chunk_bit_size, pairing_powers)
     }
 
+    /// Decrypt the ciphertexts created by `Self::encrypt_bytes` and return the bytes
+    pub fn decrypt_bytes(
+        ciphertexts: &[Ciphertext<E>],
+        sk: &SecretKey<E::ScalarField>,
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        chunk_bit_size: u8,
+    ) -> crate::Result<Vec<u8>> {
+        let dk = dk.into();
+        let elems = ciphertexts
+            .iter()
+            .map(|ct| {
+                ct.decrypt_given_groth16_vk(sk, dk.clone(), snark_vk, chunk_bit_size)
+                    .map(|(m, _)| m)
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        utils::field_elements_to_bytes(&elems)
+    }
+
     /// Same as `Self::decrypt` but takes LegoGroth16's verification key instead of the generators used for Elgamal encryption
     pub fn decrypt_given_legogroth16_vk(
         c_0: &E::G1Affine,
@@ -334,6 +1135,7 @@ impl<E: Pairing> Encryption<E> {
 
     /// Verify that commitment created during encryption opens to the message chunk
     /// Check `e(c_0, Z_0) * e(c_1, Z_1) * ... * e(c_n, Z_n)` mentioned in "Verify_Enc" in algorithm 2
+    #[must_use = "a failed verification is only reported through the returned value"]
     pub fn verify_ciphertext_commitment(
         c_0: &E::G1Affine,
         c: &[E::G1Affine],
@@ -343,6 +1145,7 @@ impl<E: Pairing> Encryption<E> {
     ) -> crate::Result<()> {
         let ek = ek.into();
         let gens = gens.into();
+        ek.check_gens_fingerprint(&gens.fingerprint)?;
         let expected_count = ek.supported_chunks_count()? as usize;
         if c.len() != expected_count {
             return Err(SaverError::IncompatibleEncryptionKey(
@@ -362,6 +1165,27 @@ impl<E: Pairing> Encryption<E> {
         }
     }
 
+    /// Verify the commitment and the snark proof of each ciphertext like `Ciphertext::verify_commitment_and_proof`
+    /// but don't stop at the first failure, eg. to report every invalid item of a batch. Returns the result
+    /// for each item, in the order of `items`. The keys are prepared once for all items, and with the
+    /// `parallel` feature the items are verified in parallel.
+    #[must_use = "a failed verification is only reported through the returned value"]
+    pub fn verify_batch_collecting(
+        items: &[(Ciphertext<E>, ark_groth16::Proof<E>)],
+        snark_vk: &ark_groth16::PreparedVerifyingKey<E>,
+        ek: impl Into<PreparedEncryptionKey<E>>,
+        gens: impl Into<PreparedEncryptionGens<E>>,
+    ) -> Vec<crate::Result<()>> {
+        let ek = ek.into();
+        let gens = gens.into();
+        cfg_iter!(items)
+            .map(|(ct, proof)| {
+                ct.verify_commitment_and_proof(proof, snark_vk, ek.clone(), gens.clone())
+            })
+            .collect()
+    }
+
+    #[must_use = "a failed verification is only reported through the returned value"]
     pub fn verify_commitments_in_batch(
         ciphertexts: &[Ciphertext<E>],
         r_powers: &[E::ScalarField],
@@ -371,6 +1195,7 @@ impl<E: Pairing> Encryption<E> {
         assert_eq!(r_powers.len(), ciphertexts.len());
         let ek = ek.into();
         let gens = gens.into();
+        ek.check_gens_fingerprint(&gens.fingerprint)?;
         let expected_count = ek.supported_chunks_count()? as usize;
         for c in ciphertexts {
             if c.enc_chunks.len() != expected_count {
@@ -392,6 +1217,12 @@ impl<E: Pairing> Encryption<E> {
     }
 
     /// Verify that ciphertext can be correctly decrypted to the given message chunks. This is "Verify_Dec" from algorithm 2 in the paper.
+    /// No field inversions are needed, `nu` is negated once and the work for each chunk is a scalar
+    /// multiplication and a multi-pairing as counted by `Ciphertext::verify_decryption_cost`.
+    /// `nu` is checked first, failing with `SaverError::InvalidDecryption`, and then the chunks in order,
+    /// stopping at the first wrong chunk with `SaverError::InvalidDecryptedChunk` with its index so a wrong
+    /// claim from an untrusted decryptor costs no more pairings than needed to find it.
+    #[must_use = "a failed verification is only reported through the returned value"]
     pub fn verify_decryption(
         messages: &[CHUNK_TYPE],
         c_0: &E::G1Affine,
@@ -410,10 +1241,11 @@ impl<E: Pairing> Encryption<E> {
             ));
         }
         if messages.len() > g_i.len() {
-            return Err(SaverError::VectorShorterThanExpected(
-                messages.len(),
-                g_i.len(),
-            ));
+            return Err(SaverError::InsufficientGenerators {
+                have: g_i.len(),
+                need: messages.len(),
+                source: "g_i",
+            });
         }
 
         let nu_prepared = E::G1Prepared::from(*nu);
@@ -423,7 +1255,7 @@ impl<E: Pairing> Encryption<E> {
             return Err(SaverError::InvalidDecryption);
         }
         for i in 0..messages.len() {
-            let g_i_m_i = g_i[i].mul(E::ScalarField::from(messages[i] as u64));
+            let g_i_m_i = g_i[i].mul(E::ScalarField::from(messages[i]));
             // e(g_i * m_i, dk.V_2_i) * e(-c_i, dk.V_2_i) = e(g_i * m_i - c_i, dk.V_2_i)
             let g_i_m_i_c_i = g_i_m_i.sub(&c[i]);
             if !E::multi_pairing(
@@ -432,13 +1264,14 @@ impl<E: Pairing> Encryption<E> {
             )
             .is_zero()
             {
-                return Err(SaverError::InvalidDecryption);
+                return Err(SaverError::InvalidDecryptedChunk(i));
             }
         }
         Ok(())
     }
 
     /// Same as `Self::verify_decryption` but takes Groth16's verification key instead of the generators used for Elgamal encryption
+    #[must_use = "a failed verification is only reported through the returned value"]
     pub fn verify_decryption_given_groth16_vk(
         messages: &[CHUNK_TYPE],
         c_0: &E::G1Affine,
@@ -452,7 +1285,102 @@ impl<E: Pairing> Encryption<E> {
         Self::verify_decryption(messages, c_0, c, nu, dk, g_i, gens)
     }
 
+    /// Verify decryptions `(ciphertext, message, nu)` returned by a decryptor with the same decryption key
+    /// together. The checks of `Self::verify_decryption` for all decryptions are combined into a single
+    /// multi-pairing of `2 * chunks_count + 2` pairings using a random weight for each decryption and each
+    /// chunk, so it fails with `SaverError::InvalidDecryption` if any decryption is wrong, except with
+    /// negligible probability, but doesn't tell which. Verify them individually to find the wrong ones.
+    #[must_use = "a failed verification is only reported through the returned value"]
+    pub fn verify_decryptions_in_batch<R: RngCore>(
+        rng: &mut R,
+        decryptions: &[(Ciphertext<E>, E::ScalarField, E::G1Affine)],
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        gens: impl Into<PreparedEncryptionGens<E>>,
+        chunk_bit_size: u8,
+    ) -> crate::Result<()> {
+        if decryptions.is_empty() {
+            return Ok(());
+        }
+        let dk = dk.into();
+        let gens = gens.into();
+        let n = dk.supported_chunks_count()? as usize;
+        let g_i = saver_groth16::get_gs_for_encryption(snark_vk);
+        if n > g_i.len() {
+            return Err(SaverError::InsufficientGenerators {
+                have: g_i.len(),
+                need: n,
+                source: "g_i",
+            });
+        }
+        let mut messages = Vec::with_capacity(decryptions.len());
+        for (ct, message, _) in decryptions {
+            if ct.enc_chunks.len() != n {
+                return Err(SaverError::IncompatibleDecryptionKey(
+                    ct.enc_chunks.len(),
+                    n,
+                ));
+            }
+            let chunks = utils::decompose(message, chunk_bit_size)?;
+            if chunks.len() != n {
+                return Err(SaverError::IncompatibleDecryptionKey(chunks.len(), n));
+            }
+            messages.push(chunks);
+        }
+
+        // Weight `r_j` for the `j`-th decryption, `s_i` for the `i`-th chunk and `t` for the check of `nu`
+        let r = (0..decryptions.len())
+            .map(|_| E::ScalarField::rand(rng))
+            .collect::<Vec<_>>();
+        let s = (0..n)
+            .map(|_| E::ScalarField::rand(rng))
+            .collect::<Vec<_>>();
+        let t = E::ScalarField::rand(rng);
+
+        // sum_j r_j * nu_j and sum_j r_j * c_0_j
+        let nus = decryptions.iter().map(|(_, _, nu)| *nu).collect::<Vec<_>>();
+        let c_0s = decryptions
+            .iter()
+            .map(|(ct, _, _)| ct.X_r)
+            .collect::<Vec<_>>();
+        let nu = E::G1::msm_unchecked(&nus, &r);
+        let c_0 = E::G1::msm_unchecked(&c_0s, &r);
+
+        // For each chunk `i`, s_i * (g_i * sum_j r_j * m_j_i - sum_j r_j * c_j_i) is paired with V_2_i
+        // and s_i * sum_j r_j * nu_j with V_1_i
+        let (a, b): (Vec<_>, Vec<_>) = cfg_into_iter!(0..n)
+            .map(|i| {
+                let m_i = cfg_iter!(messages)
+                    .zip(cfg_iter!(r))
+                    .map(|(m, r)| *r * E::ScalarField::from(m[i]))
+                    .sum::<E::ScalarField>();
+                let c_i = decryptions
+                    .iter()
+                    .map(|(ct, _, _)| ct.enc_chunks[i])
+                    .collect::<Vec<_>>();
+                let c_i = E::G1::msm_unchecked(&c_i, &r);
+                ((g_i[i].mul(m_i) - c_i) * s[i], nu * s[i])
+            })
+            .unzip();
+
+        let mut g1 = a;
+        g1.extend(b);
+        g1.push(-(nu * t));
+        g1.push(c_0 * t);
+        let g1 = E::G1::normalize_batch(&g1);
+        let mut g2 = dk.V_2.clone();
+        g2.extend(dk.V_1.iter().cloned());
+        g2.push(gens.H);
+        g2.push(dk.V_0);
+        if E::multi_pairing(g1, g2).is_zero() {
+            Ok(())
+        } else {
+            Err(SaverError::InvalidDecryption)
+        }
+    }
+
     /// Same as `Self::verify_decryption` but takes LegoGroth16's verification key instead of the generators used for Elgamal encryption
+    #[must_use = "a failed verification is only reported through the returned value"]
     pub fn verify_decryption_given_legogroth16_vk(
         messages: &[CHUNK_TYPE],
         c_0: &E::G1Affine,
@@ -491,50 +1419,116 @@ impl<E: Pairing> Encryption<E> {
         pairing_powers: Option<&[Vec<PairingOutput<E>>]>,
     ) -> crate::Result<(Vec<CHUNK_TYPE>, E::G1Affine)> {
         let dk = dk.into();
-        let n = c.len();
-        if n != dk.supported_chunks_count()? as usize {
-            return Err(SaverError::IncompatibleDecryptionKey(
-                n,
-                dk.supported_chunks_count()? as usize,
-            ));
-        }
-        if n > g_i.len() {
-            return Err(SaverError::VectorShorterThanExpected(n, g_i.len()));
-        }
-        // c_0 * -rho
-        let c_0_rho = c_0.mul_bigint((-sk.0).into_bigint());
+        let pairing_powers = if let Some(p) = pairing_powers { p } else { &[] };
+        let c_0_rho = Self::c_0_rho_for_decryption(c_0, c, sk, &dk, g_i, pairing_powers)?;
         let c_0_rho_prepared = E::G1Prepared::from(c_0_rho.into_affine());
-        let mut decrypted_chunks = vec![];
         let chunk_max_val: u32 = (1 << chunk_bit_size) - 1;
-        let pairing_powers = if let Some(p) = pairing_powers { p } else { &[] };
-        for i in 0..n {
-            let p = E::multi_pairing(
-                [c[i].into(), c_0_rho_prepared.clone()],
-                [dk.V_2[i].clone(), dk.V_1[i].clone()],
-            );
-            if p.is_zero() {
-                decrypted_chunks.push(0);
-                continue;
-            }
-
-            if pairing_powers.is_empty() {
-                // Precomputed powers are not provided, compute the necessary pairings
-                let g_i_v_i = E::pairing(E::G1Prepared::from(g_i[i]), dk.V_2[i].clone());
-                decrypted_chunks.push(Self::solve_discrete_log(
+        // Chunks are decrypted independently of each other
+        let decrypted_chunks = cfg_into_iter!(0..c.len())
+            .map(|i| {
+                Self::decrypt_chunk(
+                    i,
+                    &c[i],
+                    &c_0_rho_prepared,
+                    &dk,
+                    g_i,
                     chunk_max_val as CHUNK_TYPE,
-                    g_i_v_i,
-                    p,
-                )?);
-            } else {
-                decrypted_chunks.push(Self::solve_discrete_log_using_pairing_powers(
+                    pairing_powers,
+                )
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok((decrypted_chunks, (-c_0_rho).into_affine()))
+    }
+
+    /// Same as `Self::decrypt_to_chunks` but writes the chunks to `scratch` rather than allocating a vector
+    /// for them, and uses the pairing powers in `scratch` if it has them. Returns the "commitment" to the
+    /// randomness.
+    pub fn decrypt_to_chunks_with_scratch(
+        c_0: &E::G1Affine,
+        c: &[E::G1Affine],
+        sk: &SecretKey<E::ScalarField>,
+        dk: &PreparedDecryptionKey<E>,
+        g_i: &[E::G1Affine],
+        chunk_bit_size: u8,
+        scratch: &mut DecryptScratch<E>,
+    ) -> crate::Result<E::G1Affine> {
+        let c_0_rho = Self::c_0_rho_for_decryption(c_0, c, sk, dk, g_i, &scratch.pairing_powers)?;
+        let c_0_rho_prepared = E::G1Prepared::from(c_0_rho.into_affine());
+        let chunk_max_val: u32 = (1 << chunk_bit_size) - 1;
+        // Only allocates if the buffer is smaller than any used before
+        scratch.chunks.clear();
+        scratch.chunks.resize(c.len(), 0);
+        let pairing_powers = &scratch.pairing_powers;
+        cfg_iter_mut!(scratch.chunks)
+            .enumerate()
+            .try_for_each(|(i, chunk)| {
+                *chunk = Self::decrypt_chunk(
                     i,
+                    &c[i],
+                    &c_0_rho_prepared,
+                    dk,
+                    g_i,
                     chunk_max_val as CHUNK_TYPE,
-                    p,
                     pairing_powers,
-                )?);
-            }
+                )?;
+                Ok::<(), SaverError>(())
+            })?;
+        Ok((-c_0_rho).into_affine())
+    }
+
+    /// Check that the ciphertext's chunks `c` can be decrypted with the decryption key, `g_i` and the pairing
+    /// powers, if not empty, and return `c_0 * -rho`
+    fn c_0_rho_for_decryption(
+        c_0: &E::G1Affine,
+        c: &[E::G1Affine],
+        sk: &SecretKey<E::ScalarField>,
+        dk: &PreparedDecryptionKey<E>,
+        g_i: &[E::G1Affine],
+        pairing_powers: &[Vec<PairingOutput<E>>],
+    ) -> crate::Result<E::G1> {
+        let n = c.len();
+        let expected = dk.supported_chunks_count()? as usize;
+        if n != expected {
+            return Err(SaverError::IncompatibleDecryptionKey(n, expected));
+        }
+        if n > g_i.len() {
+            return Err(SaverError::InsufficientGenerators {
+                have: g_i.len(),
+                need: n,
+                source: "g_i",
+            });
+        }
+        if !pairing_powers.is_empty() && pairing_powers.len() != n {
+            return Err(SaverError::InvalidPairingPowers);
+        }
+        Ok(c_0.mul_bigint((-sk.0).into_bigint()))
+    }
+
+    /// Decrypt the `i`-th chunk `c_i` given `c_0 * -rho`
+    fn decrypt_chunk(
+        i: usize,
+        c_i: &E::G1Affine,
+        c_0_rho_prepared: &E::G1Prepared,
+        dk: &PreparedDecryptionKey<E>,
+        g_i: &[E::G1Affine],
+        chunk_max_val: CHUNK_TYPE,
+        pairing_powers: &[Vec<PairingOutput<E>>],
+    ) -> crate::Result<CHUNK_TYPE> {
+        let p = E::multi_pairing(
+            [(*c_i).into(), c_0_rho_prepared.clone()],
+            [dk.V_2[i].clone(), dk.V_1[i].clone()],
+        );
+        if p.is_zero() {
+            return Ok(0);
+        }
+
+        if pairing_powers.is_empty() {
+            // Precomputed powers are not provided, compute the necessary pairings
+            let g_i_v_i = E::pairing(E::G1Prepared::from(g_i[i]), dk.V_2[i].clone());
+            Self::solve_discrete_log(chunk_max_val, g_i_v_i, p)
+        } else {
+            Self::solve_discrete_log_using_pairing_powers(i, chunk_max_val, p, pairing_powers)
         }
-        Ok((decrypted_chunks, (-c_0_rho).into_affine()))
     }
 
     /// Encrypt once the message has been broken into chunks
@@ -544,6 +1538,18 @@ impl<E: Pairing> Encryption<E> {
         ek: &EncryptionKey<E>,
         g_i: &[E::G1Affine],
     ) -> crate::Result<(Vec<E::G1Affine>, E::ScalarField)> {
+        Self::check_decomposed_message(&message_chunks, ek, g_i)?;
+        let r = E::ScalarField::rand(rng);
+        let ct = Self::encrypt_decomposed_message_given_randomness(message_chunks, &r, ek, g_i);
+        Ok((ct, r))
+    }
+
+    /// Check that the chunks can be encrypted with the encryption key and `g_i`
+    fn check_decomposed_message(
+        message_chunks: &[CHUNK_TYPE],
+        ek: &EncryptionKey<E>,
+        g_i: &[E::G1Affine],
+    ) -> crate::Result<()> {
         let expected_count = ek.supported_chunks_count()? as usize;
         if message_chunks.len() != expected_count {
             return Err(SaverError::IncompatibleEncryptionKey(
@@ -552,28 +1558,46 @@ impl<E: Pairing> Encryption<E> {
             ));
         }
         if message_chunks.len() > g_i.len() {
-            return Err(SaverError::VectorShorterThanExpected(
-                message_chunks.len(),
-                g_i.len(),
-            ));
+            return Err(SaverError::InsufficientGenerators {
+                have: g_i.len(),
+                need: message_chunks.len(),
+                source: "g_i",
+            });
         }
-        let r = E::ScalarField::rand(rng);
+        let chunk_bit_size =
+            utils::chunk_bit_size_for_chunks_count::<E::ScalarField>(expected_count)
+                .ok_or(SaverError::InvalidDecomposition)?;
+        utils::check_chunks_range(message_chunks, chunk_bit_size)
+    }
+
+    /// Deterministic part of `Self::encrypt_decomposed_message`. Expects the number of chunks to have been
+    /// checked against the encryption key and `g_i`
+    fn encrypt_decomposed_message_given_randomness(
+        message_chunks: Vec<CHUNK_TYPE>,
+        r: &E::ScalarField,
+        ek: &EncryptionKey<E>,
+        g_i: &[E::G1Affine],
+    ) -> Vec<E::G1Affine> {
         let r_repr = r.into_bigint();
         let mut ct = vec![];
         ct.push(ek.X_0.mul_bigint(r_repr));
-        let mut m = cfg_into_iter!(message_chunks)
-            .map(|m_i| <E::ScalarField as PrimeField>::BigInt::from(m_i as u64))
+        let mut m = cfg_into_iter!(message_chunks, utils::PARALLEL_MIN_CHUNKS)
+            .map(<E::ScalarField as PrimeField>::BigInt::from)
             .collect::<Vec<_>>();
-        for i in 0..ek.X.len() {
-            ct.push(ek.X[i].mul_bigint(r_repr).add(g_i[i].mul_bigint(m[i])));
-        }
+        ct.extend(
+            cfg_iter!(ek.X, utils::PARALLEL_MIN_CHUNKS)
+                .zip(cfg_iter!(g_i[..ek.X.len()]))
+                .zip(cfg_iter!(m))
+                .map(|((x, g), m)| x.mul_bigint(r_repr).add(g.mul_bigint(*m)))
+                .collect::<Vec<_>>(),
+        );
 
         // Commit to the message chunks with randomness `r`
         m.push(r.into_bigint());
-        let psi = E::G1::msm_bigint(&ek.commitment_key(), &m);
+        let psi = E::G1::msm_bigint(&Ciphertext::commitment_bases(ek), &m);
 
         ct.push(psi);
-        Ok((E::G1::normalize_batch(&ct), r))
+        E::G1::normalize_batch(&ct)
     }
 
     /// Does not use precomputation
@@ -595,6 +1619,12 @@ impl<E: Pairing> Encryption<E> {
         Err(SaverError::CouldNotFindDiscreteLog)
     }
 
+    fn pairing_output_bytes(p: &PairingOutput<E>) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(p.uncompressed_size());
+        p.serialize_uncompressed(&mut bytes).unwrap();
+        bytes
+    }
+
     /// Relies on precomputation
     fn solve_discrete_log_using_pairing_powers(
         chunk_index: usize,
@@ -675,449 +1705,3055 @@ impl<E: Pairing> Encryption<E> {
     }
 }
 
+/// Ciphertext as a tuple `(X_r, enc_chunks, commitment)`. See `Ciphertext::into_parts`
+pub type CiphertextParts<E> = (
+    <E as Pairing>::G1Affine,
+    Vec<<E as Pairing>::G1Affine>,
+    <E as Pairing>::G1Affine,
+);
+
 impl<E: Pairing> Ciphertext<E> {
     impl_enc_funcs!();
 
-    /// Verify ciphertext commitment and snark proof
-    pub fn verify_commitment_and_proof(
-        &self,
-        proof: &ark_groth16::Proof<E>,
-        snark_vk: &ark_groth16::PreparedVerifyingKey<E>,
-        ek: impl Into<PreparedEncryptionKey<E>>,
-        gens: impl Into<PreparedEncryptionGens<E>>,
-    ) -> crate::Result<()> {
-        self.verify_commitment(ek, gens)?;
-        saver_groth16::verify_proof(snark_vk, proof, self)
-    }
-
-    pub fn decrypt_given_groth16_vk(
-        &self,
-        sk: &SecretKey<E::ScalarField>,
-        dk: impl Into<PreparedDecryptionKey<E>>,
-        snark_vk: &ark_groth16::VerifyingKey<E>,
-        chunk_bit_size: u8,
-    ) -> crate::Result<(E::ScalarField, E::G1Affine)> {
-        let g_i = saver_groth16::get_gs_for_encryption(snark_vk);
-        self.decrypt(sk, dk, g_i, chunk_bit_size)
-    }
-
-    pub fn decrypt_given_groth16_vk_and_pairing_powers(
-        &self,
-        sk: &SecretKey<E::ScalarField>,
-        dk: impl Into<PreparedDecryptionKey<E>>,
-        snark_vk: &ark_groth16::VerifyingKey<E>,
-        chunk_bit_size: u8,
-        pairing_powers: &[Vec<PairingOutput<E>>],
-    ) -> crate::Result<(E::ScalarField, E::G1Affine)> {
-        let g_i = saver_groth16::get_gs_for_encryption(snark_vk);
-        self.decrypt_given_pairing_powers(sk, dk, g_i, chunk_bit_size, pairing_powers)
+    /// Create a ciphertext from a tuple `(X_r, enc_chunks, commitment)` where `X_r` is `X_0 * r`
+    /// (`c_0` in the paper), `enc_chunks` are the encrypted chunks `X_i * r + G_i * m_i` (`c_1, .., c_n`)
+    /// in the order of the chunks and `commitment` is `psi`. The number of encrypted chunks must correspond
+    /// to one of the supported chunk bit sizes.
+    pub fn from_parts(parts: CiphertextParts<E>) -> crate::Result<Self> {
+        let (X_r, enc_chunks, commitment) = parts;
+        if utils::chunk_bit_size_for_chunks_count::<E::ScalarField>(enc_chunks.len()).is_none() {
+            return Err(SaverError::InvalidDecomposition);
+        }
+        Ok(Self {
+            X_r,
+            enc_chunks,
+            commitment,
+        })
     }
 
-    pub fn verify_decryption_given_groth16_vk(
-        &self,
-        message: &E::ScalarField,
-        nu: &E::G1Affine,
-        chunk_bit_size: u8,
-        dk: impl Into<PreparedDecryptionKey<E>>,
-        snark_vk: &ark_groth16::VerifyingKey<E>,
-        gens: impl Into<PreparedEncryptionGens<E>>,
-    ) -> crate::Result<()> {
-        let g_i = saver_groth16::get_gs_for_encryption(snark_vk);
-        self.verify_decryption(message, nu, chunk_bit_size, dk, g_i, gens)
+    /// Return the tuple `(X_r, enc_chunks, commitment)`. See `Self::from_parts` for their meaning
+    pub fn into_parts(self) -> CiphertextParts<E> {
+        (self.X_r, self.enc_chunks, self.commitment)
     }
-}
-
-impl<E: Pairing> CiphertextAlt<E> {
-    impl_enc_funcs!();
 
-    pub fn decrypt_given_legogroth16_vk(
+    /// Same as `Self::decrypt` but reuses the buffer in `scratch` for the decrypted chunks, and takes the
+    /// decryption key prepared so that it isn't prepared for each ciphertext. Meant for decrypting many
+    /// ciphertexts with the same key. Uses the pairing powers in `scratch` if it has them.
+    pub fn decrypt_with_scratch(
         &self,
         sk: &SecretKey<E::ScalarField>,
-        dk: impl Into<PreparedDecryptionKey<E>>,
-        snark_vk: &legogroth16::VerifyingKey<E>,
+        dk: &PreparedDecryptionKey<E>,
+        g_i: &[E::G1Affine],
         chunk_bit_size: u8,
+        scratch: &mut DecryptScratch<E>,
     ) -> crate::Result<(E::ScalarField, E::G1Affine)> {
-        Encryption::decrypt_given_legogroth16_vk(
+        self.check_chunk_bit_size(chunk_bit_size)?;
+        let nu = Encryption::decrypt_to_chunks_with_scratch(
             &self.X_r,
             &self.enc_chunks,
             sk,
             dk,
-            snark_vk,
+            g_i,
             chunk_bit_size,
-        )
+            scratch,
+        )?;
+        Ok((utils::compose(&scratch.chunks, chunk_bit_size)?, nu))
     }
 
-    pub fn verify_decryption_given_legogroth16_vk(
-        &self,
-        message: &E::ScalarField,
-        chunk_bit_size: u8,
-        nu: &E::G1Affine,
-        dk: impl Into<PreparedDecryptionKey<E>>,
-        snark_vk: &legogroth16::VerifyingKey<E>,
-        gens: impl Into<PreparedEncryptionGens<E>>,
-    ) -> crate::Result<()> {
-        let decomposed = utils::decompose(message, chunk_bit_size)?;
-        Encryption::verify_decryption_given_legogroth16_vk(
-            &decomposed,
-            &self.X_r,
-            &self.enc_chunks,
-            nu,
-            dk,
-            snark_vk,
-            gens,
-        )
+    /// Deserialize from compressed bytes while checking that each point is in the correct subgroup
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        Self::deserialize_compressed(bytes)
     }
-}
 
-#[cfg(test)]
-pub(crate) mod tests {
-    use super::*;
-    use std::time::{Duration, Instant};
+    /// Same as `Self::from_bytes` but skips the subgroup checks. This is unsafe for untrusted input, like
+    /// a ciphertext received from a prover, and should only be used for ciphertexts from a trusted source.
+    pub fn from_bytes_unchecked(bytes: &[u8]) -> Result<Self, SerializationError> {
+        Self::deserialize_compressed_unchecked(bytes)
+    }
 
-    use crate::{
-        keygen::{keygen, DecryptionKey},
-        setup::EncryptionGens,
-        utils::{chunks_count, decompose},
-    };
-    use ark_bls12_381::Bls12_381;
-    use ark_ff::One;
-    use ark_std::rand::{prelude::StdRng, SeedableRng};
+    /// Write the compressed serialization preceded by its length as a varint (`utils::write_varint`), so that
+    /// several ciphertexts written one after another to a byte stream, like a socket, can be read back with
+    /// `Self::read_framed` or `Self::read_all_framed`
+    pub fn write_framed<W: Write>(&self, mut writer: W) -> Result<(), SaverError> {
+        let bytes = self.canonical_bytes();
+        utils::write_varint(bytes.len() as u64, &mut writer)?;
+        writer.write_all(&bytes).map_err(SerializationError::from)?;
+        Ok(())
+    }
 
-    type Fr = <Bls12_381 as Pairing>::ScalarField;
+    /// Read a ciphertext written by `Self::write_framed`, checking that each point is in the correct subgroup
+    /// like `Self::from_bytes`. Returns an error if the reader has ended or the frame isn't exactly a
+    /// ciphertext.
+    pub fn read_framed<R: Read>(reader: R) -> Result<Self, SaverError> {
+        Self::read_next_framed(reader)?.ok_or_else(|| SerializationError::NotEnoughSpace.into())
+    }
 
-    pub fn enc_setup<R: RngCore>(
-        chunk_bit_size: u8,
-        rng: &mut R,
-    ) -> (
-        EncryptionGens<Bls12_381>,
-        Vec<<Bls12_381 as Pairing>::G1Affine>,
-        SecretKey<<Bls12_381 as Pairing>::ScalarField>,
-        EncryptionKey<Bls12_381>,
-        DecryptionKey<Bls12_381>,
-    ) {
-        let n = chunks_count::<Fr>(chunk_bit_size) as usize;
-        let gens = EncryptionGens::<Bls12_381>::new_using_rng(rng);
-        let g_i = (0..n)
-            .map(|_| <Bls12_381 as Pairing>::G1Affine::rand(rng))
+    /// Read ciphertexts written by `Self::write_framed` until the reader ends. Returns an error if any of
+    /// them is invalid or the last one is truncated.
+    pub fn read_all_framed<R: Read>(mut reader: R) -> Result<Vec<Self>, SaverError> {
+        let mut cts = vec![];
+        while let Some(ct) = Self::read_next_framed(&mut reader)? {
+            cts.push(ct);
+        }
+        Ok(cts)
+    }
+
+    /// Read the next framed ciphertext or `None` if the reader has ended before it
+    fn read_next_framed<R: Read>(mut reader: R) -> Result<Option<Self>, SaverError> {
+        let len = match utils::read_varint(&mut reader)? {
+            Some(len) => usize::try_from(len).map_err(|_| SerializationError::InvalidData)?,
+            None => return Ok(None),
+        };
+        // Read in pieces so that a bogus length doesn't allocate more than what the reader has
+        let mut bytes = Vec::new();
+        let mut buf = [0u8; 1024];
+        while bytes.len() < len {
+            let l = ark_std::cmp::min(buf.len(), len - bytes.len());
+            reader
+                .read_exact(&mut buf[..l])
+                .map_err(SerializationError::from)?;
+            bytes.extend_from_slice(&buf[..l]);
+        }
+        let mut bytes = &bytes[..];
+        let ct = Self::deserialize_compressed(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(SerializationError::InvalidData.into());
+        }
+        Ok(Some(ct))
+    }
+
+    /// Work done by `Self::verify_commitment` for a ciphertext with `chunks_count` chunks
+    pub fn verify_commitment_cost(chunks_count: usize) -> VerificationCost {
+        VerificationCost {
+            // A multi-pairing with a pairing for `c_0`, each chunk and the commitment
+            pairings: chunks_count + 2,
+            g1_scalar_mults: 0,
+            g1_additions: 0,
+        }
+    }
+
+    /// Work done by `Self::verify_decryption` and `Self::verify_decryption_given_groth16_vk` for a
+    /// ciphertext with `chunks_count` chunks
+    pub fn verify_decryption_cost(chunks_count: usize) -> VerificationCost {
+        VerificationCost {
+            // A multi-pairing of size 2 for `nu` and one for each chunk
+            pairings: 2 * chunks_count + 2,
+            // `g_i * m_i` for each chunk
+            g1_scalar_mults: chunks_count,
+            // `g_i * m_i - c_i` for each chunk
+            g1_additions: chunks_count,
+        }
+    }
+
+    /// Compressed serialization, which is unique for a ciphertext. Ciphertexts are ordered by these bytes.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.compressed_size());
+        self.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Serialize to compressed bytes preceded by the identifier of the curve, see `utils::curve_id`. The
+    /// plain `CanonicalSerialize` encoding has no such byte, so a ciphertext of one curve might be read as
+    /// a ciphertext of another curve with the same point sizes when the subgroup checks are skipped.
+    pub fn to_bytes_with_curve_id(&self) -> Result<Vec<u8>, SaverError> {
+        let mut bytes = vec![utils::curve_id::<E>()];
+        self.serialize_compressed(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Deserialize bytes created by `Self::to_bytes_with_curve_id`. Returns `SaverError::CurveMismatch` if
+    /// they were created for a different curve, before reading any points.
+    pub fn from_bytes_with_curve_id(bytes: &[u8]) -> Result<Self, SaverError> {
+        let (got, rest) = bytes
+            .split_first()
+            .ok_or(SerializationError::NotEnoughSpace)?;
+        let expected = utils::curve_id::<E>();
+        if *got != expected {
+            return Err(SaverError::CurveMismatch {
+                expected,
+                got: *got,
+            });
+        }
+        Ok(Self::deserialize_compressed(rest)?)
+    }
+
+    /// Check whether this ciphertext is the encryption of `message` with randomness `r`, by encrypting again
+    /// and comparing. Only meant for tests and debugging as it needs the plaintext and the randomness; use
+    /// `Self::verify_commitment_and_proof` to verify a ciphertext without them. Same as `Self::verify_opening`
+    /// with the chunk bit size inferred from the number of chunks the encryption key supports.
+    #[must_use = "a failed verification is only reported through the returned value"]
+    pub fn is_encryption_of(
+        &self,
+        message: &E::ScalarField,
+        r: &E::ScalarField,
+        ek: &EncryptionKey<E>,
+        g_i: &[E::G1Affine],
+    ) -> bool {
+        ek.supported_chunks_count()
+            .ok()
+            .and_then(|n| utils::chunk_bit_size_for_chunks_count::<E::ScalarField>(n as usize))
+            .is_some_and(|b| self.verify_opening(message, r, ek, g_i, b).is_ok())
+    }
+
+    /// Verify that this ciphertext is the encryption of `message` with randomness `r` by encrypting again.
+    /// Unlike `Self::verify_decryption`, this needs neither the decryption key nor `nu`, so the encryptor can
+    /// publish `message` and `r`, eg. in a dispute, and anyone can check the ciphertext.
+    #[must_use = "a failed verification is only reported through the returned value"]
+    pub fn verify_opening(
+        &self,
+        message: &E::ScalarField,
+        r: &E::ScalarField,
+        ek: &EncryptionKey<E>,
+        g_i: &[E::G1Affine],
+        chunk_bit_size: u8,
+    ) -> crate::Result<()> {
+        let chunks = utils::decompose(message, chunk_bit_size)?;
+        Encryption::check_decomposed_message(&chunks, ek, g_i)?;
+        let n = chunks.len();
+        if self.enc_chunks.len() != n {
+            return Err(SaverError::IncompatibleEncryptionKey(
+                self.enc_chunks.len(),
+                n,
+            ));
+        }
+        let ct = Encryption::encrypt_decomposed_message_given_randomness(chunks, r, ek, g_i);
+        if ct[0] == self.X_r && ct[1..n + 1] == self.enc_chunks[..] && ct[n + 1] == self.commitment
+        {
+            Ok(())
+        } else {
+            Err(SaverError::InvalidOpening)
+        }
+    }
+
+    /// Bases of the commitment in the ciphertext, in the order that its witness
+    /// (`utils::ciphertext_commitment_witness`) must follow: `ek.Y`, one for each chunk of the big-endian
+    /// decomposition (`utils::decompose`), followed by `ek.P_1` for the randomness. Same as
+    /// `EncryptionKey::commitment_key`. Provers and verifiers should take the bases from here rather than
+    /// building them so that they agree on the order.
+    pub fn commitment_bases(ek: &EncryptionKey<E>) -> Vec<E::G1Affine> {
+        ek.commitment_key()
+    }
+
+    /// The commitment that a ciphertext encrypting `message` with randomness `r` under `ek` must carry,
+    /// i.e. `m_1*Y_1 + m_2*Y_2 + ... + m_n*Y_n + r*P_1`. Compare it with `self.commitment` to debug a failing
+    /// `Self::verify_commitment` or `Self::verify_opening`. The commitment hides the message so it can't be
+    /// recomputed from the ciphertext and `ek` alone and needs the opening, which only the encryptor knows.
+    pub fn recompute_commitment(
+        &self,
+        message: &E::ScalarField,
+        r: &E::ScalarField,
+        ek: &EncryptionKey<E>,
+        chunk_bit_size: u8,
+    ) -> crate::Result<E::G1Affine> {
+        let chunks = utils::decompose(message, chunk_bit_size)?;
+        let n = ek.supported_chunks_count()? as usize;
+        if chunks.len() != n {
+            return Err(SaverError::IncompatibleEncryptionKey(chunks.len(), n));
+        }
+        if self.enc_chunks.len() != n {
+            return Err(SaverError::IncompatibleEncryptionKey(
+                self.enc_chunks.len(),
+                n,
+            ));
+        }
+        let chunks = cfg_into_iter!(chunks)
+            .map(E::ScalarField::from)
             .collect::<Vec<_>>();
-        let delta = Fr::rand(rng);
-        let gamma = Fr::rand(rng);
-        let g_delta = gens.G.mul_bigint(delta.into_bigint()).into_affine();
-        let g_gamma = gens.G.mul_bigint(gamma.into_bigint()).into_affine();
-        let (sk, ek, dk) = keygen(rng, chunk_bit_size, &gens, &g_i, &g_delta, &g_gamma).unwrap();
-        (gens, g_i, sk, ek, dk)
+        let witness = utils::ciphertext_commitment_witness(&chunks, *r);
+        Ok(E::G1::msm_unchecked(&Self::commitment_bases(ek), &witness).into_affine())
+    }
+
+    /// Same as `Self::verify_opening` but takes the Groth16 verification key instead of `g_i`
+    #[must_use = "a failed verification is only reported through the returned value"]
+    pub fn verify_opening_given_groth16_vk(
+        &self,
+        message: &E::ScalarField,
+        r: &E::ScalarField,
+        ek: &EncryptionKey<E>,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        chunk_bit_size: u8,
+    ) -> crate::Result<()> {
+        let g_i = saver_groth16::get_gs_for_encryption(snark_vk);
+        self.verify_opening(message, r, ek, g_i, chunk_bit_size)
+    }
+
+    /// Weighted sum `sum_i weights[i] * ciphertexts[i]` of ciphertexts created with `ek`, computed with an MSM
+    /// for each component of the ciphertext. The randomness of the result is `sum_i weights[i] * r_i` where
+    /// `r_i` is the randomness of the `i`-th ciphertext. Its commitment can be verified with
+    /// `Self::verify_commitment` but there is no SNARK proof for it.
+    ///
+    /// The chunks are combined and not the messages, so the `j`-th chunk of the result encrypts
+    /// `sum_i weights[i] * m_i_j` where `m_i_j` is the `j`-th chunk of `m_i`. Decryption only succeeds if
+    /// this sum fits in a chunk, i.e. is less than `2^chunk_bit_size`, for every `j`, otherwise it fails
+    /// with `SaverError::CouldNotFindDiscreteLog`. When it succeeds, the result decrypts to
+    /// `sum_i weights[i] * m_i` where `m_i` is the message of the `i`-th ciphertext. Eg. with 16-bit chunks,
+    /// at most 2^16 - 1 messages of value 1 can be added, and messages spanning several chunks can overflow
+    /// even with small weights.
+    pub fn linear_combination(
+        ciphertexts: &[Self],
+        weights: &[u64],
+        ek: &EncryptionKey<E>,
+    ) -> crate::Result<Self> {
+        if ciphertexts.len() != weights.len() {
+            return Err(SaverError::UnequalSizeOfSequence(
+                ciphertexts.len(),
+                weights.len(),
+            ));
+        }
+        if ciphertexts.is_empty() {
+            return Err(SaverError::VectorShorterThanExpected(0, 1));
+        }
+        let n = ek.supported_chunks_count()? as usize;
+        if let Some(ct) = ciphertexts.iter().find(|ct| ct.enc_chunks.len() != n) {
+            return Err(SaverError::IncompatibleEncryptionKey(
+                ct.enc_chunks.len(),
+                n,
+            ));
+        }
+        let weights = weights
+            .iter()
+            .map(|w| <E::ScalarField as PrimeField>::BigInt::from(*w))
+            .collect::<Vec<_>>();
+        let msm = |bases: Vec<E::G1Affine>| E::G1::msm_bigint(&bases, &weights);
+        // Combine `X_r`, each encrypted chunk and the commitment, and normalize all at once
+        let mut combined = Vec::with_capacity(n + 2);
+        combined.push(msm(ciphertexts.iter().map(|ct| ct.X_r).collect()));
+        combined.extend(
+            cfg_into_iter!(0..n)
+                .map(|j| msm(ciphertexts.iter().map(|ct| ct.enc_chunks[j]).collect()))
+                .collect::<Vec<_>>(),
+        );
+        combined.push(msm(ciphertexts.iter().map(|ct| ct.commitment).collect()));
+        let mut combined = E::G1::normalize_batch(&combined);
+        let commitment = combined.pop().unwrap();
+        let X_r = combined.remove(0);
+        Ok(Self {
+            X_r,
+            enc_chunks: combined,
+            commitment,
+        })
+    }
+
+    /// Ciphertext of the message 0 with randomness 0 for a key supporting `chunk_bit_size`, i.e. all its
+    /// components are the identity. It is the identity for the addition of ciphertexts, so it is the sum of no
+    /// ciphertexts as returned by `Self::try_sum`. It is not hiding and should never be published as the
+    /// encryption of a message.
+    pub fn zero(ek: &EncryptionKey<E>, chunk_bit_size: u8) -> crate::Result<Self> {
+        utils::check_chunk_bit_size(chunk_bit_size)?;
+        let n = ek.supported_chunks_count()?;
+        let expected = utils::chunks_count::<E::ScalarField>(chunk_bit_size);
+        if n != expected {
+            return Err(SaverError::IncompatibleEncryptionKey(
+                expected as usize,
+                n as usize,
+            ));
+        }
+        Ok(Self::zero_with_chunks(n as usize))
+    }
+
+    fn zero_with_chunks(chunks_count: usize) -> Self {
+        Self {
+            X_r: E::G1Affine::zero(),
+            enc_chunks: vec![E::G1Affine::zero(); chunks_count],
+            commitment: E::G1Affine::zero(),
+        }
+    }
+
+    /// Sum of ciphertexts created with `ek`, like summing them with `Sum` but returns an error rather than
+    /// panicking. The sum of no ciphertexts is the zero ciphertext of `ek` (`Self::zero`) and
+    /// `SaverError::UnequalSizeOfSequence` is returned if a ciphertext doesn't have a chunk for each chunk
+    /// supported by `ek`. The overflow precondition of the addition applies to the sum.
+    pub fn try_sum<'a>(
+        ek: &EncryptionKey<E>,
+        ciphertexts: impl IntoIterator<Item = &'a Self>,
+    ) -> crate::Result<Self>
+    where
+        E: 'a,
+    {
+        let zero = Self::zero_with_chunks(ek.supported_chunks_count()? as usize);
+        ciphertexts
+            .into_iter()
+            .try_fold(zero, |acc, ct| acc.try_add(ct))
+    }
+
+    /// Homomorphic addition like `+` but returns `SaverError::UnequalSizeOfSequence` rather than panicking if
+    /// the ciphertexts have a different number of chunks
+    pub fn try_add(&self, other: &Self) -> crate::Result<Self> {
+        if self.enc_chunks.len() != other.enc_chunks.len() {
+            return Err(SaverError::UnequalSizeOfSequence(
+                self.enc_chunks.len(),
+                other.enc_chunks.len(),
+            ));
+        }
+        let mut sum = Vec::with_capacity(self.enc_chunks.len() + 2);
+        sum.push(self.X_r + other.X_r);
+        sum.extend(
+            self.enc_chunks
+                .iter()
+                .zip(other.enc_chunks.iter())
+                .map(|(a, b)| *a + *b),
+        );
+        sum.push(self.commitment + other.commitment);
+        let mut sum = E::G1::normalize_batch(&sum);
+        let commitment = sum.pop().unwrap();
+        let X_r = sum.remove(0);
+        Ok(Self {
+            X_r,
+            enc_chunks: sum,
+            commitment,
+        })
+    }
+
+    /// Prove that the messages encrypted in `ciphertexts` add up to the public `total` without revealing them,
+    /// eg. for publishing the total of private contributions. The ciphertexts are added homomorphically and
+    /// their sum is decrypted, and the returned `nu` proves that decryption like for `Self::decrypt`. Returns
+    /// `SaverError::SumNotEqualToTotal` if the sum decrypts to something else. As with `Self::linear_combination`,
+    /// the sum of the `j`-th chunks of the messages must be less than `2^chunk_bit_size` for every `j`,
+    /// otherwise the sum can't be decrypted. The ciphertexts themselves should be verified with their proofs,
+    /// the sum has no proof of its own.
+    pub fn prove_sum_equals(
+        ciphertexts: &[Self],
+        total: &E::ScalarField,
+        sk: &SecretKey<E::ScalarField>,
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        g_i: &[E::G1Affine],
+        chunk_bit_size: u8,
+    ) -> crate::Result<E::G1Affine> {
+        let (m, nu) = Self::checked_sum(ciphertexts)?.decrypt(sk, dk, g_i, chunk_bit_size)?;
+        if m != *total {
+            return Err(SaverError::SumNotEqualToTotal);
+        }
+        Ok(nu)
+    }
+
+    /// Verify the `nu` created by `Self::prove_sum_equals`, i.e. that the messages encrypted in `ciphertexts`
+    /// add up to `total`
+    #[must_use = "a failed verification is only reported through the returned value"]
+    pub fn verify_sum_equals(
+        ciphertexts: &[Self],
+        total: &E::ScalarField,
+        nu: &E::G1Affine,
+        chunk_bit_size: u8,
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        g_i: &[E::G1Affine],
+        gens: impl Into<PreparedEncryptionGens<E>>,
+    ) -> crate::Result<()> {
+        Self::checked_sum(ciphertexts)?.verify_decryption(total, nu, chunk_bit_size, dk, g_i, gens)
+    }
+
+    /// Sum of at least one ciphertext, all with the same number of chunks
+    fn checked_sum(ciphertexts: &[Self]) -> crate::Result<Self> {
+        let (first, rest) = ciphertexts
+            .split_first()
+            .ok_or(SaverError::VectorShorterThanExpected(0, 1))?;
+        rest.iter()
+            .try_fold(first.clone(), |acc, ct| acc.try_add(ct))
+    }
+
+    /// Verify ciphertext commitment and snark proof. The checks are done in the order of their cost, so that
+    /// an invalid ciphertext is rejected as early as possible: first the sizes of the ciphertext and keys, then
+    /// whichever of the snark proof (`saver_groth16::verify_proof_cost`) and the ciphertext commitment
+    /// (`Self::verify_commitment_cost`) needs fewer pairings and only if that passes, the other one.
+    #[must_use = "a failed verification is only reported through the returned value"]
+    pub fn verify_commitment_and_proof(
+        &self,
+        proof: &ark_groth16::Proof<E>,
+        snark_vk: &ark_groth16::PreparedVerifyingKey<E>,
+        ek: impl Into<PreparedEncryptionKey<E>>,
+        gens: impl Into<PreparedEncryptionGens<E>>,
+    ) -> crate::Result<()> {
+        self.verify_commitment_and_snark(ek, gens, || {
+            saver_groth16::verify_proof(snark_vk, proof, self)
+        })
+    }
+
+    /// Verify the ciphertext commitment and call `verify_snark`, cheapest first as described in
+    /// `Self::verify_commitment_and_proof`. Neither is done if the ciphertext and keys have incompatible sizes.
+    pub(crate) fn verify_commitment_and_snark(
+        &self,
+        ek: impl Into<PreparedEncryptionKey<E>>,
+        gens: impl Into<PreparedEncryptionGens<E>>,
+        verify_snark: impl FnOnce() -> crate::Result<()>,
+    ) -> crate::Result<()> {
+        let (ek, gens) = (ek.into(), gens.into());
+        self.check_commitment_inputs(&ek, &gens)?;
+        let n = self.enc_chunks.len();
+        let verify_commitment = || {
+            Encryption::verify_ciphertext_commitment(
+                &self.X_r,
+                &self.enc_chunks,
+                &self.commitment,
+                ek,
+                gens,
+            )
+        };
+        if saver_groth16::verify_proof_cost(n).pairings <= Self::verify_commitment_cost(n).pairings
+        {
+            verify_snark()?;
+            verify_commitment()
+        } else {
+            verify_commitment()?;
+            verify_snark()
+        }
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip_all,
+            fields(chunks_count = self.enc_chunks.len(), chunk_bit_size = chunk_bit_size)
+        )
+    )]
+    pub fn decrypt_given_groth16_vk(
+        &self,
+        sk: &SecretKey<E::ScalarField>,
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        chunk_bit_size: u8,
+    ) -> crate::Result<(E::ScalarField, E::G1Affine)> {
+        let g_i = saver_groth16::get_gs_for_encryption(snark_vk);
+        self.decrypt(sk, dk, g_i, chunk_bit_size)
+    }
+
+    /// Same as `Self::decrypt_given_groth16_vk` but returns the message as an integer rather than a field
+    /// element, for callers that want the numeric value of a message bigger than a `u64`
+    pub fn decrypt_to_biguint(
+        &self,
+        sk: &SecretKey<E::ScalarField>,
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        chunk_bit_size: u8,
+    ) -> crate::Result<(BigUint, E::G1Affine)> {
+        let (message, nu) = self.decrypt_given_groth16_vk(sk, dk, snark_vk, chunk_bit_size)?;
+        Ok((utils::field_element_to_biguint(&message), nu))
+    }
+
+    /// Decrypt like `Self::decrypt_given_groth16_vk` and return the message with a `VerifiableDecryption`
+    /// for publishing it. The decryption is verified before returning so one that verifiers would reject
+    /// is never returned.
+    pub fn decrypt_and_prove(
+        &self,
+        sk: &SecretKey<E::ScalarField>,
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        gens: impl Into<PreparedEncryptionGens<E>>,
+        chunk_bit_size: u8,
+    ) -> crate::Result<(E::ScalarField, VerifiableDecryption<E>)> {
+        let dk = dk.into();
+        let (message, nu) =
+            self.decrypt_given_groth16_vk(sk, dk.clone(), snark_vk, chunk_bit_size)?;
+        let decryption = VerifiableDecryption {
+            message,
+            nu,
+            chunk_bit_size,
+        };
+        decryption.verify(self, dk, snark_vk, gens)?;
+        Ok((message, decryption))
+    }
+
+    pub fn decrypt_given_groth16_vk_and_pairing_powers(
+        &self,
+        sk: &SecretKey<E::ScalarField>,
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        chunk_bit_size: u8,
+        pairing_powers: &[Vec<PairingOutput<E>>],
+    ) -> crate::Result<(E::ScalarField, E::G1Affine)> {
+        let g_i = saver_groth16::get_gs_for_encryption(snark_vk);
+        self.decrypt_given_pairing_powers(sk, dk, g_i, chunk_bit_size, pairing_powers)
+    }
+
+    #[must_use = "a failed verification is only reported through the returned value"]
+    pub fn verify_decryption_given_groth16_vk(
+        &self,
+        message: &E::ScalarField,
+        nu: &E::G1Affine,
+        chunk_bit_size: u8,
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        gens: impl Into<PreparedEncryptionGens<E>>,
+    ) -> crate::Result<()> {
+        let g_i = saver_groth16::get_gs_for_encryption(snark_vk);
+        self.verify_decryption(message, nu, chunk_bit_size, dk, g_i, gens)
+    }
+
+    /// Decrypt this ciphertext and check that the decrypted message is the one committed in the chunked
+    /// commitment `chunked_comm` (see `ChunkedCommitment`), which holds the commitment and its key, with
+    /// blinding `blinding`. The blinding is needed as the chunked commitment cannot be opened without it.
+    /// Returns the plaintext and commitment to randomness like `Self::decrypt`
+    pub fn decrypt_and_check_commitment(
+        &self,
+        sk: &SecretKey<E::ScalarField>,
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        chunk_bit_size: u8,
+        chunked_comm: &ChunkedCommitment<E::G1Affine>,
+        blinding: &E::ScalarField,
+    ) -> crate::Result<(E::ScalarField, E::G1Affine)> {
+        let ChunkedCommitment(comm_chunks, comm_key) = chunked_comm;
+        // Chunked commitment key has a base for each chunk and one for the blinding
+        let expected_count = self.enc_chunks.len() + 1;
+        if comm_key.len() != expected_count {
+            return Err(SaverError::IncompatibleCommitmentBases(
+                comm_key.len(),
+                expected_count,
+            ));
+        }
+        let (message, nu) = self.decrypt_given_groth16_vk(sk, dk, snark_vk, chunk_bit_size)?;
+        let expected = ChunkedCommitment::<E::G1Affine>::get_commitment_given_commitment_key(
+            &message,
+            blinding,
+            chunk_bit_size,
+            comm_key,
+        )?;
+        if expected != *comm_chunks {
+            return Err(SaverError::InvalidCommitment);
+        }
+        Ok((message, nu))
+    }
+}
+
+/// Order by `Ciphertext::canonical_bytes` so that a list of ciphertexts can be sorted independent of the
+/// order they were received in. Each comparison serializes both ciphertexts so prefer
+/// `sort_by_cached_key(Ciphertext::canonical_bytes)` for long lists.
+impl<E: Pairing> Ord for Ciphertext<E> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.canonical_bytes().cmp(&other.canonical_bytes())
+    }
+}
+
+impl<E: Pairing> PartialOrd for Ciphertext<E> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Homomorphic addition, same as `Ciphertext::linear_combination` with all weights 1. The sum decrypts to the
+/// sum of the messages only if the sum of the `j`-th chunks of the messages is less than `2^chunk_bit_size`
+/// for every `j`, otherwise decryption fails with `SaverError::CouldNotFindDiscreteLog`.
+///
+/// # Panics
+///
+/// If the ciphertexts have a different number of chunks, i.e. were not created with the same encryption key.
+/// Use `Ciphertext::try_add` for ciphertexts that might not be.
+impl<'a, E: Pairing> Add<&'a Ciphertext<E>> for &'a Ciphertext<E> {
+    type Output = Ciphertext<E>;
+
+    fn add(self, other: &'a Ciphertext<E>) -> Ciphertext<E> {
+        self.try_add(other)
+            .expect("ciphertexts have different number of chunks")
+    }
+}
+
+impl<E: Pairing> Add for Ciphertext<E> {
+    type Output = Ciphertext<E>;
+
+    fn add(self, other: Ciphertext<E>) -> Ciphertext<E> {
+        &self + &other
+    }
+}
+
+/// Sum of ciphertexts created with the same encryption key, with the same precondition on the chunks of the
+/// messages as the addition, eg. `cts.iter().sum::<Ciphertext<E>>()`. The number of chunks of the sum comes
+/// from the ciphertexts so there is no sum of no ciphertexts.
+///
+/// # Panics
+///
+/// If the iterator is empty or the ciphertexts have a different number of chunks. Use `Ciphertext::try_sum`
+/// for ciphertexts that might be none or not created with the same key.
+impl<E: Pairing> Sum for Ciphertext<E> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(|a, b| &a + &b)
+            .expect("sum of no ciphertexts, use Ciphertext::try_sum")
+    }
+}
+
+impl<'a, E: Pairing> Sum<&'a Ciphertext<E>> for Ciphertext<E> {
+    fn sum<I: Iterator<Item = &'a Self>>(mut iter: I) -> Self {
+        let first = iter
+            .next()
+            .expect("sum of no ciphertexts, use Ciphertext::try_sum");
+        iter.fold(first.clone(), |acc, ct| &acc + ct)
+    }
+}
+
+impl<E: Pairing> TryFrom<CiphertextParts<E>> for Ciphertext<E> {
+    type Error = SaverError;
+
+    fn try_from(parts: CiphertextParts<E>) -> crate::Result<Self> {
+        Self::from_parts(parts)
+    }
+}
+
+impl<E: Pairing> From<Ciphertext<E>> for CiphertextParts<E> {
+    fn from(ct: Ciphertext<E>) -> Self {
+        ct.into_parts()
+    }
+}
+
+impl<E: Pairing> CiphertextAlt<E> {
+    impl_enc_funcs!();
+
+    pub fn decrypt_given_legogroth16_vk(
+        &self,
+        sk: &SecretKey<E::ScalarField>,
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        snark_vk: &legogroth16::VerifyingKey<E>,
+        chunk_bit_size: u8,
+    ) -> crate::Result<(E::ScalarField, E::G1Affine)> {
+        Encryption::decrypt_given_legogroth16_vk(
+            &self.X_r,
+            &self.enc_chunks,
+            sk,
+            dk,
+            snark_vk,
+            chunk_bit_size,
+        )
+    }
+
+    #[must_use = "a failed verification is only reported through the returned value"]
+    pub fn verify_decryption_given_legogroth16_vk(
+        &self,
+        message: &E::ScalarField,
+        chunk_bit_size: u8,
+        nu: &E::G1Affine,
+        dk: impl Into<PreparedDecryptionKey<E>>,
+        snark_vk: &legogroth16::VerifyingKey<E>,
+        gens: impl Into<PreparedEncryptionGens<E>>,
+    ) -> crate::Result<()> {
+        let decomposed = utils::decompose(message, chunk_bit_size)?;
+        Encryption::verify_decryption_given_legogroth16_vk(
+            &decomposed,
+            &self.X_r,
+            &self.enc_chunks,
+            nu,
+            dk,
+            snark_vk,
+            gens,
+        )
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        keygen::{keygen, DecryptionKey},
+        setup::{setup_for_groth16, ChunkedCommitmentGens, EncryptionGens},
+        utils::{chunks_count, decompose},
+    };
+    use ark_bls12_381::Bls12_381;
+    use ark_ff::One;
+    use ark_std::rand::{prelude::StdRng, SeedableRng};
+
+    type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+    pub fn enc_setup<R: RngCore>(
+        chunk_bit_size: u8,
+        rng: &mut R,
+    ) -> (
+        EncryptionGens<Bls12_381>,
+        Vec<<Bls12_381 as Pairing>::G1Affine>,
+        SecretKey<<Bls12_381 as Pairing>::ScalarField>,
+        EncryptionKey<Bls12_381>,
+        DecryptionKey<Bls12_381>,
+    ) {
+        let n = chunks_count::<Fr>(chunk_bit_size) as usize;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(rng);
+        let g_i = (0..n)
+            .map(|_| <Bls12_381 as Pairing>::G1Affine::rand(rng))
+            .collect::<Vec<_>>();
+        let delta = Fr::rand(rng);
+        let gamma = Fr::rand(rng);
+        let g_delta = gens.G.mul_bigint(delta.into_bigint()).into_affine();
+        let g_gamma = gens.G.mul_bigint(gamma.into_bigint()).into_affine();
+        let (sk, ek, dk) = keygen(rng, chunk_bit_size, &gens, &g_i, &g_delta, &g_gamma).unwrap();
+        (gens, g_i, sk, ek, dk)
+    }
+
+    pub fn gen_messages<R: RngCore>(
+        rng: &mut R,
+        count: usize,
+        chunk_bit_size: u8,
+    ) -> Vec<CHUNK_TYPE> {
+        (0..count)
+            .map(|_| (u32::rand(rng) & ((1 << chunk_bit_size) - 1)) as CHUNK_TYPE)
+            .collect()
+    }
+
+    #[test]
+    fn encrypt_decrypt() {
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let n = chunks_count::<Fr>(chunk_bit_size) as usize;
+            // Get random numbers that are of chunk_bit_size at most
+            let m = gen_messages(&mut rng, n, chunk_bit_size);
+            let (gens, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+
+            let prepared_gens = PreparedEncryptionGens::from(gens.clone());
+            let prepared_ek = PreparedEncryptionKey::from(ek.clone());
+            let prepared_dk = PreparedDecryptionKey::from(dk.clone());
+
+            let start = Instant::now();
+            let (ct, _) =
+                Encryption::encrypt_decomposed_message(&mut rng, m.clone(), &ek, &g_i).unwrap();
+            println!(
+                "Time taken to encrypt {}-bit chunks {:?}",
+                chunk_bit_size,
+                start.elapsed()
+            );
+
+            assert_eq!(ct.len(), m.len() + 2);
+            let start = Instant::now();
+            Encryption::verify_ciphertext_commitment(
+                &ct[0],
+                &ct[1..m.len() + 1],
+                &ct[m.len() + 1],
+                ek.clone(),
+                gens.clone(),
+            )
+            .unwrap();
+            println!(
+                "Time taken to verify commitment of {}-bit chunks {:?}",
+                chunk_bit_size,
+                start.elapsed()
+            );
+
+            let start = Instant::now();
+            Encryption::verify_ciphertext_commitment(
+                &ct[0],
+                &ct[1..m.len() + 1],
+                &ct[m.len() + 1],
+                prepared_ek,
+                prepared_gens.clone(),
+            )
+            .unwrap();
+            println!(
+                "Time taken to verify commitment of {}-bit chunks using prepared parameters {:?}",
+                chunk_bit_size,
+                start.elapsed()
+            );
+
+            let start = Instant::now();
+            let (m_, _) = Encryption::decrypt_to_chunks(
+                &ct[0],
+                &ct[1..m.len() + 1],
+                &sk,
+                dk.clone(),
+                &g_i,
+                chunk_bit_size,
+            )
+            .unwrap();
+            println!(
+                "Time taken to decrypt {}-bit chunks {:?}",
+                chunk_bit_size,
+                start.elapsed()
+            );
+            assert_eq!(m_, m);
+
+            let start = Instant::now();
+            let (m_, _) = Encryption::decrypt_to_chunks(
+                &ct[0],
+                &ct[1..m.len() + 1],
+                &sk,
+                prepared_dk.clone(),
+                &g_i,
+                chunk_bit_size,
+            )
+            .unwrap();
+            println!(
+                "Time taken to decrypt {}-bit chunks using prepared parameters {:?}",
+                chunk_bit_size,
+                start.elapsed()
+            );
+            assert_eq!(m_, m);
+
+            let pairing_powers = prepared_dk.pairing_powers(chunk_bit_size, &g_i).unwrap();
+            let start = Instant::now();
+            let (m_, nu) = Encryption::decrypt_to_chunks_given_pairing_powers(
+                &ct[0],
+                &ct[1..m.len() + 1],
+                &sk,
+                prepared_dk.clone(),
+                &g_i,
+                chunk_bit_size,
+                Some(&pairing_powers),
+            )
+            .unwrap();
+            println!(
+                "Time taken to decrypt {}-bit chunks using prepared parameters and pairing powers {:?}",
+                chunk_bit_size,
+                start.elapsed()
+            );
+            assert_eq!(m_, m);
+
+            let start = Instant::now();
+            Encryption::verify_decryption(&m_, &ct[0], &ct[1..m.len() + 1], &nu, dk, &g_i, gens)
+                .unwrap();
+            println!(
+                "Time taken to verify decryption of {}-bit chunks {:?}",
+                chunk_bit_size,
+                start.elapsed()
+            );
+
+            let start = Instant::now();
+            Encryption::verify_decryption(
+                &m_,
+                &ct[0],
+                &ct[1..m.len() + 1],
+                &nu,
+                prepared_dk,
+                &g_i,
+                prepared_gens,
+            )
+            .unwrap();
+            println!(
+                "Time taken to verify decryption of {}-bit chunks using prepared parameters {:?}",
+                chunk_bit_size,
+                start.elapsed()
+            );
+        }
+
+        check(4);
+        check(8);
+        check(16);
+    }
+
+    #[test]
+    fn encrypt_decrypt_timing() {
+        fn check(chunk_bit_size: u8, count: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let (gens, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+            let prepared_gens = PreparedEncryptionGens::from(gens.clone());
+            let prepared_ek = PreparedEncryptionKey::from(ek.clone());
+            let prepared_dk = PreparedDecryptionKey::from(dk.clone());
+            let pairing_powers = prepared_dk.pairing_powers(chunk_bit_size, &g_i).unwrap();
+
+            let mut total_enc = Duration::default();
+            let mut total_ver_com = Duration::default();
+            let mut total_ver_com_prep = Duration::default();
+            let mut total_dec = Duration::default();
+            let mut total_dec_prep = Duration::default();
+            let mut total_dec_prep_powers = Duration::default();
+            let mut total_ver_dec = Duration::default();
+            let mut total_ver_dec_prep = Duration::default();
+
+            for _ in 0..count {
+                let m = Fr::rand(&mut rng);
+
+                let start = Instant::now();
+                let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+                total_enc += start.elapsed();
+
+                let start = Instant::now();
+                ct.verify_commitment(ek.clone(), gens.clone()).unwrap();
+                total_ver_com += start.elapsed();
+
+                let start = Instant::now();
+                ct.verify_commitment(prepared_ek.clone(), prepared_gens.clone())
+                    .unwrap();
+                total_ver_com_prep += start.elapsed();
+
+                let (chunks, nu) = Encryption::decrypt_to_chunks(
+                    &ct.X_r,
+                    &ct.enc_chunks,
+                    &sk,
+                    dk.clone(),
+                    &g_i,
+                    chunk_bit_size,
+                )
+                .unwrap();
+
+                let decomposed = decompose(&m, chunk_bit_size).unwrap();
+                assert_eq!(decomposed, chunks);
+
+                let start = Instant::now();
+                let (m_, nu_) = ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap();
+                total_dec += start.elapsed();
+                assert_eq!(m, m_);
+                assert_eq!(nu, nu_);
+
+                let start = Instant::now();
+                let (m_, nu_) = ct
+                    .decrypt(&sk, prepared_dk.clone(), &g_i, chunk_bit_size)
+                    .unwrap();
+                total_dec_prep += start.elapsed();
+                assert_eq!(m, m_);
+                assert_eq!(nu, nu_);
+
+                let start = Instant::now();
+                let (m_, nu_) = ct
+                    .decrypt_given_pairing_powers(
+                        &sk,
+                        prepared_dk.clone(),
+                        &g_i,
+                        chunk_bit_size,
+                        &pairing_powers,
+                    )
+                    .unwrap();
+                total_dec_prep_powers += start.elapsed();
+                assert_eq!(m, m_);
+                assert_eq!(nu, nu_);
+
+                let start = Instant::now();
+                ct.verify_decryption(&m, &nu, chunk_bit_size, dk.clone(), &g_i, gens.clone())
+                    .unwrap();
+                total_ver_dec += start.elapsed();
+
+                let start = Instant::now();
+                ct.verify_decryption(
+                    &m,
+                    &nu,
+                    chunk_bit_size,
+                    prepared_dk.clone(),
+                    &g_i,
+                    prepared_gens.clone(),
+                )
+                .unwrap();
+                total_ver_dec_prep += start.elapsed();
+            }
+
+            println!(
+                "Time taken for {} iterations and {}-bit chunk size:",
+                count, chunk_bit_size
+            );
+            println!("Encryption {:?}", total_enc);
+            println!("Verifying commitment {:?}", total_ver_com);
+            println!(
+                "Verifying commitment using prepared {:?}",
+                total_ver_com_prep
+            );
+            println!("Decryption {:?}", total_dec);
+            println!("Decryption using prepared {:?}", total_dec_prep);
+            println!(
+                "Decryption using prepared and pairing powers {:?}",
+                total_dec_prep_powers
+            );
+            println!("Verifying decryption {:?}", total_ver_dec);
+            println!(
+                "Verifying decryption using prepared {:?}",
+                total_ver_dec_prep
+            );
+        }
+        check(4, 10);
+        check(8, 10);
+        check(16, 4);
+    }
+
+    #[test]
+    fn verify_batch_collecting_all_results() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 16;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        let pvk = saver_groth16::prepare_verifying_key(&snark_pk.pk.vk);
+
+        let mut items = (0..4)
+            .map(|_| {
+                let m = Fr::rand(&mut rng);
+                let (ct, _, proof) =
+                    Encryption::encrypt_with_proof(&mut rng, &m, &ek, &snark_pk, chunk_bit_size)
+                        .unwrap();
+                (ct, proof)
+            })
+            .collect::<Vec<_>>();
+        // Commitment not to the encrypted chunks
+        items[1].0.commitment = (items[1].0.commitment + gens.G).into_affine();
+        // Proof of another ciphertext
+        items[2].1 = items[0].1.clone();
+        // Ciphertext with a chunk missing
+        let mut short = items[0].clone();
+        short.0.enc_chunks.pop();
+        items.push(short);
+
+        let results = Encryption::verify_batch_collecting(&items, &pvk, ek.clone(), gens.clone());
+        assert_eq!(results.len(), 5);
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(SaverError::InvalidCommitment)));
+        assert!(matches!(results[2], Err(SaverError::PairingCheckFailed)));
+        assert!(results[3].is_ok());
+        assert!(matches!(
+            results[4],
+            Err(SaverError::IncompatibleEncryptionKey(15, 16))
+        ));
+        for (r, (ct, proof)) in results.iter().zip(items.iter()) {
+            assert_eq!(
+                r.is_ok(),
+                ct.verify_commitment_and_proof(proof, &pvk, ek.clone(), gens.clone())
+                    .is_ok()
+            );
+        }
+        assert!(Encryption::verify_batch_collecting(&[], &pvk, ek, gens).is_empty());
+    }
+
+    #[test]
+    fn batch_commitment_verification() {
+        fn check(chunk_bit_size: u8, count: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let (gens, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+
+            let mut cts = vec![];
+
+            let mut total_ver_com = Duration::default();
+
+            for _ in 0..count {
+                let m = Fr::rand(&mut rng);
+                let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+
+                let start = Instant::now();
+                ct.verify_commitment(ek.clone(), gens.clone()).unwrap();
+                total_ver_com += start.elapsed();
+
+                cts.push(ct);
+            }
+
+            let r = Fr::rand(&mut rng);
+            let mut r_powers = vec![Fr::one(); count as usize];
+            for i in 1..count as usize {
+                r_powers[i] = r_powers[i - 1] * &r;
+            }
+
+            let start = Instant::now();
+            Encryption::verify_commitments_in_batch(&cts, &r_powers, ek.clone(), gens).unwrap();
+            let t = start.elapsed();
+
+            println!(
+                "Time taken for {} iterations and {}-bit chunk size:",
+                count, chunk_bit_size
+            );
+            println!("Verifying commitment {:?}", total_ver_com);
+            println!("Verifying commitments in batch {:?}", t);
+        }
+
+        check(4, 10);
+        check(8, 10);
+        check(16, 10);
+    }
+
+    #[test]
+    fn decrypt_and_check_chunked_commitment() {
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let enc_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+            let comm_gens =
+                ChunkedCommitmentGens::<<Bls12_381 as Pairing>::G1Affine>::new_using_rng(&mut rng);
+            let (snark_pk, sk, ek, dk) =
+                setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens).unwrap();
+
+            let m = Fr::rand(&mut rng);
+            let blinding = Fr::rand(&mut rng);
+            let (ct, _) = Encryption::encrypt_given_snark_vk(
+                &mut rng,
+                &m,
+                &ek,
+                &snark_pk.pk.vk,
+                chunk_bit_size,
+            )
+            .unwrap();
+            let chunked_comm = ChunkedCommitment::<<Bls12_381 as Pairing>::G1Affine>::new(
+                &m,
+                &blinding,
+                chunk_bit_size,
+                &comm_gens,
+            )
+            .unwrap();
+
+            let (m_, nu) = ct
+                .decrypt_and_check_commitment(
+                    &sk,
+                    dk.clone(),
+                    &snark_pk.pk.vk,
+                    chunk_bit_size,
+                    &chunked_comm,
+                    &blinding,
+                )
+                .unwrap();
+            assert_eq!(m_, m);
+            ct.verify_decryption_given_groth16_vk(
+                &m_,
+                &nu,
+                chunk_bit_size,
+                dk.clone(),
+                &snark_pk.pk.vk,
+                enc_gens,
+            )
+            .unwrap();
+
+            // Commitment to a different message
+            let comm_other = ChunkedCommitment::<<Bls12_381 as Pairing>::G1Affine>::new(
+                &Fr::rand(&mut rng),
+                &blinding,
+                chunk_bit_size,
+                &comm_gens,
+            )
+            .unwrap();
+            assert!(matches!(
+                ct.decrypt_and_check_commitment(
+                    &sk,
+                    dk.clone(),
+                    &snark_pk.pk.vk,
+                    chunk_bit_size,
+                    &comm_other,
+                    &blinding,
+                ),
+                Err(SaverError::InvalidCommitment)
+            ));
+
+            // Correct commitment but wrong blinding
+            assert!(matches!(
+                ct.decrypt_and_check_commitment(
+                    &sk,
+                    dk.clone(),
+                    &snark_pk.pk.vk,
+                    chunk_bit_size,
+                    &chunked_comm,
+                    &Fr::rand(&mut rng),
+                ),
+                Err(SaverError::InvalidCommitment)
+            ));
+
+            // Commitment key of wrong size
+            assert!(matches!(
+                ct.decrypt_and_check_commitment(
+                    &sk,
+                    dk,
+                    &snark_pk.pk.vk,
+                    chunk_bit_size,
+                    &ChunkedCommitment(chunked_comm.0, chunked_comm.1[1..].to_vec()),
+                    &blinding,
+                ),
+                Err(SaverError::IncompatibleCommitmentBases(have, need)) if need == have + 1
+            ));
+        }
+
+        check(4);
+        check(8);
+    }
+
+    #[test]
+    fn commitment_bases_len_mismatch() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (gens, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+        let m = Fr::rand(&mut rng);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+
+        let expected = chunks_count::<Fr>(chunk_bit_size) as usize + 1;
+        assert_eq!(ct.expected_commitment_bases_len(), expected);
+        assert_eq!(ek.commitment_key().len(), expected);
+        ct.verify_commitment(ek.clone(), gens.clone()).unwrap();
+
+        // Commitment key with fewer bases
+        let mut ek_short = ek.clone();
+        ek_short.Y.pop();
+        assert_eq!(ek_short.commitment_key().len(), expected - 1);
+        match ct.verify_commitment(ek_short, gens.clone()) {
+            Err(SaverError::IncompatibleCommitmentBases(given, exp)) => {
+                assert_eq!(given, expected - 1);
+                assert_eq!(exp, expected);
+            }
+            r => panic!("expected IncompatibleCommitmentBases but got {:?}", r),
+        }
+
+        // Commitment key with more bases
+        let mut ek_long = ek.clone();
+        ek_long.Y.push(ek.Y[0]);
+        assert!(matches!(
+            ct.verify_commitment(ek_long, gens),
+            Err(SaverError::IncompatibleCommitmentBases(_, _))
+        ));
+    }
+
+    #[test]
+    fn ciphertext_and_key_chunk_count_mismatch() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let (gens_4, g_i_4, _, ek_4, _) = enc_setup(4, &mut rng);
+        let (_, _, _, ek_8, _) = enc_setup(8, &mut rng);
+        let m = Fr::rand(&mut rng);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek_4, &g_i_4, 4).unwrap();
+        assert_eq!(ct.encrypted_chunk_count(), chunks_count::<Fr>(4) as usize);
+        assert_eq!(
+            ct.encrypted_chunk_count(),
+            ek_4.supported_chunks_count().unwrap() as usize
+        );
+        ct.verify_commitment(ek_4, gens_4.clone()).unwrap();
+
+        match ct.verify_commitment(ek_8, gens_4) {
+            Err(SaverError::IncompatibleEncryptionKey(count, supported)) => {
+                assert_eq!(count, chunks_count::<Fr>(4) as usize);
+                assert_eq!(supported, chunks_count::<Fr>(8) as usize);
+            }
+            r => panic!("expected IncompatibleEncryptionKey but got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn commitment_with_given_bases() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (gens, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+        let m = Fr::rand(&mut rng);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+
+        let bases = ek.commitment_key();
+        ct.verify_commitment_with_bases(&bases, ek.clone(), gens.clone())
+            .unwrap();
+
+        match ct.verify_commitment_with_bases(&bases[1..], ek.clone(), gens.clone()) {
+            Err(SaverError::IncompatibleCommitmentBases(given, exp)) => {
+                assert_eq!(given, bases.len() - 1);
+                assert_eq!(exp, bases.len());
+            }
+            r => panic!("expected IncompatibleCommitmentBases but got {:?}", r),
+        }
+
+        let mut wrong_bases = bases.clone();
+        wrong_bases.swap(0, 1);
+        assert!(matches!(
+            ct.verify_commitment_with_bases(&wrong_bases, ek.clone(), gens.clone()),
+            Err(SaverError::MismatchedCommitmentBases)
+        ));
+
+        // Bases of another key
+        let (_, _, _, other_ek, _) = enc_setup(chunk_bit_size, &mut rng);
+        assert!(matches!(
+            ct.verify_commitment_with_bases(&other_ek.commitment_key(), ek.clone(), gens.clone()),
+            Err(SaverError::MismatchedCommitmentBases)
+        ));
+        // The bases match the key but the key is for other generators
+        assert!(matches!(
+            ct.verify_commitment_with_bases(&other_ek.commitment_key(), other_ek, gens),
+            Err(SaverError::MismatchedEncryptionGens)
+        ));
+    }
+
+    #[test]
+    fn commitment_verification_needs_gens() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (gens, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+        let (other_gens, _, _, _, _) = enc_setup(chunk_bit_size, &mut rng);
+        let m = Fr::rand(&mut rng);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+
+        ct.verify_commitment(ek.clone(), gens.clone()).unwrap();
+        // The key's fingerprint rejects other generators
+        assert!(matches!(
+            ct.verify_commitment(ek.clone(), other_gens.clone()),
+            Err(SaverError::MismatchedEncryptionGens)
+        ));
+
+        // Without the fingerprint, the generators are still needed as the check uses `H`
+        let mut ek_without_fingerprint = ek.clone();
+        ek_without_fingerprint.gens_fingerprint = None;
+        ct.verify_commitment(ek_without_fingerprint.clone(), gens)
+            .unwrap();
+        assert!(matches!(
+            ct.verify_commitment(ek_without_fingerprint, other_gens),
+            Err(SaverError::InvalidCommitment)
+        ));
+    }
+
+    #[test]
+    fn serialization_error_as_source() {
+        fn parse(bytes: &[u8]) -> crate::Result<Ciphertext<Bls12_381>> {
+            Ok(Ciphertext::from_bytes(bytes)?)
+        }
+
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let (_, g_i, _, ek, _) = enc_setup(8, &mut rng);
+        let m = Fr::rand(&mut rng);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, 8).unwrap();
+        let mut bytes = vec![];
+        ct.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(parse(&bytes).unwrap(), ct);
+
+        let err = parse(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(matches!(err, SaverError::Serialization(_)));
+        let source = std::error::Error::source(&err).unwrap();
+        assert!(source.downcast_ref::<SerializationError>().is_some());
+        assert!(err.to_string().contains(&source.to_string()));
+
+        // Errors without a wrapped error have no source
+        assert!(std::error::Error::source(&SaverError::InvalidCommitment).is_none());
+    }
+
+    #[test]
+    fn serialization_with_curve_id() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let (_, g_i, _, ek, _) = enc_setup(8, &mut rng);
+        let m = Fr::rand(&mut rng);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, 8).unwrap();
+
+        let bytes = ct.to_bytes_with_curve_id().unwrap();
+        assert_eq!(bytes[0], utils::curve_id::<Bls12_381>());
+        assert_eq!(
+            Ciphertext::<Bls12_381>::from_bytes_with_curve_id(&bytes).unwrap(),
+            ct
+        );
+
+        // Only BLS12-381 is available here so bytes from another curve are simulated by changing the
+        // identifier. These are rejected before any point is read.
+        let mut other_curve_bytes = bytes.clone();
+        other_curve_bytes[0] = bytes[0].wrapping_add(1);
+        match Ciphertext::<Bls12_381>::from_bytes_with_curve_id(&other_curve_bytes) {
+            Err(SaverError::CurveMismatch { expected, got }) => {
+                assert_eq!(expected, bytes[0]);
+                assert_eq!(got, other_curve_bytes[0]);
+            }
+            r => panic!("expected CurveMismatch but got {:?}", r),
+        }
+
+        assert!(matches!(
+            Ciphertext::<Bls12_381>::from_bytes_with_curve_id(&[]),
+            Err(SaverError::Serialization(_))
+        ));
+    }
+
+    #[test]
+    fn encryption_cost_estimate() {
+        for chunk_bit_size in [1, 2, 4, 8, 16] {
+            let n = chunks_count::<Fr>(chunk_bit_size) as usize;
+            let cost = Encryption::<Bls12_381>::cost_estimate(chunk_bit_size).unwrap();
+            assert_eq!(cost.chunks_count, n);
+            assert_eq!(cost.g1_msm_terms, n + 1);
+            assert_eq!(cost.discrete_log_range, (1 << chunk_bit_size) - 1);
+        }
+
+        // The MSM terms match the commitment key and the pairings match the ones done in verification
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let (gens, _, _, ek, _) = enc_setup(8, &mut rng);
+        let cost = Encryption::<Bls12_381>::cost_estimate(8).unwrap();
+        assert_eq!(cost.g1_msm_terms, ek.commitment_key().len());
+        assert_eq!(
+            cost.commitment_verification_pairings,
+            Encryption::get_g2_for_ciphertext_commitment_pairing_checks(&ek.into(), &gens.into())
+                .len()
+        );
+
+        assert!(Encryption::<Bls12_381>::cost_estimate(0).is_err());
+        assert!(Encryption::<Bls12_381>::cost_estimate(3).is_err());
+        assert!(Encryption::<Bls12_381>::cost_estimate(32).is_err());
+    }
+
+    #[test]
+    fn verification_costs() {
+        // Pinned so that a change adding pairings to verification is noticed
+        for (chunk_bit_size, commitment, proof, decryption) in
+            [(4, 66, 3, 130), (8, 34, 3, 66), (16, 18, 3, 34)]
+        {
+            let n = chunks_count::<Fr>(chunk_bit_size) as usize;
+            assert_eq!(
+                Ciphertext::<Bls12_381>::verify_commitment_cost(n),
+                VerificationCost {
+                    pairings: commitment,
+                    g1_scalar_mults: 0,
+                    g1_additions: 0
+                }
+            );
+            assert_eq!(
+                saver_groth16::verify_proof_cost(n),
+                VerificationCost {
+                    pairings: proof,
+                    g1_scalar_mults: 0,
+                    g1_additions: n + 1
+                }
+            );
+            assert_eq!(
+                Ciphertext::<Bls12_381>::verify_decryption_cost(n),
+                VerificationCost {
+                    pairings: decryption,
+                    g1_scalar_mults: n,
+                    g1_additions: n
+                }
+            );
+
+            let cost = Encryption::<Bls12_381>::cost_estimate(chunk_bit_size).unwrap();
+            assert_eq!(cost.commitment_verification_pairings, commitment);
+            assert_eq!(cost.decryption_verification_pairings, decryption);
+        }
+
+        // The pairings for verifying the commitment match the inputs of the multi-pairing
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let (gens, g_i, _, ek, _) = enc_setup(8, &mut rng);
+        let m = Fr::rand(&mut rng);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, 8).unwrap();
+        let n = ct.enc_chunks.len();
+        assert_eq!(
+            Encryption::<Bls12_381>::get_g1_for_ciphertext_commitment_pairing_checks(
+                &ct.X_r,
+                &ct.enc_chunks,
+                &ct.commitment
+            )
+            .len(),
+            Ciphertext::<Bls12_381>::verify_commitment_cost(n).pairings
+        );
+        assert_eq!(
+            Encryption::get_g2_for_ciphertext_commitment_pairing_checks(&ek.into(), &gens.into())
+                .len(),
+            Ciphertext::<Bls12_381>::verify_commitment_cost(n).pairings
+        );
+    }
+
+    #[test]
+    fn check_encryption_of_message() {
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let (_, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+            let m = Fr::rand(&mut rng);
+            let (ct, r) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+            assert!(ct.is_encryption_of(&m, &r, &ek, &g_i));
+
+            assert!(!ct.is_encryption_of(&(m + Fr::one()), &r, &ek, &g_i));
+            assert!(!ct.is_encryption_of(&m, &(r + Fr::one()), &ek, &g_i));
+            let mut ct_modified = ct.clone();
+            ct_modified.commitment = ct.X_r;
+            assert!(!ct_modified.is_encryption_of(&m, &r, &ek, &g_i));
+            // Encryption key for a different chunk size
+            let (_, _, _, ek_other, _) =
+                enc_setup(if chunk_bit_size == 4 { 8 } else { 4 }, &mut rng);
+            assert!(!ct.is_encryption_of(&m, &r, &ek_other, &g_i));
+        }
+        check(4);
+        check(8);
+        check(16);
+    }
+
+    #[test]
+    fn encryption_with_context() {
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let (gens, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+            let ctx = EncryptionContext::new(&ek, &g_i, chunk_bit_size, 3).unwrap();
+            assert_eq!(ctx.chunk_bit_size(), chunk_bit_size);
+
+            for _ in 0..3 {
+                let m = Fr::rand(&mut rng);
+                // Same randomness is used when the RNGs are in the same state
+                let seed = rng.next_u64();
+                let (ct, r) = Encryption::encrypt(
+                    &mut StdRng::seed_from_u64(seed),
+                    &m,
+                    &ek,
+                    &g_i,
+                    chunk_bit_size,
+                )
+                .unwrap();
+                let (ct_, r_) =
+                    Encryption::encrypt_given_context(&mut StdRng::seed_from_u64(seed), &m, &ctx)
+                        .unwrap();
+                assert_eq!(r_, r);
+                assert_eq!(ct_, ct);
+
+                ct_.verify_commitment(ek.clone(), gens.clone()).unwrap();
+                let (m_, _) = ct_.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap();
+                assert_eq!(m_, m);
+            }
+        }
+
+        check(4);
+        check(8);
+        check(16);
+
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let (_, g_i, _, ek, _) = enc_setup(8, &mut rng);
+        assert!(matches!(
+            EncryptionContext::new(&ek, &g_i, 4, 1),
+            Err(SaverError::IncompatibleEncryptionKey(_, _))
+        ));
+        assert!(matches!(
+            EncryptionContext::new(&ek, &g_i, 3, 1),
+            Err(SaverError::UnexpectedBase(3))
+        ));
+        assert!(matches!(
+            EncryptionContext::new(&ek, &g_i[..10], 8, 1),
+            Err(SaverError::InsufficientGenerators {
+                have: 10,
+                need: 32,
+                source: "g_i"
+            })
+        ));
+    }
+
+    #[test]
+    fn encrypt_trivial_message() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (_, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+
+        for m in [Fr::zero(), Fr::one()] {
+            assert!(matches!(
+                Encryption::encrypt_checked(&mut rng, &m, &ek, &g_i, chunk_bit_size, false),
+                Err(SaverError::TrivialMessage)
+            ));
+            let (ct, _) =
+                Encryption::encrypt_checked(&mut rng, &m, &ek, &g_i, chunk_bit_size, true).unwrap();
+            let (m_, _) = ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap();
+            assert_eq!(m_, m);
+        }
+
+        let m = Fr::rand(&mut rng);
+        Encryption::encrypt_checked(&mut rng, &m, &ek, &g_i, chunk_bit_size, false).unwrap();
+    }
+
+    #[test]
+    fn cheapest_of_commitment_and_snark_verified_first() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (gens, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+        let m = Fr::rand(&mut rng);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+        let n = ct.encrypted_chunk_count();
+        assert!(
+            saver_groth16::verify_proof_cost(n).pairings
+                < Ciphertext::<Bls12_381>::verify_commitment_cost(n).pairings
+        );
+
+        let snark_checks = core::cell::Cell::new(0);
+        let verify_snark = || {
+            snark_checks.set(snark_checks.get() + 1);
+            Ok(())
+        };
+        let reject_snark = || {
+            snark_checks.set(snark_checks.get() + 1);
+            Err(SaverError::PairingCheckFailed)
+        };
+
+        ct.verify_commitment_and_snark(ek.clone(), gens.clone(), verify_snark)
+            .unwrap();
+        assert_eq!(snark_checks.get(), 1);
+
+        // Invalid commitment is still rejected after a valid snark
+        let mut ct_modified = ct.clone();
+        ct_modified.commitment = ct.X_r;
+        assert!(matches!(
+            ct_modified.verify_commitment_and_snark(ek.clone(), gens.clone(), verify_snark),
+            Err(SaverError::InvalidCommitment)
+        ));
+        assert_eq!(snark_checks.get(), 2);
+
+        // Invalid snark is rejected before the commitment's pairing, which would have failed too
+        assert!(matches!(
+            ct_modified.verify_commitment_and_snark(ek.clone(), gens.clone(), reject_snark),
+            Err(SaverError::PairingCheckFailed)
+        ));
+        assert_eq!(snark_checks.get(), 3);
+
+        // Malformed ciphertext is rejected before any pairing
+        let mut ct_modified = ct.clone();
+        ct_modified.enc_chunks.pop();
+        assert!(matches!(
+            ct_modified.verify_commitment_and_snark(ek, gens, verify_snark),
+            Err(SaverError::IncompatibleEncryptionKey(..))
+        ));
+        assert_eq!(snark_checks.get(), 3);
+    }
+
+    #[test]
+    fn encrypt_out_of_range_chunks() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 4;
+        let (_, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+        let m = Fr::rand(&mut rng);
+        let mut chunks = utils::decompose(&m, chunk_bit_size).unwrap();
+        Encryption::encrypt_decomposed_message(&mut rng, chunks.clone(), &ek, &g_i).unwrap();
+
+        chunks[3] = 16;
+        assert!(matches!(
+            Encryption::encrypt_decomposed_message(&mut rng, chunks, &ek, &g_i),
+            Err(SaverError::ChunkOutOfRange {
+                index: 3,
+                value: 16,
+                max: 15
+            })
+        ));
+    }
+
+    #[test]
+    fn prepared_and_unprepared_decryption_key() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 4;
+        let (gens, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+        let prepared_dk = PreparedDecryptionKey::from(dk.clone());
+
+        for _ in 0..3 {
+            let m = Fr::rand(&mut rng);
+            let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+            let (m_1, nu_1) = ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap();
+            let (m_2, nu_2) = ct
+                .decrypt(&sk, prepared_dk.clone(), &g_i, chunk_bit_size)
+                .unwrap();
+            assert_eq!(m_1, m);
+            assert_eq!(m_2, m);
+            assert_eq!(nu_1, nu_2);
+
+            ct.verify_decryption(&m, &nu_1, chunk_bit_size, dk.clone(), &g_i, gens.clone())
+                .unwrap();
+            ct.verify_decryption(
+                &m,
+                &nu_1,
+                chunk_bit_size,
+                prepared_dk.clone(),
+                &g_i,
+                gens.clone(),
+            )
+            .unwrap();
+            let wrong = m + Fr::one();
+            assert!(ct
+                .verify_decryption(
+                    &wrong,
+                    &nu_1,
+                    chunk_bit_size,
+                    dk.clone(),
+                    &g_i,
+                    gens.clone()
+                )
+                .is_err());
+            assert!(ct
+                .verify_decryption(
+                    &wrong,
+                    &nu_1,
+                    chunk_bit_size,
+                    prepared_dk.clone(),
+                    &g_i,
+                    gens.clone()
+                )
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn encrypt_with_proof_matches_sequential() {
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+            let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+            let g_i = saver_groth16::get_gs_for_encryption(&snark_pk.pk.vk);
+            let m = Fr::rand(&mut rng);
+
+            let mut rng_1 = StdRng::seed_from_u64(1u64);
+            let (ct_1, r_1, proof_1) =
+                Encryption::encrypt_with_proof(&mut rng_1, &m, &ek, &snark_pk, chunk_bit_size)
+                    .unwrap();
+
+            // Encrypt and then prove with the same RNG state
+            let mut rng_2 = StdRng::seed_from_u64(1u64);
+            let (ct_2, r_2) =
+                Encryption::encrypt(&mut rng_2, &m, &ek, g_i, chunk_bit_size).unwrap();
+            let circuit = BitsizeCheckCircuit::new(
+                chunk_bit_size,
+                None,
+                Some(
+                    decompose(&m, chunk_bit_size)
+                        .unwrap()
+                        .into_iter()
+                        .map(Fr::from)
+                        .collect(),
+                ),
+                true,
+            );
+            let proof_2 =
+                saver_groth16::create_proof(circuit, &r_2, &snark_pk, &ek, &mut rng_2).unwrap();
+
+            assert_eq!(ct_1, ct_2);
+            assert_eq!(r_1, r_2);
+            assert_eq!(proof_1, proof_2);
+            // Both RNGs were used equally
+            assert_eq!(rng_1.next_u64(), rng_2.next_u64());
+        }
+        check(4);
+        check(8);
+    }
+
+    #[test]
+    fn encrypt_hashed_bytes() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (_, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+        let user_id = b"user-1234";
+
+        let (ct, r) =
+            Encryption::encrypt_bytes_hashed(&mut rng, user_id, &ek, &g_i, chunk_bit_size).unwrap();
+        let m = utils::message_from_bytes::<Fr>(user_id);
+        assert!(ct.is_encryption_of(&m, &r, &ek, &g_i));
+        assert_eq!(ct.decrypt(&sk, dk, &g_i, chunk_bit_size).unwrap().0, m);
+    }
+
+    #[cfg(feature = "derived-randomness")]
+    #[test]
+    fn encrypt_with_derived_randomness() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (_, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+        let m = Fr::rand(&mut rng);
+        let shared_secret = [7u8; 32];
+        let context = b"session 1";
+
+        // Both parties derive the same randomness and thus the same ciphertext
+        let (ct_1, r_1) = Encryption::encrypt_with_derived_randomness(
+            &m,
+            &ek,
+            &g_i,
+            chunk_bit_size,
+            &shared_secret,
+            context,
+        )
+        .unwrap();
+        let r_2 = utils::derive_randomness::<Fr>(&shared_secret, context);
+        assert_eq!(r_1, r_2);
+        let (ct_2, _) = Encryption::encrypt_with_derived_randomness(
+            &m,
+            &ek,
+            &g_i,
+            chunk_bit_size,
+            &shared_secret,
+            context,
+        )
+        .unwrap();
+        assert_eq!(ct_1, ct_2);
+        assert!(ct_1.is_encryption_of(&m, &r_2, &ek, &g_i));
+        assert_eq!(
+            ct_1.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size)
+                .unwrap()
+                .0,
+            m
+        );
+
+        // Different secret or context give different randomness
+        assert_ne!(utils::derive_randomness::<Fr>(&[8u8; 32], context), r_1);
+        assert_ne!(
+            utils::derive_randomness::<Fr>(&shared_secret, b"session 2"),
+            r_1
+        );
     }
 
-    pub fn gen_messages<R: RngCore>(
-        rng: &mut R,
-        count: usize,
-        chunk_bit_size: u8,
-    ) -> Vec<CHUNK_TYPE> {
-        (0..count)
-            .map(|_| (u32::rand(rng) & ((1 << chunk_bit_size) - 1)) as CHUNK_TYPE)
-            .collect()
+    #[test]
+    fn ciphertext_opening() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let enc_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens).unwrap();
+        let vk = &snark_pk.pk.vk;
+        let m = Fr::rand(&mut rng);
+        let (ct, r) =
+            Encryption::encrypt_given_snark_vk(&mut rng, &m, &ek, vk, chunk_bit_size).unwrap();
+
+        ct.verify_opening_given_groth16_vk(&m, &r, &ek, vk, chunk_bit_size)
+            .unwrap();
+
+        assert!(matches!(
+            ct.verify_opening_given_groth16_vk(&m, &(r + Fr::one()), &ek, vk, chunk_bit_size),
+            Err(SaverError::InvalidOpening)
+        ));
+        assert!(matches!(
+            ct.verify_opening_given_groth16_vk(&(m + Fr::one()), &r, &ek, vk, chunk_bit_size),
+            Err(SaverError::InvalidOpening)
+        ));
+        // Chunk bit size different from the encryption key's
+        assert!(matches!(
+            ct.verify_opening_given_groth16_vk(&m, &r, &ek, vk, 4),
+            Err(SaverError::IncompatibleEncryptionKey(..))
+        ));
     }
 
     #[test]
-    fn encrypt_decrypt() {
-        fn check(chunk_bit_size: u8) {
-            let mut rng = StdRng::seed_from_u64(0u64);
-            let n = chunks_count::<Fr>(chunk_bit_size) as usize;
-            // Get random numbers that are of chunk_bit_size at most
-            let m = gen_messages(&mut rng, n, chunk_bit_size);
-            let (gens, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+    fn batch_encryption() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let enc_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, sk, ek, dk) =
+            setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens).unwrap();
 
-            let prepared_gens = PreparedEncryptionGens::from(gens.clone());
-            let prepared_ek = PreparedEncryptionKey::from(ek.clone());
-            let prepared_dk = PreparedDecryptionKey::from(dk.clone());
+        let messages = (0..10).map(|_| Fr::rand(&mut rng)).collect::<Vec<_>>();
+        let seed = [5u8; 32];
+        let batch = Encryption::encrypt_batch_parallel(
+            &messages,
+            &ek,
+            &snark_pk.pk.vk,
+            chunk_bit_size,
+            &seed,
+        )
+        .unwrap();
+        assert_eq!(batch.len(), messages.len());
 
-            let start = Instant::now();
-            let (ct, _) =
-                Encryption::encrypt_decomposed_message(&mut rng, m.clone(), &ek, &g_i).unwrap();
-            println!(
-                "Time taken to encrypt {}-bit chunks {:?}",
+        // Same as encrypting serially with the RNG of each item
+        for (i, m) in messages.iter().enumerate() {
+            let mut item_rng = utils::rng_for_batch_item(&seed, i);
+            let expected = Encryption::encrypt_given_snark_vk(
+                &mut item_rng,
+                m,
+                &ek,
+                &snark_pk.pk.vk,
                 chunk_bit_size,
-                start.elapsed()
-            );
+            )
+            .unwrap();
+            assert_eq!(batch[i], expected);
 
-            assert_eq!(ct.len(), m.len() + 2);
-            let start = Instant::now();
-            Encryption::verify_ciphertext_commitment(
-                &ct[0],
-                &ct[1..m.len() + 1],
-                &ct[m.len() + 1],
+            let (m_, _) = batch[i]
+                .0
+                .decrypt_given_groth16_vk(&sk, dk.clone(), &snark_pk.pk.vk, chunk_bit_size)
+                .unwrap();
+            assert_eq!(m_, *m);
+        }
+
+        // Items get different randomness
+        assert_ne!(batch[0].1, batch[1].1);
+        // and a different seed gives different randomness
+        let other = Encryption::encrypt_batch_parallel(
+            &messages[..1],
+            &ek,
+            &snark_pk.pk.vk,
+            chunk_bit_size,
+            &[6u8; 32],
+        )
+        .unwrap();
+        assert_ne!(other[0].1, batch[0].1);
+
+        // Checks the key like `Encryption::encrypt_given_snark_vk`
+        assert!(matches!(
+            Encryption::encrypt_batch_parallel(&messages, &ek, &snark_pk.pk.vk, 16, &seed),
+            Err(SaverError::GeneratorCountMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn mismatched_encryption_gens() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (gens, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+        let (gens_other, _, _, ek_other, _) = enc_setup(chunk_bit_size, &mut rng);
+        assert_ne!(gens.fingerprint(), gens_other.fingerprint());
+        assert_eq!(ek.gens_fingerprint, Some(gens.fingerprint()));
+        assert_eq!(ek_other.gens_fingerprint, Some(gens_other.fingerprint()));
+
+        let m = Fr::rand(&mut rng);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+        ct.verify_commitment(ek.clone(), gens.clone()).unwrap();
+        assert!(matches!(
+            ct.verify_commitment(ek.clone(), gens_other.clone()),
+            Err(SaverError::MismatchedEncryptionGens)
+        ));
+        assert!(matches!(
+            Encryption::verify_commitments_in_batch(
+                core::slice::from_ref(&ct),
+                &[Fr::one()],
                 ek.clone(),
-                gens.clone(),
-            )
+                gens_other.clone()
+            ),
+            Err(SaverError::MismatchedEncryptionGens)
+        ));
+
+        // Without a fingerprint in the key, only the pairing check can catch the mismatch
+        let mut ek_no_fp = ek.clone();
+        ek_no_fp.gens_fingerprint = None;
+        ct.verify_commitment(ek_no_fp.clone(), gens).unwrap();
+        assert!(matches!(
+            ct.verify_commitment(ek_no_fp, gens_other),
+            Err(SaverError::InvalidCommitment)
+        ));
+    }
+
+    #[test]
+    fn oblivious_decryption() {
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let (_, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+            let pairing_powers = dk.pairing_powers(chunk_bit_size, &g_i).unwrap();
+            let table = ObliviousTable::new(&pairing_powers, chunk_bit_size).unwrap();
+
+            // Small message has several 0 chunks
+            for m in [Fr::rand(&mut rng), Fr::from(300u64), Fr::zero(), -Fr::one()] {
+                let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+                let (m_, nu) = ct
+                    .decrypt_given_pairing_powers(
+                        &sk,
+                        dk.clone(),
+                        &g_i,
+                        chunk_bit_size,
+                        &pairing_powers,
+                    )
+                    .unwrap();
+                let (m_oblivious, nu_oblivious) =
+                    ct.decrypt_oblivious(&sk, dk.clone(), &g_i, &table).unwrap();
+                assert_eq!(m_, m);
+                assert_eq!(m_oblivious, m_);
+                assert_eq!(nu_oblivious, nu);
+            }
+
+            // Incomplete table is rejected
+            let mut short_powers = pairing_powers.clone();
+            short_powers[0].pop();
+            assert!(matches!(
+                ObliviousTable::new(&short_powers, chunk_bit_size),
+                Err(SaverError::InvalidPairingPowers)
+            ));
+            let m = Fr::rand(&mut rng);
+            let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+            let table_for_fewer_chunks =
+                ObliviousTable::new(&pairing_powers[1..], chunk_bit_size).unwrap();
+            assert!(matches!(
+                ct.decrypt_oblivious(&sk, dk.clone(), &g_i, &table_for_fewer_chunks),
+                Err(SaverError::InvalidPairingPowers)
+            ));
+
+            // Table for another chunk bit size is rejected rather than giving a wrong message
+            let other_chunk_bit_size = chunk_bit_size / 2;
+            let other_table = ObliviousTable::new(&pairing_powers, other_chunk_bit_size).unwrap();
+            let n = ct.encrypted_chunk_count();
+            for res in [
+                ct.decrypt_oblivious(&sk, dk.clone(), &g_i, &other_table),
+                Encryption::decrypt_oblivious(
+                    &ct.X_r,
+                    &ct.enc_chunks,
+                    &sk,
+                    dk.clone(),
+                    &g_i,
+                    &other_table,
+                ),
+            ] {
+                assert!(matches!(
+                    res,
+                    Err(SaverError::ChunkBitSizeMismatch { chunk_bit_size, chunks_count })
+                        if chunk_bit_size == other_chunk_bit_size && chunks_count == n
+                ));
+            }
+        }
+        check(4);
+        check(8);
+    }
+
+    #[test]
+    fn ciphertext_linear_combination() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (gens, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+
+        // Each message has small chunks so that no chunk of the weighted sum is 256 or more
+        let messages = [
+            Fr::from(0x0102u64),
+            Fr::from(0x0203u64),
+            Fr::from(0x0301u64),
+        ];
+        let weights = [3u64, 5, 7];
+        let mut cts = vec![];
+        let mut randomness = vec![];
+        for m in &messages {
+            let (ct, r) = Encryption::encrypt(&mut rng, m, &ek, &g_i, chunk_bit_size).unwrap();
+            cts.push(ct);
+            randomness.push(r);
+        }
+
+        let combined = Ciphertext::linear_combination(&cts, &weights, &ek).unwrap();
+        let expected_total = Fr::from(3 * 0x0102u64 + 5 * 0x0203 + 7 * 0x0301);
+        let total_r = randomness
+            .iter()
+            .zip(weights)
+            .map(|(r, w)| *r * Fr::from(w))
+            .sum::<Fr>();
+        combined
+            .verify_opening(&expected_total, &total_r, &ek, &g_i, chunk_bit_size)
+            .unwrap();
+        combined
+            .verify_commitment(ek.clone(), gens.clone())
+            .unwrap();
+        let (m, nu) = combined
+            .decrypt(&sk, dk.clone(), &g_i, chunk_bit_size)
+            .unwrap();
+        assert_eq!(m, expected_total);
+        combined
+            .verify_decryption(&m, &nu, chunk_bit_size, dk.clone(), &g_i, gens)
             .unwrap();
-            println!(
-                "Time taken to verify commitment of {}-bit chunks {:?}",
-                chunk_bit_size,
-                start.elapsed()
-            );
 
-            let start = Instant::now();
-            Encryption::verify_ciphertext_commitment(
-                &ct[0],
-                &ct[1..m.len() + 1],
-                &ct[m.len() + 1],
-                prepared_ek,
-                prepared_gens.clone(),
-            )
+        // A chunk of the weighted sum overflows so decryption fails
+        let combined = Ciphertext::linear_combination(&cts, &[100, 100, 100], &ek).unwrap();
+        assert!(matches!(
+            combined.decrypt(&sk, dk, &g_i, chunk_bit_size),
+            Err(SaverError::CouldNotFindDiscreteLog)
+        ));
+
+        assert!(matches!(
+            Ciphertext::linear_combination(&cts, &weights[..2], &ek),
+            Err(SaverError::UnequalSizeOfSequence(3, 2))
+        ));
+        assert!(Ciphertext::linear_combination(&[], &[], &ek).is_err());
+        let (_, _, _, other_ek, _) = enc_setup(4, &mut rng);
+        assert!(matches!(
+            Ciphertext::linear_combination(&cts, &weights, &other_ek),
+            Err(SaverError::IncompatibleEncryptionKey(_, _))
+        ));
+    }
+
+    #[test]
+    fn sum_of_ciphertexts() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (gens, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+
+        // Small messages so that no chunk of the sum is 256 or more
+        let messages = (1..=10u64)
+            .map(|i| Fr::from(i * 0x0101))
+            .collect::<Vec<_>>();
+        let expected_total = Fr::from(55 * 0x0101u64);
+        let cts = messages
+            .iter()
+            .map(|m| {
+                Encryption::encrypt(&mut rng, m, &ek, &g_i, chunk_bit_size)
+                    .unwrap()
+                    .0
+            })
+            .collect::<Vec<_>>();
+
+        let total = cts.iter().sum::<Ciphertext<Bls12_381>>();
+        assert_eq!(
+            total,
+            Ciphertext::linear_combination(&cts, &[1; 10], &ek).unwrap()
+        );
+        assert_eq!(total, cts.clone().into_iter().sum());
+        assert_eq!(total, Ciphertext::try_sum(&ek, &cts).unwrap());
+        let first_two = &cts[0] + &cts[1];
+        let rest = cts[2..].iter().sum::<Ciphertext<_>>();
+        assert_eq!(total, first_two + rest);
+        total.verify_commitment(ek.clone(), gens).unwrap();
+        let (m, _) = total
+            .decrypt(&sk, dk.clone(), &g_i, chunk_bit_size)
             .unwrap();
-            println!(
-                "Time taken to verify commitment of {}-bit chunks using prepared parameters {:?}",
-                chunk_bit_size,
-                start.elapsed()
+        assert_eq!(m, expected_total);
+
+        let zero = Ciphertext::zero(&ek, chunk_bit_size).unwrap();
+        assert_eq!(
+            ark_std::iter::once(zero.clone())
+                .chain(cts.clone())
+                .sum::<Ciphertext<_>>(),
+            total
+        );
+        assert!(Ciphertext::<Bls12_381>::zero(&ek, 4).is_err());
+        assert!(Ciphertext::<Bls12_381>::zero(&ek, 0).is_err());
+
+        // Sum of no ciphertexts is the zero ciphertext
+        let empty = Ciphertext::<Bls12_381>::try_sum(&ek, &[]).unwrap();
+        assert_eq!(empty, zero);
+        let (m, _) = empty.decrypt(&sk, dk, &g_i, chunk_bit_size).unwrap();
+        assert!(m.is_zero());
+        assert!(std::panic::catch_unwind(|| {
+            Vec::<Ciphertext<Bls12_381>>::new()
+                .into_iter()
+                .sum::<Ciphertext<_>>()
+        })
+        .is_err());
+
+        // Ciphertexts with different number of chunks
+        let mut short = cts[1].clone();
+        short.enc_chunks.pop();
+        assert!(matches!(
+            cts[0].try_add(&short),
+            Err(SaverError::UnequalSizeOfSequence(32, 31))
+        ));
+        assert_eq!(cts[0].try_add(&cts[1]).unwrap(), &cts[0] + &cts[1]);
+        assert!(matches!(
+            Ciphertext::try_sum(&ek, [&cts[0], &cts[1], &short]),
+            Err(SaverError::UnequalSizeOfSequence(32, 31))
+        ));
+        assert!(matches!(
+            Ciphertext::try_sum(&ek, [&short]),
+            Err(SaverError::UnequalSizeOfSequence(32, 31))
+        ));
+        assert!(std::panic::catch_unwind(|| &cts[0] + &short).is_err());
+        assert!(std::panic::catch_unwind(|| {
+            [cts[0].clone(), short.clone()]
+                .iter()
+                .sum::<Ciphertext<_>>()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn encrypt_signed_value() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 16;
+        let (_, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+        // Values in [-2^31, 2^31) are encoded in 32 bits, i.e. 2 chunks
+        let offset = 1u64 << 31;
+
+        for v in [-1_234_567i64, -(1 << 31), 0, (1 << 31) - 1] {
+            let m = utils::encode_signed::<Fr>(v, offset).unwrap();
+            assert!(
+                utils::message_from_u64::<Fr>(v.wrapping_add(offset as i64) as u64, 16, 2).is_ok()
             );
+            let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+            let (decrypted, _) = ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap();
+            assert_eq!(utils::decode_signed(&decrypted, offset).unwrap(), v);
+        }
 
-            let start = Instant::now();
-            let (m_, _) = Encryption::decrypt_to_chunks(
-                &ct[0],
-                &ct[1..m.len() + 1],
-                &sk,
-                dk.clone(),
-                &g_i,
-                chunk_bit_size,
-            )
+        // The sum of encodings carries the offset once per ciphertext. The offset is small so that no chunk
+        // of the sum overflows
+        let offset = 1000;
+        let values = [-500i64, 200, -100];
+        let cts = values
+            .iter()
+            .map(|v| {
+                let m = utils::encode_signed::<Fr>(*v, offset).unwrap();
+                Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size)
+                    .unwrap()
+                    .0
+            })
+            .collect::<Vec<_>>();
+        let (decrypted, _) = Ciphertext::linear_combination(&cts, &[1, 1, 1], &ek)
+            .unwrap()
+            .decrypt(&sk, dk, &g_i, chunk_bit_size)
             .unwrap();
-            println!(
-                "Time taken to decrypt {}-bit chunks {:?}",
-                chunk_bit_size,
-                start.elapsed()
+        assert_eq!(utils::decode_signed(&decrypted, 3 * offset).unwrap(), -400);
+
+        assert!(matches!(
+            utils::encode_signed::<Fr>(-11, 10),
+            Err(SaverError::SignedValueBelowOffset {
+                value: -11,
+                offset: 10
+            })
+        ));
+        assert_eq!(
+            utils::encode_signed::<Fr>(i64::MAX, u64::MAX).unwrap(),
+            Fr::from(i64::MAX as u128 + u64::MAX as u128)
+        );
+        assert_eq!(
+            utils::decode_signed(&Fr::from(i64::MAX as u128 + u64::MAX as u128), u64::MAX).unwrap(),
+            i64::MAX
+        );
+        assert!(matches!(
+            utils::decode_signed(&-Fr::one(), offset),
+            Err(SaverError::InvalidSignedEncoding)
+        ));
+        assert!(matches!(
+            utils::decode_signed(&Fr::from(u64::MAX), 0),
+            Err(SaverError::InvalidSignedEncoding)
+        ));
+    }
+
+    #[test]
+    fn same_encryption_either_side_of_parallel_threshold() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        // 16 chunks are encrypted by a single task, 32 and 64 by several
+        for chunk_bit_size in [16, 8, 4] {
+            let (_, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+            let n = ek.X.len();
+            assert_eq!(n < 2 * utils::PARALLEL_MIN_CHUNKS, chunk_bit_size == 16);
+            let message = Fr::rand(&mut rng);
+            let (ct, r) =
+                Encryption::encrypt(&mut rng, &message, &ek, &g_i, chunk_bit_size).unwrap();
+
+            // Encrypt each chunk serially
+            let m = decompose(&message, chunk_bit_size)
+                .unwrap()
+                .into_iter()
+                .map(Fr::from)
+                .collect::<Vec<_>>();
+            let mut expected = vec![ek.X_0 * r];
+            for i in 0..n {
+                expected.push(ek.X[i] * r + g_i[i] * m[i]);
+            }
+            expected.push(
+                (0..n)
+                    .map(|i| ek.Y[i] * m[i])
+                    .sum::<<Bls12_381 as Pairing>::G1>()
+                    + ek.P_1 * r,
             );
-            assert_eq!(m_, m);
+            let expected = <Bls12_381 as Pairing>::G1::normalize_batch(&expected);
+            assert_eq!(ct.X_r, expected[0]);
+            assert_eq!(ct.enc_chunks, expected[1..n + 1]);
+            assert_eq!(ct.commitment, expected[n + 1]);
+        }
+    }
 
-            let start = Instant::now();
-            let (m_, _) = Encryption::decrypt_to_chunks(
-                &ct[0],
-                &ct[1..m.len() + 1],
-                &sk,
-                prepared_dk.clone(),
-                &g_i,
-                chunk_bit_size,
-            )
+    #[test]
+    fn prove_sum_equals_public_total() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        // With 16-bit chunks, no chunk of the sum overflows
+        let chunk_bit_size = 16;
+        let (gens, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+
+        let contributions = [120u64, 4500, 37, 9000, 310];
+        let total = Fr::from(contributions.iter().sum::<u64>());
+        let cts = contributions
+            .iter()
+            .map(|c| {
+                Encryption::encrypt(&mut rng, &Fr::from(*c), &ek, &g_i, chunk_bit_size)
+                    .unwrap()
+                    .0
+            })
+            .collect::<Vec<_>>();
+
+        let nu = Ciphertext::prove_sum_equals(&cts, &total, &sk, dk.clone(), &g_i, chunk_bit_size)
             .unwrap();
-            println!(
-                "Time taken to decrypt {}-bit chunks using prepared parameters {:?}",
-                chunk_bit_size,
-                start.elapsed()
-            );
-            assert_eq!(m_, m);
+        Ciphertext::verify_sum_equals(
+            &cts,
+            &total,
+            &nu,
+            chunk_bit_size,
+            dk.clone(),
+            &g_i,
+            gens.clone(),
+        )
+        .unwrap();
+
+        // The contributions don't add up to the claimed total
+        let wrong_total = total + Fr::one();
+        assert!(matches!(
+            Ciphertext::prove_sum_equals(&cts, &wrong_total, &sk, dk.clone(), &g_i, chunk_bit_size),
+            Err(SaverError::SumNotEqualToTotal)
+        ));
+        assert!(Ciphertext::verify_sum_equals(
+            &cts,
+            &wrong_total,
+            &nu,
+            chunk_bit_size,
+            dk.clone(),
+            &g_i,
+            gens.clone()
+        )
+        .is_err());
+        // A contribution left out
+        assert!(Ciphertext::verify_sum_equals(
+            &cts[1..],
+            &total,
+            &nu,
+            chunk_bit_size,
+            dk.clone(),
+            &g_i,
+            gens.clone()
+        )
+        .is_err());
+
+        assert!(matches!(
+            Ciphertext::verify_sum_equals(&[], &total, &nu, chunk_bit_size, dk.clone(), &g_i, gens),
+            Err(SaverError::VectorShorterThanExpected(0, 1))
+        ));
+        let mut short = cts.clone();
+        short[2].enc_chunks.pop();
+        assert!(matches!(
+            Ciphertext::prove_sum_equals(&short, &total, &sk, dk, &g_i, chunk_bit_size),
+            Err(SaverError::UnequalSizeOfSequence(16, 15))
+        ));
+    }
+
+    #[test]
+    fn decryption_with_scratch() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let mut scratch = DecryptScratch::default();
+        for chunk_bit_size in [4, 8] {
+            let (_, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+            let prepared_dk = PreparedDecryptionKey::from(dk.clone());
+            for _ in 0..3 {
+                let m = Fr::rand(&mut rng);
+                let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+                let expected = ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap();
+                assert_eq!(expected.0, m);
+                assert_eq!(
+                    ct.decrypt_with_scratch(&sk, &prepared_dk, &g_i, chunk_bit_size, &mut scratch)
+                        .unwrap(),
+                    expected
+                );
+                assert_eq!(scratch.chunks(), decompose(&m, chunk_bit_size).unwrap());
+            }
 
+            // Scratch with the pairing powers of the key
             let pairing_powers = prepared_dk.pairing_powers(chunk_bit_size, &g_i).unwrap();
-            let start = Instant::now();
-            let (m_, nu) = Encryption::decrypt_to_chunks_given_pairing_powers(
-                &ct[0],
-                &ct[1..m.len() + 1],
-                &sk,
-                prepared_dk.clone(),
-                &g_i,
-                chunk_bit_size,
-                Some(&pairing_powers),
-            )
-            .unwrap();
-            println!(
-                "Time taken to decrypt {}-bit chunks using prepared parameters and pairing powers {:?}",
-                chunk_bit_size,
-                start.elapsed()
+            let mut scratch_with_powers =
+                DecryptScratch::with_pairing_powers(pairing_powers.clone());
+            let m = Fr::rand(&mut rng);
+            let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+            assert_eq!(
+                ct.decrypt_with_scratch(
+                    &sk,
+                    &prepared_dk,
+                    &g_i,
+                    chunk_bit_size,
+                    &mut scratch_with_powers
+                )
+                .unwrap(),
+                ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap()
             );
-            assert_eq!(m_, m);
+            let mut scratch_with_fewer_powers =
+                DecryptScratch::with_pairing_powers(pairing_powers[1..].to_vec());
+            assert!(matches!(
+                ct.decrypt_with_scratch(
+                    &sk,
+                    &prepared_dk,
+                    &g_i,
+                    chunk_bit_size,
+                    &mut scratch_with_fewer_powers
+                ),
+                Err(SaverError::InvalidPairingPowers)
+            ));
+            let (ct, _) =
+                Encryption::encrypt(&mut rng, &Fr::one(), &ek, &g_i, chunk_bit_size).unwrap();
+            assert!(matches!(
+                ct.decrypt_with_scratch(&sk, &prepared_dk, &g_i, 16, &mut scratch),
+                Err(SaverError::ChunkBitSizeMismatch { .. })
+            ));
+        }
+    }
 
-            let start = Instant::now();
-            Encryption::verify_decryption(&m_, &ct[0], &ct[1..m.len() + 1], &nu, dk, &g_i, gens)
-                .unwrap();
-            println!(
-                "Time taken to verify decryption of {}-bit chunks {:?}",
-                chunk_bit_size,
-                start.elapsed()
-            );
+    #[test]
+    fn ciphertext_ordering() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (_, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+        let cts = (0..10)
+            .map(|_| {
+                let m = Fr::rand(&mut rng);
+                Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size)
+                    .unwrap()
+                    .0
+            })
+            .collect::<Vec<_>>();
 
-            let start = Instant::now();
-            Encryption::verify_decryption(
-                &m_,
-                &ct[0],
-                &ct[1..m.len() + 1],
-                &nu,
-                prepared_dk,
-                &g_i,
-                prepared_gens,
-            )
-            .unwrap();
-            println!(
-                "Time taken to verify decryption of {}-bit chunks using prepared parameters {:?}",
-                chunk_bit_size,
-                start.elapsed()
-            );
+        let mut sorted = cts.clone();
+        sorted.sort();
+        for w in sorted.windows(2) {
+            assert!(w[0].canonical_bytes() < w[1].canonical_bytes());
+        }
+
+        // Same order whatever the order of the input
+        for i in 0..cts.len() {
+            let mut cts_ = cts.clone();
+            cts_.rotate_left(i);
+            cts_.reverse();
+            cts_.sort();
+            assert_eq!(cts_, sorted);
+
+            let mut cts_ = cts.clone();
+            cts_.rotate_left(i);
+            cts_.sort_by_cached_key(Ciphertext::canonical_bytes);
+            assert_eq!(cts_, sorted);
         }
 
-        check(4);
-        check(8);
-        check(16);
+        assert_eq!(cts[0].cmp(&cts[0].clone()), Ordering::Equal);
+        assert_eq!(cts[0].canonical_bytes(), {
+            let mut bytes = vec![];
+            cts[0].serialize_compressed(&mut bytes).unwrap();
+            bytes
+        });
+    }
+
+    #[test]
+    fn migrate_to_different_chunk_bit_size() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (old_pk, old_sk, old_ek, old_dk) = setup_for_groth16(&mut rng, 8, &gens).unwrap();
+        let (new_pk, new_sk, new_ek, new_dk) = setup_for_groth16(&mut rng, 16, &gens).unwrap();
+        let (old_vk, new_vk) = (&old_pk.pk.vk, &new_pk.pk.vk);
+
+        let m = Fr::rand(&mut rng);
+        let (ct, _) = Encryption::encrypt_given_snark_vk(&mut rng, &m, &old_ek, old_vk, 8).unwrap();
+        let old_keys = MigrationSourceKeys {
+            sk: &old_sk,
+            dk: old_dk.into(),
+            snark_vk: old_vk,
+            chunk_bit_size: 8,
+        };
+        let new_keys = MigrationTargetKeys {
+            ek: &new_ek,
+            snark_vk: new_vk,
+            chunk_bit_size: 16,
+        };
+        let (new_ct, new_r) =
+            Encryption::migrate_ciphertext(&mut rng, &ct, old_keys.clone(), &new_keys).unwrap();
+        assert_eq!(new_ct.enc_chunks.len(), chunks_count::<Fr>(16) as usize);
+        new_ct
+            .verify_opening_given_groth16_vk(&m, &new_r, &new_ek, new_vk, 16)
+            .unwrap();
+        new_ct
+            .verify_commitment(new_ek.clone(), gens.clone())
+            .unwrap();
+        assert_eq!(
+            new_ct
+                .decrypt_given_groth16_vk(&new_sk, new_dk, new_vk, 16)
+                .unwrap()
+                .0,
+            m
+        );
+
+        // A proof can be created for the new ciphertext with its randomness
+        let decomposed = decompose(&m, 16)
+            .unwrap()
+            .into_iter()
+            .map(Fr::from)
+            .collect::<Vec<_>>();
+        let circuit = BitsizeCheckCircuit::new(16, None, Some(decomposed), true);
+        let proof =
+            saver_groth16::create_proof(circuit, &new_r, &new_pk, &new_ek, &mut rng).unwrap();
+        new_ct
+            .verify_commitment_and_proof(
+                &proof,
+                &saver_groth16::prepare_verifying_key(new_vk),
+                new_ek.clone(),
+                gens.clone(),
+            )
+            .unwrap();
+
+        // Migrating with the wrong old chunk bit size fails
+        assert!(matches!(
+            Encryption::migrate_ciphertext(
+                &mut rng,
+                &ct,
+                MigrationSourceKeys {
+                    chunk_bit_size: 16,
+                    ..old_keys
+                },
+                &new_keys,
+            ),
+            Err(SaverError::IncompatibleEncryptionKey(32, 16))
+        ));
     }
 
     #[test]
-    fn encrypt_decrypt_timing() {
-        fn check(chunk_bit_size: u8, count: u8) {
-            let mut rng = StdRng::seed_from_u64(0u64);
-            let (gens, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
-            let prepared_gens = PreparedEncryptionGens::from(gens.clone());
-            let prepared_ek = PreparedEncryptionKey::from(ek.clone());
-            let prepared_dk = PreparedDecryptionKey::from(dk.clone());
-            let pairing_powers = prepared_dk.pairing_powers(chunk_bit_size, &g_i).unwrap();
+    fn ciphertext_parts() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (gens, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+        let m = Fr::rand(&mut rng);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
 
-            let mut total_enc = Duration::default();
-            let mut total_ver_com = Duration::default();
-            let mut total_ver_com_prep = Duration::default();
-            let mut total_dec = Duration::default();
-            let mut total_dec_prep = Duration::default();
-            let mut total_dec_prep_powers = Duration::default();
-            let mut total_ver_dec = Duration::default();
-            let mut total_ver_dec_prep = Duration::default();
+        let (x_r, enc_chunks, commitment) = ct.clone().into_parts();
+        assert_eq!(x_r, ct.X_r);
+        assert_eq!(enc_chunks, ct.enc_chunks);
+        assert_eq!(commitment, ct.commitment);
 
-            for _ in 0..count {
-                let m = Fr::rand(&mut rng);
+        let ct_ = Ciphertext::from_parts(ct.clone().into_parts()).unwrap();
+        assert_eq!(ct_, ct);
+        let ct_: Ciphertext<Bls12_381> = CiphertextParts::<Bls12_381>::from(ct.clone())
+            .try_into()
+            .unwrap();
+        assert_eq!(ct_, ct);
+        ct_.verify_commitment(ek.clone(), gens).unwrap();
+        assert_eq!(ct_.decrypt(&sk, dk, &g_i, chunk_bit_size).unwrap().0, m);
 
-                let start = Instant::now();
-                let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
-                total_enc += start.elapsed();
+        // Number of encrypted chunks must be valid
+        let (x_r, mut enc_chunks, commitment) = ct.into_parts();
+        enc_chunks.pop();
+        assert!(Ciphertext::<Bls12_381>::from_parts((x_r, enc_chunks, commitment)).is_err());
+        assert!(Ciphertext::<Bls12_381>::from_parts((x_r, vec![], commitment)).is_err());
+    }
 
-                let start = Instant::now();
-                ct.verify_commitment(ek.clone(), gens.clone()).unwrap();
-                total_ver_com += start.elapsed();
+    #[test]
+    fn encrypt_committed_message() {
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let enc_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+            let comm_gens =
+                ChunkedCommitmentGens::<<Bls12_381 as Pairing>::G1Affine>::new_using_rng(&mut rng);
+            let (snark_pk, sk, ek, dk) =
+                setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens).unwrap();
 
-                let start = Instant::now();
-                ct.verify_commitment(prepared_ek.clone(), prepared_gens.clone())
-                    .unwrap();
-                total_ver_com_prep += start.elapsed();
+            let m = Fr::rand(&mut rng);
+            let blinding = Fr::rand(&mut rng);
+            let enc = Encryption::encrypt_committed(
+                &mut rng,
+                &m,
+                &blinding,
+                &comm_gens,
+                &ek,
+                &snark_pk.pk.vk,
+                chunk_bit_size,
+            )
+            .unwrap();
 
-                let (chunks, nu) = Encryption::decrypt_to_chunks(
-                    &ct.X_r,
-                    &ct.enc_chunks,
-                    &sk,
-                    dk.clone(),
-                    &g_i,
-                    chunk_bit_size,
+            // Commitment opens to the message
+            assert_eq!(
+                enc.commitment,
+                (comm_gens.G * m + comm_gens.H * blinding).into_affine()
+            );
+            assert_eq!(
+                enc.commitment,
+                <Bls12_381 as Pairing>::G1::msm(
+                    &enc.chunked_comm_key,
+                    &enc.chunked_commitment_witnesses()
+                )
+                .unwrap()
+                .into_affine()
+            );
+            // Ciphertext's commitment opens to the chunks of the message
+            assert_eq!(
+                enc.ciphertext.commitment,
+                <Bls12_381 as Pairing>::G1::msm(
+                    &enc.ct_comm_key,
+                    &enc.ciphertext_commitment_witnesses()
                 )
+                .unwrap()
+                .into_affine()
+            );
+            enc.ciphertext
+                .verify_commitment(ek.clone(), enc_gens)
                 .unwrap();
 
-                let decomposed = decompose(&m, chunk_bit_size).unwrap();
-                assert_eq!(decomposed, chunks);
+            let n = chunks_count::<Fr>(chunk_bit_size) as usize;
+            let eqs = enc.witness_equalities(2, 3);
+            assert_eq!(eqs.len(), n);
+            for (i, (a, b)) in eqs.into_iter().enumerate() {
+                assert_eq!(a, (2, i));
+                assert_eq!(b, (3, i));
+                assert_eq!(
+                    enc.chunked_commitment_witnesses()[a.1],
+                    enc.ciphertext_commitment_witnesses()[b.1]
+                );
+            }
 
-                let start = Instant::now();
-                let (m_, nu_) = ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap();
-                total_dec += start.elapsed();
-                assert_eq!(m, m_);
-                assert_eq!(nu, nu_);
+            let (m_, _) = enc
+                .ciphertext
+                .decrypt_given_groth16_vk(&sk, dk, &snark_pk.pk.vk, chunk_bit_size)
+                .unwrap();
+            assert_eq!(m_, m);
+        }
+        check(4);
+        check(8);
+    }
 
-                let start = Instant::now();
-                let (m_, nu_) = ct
-                    .decrypt(&sk, prepared_dk.clone(), &g_i, chunk_bit_size)
-                    .unwrap();
-                total_dec_prep += start.elapsed();
-                assert_eq!(m, m_);
-                assert_eq!(nu, nu_);
+    #[test]
+    fn encrypt_and_decrypt_bytes() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let enc_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, sk, ek, dk) =
+            setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens).unwrap();
 
-                let start = Instant::now();
-                let (m_, nu_) = ct
-                    .decrypt_given_pairing_powers(
-                        &sk,
-                        prepared_dk.clone(),
-                        &g_i,
-                        chunk_bit_size,
-                        &pairing_powers,
-                    )
-                    .unwrap();
-                total_dec_prep_powers += start.elapsed();
-                assert_eq!(m, m_);
-                assert_eq!(nu, nu_);
+        let mut payload = [0u8; 40];
+        rng.fill_bytes(&mut payload);
+        let mut payload_with_trailing_zeros = payload.to_vec();
+        payload_with_trailing_zeros.extend_from_slice(&[0; 5]);
 
-                let start = Instant::now();
-                ct.verify_decryption(&m, &nu, chunk_bit_size, dk.clone(), &g_i, gens.clone())
+        for data in [&payload[..], &payload_with_trailing_zeros, &[]] {
+            let cts =
+                Encryption::encrypt_bytes(&mut rng, data, &ek, &snark_pk.pk.vk, chunk_bit_size)
                     .unwrap();
-                total_ver_dec += start.elapsed();
+            // 31 bytes fit in a field element and the length prefix takes 4 bytes
+            assert_eq!(cts.len(), (data.len() + 4).div_ceil(31));
+            let cts = cts.into_iter().map(|(ct, _)| ct).collect::<Vec<_>>();
+            for ct in &cts {
+                ct.verify_commitment(ek.clone(), enc_gens.clone()).unwrap();
+            }
+            let decrypted =
+                Encryption::decrypt_bytes(&cts, &sk, dk.clone(), &snark_pk.pk.vk, chunk_bit_size)
+                    .unwrap();
+            assert_eq!(decrypted, data);
+        }
+    }
 
-                let start = Instant::now();
-                ct.verify_decryption(
-                    &m,
-                    &nu,
-                    chunk_bit_size,
-                    prepared_dk.clone(),
-                    &g_i,
-                    prepared_gens.clone(),
-                )
-                .unwrap();
-                total_ver_dec_prep += start.elapsed();
+    #[test]
+    fn decrypt_to_integer() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, sk, ek, dk) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        let snark_vk = &snark_pk.pk.vk;
+
+        // A message bigger than a u64, spanning all the chunks
+        let m = Fr::rand(&mut rng);
+        let (ct, _, _) =
+            Encryption::encrypt_with_proof(&mut rng, &m, &ek, &snark_pk, chunk_bit_size).unwrap();
+        let (decrypted, nu) = ct
+            .decrypt_to_biguint(&sk, dk.clone(), snark_vk, chunk_bit_size)
+            .unwrap();
+        assert!(decrypted > BigUint::from(u64::MAX));
+        assert_eq!(
+            nu,
+            ct.decrypt_given_groth16_vk(&sk, dk, snark_vk, chunk_bit_size)
+                .unwrap()
+                .1
+        );
+
+        // The chunks composed as an integer, big-endian in base 2^chunk_bit_size
+        let expected = decompose(&m, chunk_bit_size)
+            .unwrap()
+            .into_iter()
+            .fold(BigUint::from(0u8), |acc, c| {
+                (acc << chunk_bit_size) + BigUint::from(c)
+            });
+        assert_eq!(decrypted, expected);
+        assert_eq!(
+            utils::field_element_to_biguint(
+                &utils::compose::<Fr>(&decompose(&m, chunk_bit_size).unwrap(), chunk_bit_size)
+                    .unwrap()
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn chunk_ciphertexts() {
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let (_, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+            let m = Fr::rand(&mut rng);
+            let (ct, r) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+            let chunks = ct.chunk_ciphertexts();
+            assert_eq!(chunks.len(), chunks_count::<Fr>(chunk_bit_size) as usize);
+            assert_eq!(chunks.len(), ct.encrypted_chunk_count());
+            // Indexed like the decomposition
+            for (i, m_i) in decompose(&m, chunk_bit_size)
+                .unwrap()
+                .into_iter()
+                .enumerate()
+            {
+                assert_eq!(
+                    chunks[i],
+                    (ek.X[i] * r + g_i[i] * Fr::from(m_i)).into_affine()
+                );
             }
+        }
+        check(4);
+        check(8);
+    }
 
-            println!(
-                "Time taken for {} iterations and {}-bit chunk size:",
-                count, chunk_bit_size
-            );
-            println!("Encryption {:?}", total_enc);
-            println!("Verifying commitment {:?}", total_ver_com);
-            println!(
-                "Verifying commitment using prepared {:?}",
-                total_ver_com_prep
+    #[test]
+    fn commitment_bases_order() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (_, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+        // Built by hand as `ek.Y` followed by `ek.P_1`
+        let mut expected = ek.Y.clone();
+        expected.push(ek.P_1);
+        let bases = Ciphertext::commitment_bases(&ek);
+        assert_eq!(bases, expected);
+        assert_eq!(bases, ek.commitment_key());
+        assert_eq!(bases.len(), chunks_count::<Fr>(chunk_bit_size) as usize + 1);
+
+        // Committing with the bases and the witness gives the ciphertext's commitment
+        let m = Fr::rand(&mut rng);
+        let (ct, r) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+        let decomposed = decompose(&m, chunk_bit_size)
+            .unwrap()
+            .into_iter()
+            .map(Fr::from)
+            .collect::<Vec<_>>();
+        let witness = utils::ciphertext_commitment_witness(&decomposed, r);
+        assert_eq!(
+            <Bls12_381 as Pairing>::G1::msm_unchecked(&bases, &witness).into_affine(),
+            ct.commitment
+        );
+    }
+
+    #[test]
+    fn recompute_commitment() {
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let (_, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+            let m = Fr::rand(&mut rng);
+            let (ct, r) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+            assert_eq!(
+                ct.recompute_commitment(&m, &r, &ek, chunk_bit_size)
+                    .unwrap(),
+                ct.commitment
             );
-            println!("Decryption {:?}", total_dec);
-            println!("Decryption using prepared {:?}", total_dec_prep);
-            println!(
-                "Decryption using prepared and pairing powers {:?}",
-                total_dec_prep_powers
+
+            // A wrong opening gives a different commitment
+            let m_1 = Fr::rand(&mut rng);
+            assert_ne!(
+                ct.recompute_commitment(&m_1, &r, &ek, chunk_bit_size)
+                    .unwrap(),
+                ct.commitment
             );
-            println!("Verifying decryption {:?}", total_ver_dec);
-            println!(
-                "Verifying decryption using prepared {:?}",
-                total_ver_dec_prep
+            assert_ne!(
+                ct.recompute_commitment(&m, &Fr::rand(&mut rng), &ek, chunk_bit_size)
+                    .unwrap(),
+                ct.commitment
             );
+
+            // The chunk bit size must match the key
+            let other_chunk_bit_size = if chunk_bit_size == 4 { 8 } else { 4 };
+            assert!(matches!(
+                ct.recompute_commitment(&m, &r, &ek, other_chunk_bit_size),
+                Err(SaverError::IncompatibleEncryptionKey(..))
+            ));
         }
-        check(4, 10);
-        check(8, 10);
-        check(16, 4);
+        check(4);
+        check(8);
     }
 
     #[test]
-    fn batch_commitment_verification() {
-        fn check(chunk_bit_size: u8, count: u8) {
-            let mut rng = StdRng::seed_from_u64(0u64);
-            let (gens, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+    fn framed_ciphertexts() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (_, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+        let cts = (0..3)
+            .map(|_| {
+                let m = Fr::rand(&mut rng);
+                Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size)
+                    .unwrap()
+                    .0
+            })
+            .collect::<Vec<_>>();
 
-            let mut cts = vec![];
+        let mut buf = vec![];
+        for ct in &cts {
+            ct.write_framed(&mut buf).unwrap();
+        }
+        assert_eq!(
+            Ciphertext::<Bls12_381>::read_all_framed(&buf[..]).unwrap(),
+            cts
+        );
 
-            let mut total_ver_com = Duration::default();
+        // Read one at a time
+        let mut reader = &buf[..];
+        for ct in &cts {
+            assert_eq!(
+                &Ciphertext::<Bls12_381>::read_framed(&mut reader).unwrap(),
+                ct
+            );
+        }
+        assert!(Ciphertext::<Bls12_381>::read_framed(&mut reader).is_err());
+        assert!(Ciphertext::<Bls12_381>::read_all_framed(&[][..])
+            .unwrap()
+            .is_empty());
 
-            for _ in 0..count {
+        // Truncated last ciphertext
+        assert!(Ciphertext::<Bls12_381>::read_all_framed(&buf[..buf.len() - 1]).is_err());
+
+        // Frame length not matching the ciphertext
+        let mut bytes = vec![];
+        utils::write_varint(cts[0].compressed_size() as u64 + 1, &mut bytes).unwrap();
+        bytes.extend_from_slice(&cts[0].canonical_bytes());
+        bytes.push(0);
+        assert!(Ciphertext::<Bls12_381>::read_framed(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn verify_decryptions_in_batch() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, sk, ek, dk) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        let vk = &snark_pk.pk.vk;
+        let decryptions = (0..10)
+            .map(|_| {
                 let m = Fr::rand(&mut rng);
-                let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+                let (ct, _) =
+                    Encryption::encrypt_given_snark_vk(&mut rng, &m, &ek, vk, chunk_bit_size)
+                        .unwrap();
+                let (m_, nu) = ct
+                    .decrypt_given_groth16_vk(&sk, dk.clone(), vk, chunk_bit_size)
+                    .unwrap();
+                assert_eq!(m_, m);
+                (ct, m, nu)
+            })
+            .collect::<Vec<_>>();
 
-                let start = Instant::now();
-                ct.verify_commitment(ek.clone(), gens.clone()).unwrap();
-                total_ver_com += start.elapsed();
+        Encryption::verify_decryptions_in_batch(
+            &mut rng,
+            &decryptions,
+            dk.clone(),
+            vk,
+            gens.clone(),
+            chunk_bit_size,
+        )
+        .unwrap();
+        Encryption::verify_decryptions_in_batch(
+            &mut rng,
+            &[],
+            dk.clone(),
+            vk,
+            gens.clone(),
+            chunk_bit_size,
+        )
+        .unwrap();
 
-                cts.push(ct);
-            }
+        // A single wrong message, nu or ciphertext fails the batch
+        let mut wrong_message = decryptions.clone();
+        wrong_message[3].1 += Fr::one();
+        let mut wrong_nu = decryptions.clone();
+        wrong_nu[9].2 = decryptions[8].2;
+        let mut wrong_ct = decryptions.clone();
+        wrong_ct[0].0 = decryptions[1].0.clone();
+        for batch in [wrong_message, wrong_nu, wrong_ct] {
+            assert!(matches!(
+                Encryption::verify_decryptions_in_batch(
+                    &mut rng,
+                    &batch,
+                    dk.clone(),
+                    vk,
+                    gens.clone(),
+                    chunk_bit_size,
+                ),
+                Err(SaverError::InvalidDecryption)
+            ));
+        }
 
-            let r = Fr::rand(&mut rng);
-            let mut r_powers = vec![Fr::one(); count as usize];
-            for i in 1..count as usize {
-                r_powers[i] = r_powers[i - 1] * &r;
-            }
+        // Wrong chunk bit size
+        assert!(matches!(
+            Encryption::verify_decryptions_in_batch(&mut rng, &decryptions, dk, vk, gens, 4,),
+            Err(SaverError::IncompatibleDecryptionKey(64, 32))
+        ));
+    }
 
-            let start = Instant::now();
-            Encryption::verify_commitments_in_batch(&cts, &r_powers, ek.clone(), gens).unwrap();
-            let t = start.elapsed();
+    #[test]
+    fn chunk_bit_size_from_keys() {
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let (_, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+            assert_eq!(ek.chunk_bit_size().unwrap(), chunk_bit_size);
+            assert_eq!(
+                PreparedEncryptionKey::from(ek.clone())
+                    .chunk_bit_size()
+                    .unwrap(),
+                chunk_bit_size
+            );
 
-            println!(
-                "Time taken for {} iterations and {}-bit chunk size:",
-                count, chunk_bit_size
+            // The chunk bit size of the key and ciphertext is used when not given
+            let m = Fr::rand(&mut rng);
+            let (ct, _) =
+                Encryption::encrypt_with_key_chunk_bit_size(&mut rng, &m, &ek, &g_i).unwrap();
+            assert_eq!(ct.chunk_bit_size().unwrap(), chunk_bit_size);
+            let (m_, nu) = ct
+                .decrypt_with_own_chunk_bit_size(&sk, dk.clone(), &g_i)
+                .unwrap();
+            assert_eq!(m_, m);
+            assert_eq!(
+                ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap(),
+                (m_, nu)
             );
-            println!("Verifying commitment {:?}", total_ver_com);
-            println!("Verifying commitments in batch {:?}", t);
+
+            // A different chunk bit size is rejected
+            for other in [4, 8, 16] {
+                if other == chunk_bit_size {
+                    continue;
+                }
+                assert!(matches!(
+                    Encryption::encrypt(&mut rng, &m, &ek, &g_i, other),
+                    Err(SaverError::IncompatibleEncryptionKey(..))
+                ));
+                assert!(matches!(
+                    ct.decrypt(&sk, dk.clone(), &g_i, other),
+                    Err(SaverError::ChunkBitSizeMismatch { chunk_bit_size: c, chunks_count: n })
+                        if c == other && n == chunks_count::<Fr>(chunk_bit_size) as usize
+                ));
+                assert!(matches!(
+                    ct.decrypt_given_pairing_powers(&sk, dk.clone(), &g_i, other, &[]),
+                    Err(SaverError::ChunkBitSizeMismatch { .. })
+                ));
+            }
         }
+        check(4);
+        check(8);
+        check(16);
+    }
 
-        check(4, 10);
-        check(8, 10);
-        check(16, 10);
+    #[test]
+    fn encrypt_given_snark_vk_with_wrong_generator_count() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, 8, &gens).unwrap();
+        let m = Fr::rand(&mut rng);
+        Encryption::encrypt_given_snark_vk(&mut rng, &m, &ek, &snark_pk.pk.vk, 8).unwrap();
+
+        // The key has a base for each of the 32 chunks of 8 bits and the randomness but the message has 64
+        // chunks of 4 bits
+        assert!(matches!(
+            Encryption::encrypt_given_snark_vk(&mut rng, &m, &ek, &snark_pk.pk.vk, 4),
+            Err(SaverError::GeneratorCountMismatch {
+                key: 33,
+                message: 65
+            })
+        ));
+        assert!(matches!(
+            Encryption::encrypt_given_snark_vk(&mut rng, &m, &ek, &snark_pk.pk.vk, 16),
+            Err(SaverError::GeneratorCountMismatch {
+                key: 33,
+                message: 17
+            })
+        ));
+        assert!(matches!(
+            Encryption::encrypt_given_snark_vk(&mut rng, &m, &ek, &snark_pk.pk.vk, 0),
+            Err(SaverError::UnexpectedBase(0))
+        ));
+    }
+
+    #[test]
+    fn decrypt_and_prove() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, sk, ek, dk) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        let m = Fr::rand(&mut rng);
+        let (ct, _) =
+            Encryption::encrypt_given_snark_vk(&mut rng, &m, &ek, &snark_pk.pk.vk, chunk_bit_size)
+                .unwrap();
+
+        let (m_, decryption) = ct
+            .decrypt_and_prove(
+                &sk,
+                dk.clone(),
+                &snark_pk.pk.vk,
+                gens.clone(),
+                chunk_bit_size,
+            )
+            .unwrap();
+        assert_eq!(m_, m);
+        assert_eq!(decryption.message, m);
+
+        // Verifier gets the serialized decryption
+        let mut bytes = vec![];
+        decryption.serialize_compressed(&mut bytes).unwrap();
+        let received =
+            VerifiableDecryption::<Bls12_381>::deserialize_compressed(&bytes[..]).unwrap();
+        received
+            .verify(&ct, dk.clone(), &snark_pk.pk.vk, gens.clone())
+            .unwrap();
+        test_serialization!(VerifiableDecryption<Bls12_381>, decryption);
+
+        // Doesn't verify for another ciphertext or with a changed message
+        let (other_ct, _) =
+            Encryption::encrypt_given_snark_vk(&mut rng, &m, &ek, &snark_pk.pk.vk, chunk_bit_size)
+                .unwrap();
+        assert!(received
+            .verify(&other_ct, dk.clone(), &snark_pk.pk.vk, gens.clone())
+            .is_err());
+        let mut tampered = received;
+        tampered.message += Fr::one();
+        assert!(tampered.verify(&ct, dk, &snark_pk.pk.vk, gens).is_err());
+    }
+
+    #[test]
+    fn decryption_verification_reports_first_wrong_chunk() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (gens, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+        let m = Fr::rand(&mut rng);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+        let (decrypted, nu) = ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap();
+        let decomposed = decompose(&decrypted, chunk_bit_size).unwrap();
+        Encryption::verify_decryption(
+            &decomposed,
+            &ct.X_r,
+            &ct.enc_chunks,
+            &nu,
+            dk.clone(),
+            &g_i,
+            gens.clone(),
+        )
+        .unwrap();
+
+        // Claim wrong in the third chunk and in a later one
+        let mut wrong = decomposed.clone();
+        wrong[2] ^= 1;
+        wrong[10] ^= 1;
+        assert!(matches!(
+            Encryption::verify_decryption(
+                &wrong,
+                &ct.X_r,
+                &ct.enc_chunks,
+                &nu,
+                dk.clone(),
+                &g_i,
+                gens.clone()
+            ),
+            Err(SaverError::InvalidDecryptedChunk(2))
+        ));
+
+        // Wrong `nu` fails before any chunk is checked
+        let wrong_nu = (nu.into_group() + gens.G).into_affine();
+        assert!(matches!(
+            ct.verify_decryption(&decrypted, &wrong_nu, chunk_bit_size, dk, &g_i, gens),
+            Err(SaverError::InvalidDecryption)
+        ));
     }
 }
@@ -1,7 +1,9 @@
 use ark_relations::r1cs::SynthesisError;
+use ark_serialize::SerializationError;
+use ark_std::fmt;
 use legogroth16::error::Error as LegoGroth16Error;
 
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub enum SaverError {
     UnexpectedBase(u8),
     InvalidDecomposition,
@@ -9,16 +11,223 @@ pub enum SaverError {
     SynthesisError(SynthesisError),
     AtLeastOneNonNoneRequired,
     VectorShorterThanExpected(usize, usize),
+    InsufficientGenerators {
+        have: usize,
+        need: usize,
+        source: &'static str,
+    },
     MalformedEncryptionKey(usize, usize),
     MalformedDecryptionKey(usize, usize),
     IncompatibleEncryptionKey(usize, usize),
     IncompatibleDecryptionKey(usize, usize),
+    IncompatibleCommitmentBases(usize, usize),
+    GeneratorCountMismatch {
+        key: usize,
+        message: usize,
+    },
+    UnexpectedSecretsCount(usize, usize),
+    InconsistentSetup,
+    ZeroRotationDelta,
+    NotARerandomization,
+    MismatchedEncryptionGens,
+    MismatchedCommitmentBases,
     InvalidProof,
+    EmptyVerifyingKey,
     InvalidCommitment,
     InvalidDecryption,
+    InvalidDecryptedChunk(usize),
+    ChunkBitSizeMismatch {
+        chunk_bit_size: u8,
+        chunks_count: usize,
+    },
+    InvalidOpening,
     CouldNotFindDiscreteLog,
     InvalidPairingPowers,
     PairingCheckFailed,
+    InvalidByteEncoding,
+    InvalidJson,
+    InvalidEvmEncoding,
+    TrivialMessage,
+    MessageOutOfRange {
+        value: u64,
+        max: u64,
+    },
+    TooManyChunks {
+        chunk_bit_size: u8,
+        chunks_count: u8,
+        max: u8,
+    },
+    ChunkOutOfRange {
+        index: usize,
+        value: u64,
+        max: u64,
+    },
+    CurveMismatch {
+        expected: u8,
+        got: u8,
+    },
+    SignedValueBelowOffset {
+        value: i64,
+        offset: u64,
+    },
+    InvalidSignedEncoding,
+    SumNotEqualToTotal,
+    UnequalSizeOfSequence(usize, usize),
+    InvalidHybridCiphertext,
+    Serialization(SerializationError),
+}
+
+impl fmt::Display for SaverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedBase(b) => write!(f, "unsupported chunk bit size {}", b),
+            Self::InvalidDecomposition => write!(f, "invalid decomposition of message into chunks"),
+            Self::LegoGroth16Error(e) => write!(f, "LegoGroth16 error: {:?}", e),
+            Self::SynthesisError(e) => write!(f, "circuit synthesis error: {}", e),
+            Self::AtLeastOneNonNoneRequired => write!(f, "at least one non-None value is required"),
+            Self::VectorShorterThanExpected(l, e) => {
+                write!(f, "vector of length {} is shorter than expected {}", l, e)
+            }
+            Self::InsufficientGenerators { have, need, source } => write!(
+                f,
+                "{} has {} generators but {} are needed, one for each chunk. Use generators created for the same chunk bit size as the keys",
+                source, have, need
+            ),
+            Self::MalformedEncryptionKey(l, e) => {
+                write!(
+                    f,
+                    "malformed encryption key, found {} but expected {}",
+                    l, e
+                )
+            }
+            Self::MalformedDecryptionKey(l, e) => {
+                write!(
+                    f,
+                    "malformed decryption key, found {} but expected {}",
+                    l, e
+                )
+            }
+            Self::IncompatibleEncryptionKey(l, e) => write!(
+                f,
+                "encryption key incompatible with {} chunks, expected {}",
+                l, e
+            ),
+            Self::IncompatibleDecryptionKey(l, e) => write!(
+                f,
+                "decryption key incompatible with {} chunks, expected {}",
+                l, e
+            ),
+            Self::IncompatibleCommitmentBases(l, e) => {
+                write!(f, "{} commitment bases given but expected {}", l, e)
+            }
+            Self::GeneratorCountMismatch { key, message } => write!(
+                f,
+                "encryption key has {} commitment bases but the message needs {}",
+                key, message
+            ),
+            Self::UnexpectedSecretsCount(l, e) => {
+                write!(f, "{} secrets given but expected {}", l, e)
+            }
+            Self::InconsistentSetup => write!(f, "inconsistent setup"),
+            Self::ZeroRotationDelta => write!(f, "delta for rotating the decryption key is zero"),
+            Self::NotARerandomization => write!(
+                f,
+                "ciphertext is not the rerandomization of the other ciphertext with the delta"
+            ),
+            Self::MismatchedEncryptionGens => write!(
+                f,
+                "encryption generators differ from the ones the key was created with"
+            ),
+            Self::MismatchedCommitmentBases => write!(
+                f,
+                "commitment bases differ from the ones of the encryption key"
+            ),
+            Self::InvalidProof => write!(f, "invalid proof"),
+            Self::EmptyVerifyingKey => write!(
+                f,
+                "verifying key has no public input bases, not even the one for the constant"
+            ),
+            Self::InvalidCommitment => write!(f, "invalid commitment"),
+            Self::InvalidDecryption => write!(f, "invalid decryption"),
+            Self::InvalidDecryptedChunk(i) => {
+                write!(f, "chunk at index {} of the decrypted message is invalid", i)
+            }
+            Self::ChunkBitSizeMismatch {
+                chunk_bit_size,
+                chunks_count,
+            } => write!(
+                f,
+                "chunk bit size {} given but the ciphertext has {} chunks",
+                chunk_bit_size, chunks_count
+            ),
+            Self::InvalidOpening => write!(
+                f,
+                "ciphertext is not the encryption of the message with the randomness"
+            ),
+            Self::CouldNotFindDiscreteLog => write!(f, "could not find discrete log"),
+            Self::InvalidPairingPowers => write!(f, "invalid pairing powers"),
+            Self::PairingCheckFailed => write!(f, "pairing check failed"),
+            Self::InvalidByteEncoding => write!(f, "invalid byte encoding"),
+            Self::InvalidJson => write!(f, "malformed JSON or unsupported schema version"),
+            Self::InvalidEvmEncoding => write!(f, "invalid encoding of points as 32-byte words"),
+            Self::TrivialMessage => write!(f, "message is 0 or 1"),
+            Self::MessageOutOfRange { value, max } => write!(
+                f,
+                "message {} is more than the maximum {} that can be decrypted",
+                value, max
+            ),
+            Self::TooManyChunks {
+                chunk_bit_size,
+                chunks_count,
+                max,
+            } => write!(
+                f,
+                "{} chunks of {} bits given but a field element only has {}",
+                chunks_count, chunk_bit_size, max
+            ),
+            Self::ChunkOutOfRange { index, value, max } => write!(
+                f,
+                "chunk {} has value {} which is more than the maximum {}",
+                index, value, max
+            ),
+            Self::CurveMismatch { expected, got } => write!(
+                f,
+                "bytes are for the curve with id {} but expected the curve with id {}",
+                got, expected
+            ),
+            Self::SignedValueBelowOffset { value, offset } => write!(
+                f,
+                "value {} is negative even after adding the offset {}",
+                value, offset
+            ),
+            Self::InvalidSignedEncoding => {
+                write!(f, "message minus the offset does not fit in an i64")
+            }
+            Self::SumNotEqualToTotal => {
+                write!(f, "sum of the encrypted messages is not the claimed total")
+            }
+            Self::UnequalSizeOfSequence(a, b) => {
+                write!(f, "sequences have unequal sizes {} and {}", a, b)
+            }
+            Self::InvalidHybridCiphertext => write!(
+                f,
+                "data in the hybrid ciphertext could not be decrypted with the decrypted key"
+            ),
+            Self::Serialization(e) => write!(f, "serialization error: {}", e),
+        }
+    }
+}
+
+/// `SynthesisError` and `SerializationError` are available as the source. `LegoGroth16Error` does not
+/// implement `Error` so it's only part of the message.
+impl ark_std::error::Error for SaverError {
+    fn source(&self) -> Option<&(dyn ark_std::error::Error + 'static)> {
+        match self {
+            Self::SynthesisError(e) => Some(e),
+            Self::Serialization(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 impl From<SynthesisError> for SaverError {
@@ -32,3 +241,9 @@ impl From<LegoGroth16Error> for SaverError {
         Self::LegoGroth16Error(e)
     }
 }
+
+impl From<SerializationError> for SaverError {
+    fn from(e: SerializationError) -> Self {
+        Self::Serialization(e)
+    }
+}
@@ -0,0 +1,201 @@
+//! Hybrid encryption where SAVER encrypts a key and the data is encrypted with ChaCha20-Poly1305 under a
+//! symmetric key derived from it. SAVER can only encrypt a field element and decrypting needs a discrete log
+//! for each chunk so encrypting bulk data with it is impractical. Here the SAVER ciphertext of the key comes
+//! with the usual proof that it's well-formed, so it can be decrypted by the decryptor, and the data is
+//! encrypted with the symmetric key with the SAVER ciphertext as associated data, binding the two.
+//!
+//! The key message must be random and must not be used to encrypt anything else or be revealed. Anyone
+//! learning it can decrypt the data.
+
+use crate::{
+    encryption::{Ciphertext, Encryption},
+    error::SaverError,
+    keygen::{EncryptionKey, PreparedDecryptionKey, SecretKey},
+    saver_groth16::ProvingKey,
+};
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{rand::RngCore, vec::Vec};
+use blake2::Blake2s256;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use digest::Digest;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+/// SAVER ciphertext of the key message and the data encrypted with the symmetric key derived from it
+#[derive(
+    Clone, PartialEq, Eq, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize,
+)]
+#[serde(bound = "")]
+pub struct HybridCiphertext<E: Pairing> {
+    /// Encryption of the key message
+    pub key_ciphertext: Ciphertext<E>,
+    pub nonce: [u8; 12],
+    /// Data encrypted with ChaCha20-Poly1305, including the authentication tag. Opaque to SAVER
+    pub data_ciphertext: Vec<u8>,
+}
+
+/// Encrypt `key_message` with SAVER, creating the proof that its ciphertext is well-formed, and `data` with
+/// the symmetric key derived from `key_message`. Returns the hybrid ciphertext, the randomness used in the
+/// SAVER encryption and the proof. The proof is verified like the proof of `Encryption::encrypt_with_proof`,
+/// eg. with `Ciphertext::verify_commitment_and_proof` on `key_ciphertext`.
+pub fn hybrid_encrypt<R: RngCore, E: Pairing>(
+    rng: &mut R,
+    key_message: &E::ScalarField,
+    data: &[u8],
+    ek: &EncryptionKey<E>,
+    snark_pk: &ProvingKey<E>,
+    chunk_bit_size: u8,
+) -> crate::Result<(HybridCiphertext<E>, E::ScalarField, ark_groth16::Proof<E>)> {
+    let (key_ciphertext, r, proof) =
+        Encryption::encrypt_with_proof(rng, key_message, ek, snark_pk, chunk_bit_size)?;
+    let mut nonce = [0u8; 12];
+    rng.fill_bytes(&mut nonce);
+    let aad = associated_data(&key_ciphertext)?;
+    let cipher = cipher_for_key_message::<E>(key_message)?;
+    let data_ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: data,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| SaverError::InvalidHybridCiphertext)?;
+    Ok((
+        HybridCiphertext {
+            key_ciphertext,
+            nonce,
+            data_ciphertext,
+        },
+        r,
+        proof,
+    ))
+}
+
+/// Decrypt the key message from the SAVER ciphertext and then the data with the symmetric key derived from
+/// it. Returns `SaverError::InvalidHybridCiphertext` if the data or the SAVER ciphertext was modified.
+pub fn hybrid_decrypt<E: Pairing>(
+    ciphertext: &HybridCiphertext<E>,
+    sk: &SecretKey<E::ScalarField>,
+    dk: impl Into<PreparedDecryptionKey<E>>,
+    snark_vk: &ark_groth16::VerifyingKey<E>,
+    chunk_bit_size: u8,
+) -> crate::Result<Vec<u8>> {
+    let (key_message, _) =
+        ciphertext
+            .key_ciphertext
+            .decrypt_given_groth16_vk(sk, dk, snark_vk, chunk_bit_size)?;
+    let aad = associated_data(&ciphertext.key_ciphertext)?;
+    let cipher = cipher_for_key_message::<E>(&key_message)?;
+    cipher
+        .decrypt(
+            Nonce::from_slice(&ciphertext.nonce),
+            Payload {
+                msg: &ciphertext.data_ciphertext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| SaverError::InvalidHybridCiphertext)
+}
+
+fn cipher_for_key_message<E: Pairing>(
+    key_message: &E::ScalarField,
+) -> crate::Result<ChaCha20Poly1305> {
+    let mut bytes = Vec::new();
+    key_message.serialize_compressed(&mut bytes)?;
+    let mut key: [u8; 32] = Blake2s256::new()
+        .chain_update(b"SAVER hybrid encryption key")
+        .chain_update(&bytes)
+        .finalize()
+        .into();
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    key.zeroize();
+    bytes.zeroize();
+    Ok(cipher)
+}
+
+fn associated_data<E: Pairing>(key_ciphertext: &Ciphertext<E>) -> crate::Result<Vec<u8>> {
+    let mut aad = Vec::new();
+    key_ciphertext.serialize_compressed(&mut aad)?;
+    Ok(aad)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        saver_groth16::prepare_verifying_key,
+        setup::{setup_for_groth16, EncryptionGens},
+    };
+    use ark_bls12_381::Bls12_381;
+    use ark_std::{
+        rand::{prelude::StdRng, SeedableRng},
+        UniformRand,
+    };
+
+    type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+    #[test]
+    fn hybrid_encryption_round_trip() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, sk, ek, dk) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        let snark_vk = &snark_pk.pk.vk;
+        let pvk = prepare_verifying_key(snark_vk);
+
+        let payload = b"a payload much longer than what fits in a single field element".repeat(10);
+        let key_message = Fr::rand(&mut rng);
+        let (ct, _, proof) = hybrid_encrypt(
+            &mut rng,
+            &key_message,
+            &payload,
+            &ek,
+            &snark_pk,
+            chunk_bit_size,
+        )
+        .unwrap();
+        ct.key_ciphertext
+            .verify_commitment_and_proof(&proof, &pvk, ek.clone(), gens.clone())
+            .unwrap();
+        assert_ne!(ct.data_ciphertext[..payload.len()], payload[..]);
+
+        let decrypted = hybrid_decrypt(&ct, &sk, dk.clone(), snark_vk, chunk_bit_size).unwrap();
+        assert_eq!(decrypted, payload);
+
+        let mut bytes = Vec::new();
+        ct.serialize_compressed(&mut bytes).unwrap();
+        let deserialized =
+            HybridCiphertext::<Bls12_381>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(deserialized, ct);
+
+        // Modified data can't be decrypted
+        let mut tampered = ct.clone();
+        tampered.data_ciphertext[0] ^= 1;
+        assert!(matches!(
+            hybrid_decrypt(&tampered, &sk, dk.clone(), snark_vk, chunk_bit_size),
+            Err(SaverError::InvalidHybridCiphertext)
+        ));
+
+        // Neither can the data with the key ciphertext of another hybrid ciphertext, even of the same key
+        let (other_ct, _, _) = hybrid_encrypt(
+            &mut rng,
+            &key_message,
+            &payload,
+            &ek,
+            &snark_pk,
+            chunk_bit_size,
+        )
+        .unwrap();
+        let mut swapped = ct.clone();
+        swapped.key_ciphertext = other_ct.key_ciphertext;
+        assert!(matches!(
+            hybrid_decrypt(&swapped, &sk, dk, snark_vk, chunk_bit_size),
+            Err(SaverError::InvalidHybridCiphertext)
+        ));
+    }
+}
@@ -0,0 +1,302 @@
+//! JSON representation of the public artifacts, the encryption key and the Groth16 verification key, for
+//! clients not written in Rust, like verifiers in TypeScript. Unlike the `Serialize` implementations of the
+//! keys, whose output depends on the serde format and encodes points as arrays of bytes, this is a fixed
+//! schema where each point is the standard base64 (with padding) of its compressed `CanonicalSerialize`
+//! encoding, as produced by the arkworks curve implementations.
+//!
+//! Encryption key, with one element in `X` and `Y` for each chunk and one more in `Z`:
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "curve_id": 123,
+//!   "X_0": "<G1>",
+//!   "X": ["<G1>", ...],
+//!   "Y": ["<G1>", ...],
+//!   "Z": ["<G2>", ...],
+//!   "P_1": "<G1>",
+//!   "P_2": "<G1>",
+//!   "gens_fingerprint": "<base64 of 32 bytes>" or null
+//! }
+//! ```
+//!
+//! Groth16 verification key, where `gamma_abc_g1` has the generators `g_i` used in encryption from index 1:
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "curve_id": 123,
+//!   "alpha_g1": "<G1>",
+//!   "beta_g2": "<G2>",
+//!   "gamma_g2": "<G2>",
+//!   "delta_g2": "<G2>",
+//!   "gamma_abc_g1": ["<G1>", ...]
+//! }
+//! ```
+//!
+//! `curve_id` is `utils::curve_id` of the pairing curve. Parsing rejects another `version` or `curve_id` and
+//! checks that each point is on the curve and in the correct subgroup.
+
+use crate::{error::SaverError, keygen::EncryptionKey, saver_groth16::VerifyingKey, utils};
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{string::String, vec::Vec};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+
+/// Version of the schema, increased on any incompatible change
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Encryption key as in the schema of the module docs
+#[allow(non_snake_case)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EncryptionKeyJson {
+    pub version: u32,
+    pub curve_id: u8,
+    pub X_0: String,
+    pub X: Vec<String>,
+    pub Y: Vec<String>,
+    pub Z: Vec<String>,
+    pub P_1: String,
+    pub P_2: String,
+    pub gens_fingerprint: Option<String>,
+}
+
+/// Groth16 verification key as in the schema of the module docs
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VerifyingKeyJson {
+    pub version: u32,
+    pub curve_id: u8,
+    pub alpha_g1: String,
+    pub beta_g2: String,
+    pub gamma_g2: String,
+    pub delta_g2: String,
+    pub gamma_abc_g1: Vec<String>,
+}
+
+impl<E: Pairing> EncryptionKey<E> {
+    /// Encode in the JSON schema of the `json` module
+    pub fn to_json(&self) -> String {
+        let json = EncryptionKeyJson {
+            version: JSON_SCHEMA_VERSION,
+            curve_id: utils::curve_id::<E>(),
+            X_0: point_to_base64(&self.X_0),
+            X: self.X.iter().map(point_to_base64).collect(),
+            Y: self.Y.iter().map(point_to_base64).collect(),
+            Z: self.Z.iter().map(point_to_base64).collect(),
+            P_1: point_to_base64(&self.P_1),
+            P_2: point_to_base64(&self.P_2),
+            gens_fingerprint: self.gens_fingerprint.map(|f| STANDARD.encode(f)),
+        };
+        serde_json::to_string(&json).unwrap()
+    }
+
+    /// Decode from the JSON schema of the `json` module. The key's structure is checked like
+    /// `Self::validate`
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        let json: EncryptionKeyJson =
+            serde_json::from_str(json).map_err(|_| SaverError::InvalidJson)?;
+        check_header::<E>(json.version, json.curve_id)?;
+        let gens_fingerprint = match json.gens_fingerprint {
+            Some(f) => Some(
+                <[u8; 32]>::try_from(base64_decode(&f)?)
+                    .map_err(|_| SaverError::InvalidByteEncoding)?,
+            ),
+            None => None,
+        };
+        let ek = Self {
+            X_0: point_from_base64(&json.X_0)?,
+            X: points_from_base64(&json.X)?,
+            Y: points_from_base64(&json.Y)?,
+            Z: points_from_base64(&json.Z)?,
+            P_1: point_from_base64(&json.P_1)?,
+            P_2: point_from_base64(&json.P_2)?,
+            gens_fingerprint,
+        };
+        ek.validate()?;
+        Ok(ek)
+    }
+}
+
+/// Encode the Groth16 verification key in the JSON schema of the `json` module
+pub fn verifying_key_to_json<E: Pairing>(vk: &VerifyingKey<E>) -> String {
+    let json = VerifyingKeyJson {
+        version: JSON_SCHEMA_VERSION,
+        curve_id: utils::curve_id::<E>(),
+        alpha_g1: point_to_base64(&vk.alpha_g1),
+        beta_g2: point_to_base64(&vk.beta_g2),
+        gamma_g2: point_to_base64(&vk.gamma_g2),
+        delta_g2: point_to_base64(&vk.delta_g2),
+        gamma_abc_g1: vk.gamma_abc_g1.iter().map(point_to_base64).collect(),
+    };
+    serde_json::to_string(&json).unwrap()
+}
+
+/// Decode the Groth16 verification key from the JSON schema of the `json` module
+pub fn verifying_key_from_json<E: Pairing>(json: &str) -> crate::Result<VerifyingKey<E>> {
+    let json: VerifyingKeyJson = serde_json::from_str(json).map_err(|_| SaverError::InvalidJson)?;
+    check_header::<E>(json.version, json.curve_id)?;
+    Ok(VerifyingKey {
+        alpha_g1: point_from_base64(&json.alpha_g1)?,
+        beta_g2: point_from_base64(&json.beta_g2)?,
+        gamma_g2: point_from_base64(&json.gamma_g2)?,
+        delta_g2: point_from_base64(&json.delta_g2)?,
+        gamma_abc_g1: points_from_base64(&json.gamma_abc_g1)?,
+    })
+}
+
+fn check_header<E: Pairing>(version: u32, curve_id: u8) -> crate::Result<()> {
+    if version != JSON_SCHEMA_VERSION {
+        return Err(SaverError::InvalidJson);
+    }
+    let expected = utils::curve_id::<E>();
+    if curve_id != expected {
+        return Err(SaverError::CurveMismatch {
+            expected,
+            got: curve_id,
+        });
+    }
+    Ok(())
+}
+
+fn point_to_base64<T: CanonicalSerialize>(point: &T) -> String {
+    let mut bytes = Vec::with_capacity(point.compressed_size());
+    point.serialize_compressed(&mut bytes).unwrap();
+    STANDARD.encode(&bytes)
+}
+
+fn point_from_base64<T: CanonicalDeserialize>(encoded: &str) -> crate::Result<T> {
+    let bytes = base64_decode(encoded)?;
+    let mut reader = &bytes[..];
+    let point = T::deserialize_compressed(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(SaverError::InvalidByteEncoding);
+    }
+    Ok(point)
+}
+
+fn points_from_base64<T: CanonicalDeserialize>(encoded: &[String]) -> crate::Result<Vec<T>> {
+    encoded.iter().map(|e| point_from_base64(e)).collect()
+}
+
+/// Decode standard base64 with padding, rejecting any other encoding of the same bytes
+fn base64_decode(encoded: &str) -> crate::Result<Vec<u8>> {
+    STANDARD
+        .decode(encoded)
+        .map_err(|_| SaverError::InvalidByteEncoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        encryption::Encryption,
+        saver_groth16::{
+            create_proof_from_assignment, get_gs_for_encryption, prepare_verifying_key,
+            verify_proof,
+        },
+        setup::{setup_for_groth16, EncryptionGens},
+    };
+    use ark_bls12_381::Bls12_381;
+    use ark_std::{
+        rand::{prelude::StdRng, SeedableRng},
+        UniformRand,
+    };
+
+    type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+    #[test]
+    fn base64() {
+        for (bytes, encoded) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"fooba", "Zm9vYmE="),
+            (b"foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(STANDARD.encode(bytes), encoded);
+            assert_eq!(base64_decode(encoded).unwrap(), bytes);
+        }
+        for invalid in ["Zg", "Zg=", "Zh==", "Z===", "Zg==Zg==", "Zm9v!A==", "Zm=v"] {
+            assert!(base64_decode(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn keys_json_round_trip() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, sk, ek, dk) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+
+        let ek_json = ek.to_json();
+        let vk_json = verifying_key_to_json(&snark_pk.pk.vk);
+        let decoded_ek = EncryptionKey::<Bls12_381>::from_json(&ek_json).unwrap();
+        let decoded_vk = verifying_key_from_json::<Bls12_381>(&vk_json).unwrap();
+        assert_eq!(decoded_ek, ek);
+        assert_eq!(decoded_vk, snark_pk.pk.vk);
+
+        // The schema's fields are present
+        let value: serde_json::Value = serde_json::from_str(&ek_json).unwrap();
+        assert_eq!(value["version"], JSON_SCHEMA_VERSION);
+        assert_eq!(value["X"].as_array().unwrap().len(), ek.X.len());
+        assert_eq!(
+            value["P_1"].as_str().unwrap(),
+            point_to_base64(&ek.P_1).as_str()
+        );
+
+        // The decoded keys still encrypt, prove and verify
+        let m = Fr::rand(&mut rng);
+        let (ct, r) = Encryption::encrypt(
+            &mut rng,
+            &m,
+            &decoded_ek,
+            get_gs_for_encryption(&decoded_vk),
+            chunk_bit_size,
+        )
+        .unwrap();
+        let decomposed = utils::decompose(&m, chunk_bit_size)
+            .unwrap()
+            .into_iter()
+            .map(Fr::from)
+            .collect::<Vec<_>>();
+        let proof = create_proof_from_assignment(&decomposed, &r, &snark_pk, &decoded_ek, &mut rng)
+            .unwrap();
+        let pvk = prepare_verifying_key(&decoded_vk);
+        verify_proof(&pvk, &proof, &ct).unwrap();
+        ct.verify_commitment(decoded_ek.clone(), gens.clone())
+            .unwrap();
+        let (decrypted, _) = ct
+            .decrypt_given_groth16_vk(&sk, dk, &decoded_vk, chunk_bit_size)
+            .unwrap();
+        assert_eq!(decrypted, m);
+
+        // Another version, unknown fields and bad points are rejected
+        let mut value: serde_json::Value = serde_json::from_str(&ek_json).unwrap();
+        value["version"] = (JSON_SCHEMA_VERSION + 1).into();
+        assert!(matches!(
+            EncryptionKey::<Bls12_381>::from_json(&value.to_string()),
+            Err(SaverError::InvalidJson)
+        ));
+        let mut value: serde_json::Value = serde_json::from_str(&ek_json).unwrap();
+        value["extra"] = 1.into();
+        assert!(EncryptionKey::<Bls12_381>::from_json(&value.to_string()).is_err());
+        let mut g1 = vec![];
+        ek.X_0.serialize_compressed(&mut g1).unwrap();
+        for bad_point in [point_to_base64(&ek.Z[0]), STANDARD.encode(&g1[..47])] {
+            let mut value: serde_json::Value = serde_json::from_str(&ek_json).unwrap();
+            value["X_0"] = bad_point.into();
+            assert!(EncryptionKey::<Bls12_381>::from_json(&value.to_string()).is_err());
+        }
+        let mut value: serde_json::Value = serde_json::from_str(&vk_json).unwrap();
+        value["curve_id"] = (utils::curve_id::<Bls12_381>().wrapping_add(1)).into();
+        assert!(matches!(
+            verifying_key_from_json::<Bls12_381>(&value.to_string()),
+            Err(SaverError::CurveMismatch { .. })
+        ));
+    }
+}
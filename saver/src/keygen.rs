@@ -2,9 +2,9 @@ use ark_ec::{
     pairing::{Pairing, PairingOutput},
     AffineRepr, CurveGroup, Group,
 };
-use ark_ff::{One, PrimeField};
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{cfg_iter, rand::RngCore, vec::Vec, UniformRand};
+use ark_ff::{Field, One, PrimeField, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::{cfg_into_iter, cfg_iter, fmt, ops::Neg, rand::RngCore, vec::Vec};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use zeroize::{Zeroize, ZeroizeOnDrop};
@@ -12,7 +12,12 @@ use zeroize::{Zeroize, ZeroizeOnDrop};
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
-use crate::{error::SaverError, saver_groth16, setup::EncryptionGens, utils::chunks_count};
+use crate::{
+    error::SaverError,
+    saver_groth16,
+    setup::EncryptionGens,
+    utils::{self, chunks_count},
+};
 use dock_crypto_utils::{msm::multiply_field_elems_with_same_group_elem, serde_utils::*};
 
 /// Used to decrypt
@@ -31,11 +36,55 @@ use dock_crypto_utils::{msm::multiply_field_elems_with_same_group_elem, serde_ut
 )]
 pub struct SecretKey<F: PrimeField>(#[serde_as(as = "ArkObjectBytes")] pub F);
 
+impl<F: PrimeField> SecretKey<F> {
+    /// `H * rho`, which is `V_0` of the decryption key created with this secret key and `gens`. A decryptor
+    /// can publish it so that others can check that a decryption key belongs to it by comparing with `V_0`
+    pub fn public_component<E: Pairing<ScalarField = F>>(
+        &self,
+        gens: &EncryptionGens<E>,
+    ) -> E::G2Affine {
+        gens.H.mul_bigint(self.0.into_bigint()).into_affine()
+    }
+}
+
+/// Secrets from which `keygen_from_secrets` creates the keys. `rho` is the secret key and `s`, `t` and `v`
+/// are the secrets used to create the encryption and decryption key. `s` and `v` must have `chunks_count`
+/// elements and `t` must have one more. Anyone having these can decrypt so keep them as safe as `rho`.
+#[derive(Clone, PartialEq, Eq, Debug, Zeroize, ZeroizeOnDrop)]
+pub struct KeySecrets<F: PrimeField> {
+    pub rho: F,
+    pub s: Vec<F>,
+    pub t: Vec<F>,
+    pub v: Vec<F>,
+}
+
+/// Secret key, encryption key and decryption key created by `keygen`
+pub type Keys<E> = (
+    SecretKey<<E as Pairing>::ScalarField>,
+    EncryptionKey<E>,
+    DecryptionKey<E>,
+);
+
+/// Keys created by `keygen_with_internals` along with the secrets used to create them
+#[cfg(feature = "keygen-internals")]
+pub type KeysWithInternals<E> = (
+    SecretKey<<E as Pairing>::ScalarField>,
+    EncryptionKey<E>,
+    DecryptionKey<E>,
+    KeySecrets<<E as Pairing>::ScalarField>,
+);
+
 /// Used to encrypt, rerandomize and verify the encryption. Called "PK" in the paper.
+///
+/// The vectors are ordered by chunk index, i.e. for the `i`-th chunk of the decomposed message (0-based,
+/// big-endian), `X[i]`, `Y[i]` and `Z[i+1]` are used and `Z[0]` corresponds to `X_0`. The same holds for
+/// `V_1[i]` and `V_2[i]` of the `DecryptionKey`. Encryption, decryption and verification rely on this
+/// so any code creating or transforming these keys must preserve it.
+///
+/// A serialized key without the trailing `gens_fingerprint`, as serialized by 0.12, is deserialized as a key
+/// without a fingerprint. See "Upgrading from 0.12" in the crate docs.
 #[serde_as]
-#[derive(
-    Clone, PartialEq, Eq, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize,
-)]
+#[derive(Clone, PartialEq, Eq, Debug, CanonicalSerialize, Serialize, Deserialize)]
 pub struct EncryptionKey<E: Pairing> {
     /// `G * delta`
     #[serde_as(as = "ArkObjectBytes")]
@@ -55,6 +104,16 @@ pub struct EncryptionKey<E: Pairing> {
     /// `(G*-gamma) * (1 + s_0 + s_1 + .. s_{n-1})`
     #[serde_as(as = "ArkObjectBytes")]
     pub P_2: E::G1Affine,
+    /// Fingerprint of the `EncryptionGens` this key was created with. When present, it's checked against the
+    /// generators passed during verification. Only a hash, so the generators must still be passed.
+    pub gens_fingerprint: Option<[u8; 32]>,
+}
+
+/// Prints the key's fingerprint, see `utils::fingerprint`
+impl<E: Pairing> fmt::Display for EncryptionKey<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        utils::write_fingerprint(self, f)
+    }
 }
 
 /// Same as EncryptionKey but the elements in G2 are prepared for pairing making pairing faster
@@ -72,9 +131,11 @@ pub struct PreparedEncryptionKey<E: Pairing> {
     pub P_1: E::G1Affine,
     /// `(G*-gamma) * (1 + s_0 + s_1 + .. s_{n-1})`
     pub P_2: E::G1Affine,
+    pub gens_fingerprint: Option<[u8; 32]>,
 }
 
-/// Used to decrypt and verify decryption. Called "VK" in the paper.
+/// Used to decrypt and verify decryption. Called "VK" in the paper. `V_1` and `V_2` are ordered by chunk
+/// index like the vectors of `EncryptionKey`.
 #[serde_as]
 #[derive(
     Clone, PartialEq, Eq, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize,
@@ -115,16 +176,63 @@ macro_rules! impl_enc_key_funcs {
             Ok(n as u8)
         }
 
+        #[must_use = "invalid parameters are only reported through the returned value"]
         pub fn validate(&self) -> crate::Result<()> {
             self.supported_chunks_count()?;
             Ok(())
         }
 
+        /// Check that the given fingerprint of `EncryptionGens` matches the one stored in this key. Passes
+        /// if the key does not store a fingerprint.
+        pub fn check_gens_fingerprint(&self, fingerprint: &[u8; 32]) -> crate::Result<()> {
+            match &self.gens_fingerprint {
+                Some(f) if f != fingerprint => Err(SaverError::MismatchedEncryptionGens),
+                _ => Ok(()),
+            }
+        }
+
+        /// Chunk bit size the key was created for. It isn't stored in the key as its number of chunks
+        /// determines it, so it can't disagree with the key. Returns an error if no supported chunk bit size
+        /// gives that number of chunks.
+        pub fn chunk_bit_size(&self) -> crate::Result<u8> {
+            let n = self.supported_chunks_count()?;
+            utils::chunk_bit_size_for_chunks_count::<E::ScalarField>(n as usize)
+                .ok_or(SaverError::InvalidDecomposition)
+        }
+
+        /// Whether messages decomposed in chunks of `chunk_bit_size` bits can be encrypted with this key, i.e.
+        /// the chunk bit size is supported and the key is well-formed with a chunk for each of the
+        /// `chunks_count` chunks of the decomposition. Since every message is decomposed in the same number of
+        /// chunks, a key with more chunks can't be used either. Unlike `Self::validate`, this checks the key
+        /// against the caller's requirement and not only its own structure.
+        pub fn can_encrypt(&self, chunk_bit_size: u8) -> bool {
+            if utils::check_chunk_bit_size(chunk_bit_size).is_err() {
+                return false;
+            }
+            match self.supported_chunks_count() {
+                Ok(n) => n == chunks_count::<E::ScalarField>(chunk_bit_size),
+                Err(_) => false,
+            }
+        }
+
         pub fn commitment_key(&self) -> Vec<E::G1Affine> {
             let mut ck = self.Y.clone();
             ck.push(self.P_1.clone());
             ck
         }
+
+        /// Largest message that can be encrypted and decrypted with this key when using chunks of
+        /// `chunk_bit_size` bits. With `n` chunks supported by the key, this is `2^{n*chunk_bit_size} - 1`
+        /// bounded by the largest field element, i.e. a key created for the given chunk bit size with
+        /// `keygen` can decrypt any field element. Decrypting a chunk takes up to `2^chunk_bit_size - 1` steps.
+        pub fn max_decryptable_value(&self, chunk_bit_size: u8) -> crate::Result<E::ScalarField> {
+            let bits = self.supported_chunks_count()? as u64 * chunk_bit_size as u64;
+            if bits >= E::ScalarField::MODULUS_BIT_SIZE as u64 {
+                Ok(-E::ScalarField::one())
+            } else {
+                Ok(E::ScalarField::from(2u64).pow([bits]) - E::ScalarField::one())
+            }
+        }
     };
 }
 
@@ -138,11 +246,34 @@ macro_rules! impl_dec_key_funcs {
             Ok(n as u8)
         }
 
+        #[must_use = "invalid parameters are only reported through the returned value"]
         pub fn validate(&self) -> crate::Result<()> {
             self.supported_chunks_count()?;
             Ok(())
         }
 
+        /// Keep only the last `n` chunks, i.e. the least significant ones as the decomposition is big-endian.
+        /// Decrypting the last `n` chunks of a ciphertext with this key (`Ciphertext::decrypt_last_chunks`)
+        /// gives the message when it fits in them, and needs `n` discrete logs instead of one for every chunk.
+        /// There is no counterpart for the encryption key as its `P_1` and `P_2` depend on the secrets of all
+        /// chunks and can't be computed for fewer chunks without them. So encryption, commitment and proof
+        /// verification still use the full keys, and fewer chunks for these need a new keygen and SNARK setup.
+        pub fn truncate_to_chunks(&self, n: u8) -> crate::Result<Self> {
+            let count = self.supported_chunks_count()?;
+            if n > count {
+                return Err(SaverError::IncompatibleDecryptionKey(
+                    n as usize,
+                    count as usize,
+                ));
+            }
+            let skip = (count - n) as usize;
+            Ok(Self {
+                V_0: self.V_0.clone(),
+                V_1: self.V_1[skip..].to_vec(),
+                V_2: self.V_2[skip..].to_vec(),
+            })
+        }
+
         pub fn pairing_powers_given_groth16_vk(
             &self,
             chunk_bit_size: u8,
@@ -156,6 +287,93 @@ macro_rules! impl_dec_key_funcs {
 
 impl<E: Pairing> EncryptionKey<E> {
     impl_enc_key_funcs!();
+
+    /// Deserialize from compressed bytes while checking that each point is in the correct subgroup
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        Self::deserialize_compressed(bytes)
+    }
+
+    /// Same as `Self::from_bytes` but skips the subgroup checks which makes it considerably faster.
+    /// This is unsafe for untrusted input and should only be used for keys from a trusted source, like
+    /// the ones generated by the caller itself.
+    pub fn from_bytes_unchecked(bytes: &[u8]) -> Result<Self, SerializationError> {
+        Self::deserialize_compressed_unchecked(bytes)
+    }
+
+    /// Compare the generators this key shares with the Groth16 verification key `snark_vk` and the
+    /// generators `gens`, returning which of them match. Meant for diagnosing why proofs of encryption don't
+    /// verify; unlike `Self::can_encrypt`, which only checks the key's size, this tells which generator is
+    /// off. Takes a pairing per chunk so it should not be used on every verification.
+    pub fn merge_generators_check(
+        &self,
+        snark_vk: &ark_groth16::VerifyingKey<E>,
+        gens: &EncryptionGens<E>,
+    ) -> GeneratorsReport {
+        let g_i = saver_groth16::get_gs_for_encryption(snark_vk);
+        let chunks_count =
+            self.X.len() == g_i.len() && self.Y.len() == g_i.len() && self.Z.len() == g_i.len() + 1;
+        // X_0 is G * delta: e(X_0, H) = e(G, H * delta)
+        let delta = E::multi_pairing(
+            [self.X_0, gens.G.into_group().neg().into_affine()],
+            [gens.H, snark_vk.delta_g2],
+        )
+        .is_zero();
+        // Y_i is g_i * t_{i+1} and Z_{i+1} is H * t_{i+1}: e(Y_i, H) = e(g_i, Z_{i+1})
+        let g_i = cfg_into_iter!(0..self.Y.len())
+            .map(|i| match (g_i.get(i), self.Z.get(i + 1)) {
+                (Some(g), Some(z)) => E::multi_pairing(
+                    [self.Y[i], g.into_group().neg().into_affine()],
+                    [gens.H, *z],
+                )
+                .is_zero(),
+                _ => false,
+            })
+            .collect::<Vec<_>>();
+        // P_2 is -gamma*G * (1 + sum(s_i)) and X_0 + sum(X_i) is delta*G * (1 + sum(s_i)), so
+        // e(P_2, H * delta) * e(X_0 + sum(X_i), H * gamma) = 1
+        let x_sum = self.X.iter().fold(self.X_0.into_group(), |acc, x| acc + x);
+        let gamma = E::multi_pairing(
+            [self.P_2, x_sum.into_affine()],
+            [snark_vk.delta_g2, snark_vk.gamma_g2],
+        )
+        .is_zero();
+        let gens_fingerprint = self.check_gens_fingerprint(&gens.fingerprint()).is_ok();
+        GeneratorsReport {
+            chunks_count,
+            delta,
+            g_i,
+            gamma,
+            gens_fingerprint,
+        }
+    }
+}
+
+/// Result of `EncryptionKey::merge_generators_check`. Each field is true if the corresponding part of the
+/// encryption key matches the verification key and generators it was checked against.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GeneratorsReport {
+    /// The key has as many chunks as the verification key has generators `g_i`
+    pub chunks_count: bool,
+    /// `X_0` is the verification key's `delta` in G1
+    pub delta: bool,
+    /// For each chunk, the key's `Y_i` was created from the verification key's `g_i`. False for chunks that
+    /// the verification key doesn't have.
+    pub g_i: Vec<bool>,
+    /// `P_2` was created from the verification key's `gamma` in G1 and is consistent with `X_0` and `X`
+    pub gamma: bool,
+    /// The fingerprint of the generators stored in the key, if any, is the fingerprint of the generators
+    pub gens_fingerprint: bool,
+}
+
+impl GeneratorsReport {
+    /// Whether every generator matched
+    pub fn all_match(&self) -> bool {
+        self.chunks_count
+            && self.delta
+            && self.g_i.iter().all(|m| *m)
+            && self.gamma
+            && self.gens_fingerprint
+    }
 }
 
 impl<E: Pairing> From<EncryptionKey<E>> for PreparedEncryptionKey<E> {
@@ -169,6 +387,7 @@ impl<E: Pairing> From<EncryptionKey<E>> for PreparedEncryptionKey<E> {
                 .collect::<Vec<_>>(),
             P_1: ek.P_1,
             P_2: ek.P_2,
+            gens_fingerprint: ek.gens_fingerprint,
         }
     }
 }
@@ -219,27 +438,46 @@ impl<E: Pairing> PreparedDecryptionKey<E> {
     ) -> crate::Result<Vec<Vec<PairingOutput<E>>>> {
         let n = self.supported_chunks_count()? as usize;
         let chunk_max_val = (1 << chunk_bit_size) - 1;
-        let mut powers = Vec::<Vec<PairingOutput<E>>>::with_capacity(n);
-        for i in 0..n {
-            // Powers of `g_i_v_i` will be created
-            let g_i_v_i = E::pairing(g_i[i], self.V_2[i].clone());
-
-            // `powers_i` will have `chunk_max_val` powers of `g_i_v_i` like [g_i_v_i, g_i_v_i^2, g_i_v_i^3, ...]
-            let mut powers_i = Vec::<PairingOutput<E>>::with_capacity(chunk_max_val as usize);
-            let mut cur = g_i_v_i;
-            powers_i.push(cur);
-            for _ in 1..chunk_max_val {
-                cur += g_i_v_i;
+        // The powers for each chunk are independent of the other chunks
+        let powers = cfg_into_iter!(0..n)
+            .map(|i| {
+                // Powers of `g_i_v_i` will be created
+                let g_i_v_i = E::pairing(g_i[i], self.V_2[i].clone());
+
+                // `powers_i` will have `chunk_max_val` powers of `g_i_v_i` like [g_i_v_i, g_i_v_i^2, g_i_v_i^3, ...]
+                let mut powers_i = Vec::<PairingOutput<E>>::with_capacity(chunk_max_val as usize);
+                let mut cur = g_i_v_i;
                 powers_i.push(cur);
-            }
-            powers.push(powers_i);
-        }
+                for _ in 1..chunk_max_val {
+                    cur += g_i_v_i;
+                    powers_i.push(cur);
+                }
+                powers_i
+            })
+            .collect::<Vec<_>>();
         Ok(powers)
     }
 }
 
 /// Generate keys for encryption and decryption. The parameters `g_i`, `delta_g` and `gamma_g` are
 /// shared with the SNARK SRS.
+///
+/// They don't have to come from a new SRS as with `setup::setup_for_groth16`. Taking them from an existing
+/// SNARK proving key (`saver_groth16::get_gs_for_encryption`, `delta_g1` and `gamma_g1`) gives keys for
+/// several decryptors, or for several chunk sizes, that share its bases. Only the first
+/// `chunks_count(chunk_bit_size)` elements of `g_i` are used, so the bases of the smallest chunk size can be
+/// used for larger ones, and `SaverError::InsufficientGenerators` is returned if there are fewer. A SNARK proof
+/// can be created only for keys whose bases match the SNARK's verifying key.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        skip_all,
+        fields(
+            chunks_count = ?utils::valid_chunks_count::<E::ScalarField>(chunk_bit_size),
+            chunk_bit_size = chunk_bit_size
+        )
+    )
+)]
 pub fn keygen<R: RngCore, E: Pairing>(
     rng: &mut R,
     chunk_bit_size: u8,
@@ -247,35 +485,87 @@ pub fn keygen<R: RngCore, E: Pairing>(
     g_i: &[E::G1Affine],
     delta_g: &E::G1Affine,
     gamma_g: &E::G1Affine,
-) -> crate::Result<(
-    SecretKey<E::ScalarField>,
-    EncryptionKey<E>,
-    DecryptionKey<E>,
-)> {
-    let n = chunks_count::<E::ScalarField>(chunk_bit_size) as usize;
+) -> crate::Result<Keys<E>> {
+    let n = keygen_chunks_count::<E>(chunk_bit_size, g_i)?;
+    let secrets = sample_secrets::<R, E::ScalarField>(rng, n);
+    keygen_from_secrets(&secrets, chunk_bit_size, gens, g_i, delta_g, gamma_g)
+}
+
+/// Same as `keygen` but also returns the sampled secrets. **Dangerous**: `rho` is the secret key and the
+/// others allow computing discrete logs of key elements so anyone having them can decrypt or create keys that
+/// pass verification. Only meant for test vectors and checking the construction.
+#[cfg(feature = "keygen-internals")]
+pub fn keygen_with_internals<R: RngCore, E: Pairing>(
+    rng: &mut R,
+    chunk_bit_size: u8,
+    gens: &EncryptionGens<E>,
+    g_i: &[E::G1Affine],
+    delta_g: &E::G1Affine,
+    gamma_g: &E::G1Affine,
+) -> crate::Result<KeysWithInternals<E>> {
+    let n = keygen_chunks_count::<E>(chunk_bit_size, g_i)?;
+    let secrets = sample_secrets::<R, E::ScalarField>(rng, n);
+    let (sk, ek, dk) = keygen_from_secrets(&secrets, chunk_bit_size, gens, g_i, delta_g, gamma_g)?;
+    Ok((sk, ek, dk, secrets))
+}
+
+/// Number of chunks for `chunk_bit_size`, checking that it's supported and that `g_i` has a generator for
+/// each chunk
+fn keygen_chunks_count<E: Pairing>(
+    chunk_bit_size: u8,
+    g_i: &[E::G1Affine],
+) -> crate::Result<usize> {
+    let n = utils::valid_chunks_count::<E::ScalarField>(chunk_bit_size)
+        .ok_or(SaverError::UnexpectedBase(chunk_bit_size))? as usize;
     if n > g_i.len() {
-        return Err(SaverError::VectorShorterThanExpected(g_i.len(), n));
+        return Err(SaverError::InsufficientGenerators {
+            have: g_i.len(),
+            need: n,
+            source: "g_i",
+        });
     }
+    Ok(n)
+}
 
-    let rho = E::ScalarField::rand(rng);
-    let s = (0..n)
-        .map(|_| E::ScalarField::rand(rng))
-        .collect::<Vec<_>>();
-    let t = (0..=n)
-        .map(|_| E::ScalarField::rand(rng))
-        .collect::<Vec<_>>();
-    let v = (0..n)
-        .map(|_| E::ScalarField::rand(rng))
-        .collect::<Vec<_>>();
+/// Sample `rho`, `s`, `t` and `v` for `n` chunks
+fn sample_secrets<R: RngCore, F: PrimeField>(rng: &mut R, n: usize) -> KeySecrets<F> {
+    let rho = F::rand(rng);
+    let s = (0..n).map(|_| F::rand(rng)).collect::<Vec<_>>();
+    let t = (0..=n).map(|_| F::rand(rng)).collect::<Vec<_>>();
+    let v = (0..n).map(|_| F::rand(rng)).collect::<Vec<_>>();
+    KeySecrets { rho, s, t, v }
+}
+
+/// Same as `keygen` but deterministic as the secrets are given rather than sampled. Useful for recreating
+/// keys from backed up secrets.
+pub fn keygen_from_secrets<E: Pairing>(
+    secrets: &KeySecrets<E::ScalarField>,
+    chunk_bit_size: u8,
+    gens: &EncryptionGens<E>,
+    g_i: &[E::G1Affine],
+    delta_g: &E::G1Affine,
+    gamma_g: &E::G1Affine,
+) -> crate::Result<Keys<E>> {
+    let (rho, s, t, v) = (secrets.rho, &secrets.s, &secrets.t, &secrets.v);
+    let n = keygen_chunks_count::<E>(chunk_bit_size, g_i)?;
+    if s.len() != n {
+        return Err(SaverError::UnexpectedSecretsCount(s.len(), n));
+    }
+    if t.len() != n + 1 {
+        return Err(SaverError::UnexpectedSecretsCount(t.len(), n + 1));
+    }
+    if v.len() != n {
+        return Err(SaverError::UnexpectedSecretsCount(v.len(), n));
+    }
 
     let delta_g_proj = delta_g.into_group();
     let t_repr = cfg_iter!(t).map(|t| t.into_bigint()).collect::<Vec<_>>();
 
-    let X = multiply_field_elems_with_same_group_elem(delta_g_proj, &s);
-    let Y = (0..n)
+    let X = multiply_field_elems_with_same_group_elem(delta_g_proj, s);
+    let Y = cfg_into_iter!(0..n, utils::PARALLEL_MIN_CHUNKS)
         .map(|i| g_i[i].mul_bigint(t_repr[i + 1]))
         .collect::<Vec<_>>();
-    let Z = multiply_field_elems_with_same_group_elem(gens.H.into_group(), &t);
+    let Z = multiply_field_elems_with_same_group_elem(gens.H.into_group(), t);
 
     // P_1 = G*delta * (t_0 + \sum_{j in 0..n}(s_j * t_{j+1}))
     let P_1 = delta_g_proj
@@ -290,14 +580,15 @@ pub fn keygen<R: RngCore, E: Pairing>(
         P_2: gamma_g
             .mul_bigint((E::ScalarField::one() + s.iter().sum::<E::ScalarField>()).into_bigint())
             .into_affine(),
+        gens_fingerprint: Some(gens.fingerprint()),
     };
     let V_0 = gens.H.mul_bigint(rho.into_bigint());
-    let V_2 = multiply_field_elems_with_same_group_elem(V_0, &v);
+    let V_2 = multiply_field_elems_with_same_group_elem(V_0, v);
     let V_1 = multiply_field_elems_with_same_group_elem(
         gens.H.into_group(),
-        &s.into_iter()
-            .zip(v.into_iter())
-            .map(|(s_i, v_i)| s_i * v_i)
+        &s.iter()
+            .zip(v.iter())
+            .map(|(s_i, v_i)| *s_i * v_i)
             .collect::<Vec<_>>(),
     );
     let dk = DecryptionKey {
@@ -308,15 +599,267 @@ pub fn keygen<R: RngCore, E: Pairing>(
     Ok((SecretKey(rho), ek, dk))
 }
 
+/// Rotate the secret and decryption keys by `delta`, returning `rho * delta` as the new secret key and the
+/// decryption key with `V_0` and `V_2` multiplied by `delta`. This is possible because a chunk is decrypted
+/// with `e(c_i, V_2_i) * e(-X_r * rho, V_1_i)` which stays the same when both `rho` and `V_2_i` are scaled.
+///
+/// The encryption key, SNARK keys and existing ciphertexts are unchanged and the new keys decrypt (and
+/// verify decryption of) every ciphertext created with the encryption key. However, the old keys also keep
+/// decrypting all of them, including those created after the rotation, as they are still valid keys for
+/// the same encryption key. So rotation does not revoke a leaked key; that needs new keys from `keygen` and
+/// re-encrypting the ciphertexts with `Encryption::migrate_ciphertext`.
+pub fn rotate_decryption_key<E: Pairing>(
+    old_sk: &SecretKey<E::ScalarField>,
+    old_dk: &DecryptionKey<E>,
+    delta: &E::ScalarField,
+) -> crate::Result<(SecretKey<E::ScalarField>, DecryptionKey<E>)> {
+    if delta.is_zero() {
+        return Err(SaverError::ZeroRotationDelta);
+    }
+    let delta_repr = delta.into_bigint();
+    let V_2 = cfg_iter!(old_dk.V_2)
+        .map(|v| v.mul_bigint(delta_repr))
+        .collect::<Vec<_>>();
+    let dk = DecryptionKey {
+        V_0: old_dk.V_0.mul_bigint(delta_repr).into_affine(),
+        V_1: old_dk.V_1.clone(),
+        V_2: E::G2::normalize_batch(&V_2),
+    };
+    Ok((SecretKey(old_sk.0 * delta), dk))
+}
+
+/// Secret, encryption and decryption keys created together, with the chunk bit size they were created for,
+/// so that they can be stored and loaded as one. Deserializing with validation (eg. `deserialize_compressed`)
+/// checks that the keys are consistent with each other like `Self::validate`.
+#[derive(Clone, PartialEq, Eq, Debug, CanonicalSerialize)]
+pub struct KeygenBundle<E: Pairing> {
+    pub sk: SecretKey<E::ScalarField>,
+    pub ek: EncryptionKey<E>,
+    pub dk: DecryptionKey<E>,
+    pub chunk_bit_size: u8,
+}
+
+impl<E: Pairing> KeygenBundle<E> {
+    /// Bundle the keys returned by `keygen`. Returns an error if they aren't consistent with each other.
+    pub fn new(
+        sk: SecretKey<E::ScalarField>,
+        ek: EncryptionKey<E>,
+        dk: DecryptionKey<E>,
+        chunk_bit_size: u8,
+    ) -> crate::Result<Self> {
+        let bundle = Self {
+            sk,
+            ek,
+            dk,
+            chunk_bit_size,
+        };
+        bundle.validate()?;
+        Ok(bundle)
+    }
+
+    /// Check that the keys are for the chunk bit size and that the decryption key was created with the
+    /// secret key for the encryption key. Returns `SaverError::InconsistentSetup` if not. The parts of the
+    /// keys that depend on the generators or the SNARK SRS are not checked, use `SaverSetup::validate` for that.
+    #[must_use = "invalid parameters are only reported through the returned value"]
+    pub fn validate(&self) -> crate::Result<()> {
+        utils::check_chunk_bit_size(self.chunk_bit_size)?;
+        let n = chunks_count::<E::ScalarField>(self.chunk_bit_size) as usize;
+        let ek_n = self.ek.supported_chunks_count()? as usize;
+        if ek_n != n {
+            return Err(SaverError::IncompatibleEncryptionKey(ek_n, n));
+        }
+        let dk_n = self.dk.supported_chunks_count()? as usize;
+        if dk_n != n {
+            return Err(SaverError::IncompatibleDecryptionKey(dk_n, n));
+        }
+        let minus_rho_x_0 = self
+            .ek
+            .X_0
+            .mul_bigint(self.sk.0.into_bigint())
+            .neg()
+            .into_affine();
+        for i in 0..n {
+            // e(X_i, V_2_i) = e(X_0 * rho, V_1_i) as X_i = G*delta*s_i, V_1_i = H*s_i*v_i and V_2_i = H*rho*v_i
+            if !E::multi_pairing(
+                [self.ek.X[i], minus_rho_x_0],
+                [self.dk.V_2[i], self.dk.V_1[i]],
+            )
+            .is_zero()
+            {
+                return Err(SaverError::InconsistentSetup);
+            }
+        }
+        Ok(())
+    }
+}
+
+mod serialization {
+    use super::*;
+    use ark_serialize::{Compress, Read, Valid, Validate};
+
+    impl<E: Pairing> Valid for EncryptionKey<E> {
+        fn check(&self) -> Result<(), SerializationError> {
+            self.X_0.check()?;
+            self.X.check()?;
+            self.Y.check()?;
+            self.Z.check()?;
+            self.P_1.check()?;
+            self.P_2.check()
+        }
+    }
+
+    impl<E: Pairing> CanonicalDeserialize for EncryptionKey<E> {
+        fn deserialize_with_mode<R: Read>(
+            mut reader: R,
+            compress: Compress,
+            validate: Validate,
+        ) -> Result<Self, SerializationError> {
+            let X_0 = CanonicalDeserialize::deserialize_with_mode(&mut reader, compress, validate)?;
+            let X = CanonicalDeserialize::deserialize_with_mode(&mut reader, compress, validate)?;
+            let Y = CanonicalDeserialize::deserialize_with_mode(&mut reader, compress, validate)?;
+            let Z = CanonicalDeserialize::deserialize_with_mode(&mut reader, compress, validate)?;
+            let P_1 = CanonicalDeserialize::deserialize_with_mode(&mut reader, compress, validate)?;
+            let P_2 = CanonicalDeserialize::deserialize_with_mode(&mut reader, compress, validate)?;
+            // `gens_fingerprint` is written as an `Option`, i.e. a flag byte followed by the fingerprint when
+            // present. Keys serialized by 0.12 end before the flag so its absence is read as `None`. The
+            // fingerprint is read with `read_exact` as deserializing `[u8; 32]` panics on truncated input.
+            let mut flag = [0u8; 1];
+            let gens_fingerprint = match reader.read(&mut flag)? {
+                0 => None,
+                _ => match flag[0] {
+                    0 => None,
+                    1 => {
+                        let mut fingerprint = [0u8; 32];
+                        reader.read_exact(&mut fingerprint)?;
+                        Some(fingerprint)
+                    }
+                    _ => return Err(SerializationError::InvalidData),
+                },
+            };
+            Ok(Self {
+                X_0,
+                X,
+                Y,
+                Z,
+                P_1,
+                P_2,
+                gens_fingerprint,
+            })
+        }
+    }
+
+    impl<E: Pairing> Valid for KeygenBundle<E> {
+        fn check(&self) -> Result<(), SerializationError> {
+            self.sk.check()?;
+            self.ek.check()?;
+            self.dk.check()?;
+            self.validate().map_err(|_| SerializationError::InvalidData)
+        }
+    }
+
+    impl<E: Pairing> CanonicalDeserialize for KeygenBundle<E> {
+        fn deserialize_with_mode<R: Read>(
+            mut reader: R,
+            compress: Compress,
+            validate: Validate,
+        ) -> Result<Self, SerializationError> {
+            let bundle = Self {
+                sk: CanonicalDeserialize::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    Validate::No,
+                )?,
+                ek: CanonicalDeserialize::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    Validate::No,
+                )?,
+                dk: CanonicalDeserialize::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    Validate::No,
+                )?,
+                chunk_bit_size: CanonicalDeserialize::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    Validate::No,
+                )?,
+            };
+            if let Validate::Yes = validate {
+                bundle.check()?;
+            }
+            Ok(bundle)
+        }
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
 
-    use crate::test_serialization;
+    use crate::encryption::{tests::enc_setup, Ciphertext, Encryption};
     use ark_bls12_381::Bls12_381;
-    use ark_std::rand::{prelude::StdRng, SeedableRng};
+    use ark_std::{
+        rand::{prelude::StdRng, SeedableRng},
+        UniformRand,
+    };
     type Fr = <Bls12_381 as Pairing>::ScalarField;
 
+    #[test]
+    fn keygen_from_given_secrets() {
+        fn check(chunk_bit_size: u8) {
+            let n = chunks_count::<Fr>(chunk_bit_size) as usize;
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+            let g_i = (0..n)
+                .map(|_| <Bls12_381 as Pairing>::G1Affine::rand(&mut rng))
+                .collect::<Vec<_>>();
+            let delta = <Bls12_381 as Pairing>::G1Affine::rand(&mut rng);
+            let gamma = <Bls12_381 as Pairing>::G1Affine::rand(&mut rng);
+
+            let keys = keygen(
+                &mut StdRng::seed_from_u64(1u64),
+                chunk_bit_size,
+                &gens,
+                &g_i,
+                &delta,
+                &gamma,
+            )
+            .unwrap();
+
+            // Sample the secrets in the same order as `keygen`
+            let mut secrets_rng = StdRng::seed_from_u64(1u64);
+            let rho = Fr::rand(&mut secrets_rng);
+            let s = (0..n)
+                .map(|_| Fr::rand(&mut secrets_rng))
+                .collect::<Vec<_>>();
+            let t = (0..=n)
+                .map(|_| Fr::rand(&mut secrets_rng))
+                .collect::<Vec<_>>();
+            let v = (0..n)
+                .map(|_| Fr::rand(&mut secrets_rng))
+                .collect::<Vec<_>>();
+
+            let secrets = KeySecrets { rho, s, t, v };
+            let keys_ =
+                keygen_from_secrets(&secrets, chunk_bit_size, &gens, &g_i, &delta, &gamma).unwrap();
+            assert_eq!(keys, keys_);
+
+            let mut fewer_s = secrets.clone();
+            fewer_s.s.pop();
+            assert!(
+                keygen_from_secrets(&fewer_s, chunk_bit_size, &gens, &g_i, &delta, &gamma).is_err()
+            );
+            let mut fewer_t = secrets.clone();
+            fewer_t.t.pop();
+            assert!(
+                keygen_from_secrets(&fewer_t, chunk_bit_size, &gens, &g_i, &delta, &gamma).is_err()
+            );
+        }
+        check(4);
+        check(8);
+    }
+
     #[test]
     fn keygen_works() {
         fn check_keygen(chunk_bit_size: u8) {
@@ -378,4 +921,537 @@ pub(crate) mod tests {
         check_keygen(8);
         check_keygen(16);
     }
+
+    #[test]
+    fn secret_key_public_component() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let chunk_count = chunks_count::<Fr>(chunk_bit_size) as usize;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let g_i = (0..chunk_count)
+            .map(|_| <Bls12_381 as Pairing>::G1Affine::rand(&mut rng))
+            .collect::<Vec<_>>();
+        let g_delta = <Bls12_381 as Pairing>::G1Affine::rand(&mut rng);
+        let g_gamma = <Bls12_381 as Pairing>::G1Affine::rand(&mut rng);
+        let (sk_1, _, dk_1) =
+            keygen(&mut rng, chunk_bit_size, &gens, &g_i, &g_delta, &g_gamma).unwrap();
+        let (sk_2, _, dk_2) =
+            keygen(&mut rng, chunk_bit_size, &gens, &g_i, &g_delta, &g_gamma).unwrap();
+
+        assert_eq!(sk_1.public_component(&gens), dk_1.V_0);
+        assert_eq!(sk_2.public_component(&gens), dk_2.V_0);
+        assert_ne!(sk_1.public_component(&gens), dk_2.V_0);
+    }
+
+    #[test]
+    fn max_decryptable_value() {
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let (gens, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+            let n = chunks_count::<Fr>(chunk_bit_size) as usize;
+
+            // Key created for the chunk bit size covers the whole field
+            let max = ek.max_decryptable_value(chunk_bit_size).unwrap();
+            assert_eq!(max, -Fr::one());
+            assert_eq!(
+                PreparedEncryptionKey::from(ek.clone())
+                    .max_decryptable_value(chunk_bit_size)
+                    .unwrap(),
+                max
+            );
+            let (ct, _) = Encryption::encrypt(&mut rng, &max, &ek, &g_i, chunk_bit_size).unwrap();
+            let (m, nu) = ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap();
+            assert_eq!(m, max);
+            ct.verify_decryption(&m, &nu, chunk_bit_size, dk, &g_i, gens)
+                .unwrap();
+
+            // Key with fewer chunks supports smaller messages
+            let m = n / 4;
+            let mut small_ek = ek.clone();
+            small_ek.X.truncate(m);
+            small_ek.Y.truncate(m);
+            small_ek.Z.truncate(m + 1);
+            assert_eq!(
+                small_ek.max_decryptable_value(chunk_bit_size).unwrap(),
+                Fr::from(2u64).pow([(m * chunk_bit_size as usize) as u64]) - Fr::one()
+            );
+            if chunk_bit_size == 4 {
+                assert_eq!(
+                    small_ek.max_decryptable_value(chunk_bit_size).unwrap(),
+                    Fr::from(u64::MAX)
+                );
+            }
+
+            small_ek.Z.pop();
+            assert!(small_ek.max_decryptable_value(chunk_bit_size).is_err());
+        }
+        check(4);
+        check(8);
+    }
+
+    #[test]
+    fn encryption_key_can_encrypt() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (_, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+        let m = Fr::rand(&mut rng);
+
+        // 32 chunks are needed and supported
+        assert_eq!(ek.supported_chunks_count().unwrap(), 32);
+        assert!(ek.can_encrypt(chunk_bit_size));
+        assert!(PreparedEncryptionKey::from(ek.clone()).can_encrypt(chunk_bit_size));
+        assert!(Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).is_ok());
+
+        // Other chunk bit sizes need a different number of chunks or aren't supported
+        assert!(!ek.can_encrypt(4));
+        assert!(!ek.can_encrypt(16));
+        assert!(!ek.can_encrypt(3));
+
+        // Key supporting 31 chunks when 32 are needed
+        let mut short_ek = ek.clone();
+        short_ek.X.pop();
+        short_ek.Y.pop();
+        short_ek.Z.pop();
+        assert_eq!(short_ek.supported_chunks_count().unwrap(), 31);
+        assert!(!short_ek.can_encrypt(chunk_bit_size));
+        assert!(Encryption::encrypt(&mut rng, &m, &short_ek, &g_i, chunk_bit_size).is_err());
+
+        // More chunks than needed can't be used either
+        let mut long_ek = ek.clone();
+        long_ek.X.push(ek.X[0]);
+        long_ek.Y.push(ek.Y[0]);
+        long_ek.Z.push(ek.Z[0]);
+        assert_eq!(long_ek.supported_chunks_count().unwrap(), 33);
+        assert!(!long_ek.can_encrypt(chunk_bit_size));
+        assert!(Encryption::encrypt(&mut rng, &m, &long_ek, &g_i, chunk_bit_size).is_err());
+
+        // Malformed key
+        let mut malformed_ek = ek;
+        malformed_ek.Z.pop();
+        assert!(malformed_ek.validate().is_err());
+        assert!(!malformed_ek.can_encrypt(chunk_bit_size));
+    }
+
+    #[test]
+    fn decryption_key_rotation() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (gens, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+        let m_old = Fr::rand(&mut rng);
+        let (ct_old, _) = Encryption::encrypt(&mut rng, &m_old, &ek, &g_i, chunk_bit_size).unwrap();
+
+        let delta = Fr::rand(&mut rng);
+        let (new_sk, new_dk) = rotate_decryption_key(&sk, &dk, &delta).unwrap();
+        assert_ne!(new_sk, sk);
+        assert_ne!(new_dk, dk);
+        assert_eq!(new_sk.public_component(&gens), new_dk.V_0);
+        KeygenBundle::new(new_sk.clone(), ek.clone(), new_dk.clone(), chunk_bit_size).unwrap();
+
+        // Ciphertexts created before and after the rotation are decrypted with the new keys
+        let m_new = Fr::rand(&mut rng);
+        let (ct_new, _) = Encryption::encrypt(&mut rng, &m_new, &ek, &g_i, chunk_bit_size).unwrap();
+        for (ct, m) in [(&ct_old, m_old), (&ct_new, m_new)] {
+            let (decrypted, nu) = ct
+                .decrypt(&new_sk, new_dk.clone(), &g_i, chunk_bit_size)
+                .unwrap();
+            assert_eq!(decrypted, m);
+            ct.verify_decryption(
+                &decrypted,
+                &nu,
+                chunk_bit_size,
+                new_dk.clone(),
+                &g_i,
+                gens.clone(),
+            )
+            .unwrap();
+
+            // The old keys still decrypt as rotation does not revoke them
+            let (decrypted, _) = ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap();
+            assert_eq!(decrypted, m);
+        }
+
+        // The new secret key doesn't work with the old decryption key
+        assert!(ct_new
+            .decrypt(&new_sk, dk.clone(), &g_i, chunk_bit_size)
+            .is_err());
+
+        assert!(matches!(
+            rotate_decryption_key(&sk, &dk, &Fr::zero()),
+            Err(SaverError::ZeroRotationDelta)
+        ));
+    }
+
+    #[test]
+    fn keygen_bundle() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (_, _, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+        let bundle = KeygenBundle::new(sk.clone(), ek.clone(), dk.clone(), chunk_bit_size).unwrap();
+
+        let mut bytes = vec![];
+        bundle.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(
+            KeygenBundle::<Bls12_381>::deserialize_compressed(&bytes[..]).unwrap(),
+            bundle
+        );
+        let mut bytes = vec![];
+        bundle.serialize_uncompressed(&mut bytes).unwrap();
+        assert_eq!(
+            KeygenBundle::<Bls12_381>::deserialize_uncompressed(&bytes[..]).unwrap(),
+            bundle
+        );
+
+        // Decryption key from another keygen
+        let (_, _, _, _, other_dk) = enc_setup(chunk_bit_size, &mut rng);
+        assert!(matches!(
+            KeygenBundle::new(sk.clone(), ek.clone(), other_dk.clone(), chunk_bit_size),
+            Err(SaverError::InconsistentSetup)
+        ));
+        // Wrong chunk bit size
+        assert!(matches!(
+            KeygenBundle::new(sk.clone(), ek.clone(), dk, 4),
+            Err(SaverError::IncompatibleEncryptionKey(32, 64))
+        ));
+
+        // An inconsistent bundle is rejected on load unless validation is skipped
+        let mixed = KeygenBundle {
+            sk,
+            ek,
+            dk: other_dk,
+            chunk_bit_size,
+        };
+        let mut bytes = vec![];
+        mixed.serialize_compressed(&mut bytes).unwrap();
+        assert!(KeygenBundle::<Bls12_381>::deserialize_compressed(&bytes[..]).is_err());
+        assert_eq!(
+            KeygenBundle::<Bls12_381>::deserialize_compressed_unchecked(&bytes[..]).unwrap(),
+            mixed
+        );
+    }
+
+    #[test]
+    fn checked_and_unchecked_deserialization_agree() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let (_, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+        let m = Fr::rand(&mut rng);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+
+        let mut ek_bytes = vec![];
+        ek.serialize_compressed(&mut ek_bytes).unwrap();
+        let ek_checked = EncryptionKey::<Bls12_381>::from_bytes(&ek_bytes).unwrap();
+        let ek_unchecked = EncryptionKey::<Bls12_381>::from_bytes_unchecked(&ek_bytes).unwrap();
+        assert_eq!(ek_checked, ek);
+        assert_eq!(ek_unchecked, ek);
+
+        let mut ct_bytes = vec![];
+        ct.serialize_compressed(&mut ct_bytes).unwrap();
+        let ct_checked = Ciphertext::<Bls12_381>::from_bytes(&ct_bytes).unwrap();
+        let ct_unchecked = Ciphertext::<Bls12_381>::from_bytes_unchecked(&ct_bytes).unwrap();
+        assert_eq!(ct_checked, ct);
+        assert_eq!(ct_unchecked, ct);
+
+        // Truncated bytes fail in both cases
+        assert!(EncryptionKey::<Bls12_381>::from_bytes(&ek_bytes[1..]).is_err());
+        assert!(EncryptionKey::<Bls12_381>::from_bytes_unchecked(&ek_bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn encryption_key_from_bytes_without_fingerprint() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let (_, _, _, ek, _) = enc_setup(8, &mut rng);
+
+        // The layout before `gens_fingerprint` was added, see "Upgrading from 0.12" in the crate docs
+        let mut old_bytes = vec![];
+        ek.X_0.serialize_compressed(&mut old_bytes).unwrap();
+        ek.X.serialize_compressed(&mut old_bytes).unwrap();
+        ek.Y.serialize_compressed(&mut old_bytes).unwrap();
+        ek.Z.serialize_compressed(&mut old_bytes).unwrap();
+        ek.P_1.serialize_compressed(&mut old_bytes).unwrap();
+        ek.P_2.serialize_compressed(&mut old_bytes).unwrap();
+        let ek_old = EncryptionKey::<Bls12_381>::from_bytes(&old_bytes).unwrap();
+        assert_eq!(ek_old.gens_fingerprint, None);
+        assert_eq!(
+            EncryptionKey {
+                gens_fingerprint: None,
+                ..ek.clone()
+            },
+            ek_old
+        );
+        assert_eq!(
+            EncryptionKey::<Bls12_381>::from_bytes_unchecked(&old_bytes).unwrap(),
+            ek_old
+        );
+
+        // Same as a key written without a fingerprint by this version
+        let mut no_fingerprint_bytes = vec![];
+        ek_old
+            .serialize_compressed(&mut no_fingerprint_bytes)
+            .unwrap();
+        assert_eq!(no_fingerprint_bytes.len(), old_bytes.len() + 1);
+        assert_eq!(
+            EncryptionKey::<Bls12_381>::from_bytes(&no_fingerprint_bytes).unwrap(),
+            ek_old
+        );
+
+        // A key with a fingerprint still round trips, and an invalid flag or a truncated fingerprint fails
+        let mut bytes = vec![];
+        ek.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(EncryptionKey::<Bls12_381>::from_bytes(&bytes).unwrap(), ek);
+        assert!(EncryptionKey::<Bls12_381>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut bad_flag = old_bytes.clone();
+        bad_flag.push(2);
+        assert!(EncryptionKey::<Bls12_381>::from_bytes(&bad_flag).is_err());
+    }
+
+    #[test]
+    fn key_vectors_ordered_by_chunk_index() {
+        let chunk_bit_size = 4;
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let (gens, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+        let m = Fr::rand(&mut rng);
+
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+        ct.verify_commitment(ek.clone(), gens.clone()).unwrap();
+        let (m_, _) = ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap();
+        assert_eq!(m_, m);
+
+        // Swapping elements of `X` breaks decryption
+        let mut ek_1 = ek.clone();
+        ek_1.X.swap(0, 1);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek_1, &g_i, chunk_bit_size).unwrap();
+        assert!(ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).is_err());
+
+        // Swapping elements of `V_2` breaks decryption as well
+        let mut dk_1 = dk.clone();
+        dk_1.V_2.swap(0, 1);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+        assert!(ct.decrypt(&sk, dk_1, &g_i, chunk_bit_size).is_err());
+
+        // Swapping elements of `Y` or `Z` breaks the commitment
+        let mut ek_2 = ek.clone();
+        ek_2.Y.swap(0, 1);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek_2, &g_i, chunk_bit_size).unwrap();
+        assert!(ct.verify_commitment(ek_2, gens.clone()).is_err());
+
+        let mut ek_3 = ek.clone();
+        ek_3.Z.swap(1, 2);
+        let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek_3, &g_i, chunk_bit_size).unwrap();
+        assert!(ct.verify_commitment(ek_3, gens).is_err());
+    }
+
+    #[test]
+    fn decrypt_with_truncated_key() {
+        fn check(chunk_bit_size: u8, n: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let (_, g_i, sk, ek, dk) = enc_setup(chunk_bit_size, &mut rng);
+            let dk_truncated = dk.truncate_to_chunks(n).unwrap();
+            assert_eq!(dk_truncated.supported_chunks_count().unwrap(), n);
+            let count = dk.V_1.len();
+            assert_eq!(dk_truncated.V_1[..], dk.V_1[count - n as usize..]);
+            assert_eq!(dk_truncated.V_2[..], dk.V_2[count - n as usize..]);
+            let prepared_truncated = PreparedDecryptionKey::from(dk.clone())
+                .truncate_to_chunks(n)
+                .unwrap();
+            assert_eq!(
+                prepared_truncated,
+                PreparedDecryptionKey::from(dk_truncated.clone())
+            );
+
+            // Message fitting in the last `n` chunks
+            let bits = chunk_bit_size as u32 * n as u32;
+            let max = (1u64 << bits) - 1;
+            for m in [Fr::from(0u64), Fr::from(max / 3), Fr::from(max)] {
+                let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+                let (m_, nu) = ct
+                    .decrypt_last_chunks(&sk, dk_truncated.clone(), &g_i, chunk_bit_size)
+                    .unwrap();
+                assert_eq!(m_, m);
+                assert_eq!(
+                    ct.decrypt_last_chunks(&sk, prepared_truncated.clone(), &g_i, chunk_bit_size)
+                        .unwrap(),
+                    (m, nu)
+                );
+                // `nu` is the same as for the full key so the decryption can be verified as usual
+                assert_eq!(
+                    ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap(),
+                    (m, nu)
+                );
+            }
+
+            // Message not fitting gives only its least significant chunks
+            let m = Fr::from(max) + Fr::from(1u64) + Fr::from(5u64);
+            let (ct, _) = Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+            let (m_, _) = ct
+                .decrypt_last_chunks(&sk, dk_truncated.clone(), &g_i, chunk_bit_size)
+                .unwrap();
+            assert_eq!(m_, Fr::from(5u64));
+
+            assert!(dk.truncate_to_chunks(count as u8 + 1).is_err());
+        }
+        check(4, 3);
+        check(8, 2);
+        check(16, 1);
+    }
+
+    #[cfg(feature = "keygen-internals")]
+    #[test]
+    fn keygen_internals_match_keys() {
+        let chunk_bit_size = 8;
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let n = chunks_count::<Fr>(chunk_bit_size) as usize;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let g_i = (0..n)
+            .map(|_| <Bls12_381 as Pairing>::G1Affine::rand(&mut rng))
+            .collect::<Vec<_>>();
+        let delta = Fr::rand(&mut rng);
+        let gamma = Fr::rand(&mut rng);
+        let g_delta = (gens.G * delta).into_affine();
+        let g_gamma = (gens.G * gamma).into_affine();
+
+        let (sk, ek, dk, internals) =
+            keygen_with_internals(&mut rng, chunk_bit_size, &gens, &g_i, &g_delta, &g_gamma)
+                .unwrap();
+        let (rho, s, t, v) = (internals.rho, &internals.s, &internals.t, &internals.v);
+        assert_eq!(s.len(), n);
+        assert_eq!(t.len(), n + 1);
+        assert_eq!(v.len(), n);
+
+        // Recompute the keys as defined in the paper
+        assert_eq!(sk.0, rho);
+        assert_eq!(ek.X_0, g_delta);
+        for i in 0..n {
+            assert_eq!(ek.X[i], (gens.G * (delta * s[i])).into_affine());
+            assert_eq!(ek.Y[i], (g_i[i] * t[i + 1]).into_affine());
+            assert_eq!(dk.V_1[i], (gens.H * (s[i] * v[i])).into_affine());
+            assert_eq!(dk.V_2[i], (gens.H * (rho * v[i])).into_affine());
+        }
+        assert_eq!(ek.Z.len(), t.len());
+        for (z, t_i) in ek.Z.iter().zip(t.iter()) {
+            assert_eq!(*z, (gens.H * t_i).into_affine());
+        }
+        let p_1 = t[0] + (0..n).map(|i| s[i] * t[i + 1]).sum::<Fr>();
+        assert_eq!(ek.P_1, (gens.G * (delta * p_1)).into_affine());
+        let p_2 = Fr::one() + s.iter().sum::<Fr>();
+        assert_eq!(ek.P_2, (gens.G * (gamma * p_2)).into_affine());
+        assert_eq!(dk.V_0, (gens.H * rho).into_affine());
+
+        // Same keys as `keygen_from_secrets`
+        let (sk_, ek_, dk_) =
+            keygen_from_secrets(&internals, chunk_bit_size, &gens, &g_i, &g_delta, &g_gamma)
+                .unwrap();
+        assert_eq!(sk_, sk);
+        assert_eq!(ek_, ek);
+        assert_eq!(dk_, dk);
+    }
+
+    #[test]
+    fn keygen_with_too_few_generators() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let chunk_count = chunks_count::<Fr>(chunk_bit_size) as usize;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let g_i = (0..chunk_count - 1)
+            .map(|_| <Bls12_381 as Pairing>::G1Affine::rand(&mut rng))
+            .collect::<Vec<_>>();
+        let g_delta = <Bls12_381 as Pairing>::G1Affine::rand(&mut rng);
+        let g_gamma = <Bls12_381 as Pairing>::G1Affine::rand(&mut rng);
+
+        let err = keygen(&mut rng, chunk_bit_size, &gens, &g_i, &g_delta, &g_gamma).unwrap_err();
+        assert!(matches!(
+            err,
+            SaverError::InsufficientGenerators {
+                have,
+                need,
+                source: "g_i"
+            } if have == chunk_count - 1 && need == chunk_count
+        ));
+        assert_eq!(
+            err.to_string(),
+            "g_i has 31 generators but 32 are needed, one for each chunk. Use generators created for the same chunk bit size as the keys"
+        );
+
+        // Unsupported chunk bit sizes are an error rather than a panic
+        for chunk_bit_size in [0, 3, 32] {
+            assert!(matches!(
+                keygen(&mut rng, chunk_bit_size, &gens, &g_i, &g_delta, &g_gamma),
+                Err(SaverError::UnexpectedBase(b)) if b == chunk_bit_size
+            ));
+        }
+    }
+
+    #[test]
+    fn fingerprints() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 16;
+        let (_, g_i, _, ek, _) = enc_setup(chunk_bit_size, &mut rng);
+        let (_, _, _, other_ek, _) = enc_setup(chunk_bit_size, &mut rng);
+
+        let fingerprint = ek.to_string();
+        assert_eq!(fingerprint.len(), 16);
+        assert!(fingerprint.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(ek.clone().to_string(), fingerprint);
+        assert_ne!(other_ek.to_string(), fingerprint);
+        assert_eq!(format!("{}", ek), fingerprint);
+
+        let msg = Fr::rand(&mut rng);
+        let (ct, _) = Encryption::encrypt(&mut rng, &msg, &ek, &g_i, chunk_bit_size).unwrap();
+        let (other_ct, _) = Encryption::encrypt(&mut rng, &msg, &ek, &g_i, chunk_bit_size).unwrap();
+        assert_eq!(ct.to_string(), ct.clone().to_string());
+        assert_ne!(ct.to_string(), other_ct.to_string());
+        assert_ne!(ct.to_string(), fingerprint);
+    }
+
+    #[test]
+    fn generators_report() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 16;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, _, ek, _) =
+            crate::setup::setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        let vk = &snark_pk.pk.vk;
+        let report = ek.merge_generators_check(vk, &gens);
+        assert!(report.all_match());
+        assert_eq!(report.g_i, vec![true; 16]);
+
+        // A different g_2 in the verification key is flagged only for that chunk
+        let mut bad_vk = vk.clone();
+        bad_vk.gamma_abc_g1[3] = <Bls12_381 as Pairing>::G1Affine::rand(&mut rng);
+        let report = ek.merge_generators_check(&bad_vk, &gens);
+        assert!(!report.all_match());
+        assert!(report.chunks_count && report.delta && report.gamma && report.gens_fingerprint);
+        assert_eq!(
+            report
+                .g_i
+                .iter()
+                .enumerate()
+                .filter(|(_, m)| !**m)
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>(),
+            vec![2]
+        );
+
+        let mut bad_vk = vk.clone();
+        bad_vk.delta_g2 = <Bls12_381 as Pairing>::G2Affine::rand(&mut rng);
+        let report = ek.merge_generators_check(&bad_vk, &gens);
+        // P_2 is checked against delta as well
+        assert!(!report.delta && !report.gamma);
+        assert!(report.chunks_count && report.g_i.iter().all(|m| *m));
+
+        let mut bad_vk = vk.clone();
+        bad_vk.gamma_g2 = <Bls12_381 as Pairing>::G2Affine::rand(&mut rng);
+        let report = ek.merge_generators_check(&bad_vk, &gens);
+        assert!(!report.gamma);
+        assert!(report.delta && report.g_i.iter().all(|m| *m));
+
+        // Verification key for another chunk bit size
+        let (other_pk, _, _, _) = crate::setup::setup_for_groth16(&mut rng, 8, &gens).unwrap();
+        let report = ek.merge_generators_check(&other_pk.pk.vk, &gens);
+        assert!(!report.chunks_count && !report.delta);
+        assert!(report.g_i.iter().all(|m| !*m));
+
+        let other_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let report = ek.merge_generators_check(vk, &other_gens);
+        assert!(!report.gens_fingerprint && !report.delta);
+    }
 }
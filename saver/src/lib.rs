@@ -25,32 +25,151 @@
 //! To use the ciphertext commitment for equality of a committed message using a Schnorr protocol, the commitment must be transformed
 //! to a commitment to the full (non-decomposed) message. This is implemented with [`ChunkedCommitment`] and its docs describe the process.
 //!
+//! ## Example
+//!
+//! `examples/verifiable_encryption.rs` does the setup, encrypts with a proof, verifies it, decrypts and verifies
+//! the decryption, printing the time taken by each step. The chunk bit size is given as an argument
+//!
+//! `cargo run --release --example verifiable_encryption -- 8`
+//!
+//! ## Proving correct encryption without a SNARK
+//!
+//! [`encrypt_with_sigma_proof`] proves that the ciphertext is well-formed using only sigma protocols, committing
+//! to each bit of the chunks, so that the encryptor does not need the Groth16 proving key. The proof is verified
+//! with [`verify_sigma_proof`] and is much larger than the Groth16 proof but faster to create.
+//!
+//! ## Linking rerandomized ciphertexts
+//!
+//! Rerandomized ciphertexts can't be linked to the original. The party that rerandomized, eg. a mix-net, can
+//! prove the link to an auditor with [`prove_rerandomization`], verified with [`verify_rerandomization`].
+//!
+//! ## Encrypting bulk data
+//!
+//! With the `hybrid` feature, [`hybrid_encrypt`] encrypts a random key message with SAVER along with the proof
+//! and the data with ChaCha20-Poly1305 under a key derived from it. [`hybrid_decrypt`] decrypts the key message
+//! and then the data.
+//!
 //! ## Use with BBS+ signature
 //!
 //! See the tests.rs file
 //!
+//! ## JSON for other languages
+//!
+//! With the `json` feature, the encryption key and the Groth16 verification key can be converted to and
+//! from a documented JSON schema with base64 encoded points, see the [`json`] module.
+//!
+//! ## Parallelism
+//!
+//! With the `parallel` feature (on by default), the loops over chunks use rayon: computing `Y` in key
+//! generation, the encryption of each chunk, the decryption of each chunk and [`pairing_powers`]. MSMs are
+//! parallelized by arkworks. The constant-time decryption and the decryption verification, which stops at
+//! the first wrong chunk, stay sequential. The outputs do not depend on the feature, which the test vectors
+//! check when the tests are run with and without it
+//!
+//! `cargo test -p saver --no-default-features --features std`
+//!
+//! ## Choice of hash function
+//!
+//! The hash function isn't fixed by this crate. Functions that hash, like [`EncryptionGens::new`] and
+//! [`ChunkedCommitmentGens::new`] (and `Proof::new` and `Proof::verify` of the `proof_system` crate for the
+//! Fiat-Shamir challenge), take it as a type parameter implementing [`Digest`]. So using SHA-256 instead
+//! of Blake2b only needs a different type parameter, eg.
+//!
+//! ```ignore
+//! let enc_gens = EncryptionGens::<Bls12_381>::new::<sha2::Sha256>(b"test");
+//! let (proof, _) = Proof::new::<StdRng, sha2::Sha256>(&mut rng, proof_spec.clone(), witnesses, None, Default::default())?;
+//! proof.verify::<StdRng, sha2::Sha256>(&mut rng, proof_spec, None, Default::default())?;
+//! ```
+//!
+//! The same hash function must be used by the prover and the verifier. With the `sha2` and `blake3` features,
+//! `saver::Sha256` and `saver::Blake3` can be used as the type parameter without depending on those crates, eg.
+//! `EncryptionGens::<Bls12_381>::new::<saver::Sha256>(b"test")`.
+//!
+//! ## Upgrading from 0.12
+//!
+//! `EncryptionKey` and `PreparedEncryptionKey` have a new field `gens_fingerprint`, the fingerprint of the
+//! `EncryptionGens` the key was created with, so the serialization of `EncryptionKey` has changed. With
+//! `CanonicalSerialize`, the field is written after `P_2` as an `Option`, i.e. a flag byte followed by the 32
+//! bytes when present. A key serialized by 0.12 on its own, like with `EncryptionKey::from_bytes`, is still
+//! read as is, as a key without a fingerprint. Such a key is accepted with any generators, like before. A 0.12
+//! key serialized inside another object, like a `proof_system` statement, is followed by other bytes so a `0`
+//! byte must be inserted after it. With serde, a missing `gens_fingerprint` is read as `None` so nothing needs
+//! to change. Keys created by [`keygen`] now always have the fingerprint, so they can't be read by 0.12.
+//!
+//! `PreparedEncryptionGens` has a new public field `fingerprint`, the fingerprint of the generators before
+//! they were prepared, which is checked against the encryption key's. Its serialization (both
+//! `CanonicalSerialize` and serde) has the 32 bytes after `H` and prepared generators serialized by 0.12
+//! can't be read. Prepare them again from the `EncryptionGens` with `From`, which also sets the fingerprint,
+//! rather than with a struct literal.
+//!
+//! Other changes that need updating code written for 0.12:
+//!
+//! - [`SaverError`] is no longer `Clone`, as its new `Serialization` variant wraps a `SerializationError`,
+//!   which isn't `Clone`, so that it's available as the error's `source()`. `SaverError` also has new
+//!   variants, so exhaustive matches on it need new arms.
+//! - [`ChunkedCommitment::commitment_key`] and `SaverProtocol::chunked_comm_keys` of the `proof_system`
+//!   crate return a `Result` as they fail for an unsupported chunk bit size.
+//! - [`create_proof`] takes a [`BitsizeCheckCircuit`] rather than any `ConstraintSynthesizer`, so that the
+//!   chunks can be range checked before proving. Create it with `BitsizeCheckCircuit::new` or
+//!   `BitsizeCheckCircuit::builder`.
+//! - [`encrypt_decomposed_message`] returns `SaverError::ChunkOutOfRange` for a chunk that doesn't fit in
+//!   the chunk bit size instead of encrypting it. `CHUNK_TYPE` is still `u16` as in 0.12.
+//!
 //! [`SAVER`]: https://eprint.iacr.org/2019/1270
 //! [`Groth16`]: crate::saver_groth16
 //! [`LegoGroth16`]: crate::saver_legogroth16
 //! [`ChunkedCommitment`]: crate::commitment::ChunkedCommitment
+//! [`EncryptionGens::new`]: crate::setup::EncryptionGens::new
+//! [`ChunkedCommitmentGens::new`]: crate::setup::ChunkedCommitmentGens::new
+//! [`Digest`]: digest::Digest
+//! [`encrypt_with_sigma_proof`]: crate::sigma_encryption::encrypt_with_sigma_proof
+//! [`verify_sigma_proof`]: crate::sigma_encryption::verify_sigma_proof
+//! [`prove_rerandomization`]: crate::sigma_encryption::prove_rerandomization
+//! [`verify_rerandomization`]: crate::sigma_encryption::verify_rerandomization
+//! [`hybrid_encrypt`]: crate::hybrid::hybrid_encrypt
+//! [`hybrid_decrypt`]: crate::hybrid::hybrid_decrypt
+//! [`pairing_powers`]: crate::keygen::PreparedDecryptionKey::pairing_powers
+//! [`json`]: crate::json
+//! [`keygen`]: crate::keygen::keygen
+//! [`SaverError`]: crate::error::SaverError
+//! [`ChunkedCommitment::commitment_key`]: crate::commitment::ChunkedCommitment::commitment_key
+//! [`create_proof`]: crate::saver_groth16::create_proof
+//! [`BitsizeCheckCircuit`]: crate::circuit::BitsizeCheckCircuit
+//! [`encrypt_decomposed_message`]: crate::encryption::Encryption::encrypt_decomposed_message
 
 #[macro_use]
 pub mod utils;
 pub mod circuit;
 pub mod commitment;
+pub mod committed_decryption;
 #[macro_use]
 pub mod encryption;
 pub mod error;
+#[cfg(feature = "hybrid")]
+pub mod hybrid;
+#[cfg(feature = "json")]
+pub mod json;
 #[macro_use]
 pub mod keygen;
 pub mod saver_groth16;
 pub mod saver_legogroth16;
 pub mod setup;
+pub mod sigma_encryption;
+#[cfg(test)]
+mod test_vectors;
 #[cfg(test)]
 pub mod tests;
 
 pub type Result<T> = core::result::Result<T, error::SaverError>;
 
+/// SHA-256 as the hash function type parameter, see "Choice of hash function" above
+#[cfg(feature = "sha2")]
+pub type Sha256 = sha2::Sha256;
+
+/// BLAKE3 as the hash function type parameter, see "Choice of hash function" above
+#[cfg(feature = "blake3")]
+pub type Blake3 = blake3::Hasher;
+
 pub mod prelude {
     pub use crate::{
         commitment::ChunkedCommitment,
@@ -61,8 +180,11 @@ pub mod prelude {
         },
         saver_groth16::{
             create_proof, generate_srs, verify_proof, PreparedVerifyingKey, ProvingKey,
-            VerifyingKey,
+            VerifierContext, VerifyingKey,
+        },
+        setup::{
+            setup_for_groth16, ChunkedCommitmentGens, EncryptionGens, PreparedEncryptionGens,
+            SaverSetup,
         },
-        setup::{setup_for_groth16, ChunkedCommitmentGens, EncryptionGens, PreparedEncryptionGens},
     };
 }
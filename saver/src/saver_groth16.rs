@@ -1,10 +1,15 @@
 //! Using SAVER with Groth16
-use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup, Group, VariableBaseMSM};
-use ark_ff::{Field, PrimeField};
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine as SWAffine, SWCurveConfig},
+    AffineRepr, CurveGroup, Group, VariableBaseMSM,
+};
+use ark_ff::{BigInteger, Field, PrimeField, Zero};
 use ark_relations::r1cs::{ConstraintSynthesizer, SynthesisError};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::{
-    ops::{AddAssign, Mul},
+    cell::OnceCell,
+    ops::{AddAssign, Deref, Mul},
     rand::{Rng, RngCore},
     string::ToString,
     vec,
@@ -16,7 +21,11 @@ use legogroth16::aggregation::{groth16::AggregateProof, srs::VerifierSRS};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
-use crate::encryption::Ciphertext;
+use crate::{
+    circuit::{BitsizeCheckCircuit, RangeCheckGadget},
+    encryption::{Ciphertext, VerificationCost},
+    utils::{check_chunk_bit_size, chunk_bit_size_for_chunks_count, chunks_count},
+};
 pub use ark_groth16::{
     prepare_verifying_key, Groth16, PreparedVerifyingKey, Proof, ProvingKey as Groth16ProvingKey,
     VerifyingKey,
@@ -29,7 +38,10 @@ use dock_crypto_utils::{
 use crate::error::SaverError;
 use dock_crypto_utils::{serde_utils::*, transcript::Transcript};
 
-use crate::{keygen::EncryptionKey, setup::EncryptionGens};
+use crate::{
+    keygen::{DecryptionKey, EncryptionKey, PreparedDecryptionKey, PreparedEncryptionKey},
+    setup::{EncryptionGens, PreparedEncryptionGens},
+};
 
 #[serde_as]
 #[derive(
@@ -44,6 +56,49 @@ pub struct ProvingKey<E: Pairing> {
     pub gamma_g1: E::G1Affine,
 }
 
+/// `ProvingKey` along with its prepared verification key, which is computed on the first call to
+/// `Self::prepared_vk` and then reused. Preparing computes a pairing so this avoids preparing again for each
+/// proof. The prepared key isn't kept in `ProvingKey` as that would change its serialization. Dereferences to
+/// the `ProvingKey`. The cache can't be shared between threads, use `VerifierContext` for that.
+#[derive(Clone, Debug)]
+pub struct CachedProvingKey<E: Pairing> {
+    snark_pk: ProvingKey<E>,
+    pvk: OnceCell<PreparedVerifyingKey<E>>,
+}
+
+impl<E: Pairing> CachedProvingKey<E> {
+    pub fn new(snark_pk: ProvingKey<E>) -> Self {
+        Self {
+            snark_pk,
+            pvk: OnceCell::new(),
+        }
+    }
+
+    /// Prepared verification key for `verify_proof` and the verification functions of `Ciphertext`
+    pub fn prepared_vk(&self) -> &PreparedVerifyingKey<E> {
+        self.pvk
+            .get_or_init(|| prepare_verifying_key(&self.snark_pk.pk.vk))
+    }
+
+    pub fn into_inner(self) -> ProvingKey<E> {
+        self.snark_pk
+    }
+}
+
+impl<E: Pairing> From<ProvingKey<E>> for CachedProvingKey<E> {
+    fn from(snark_pk: ProvingKey<E>) -> Self {
+        Self::new(snark_pk)
+    }
+}
+
+impl<E: Pairing> Deref for CachedProvingKey<E> {
+    type Target = ProvingKey<E>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.snark_pk
+    }
+}
+
 /// These parameters are needed for setting up keys for encryption/decryption
 pub fn get_gs_for_encryption<E: Pairing>(vk: &VerifyingKey<E>) -> &[E::G1Affine] {
     &vk.gamma_abc_g1[1..]
@@ -80,9 +135,10 @@ pub fn generate_srs<E: Pairing, R: RngCore, C: ConstraintSynthesizer<E::ScalarFi
     })
 }
 
-/// `r` is the randomness used during the encryption
-pub fn create_proof<E, C, R>(
-    circuit: C,
+/// `r` is the randomness used during the encryption. Returns `SaverError::ChunkOutOfRange` for the first
+/// value of the circuit that is too big.
+pub fn create_proof<E, G, R>(
+    circuit: BitsizeCheckCircuit<E::ScalarField, G>,
     r: &E::ScalarField,
     pk: &ProvingKey<E>,
     encryption_key: &EncryptionKey<E>,
@@ -90,12 +146,41 @@ pub fn create_proof<E, C, R>(
 ) -> Result<Proof<E>, SaverError>
 where
     E: Pairing,
-    C: ConstraintSynthesizer<E::ScalarField>,
+    G: RangeCheckGadget<E::ScalarField>,
     R: Rng,
 {
     let t = E::ScalarField::rand(rng);
     let s = E::ScalarField::rand(rng);
-    let mut proof = Groth16::<E>::create_proof_with_reduction(circuit, &pk.pk, t, s)?;
+    create_proof_given_randomness(circuit, r, &t, &s, pk, encryption_key)
+}
+
+/// Same as `create_proof` but takes the randomness `t` and `s` of the Groth16 proof. Every way of creating
+/// a proof goes through this so the span is named after `create_proof`.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "create_proof",
+        skip_all,
+        fields(
+            chunks_count = pk.pk.vk.gamma_abc_g1.len().saturating_sub(1),
+            chunk_bit_size = ?chunk_bit_size_for_chunks_count::<E::ScalarField>(pk.pk.vk.gamma_abc_g1.len().saturating_sub(1))
+        )
+    )
+)]
+pub(crate) fn create_proof_given_randomness<E, G>(
+    circuit: BitsizeCheckCircuit<E::ScalarField, G>,
+    r: &E::ScalarField,
+    t: &E::ScalarField,
+    s: &E::ScalarField,
+    pk: &ProvingKey<E>,
+    encryption_key: &EncryptionKey<E>,
+) -> Result<Proof<E>, SaverError>
+where
+    E: Pairing,
+    G: RangeCheckGadget<E::ScalarField>,
+{
+    circuit.check_values()?;
+    let mut proof = Groth16::<E>::create_proof_with_reduction(circuit, &pk.pk, *t, *s)?;
 
     // proof.c = proof.c + r * P_2
     let mut c = proof.c.into_group();
@@ -105,6 +190,31 @@ where
     Ok(proof)
 }
 
+/// Same as `create_proof` but takes the decomposed message (chunks as field elements) instead of the circuit.
+/// The chunk bit size is inferred from the number of public inputs in the proving key, and the number of
+/// chunks must match it. Returns `SaverError::ChunkOutOfRange` for the first chunk that is too big.
+/// `r` is the randomness used during the encryption
+pub fn create_proof_from_assignment<E, R>(
+    decomposed: &[E::ScalarField],
+    r: &E::ScalarField,
+    pk: &ProvingKey<E>,
+    encryption_key: &EncryptionKey<E>,
+    rng: &mut R,
+) -> Result<Proof<E>, SaverError>
+where
+    E: Pairing,
+    R: Rng,
+{
+    let n = get_gs_for_encryption(&pk.pk.vk).len();
+    if decomposed.len() != n {
+        return Err(SaverError::InvalidDecomposition);
+    }
+    let chunk_bit_size = chunk_bit_size_for_chunks_count::<E::ScalarField>(n)
+        .ok_or(SaverError::InvalidDecomposition)?;
+    let circuit = BitsizeCheckCircuit::new(chunk_bit_size, None, Some(decomposed.to_vec()), true);
+    create_proof(circuit, r, pk, encryption_key, rng)
+}
+
 /// Randomize the Groth16 proof as per algorithm 2 of the paper. Can alternatively use
 /// `rerandomize_proof` from `ark_groth16`
 pub fn randomize_proof<E, R>(
@@ -140,6 +250,37 @@ where
     Ok(proof)
 }
 
+/// Verify the proof that `ciphertext` encrypts chunks of the expected bit size. The proof is valid only if
+/// this returns `Ok(())`, and ignoring the result is a warning, so the following fails to compile when
+/// warnings are denied
+///
+/// ```compile_fail
+/// #![deny(unused_must_use)]
+/// use ark_bls12_381::Bls12_381;
+/// use saver::{
+///     encryption::Ciphertext,
+///     saver_groth16::{verify_proof, PreparedVerifyingKey, Proof},
+/// };
+///
+/// fn check(
+///     pvk: &PreparedVerifyingKey<Bls12_381>,
+///     proof: &Proof<Bls12_381>,
+///     ct: &Ciphertext<Bls12_381>,
+/// ) {
+///     verify_proof(pvk, proof, ct);
+/// }
+/// ```
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        skip_all,
+        fields(
+            chunks_count = ciphertext.enc_chunks.len(),
+            chunk_bit_size = ?chunk_bit_size_for_chunks_count::<E::ScalarField>(ciphertext.enc_chunks.len())
+        )
+    )
+)]
+#[must_use = "a failed verification is only reported through the returned value"]
 pub fn verify_proof<E: Pairing>(
     pvk: &PreparedVerifyingKey<E>,
     proof: &Proof<E>,
@@ -154,6 +295,180 @@ pub fn verify_proof<E: Pairing>(
     )
 }
 
+/// Result of `verify_proof_detailed`, telling which check failed
+#[derive(Debug)]
+pub enum VerifyOutcome {
+    Valid,
+    /// The Groth16 pairing check failed
+    InvalidProof,
+    /// The ciphertext's commitment is not a commitment to the encrypted chunks
+    CommitmentMismatch,
+    /// The inputs can't be used together, eg. the ciphertext has a different number of chunks than the
+    /// keys support. Contains the error describing the mismatch.
+    MalformedInput(SaverError),
+}
+
+impl VerifyOutcome {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Self::Valid)
+    }
+}
+
+/// Verify the ciphertext's commitment and the proof, in the order of `Ciphertext::verify_commitment_and_proof`,
+/// but return which check failed instead of an error. Errors not caused by the inputs are still returned
+/// as errors.
+#[must_use = "a failed verification is only reported through the returned value"]
+pub fn verify_proof_detailed<E: Pairing>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    ciphertext: &Ciphertext<E>,
+    ek: impl Into<PreparedEncryptionKey<E>>,
+    gens: impl Into<PreparedEncryptionGens<E>>,
+) -> Result<VerifyOutcome, SaverError> {
+    // The verification key has a base for each chunk and one for the constant
+    let inputs = pvk
+        .vk
+        .gamma_abc_g1
+        .len()
+        .checked_sub(1)
+        .ok_or(SaverError::EmptyVerifyingKey)?;
+    if inputs != ciphertext.enc_chunks.len() {
+        return Ok(VerifyOutcome::MalformedInput(
+            SaverError::UnequalSizeOfSequence(inputs, ciphertext.enc_chunks.len()),
+        ));
+    }
+    match ciphertext.verify_commitment_and_snark(ek, gens, || verify_proof(pvk, proof, ciphertext))
+    {
+        Ok(()) => Ok(VerifyOutcome::Valid),
+        Err(SaverError::PairingCheckFailed) => Ok(VerifyOutcome::InvalidProof),
+        Err(SaverError::InvalidCommitment) => Ok(VerifyOutcome::CommitmentMismatch),
+        Err(
+            e @ (SaverError::IncompatibleEncryptionKey(..)
+            | SaverError::IncompatibleCommitmentBases(..)
+            | SaverError::MalformedEncryptionKey(..)
+            | SaverError::MismatchedEncryptionGens),
+        ) => Ok(VerifyOutcome::MalformedInput(e)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Work done by `verify_proof` for a ciphertext with `chunks_count` chunks. The prepared public inputs are
+/// `gamma_abc_g1[0] + X_r + c_1 + .. + c_n` (`calculate_d`), i.e. `chunks_count + 1` additions in G1 with no
+/// scalar multiplications, and the pairing of `alpha` and `beta` is precomputed in the prepared verifying
+/// key, so only a multi-pairing of size 3 is left.
+pub fn verify_proof_cost(chunks_count: usize) -> VerificationCost {
+    VerificationCost {
+        pairings: 3,
+        g1_scalar_mults: 0,
+        g1_additions: chunks_count + 1,
+    }
+}
+
+/// Verify the proof against each of the given verification keys and return the index of the first one
+/// it verifies with or `None` if it verifies with none of them. Useful when the verifier does not know
+/// which SRS the proof was created with. The sum of the ciphertext's elements is computed only once.
+#[must_use = "a failed verification is only reported through the returned value"]
+pub fn verify_proof_any<E: Pairing>(
+    pvks: &[PreparedVerifyingKey<E>],
+    proof: &Proof<E>,
+    ciphertext: &Ciphertext<E>,
+) -> Result<Option<usize>, SaverError> {
+    let mut ct_sum = ciphertext.X_r.into_group();
+    for c in ciphertext.enc_chunks.iter() {
+        ct_sum.add_assign(c.into_group())
+    }
+    for (i, pvk) in pvks.iter().enumerate() {
+        // Verification key for a different number of chunks can't be the one
+        if pvk.vk.gamma_abc_g1.len() != ciphertext.enc_chunks.len() + 1 {
+            continue;
+        }
+        let d = ct_sum + pvk.vk.gamma_abc_g1[0];
+        match verify_qap_proof(pvk, proof.a, proof.b, proof.c, d.into_affine()) {
+            Ok(_) => return Ok(Some(i)),
+            Err(SaverError::PairingCheckFailed) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(None)
+}
+
+/// Everything needed for verifying Groth16 proofs of encryption and decryptions of ciphertexts, prepared once.
+/// `verify_proof` and the functions of `Ciphertext` prepare the verification key, encryption key, decryption
+/// key and generators on each call which a verifier of many ciphertexts can avoid by creating this once. It
+/// is `Send` and `Sync` so it can be shared between threads, eg. in an `Arc`.
+#[derive(Clone, Debug)]
+pub struct VerifierContext<E: Pairing> {
+    pub chunk_bit_size: u8,
+    pub pvk: PreparedVerifyingKey<E>,
+    pub ek: PreparedEncryptionKey<E>,
+    pub dk: PreparedDecryptionKey<E>,
+    pub gens: PreparedEncryptionGens<E>,
+}
+
+impl<E: Pairing> VerifierContext<E> {
+    /// Prepare the given keys and generators. Returns an error if the keys and the verification key are not
+    /// for `chunk_bit_size` or the encryption key was not created with `gens`.
+    pub fn new(
+        chunk_bit_size: u8,
+        snark_vk: &VerifyingKey<E>,
+        ek: EncryptionKey<E>,
+        dk: DecryptionKey<E>,
+        gens: EncryptionGens<E>,
+    ) -> crate::Result<Self> {
+        check_chunk_bit_size(chunk_bit_size)?;
+        let n = chunks_count::<E::ScalarField>(chunk_bit_size) as usize;
+        let ek_n = ek.supported_chunks_count()? as usize;
+        if ek_n != n {
+            return Err(SaverError::IncompatibleEncryptionKey(ek_n, n));
+        }
+        let dk_n = dk.supported_chunks_count()? as usize;
+        if dk_n != n {
+            return Err(SaverError::IncompatibleDecryptionKey(dk_n, n));
+        }
+        let g_i = get_gs_for_encryption(snark_vk);
+        if g_i.len() != n {
+            return Err(SaverError::VectorShorterThanExpected(g_i.len(), n));
+        }
+        ek.check_gens_fingerprint(&gens.fingerprint())?;
+        Ok(Self {
+            chunk_bit_size,
+            pvk: prepare_verifying_key(snark_vk),
+            ek: ek.into(),
+            dk: dk.into(),
+            gens: gens.into(),
+        })
+    }
+
+    /// Generators used for encrypting the chunks, taken from the verification key
+    pub fn g_i(&self) -> &[E::G1Affine] {
+        get_gs_for_encryption(&self.pvk.vk)
+    }
+
+    /// Verify the ciphertext's commitment and the proof of encryption, like `Ciphertext::verify_commitment_and_proof`
+    #[must_use = "a failed verification is only reported through the returned value"]
+    pub fn verify(&self, ciphertext: &Ciphertext<E>, proof: &Proof<E>) -> crate::Result<()> {
+        ciphertext.verify_commitment_and_proof(proof, &self.pvk, self.ek.clone(), self.gens.clone())
+    }
+
+    /// Verify that the ciphertext decrypts to `message` with `nu`, like `Ciphertext::verify_decryption`
+    #[must_use = "a failed verification is only reported through the returned value"]
+    pub fn verify_decryption(
+        &self,
+        ciphertext: &Ciphertext<E>,
+        message: &E::ScalarField,
+        nu: &E::G1Affine,
+    ) -> crate::Result<()> {
+        ciphertext.verify_decryption(
+            message,
+            nu,
+            self.chunk_bit_size,
+            self.dk.clone(),
+            self.g_i(),
+            self.gens.clone(),
+        )
+    }
+}
+
 pub fn calculate_d<E: Pairing>(
     pvk: &PreparedVerifyingKey<E>,
     ciphertext: &Ciphertext<E>,
@@ -162,10 +477,45 @@ pub fn calculate_d<E: Pairing>(
     for c in ciphertext.enc_chunks.iter() {
         d.add_assign(c.into_group())
     }
-    d.add_assign(&pvk.vk.gamma_abc_g1[0]);
+    d.add_assign(
+        pvk.vk
+            .gamma_abc_g1
+            .first()
+            .ok_or(SaverError::EmptyVerifyingKey)?,
+    );
     Ok(d.into_affine())
 }
 
+/// The Groth16 public inputs of the proof for `ciphertext`, prepared as `Groth16::prepare_inputs` does, so
+/// that another Groth16 verifier can verify the proof with `Groth16::verify_proof_with_prepared_inputs`.
+/// The inputs are the chunks of the message `m_i` which the verifier doesn't know, so they can't be given as
+/// field elements. Their combination with the verifying key `gamma_abc_g1[0] + m_1*g_1 + .. + m_n*g_n`
+/// is computed from the ciphertext as `gamma_abc_g1[0] + X_r + c_1 + .. + c_n`, the randomness in `X_r` and
+/// `c_i` being cancelled by the proof's `C`. Same as `calculate_d` but checks that the ciphertext has a
+/// chunk for each input of `vk`.
+pub fn prepared_inputs_from_ciphertext<E: Pairing>(
+    ciphertext: &Ciphertext<E>,
+    vk: &VerifyingKey<E>,
+) -> Result<E::G1, SaverError> {
+    let inputs = vk
+        .gamma_abc_g1
+        .len()
+        .checked_sub(1)
+        .ok_or(SaverError::EmptyVerifyingKey)?;
+    if ciphertext.enc_chunks.len() != inputs {
+        return Err(SaverError::UnequalSizeOfSequence(
+            ciphertext.enc_chunks.len(),
+            inputs,
+        ));
+    }
+    let mut d = vk.gamma_abc_g1[0] + ciphertext.X_r;
+    for c in ciphertext.enc_chunks.iter() {
+        d.add_assign(c.into_group())
+    }
+    Ok(d)
+}
+
+#[must_use = "a failed verification is only reported through the returned value"]
 pub fn verify_qap_proof<E: Pairing>(
     pvk: &PreparedVerifyingKey<E>,
     a: E::G1Affine,
@@ -192,6 +542,7 @@ pub fn verify_qap_proof<E: Pairing>(
     Ok(())
 }
 
+#[must_use = "a failed verification is only reported through the returned value"]
 pub fn verify_aggregate_proof<E: Pairing, R: Rng, T: Transcript>(
     ip_verifier_srs: &VerifierSRS<E>,
     pvk: &PreparedVerifyingKey<E>,
@@ -272,11 +623,173 @@ pub fn verify_aggregate_proof<E: Pairing, R: Rng, T: Transcript>(
     }
 }
 
+/// Number of 32-byte words used for one element of the prime field `F`, eg. 2 for the base field of
+/// BLS12-381 and 1 for that of BN254
+fn evm_words_per_element<F: PrimeField>() -> usize {
+    F::MODULUS.to_bytes_be().len().div_ceil(32)
+}
+
+fn push_evm_field_words<F: Field>(f: &F, out: &mut Vec<[u8; 32]>) {
+    let n = evm_words_per_element::<F::BasePrimeField>();
+    for c in f.to_base_prime_field_elements() {
+        let bytes = c.into_bigint().to_bytes_be();
+        let mut padded = vec![0u8; n * 32];
+        padded[n * 32 - bytes.len()..].copy_from_slice(&bytes);
+        out.extend(padded.chunks_exact(32).map(|w| {
+            let mut word = [0u8; 32];
+            word.copy_from_slice(w);
+            word
+        }));
+    }
+}
+
+fn push_evm_point_words<G: AffineRepr>(p: &G, out: &mut Vec<[u8; 32]>) {
+    match p.xy() {
+        Some((x, y)) => {
+            push_evm_field_words(x, out);
+            push_evm_field_words(y, out);
+        }
+        None => {
+            let n = 2
+                * G::BaseField::extension_degree() as usize
+                * evm_words_per_element::<<G::BaseField as Field>::BasePrimeField>();
+            out.extend(ark_std::iter::repeat_n([0u8; 32], n));
+        }
+    }
+}
+
+/// Read a field element from the front of `words` and advance `words`. Only the canonical encoding,
+/// i.e. the one `push_evm_field_words` produces, is accepted.
+fn read_evm_field_words<F: Field>(words: &mut &[[u8; 32]]) -> Result<F, SaverError> {
+    let n = evm_words_per_element::<F::BasePrimeField>();
+    let mut elems = Vec::with_capacity(F::extension_degree() as usize);
+    for _ in 0..F::extension_degree() {
+        if words.len() < n {
+            return Err(SaverError::InvalidEvmEncoding);
+        }
+        let (elem_words, rest) = words.split_at(n);
+        let bytes = elem_words.iter().flatten().copied().collect::<Vec<_>>();
+        let elem = F::BasePrimeField::from_be_bytes_mod_order(&bytes);
+        let mut encoded = Vec::with_capacity(n);
+        push_evm_field_words(&elem, &mut encoded);
+        if encoded != elem_words {
+            return Err(SaverError::InvalidEvmEncoding);
+        }
+        elems.push(elem);
+        *words = rest;
+    }
+    F::from_base_prime_field_elems(&elems).ok_or(SaverError::InvalidEvmEncoding)
+}
+
+fn read_evm_point_words<P: SWCurveConfig>(
+    words: &mut &[[u8; 32]],
+) -> Result<SWAffine<P>, SaverError> {
+    let x = read_evm_field_words::<P::BaseField>(words)?;
+    let y = read_evm_field_words::<P::BaseField>(words)?;
+    if x.is_zero() && y.is_zero() {
+        return Ok(SWAffine::identity());
+    }
+    let p = SWAffine::new_unchecked(x, y);
+    if !p.is_on_curve() || !p.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(SaverError::InvalidEvmEncoding);
+    }
+    Ok(p)
+}
+
+/// Flatten the verifying key into 32-byte big-endian words for a Solidity or precompile based verifier.
+/// The layout is `alpha_g1, beta_g2, gamma_g2, delta_g2, gamma_abc_g1[0], gamma_abc_g1[1], ...` where
+/// - a base field element takes as many words as needed to hold the modulus, left padded with zeroes, so
+///   2 words (64 bytes) for BLS12-381 as in EIP-2537 and 1 word for BN254,
+/// - a G1 point is `x, y` and a G2 point is `x.c0, x.c1, y.c0, y.c1`. This is the order of EIP-2537,
+///   the precompiles of EIP-197 expect `c1` before `c0`,
+/// - the point at infinity is all zero words.
+///
+/// `gamma_abc_g1[1..]` are the generators used by the encryption key so these are only needed by the
+/// verifier if it checks that the key was created for this verifying key.
+pub fn export_vk_for_evm<E: Pairing>(vk: &VerifyingKey<E>) -> Vec<[u8; 32]> {
+    let mut out = Vec::new();
+    push_evm_point_words(&vk.alpha_g1, &mut out);
+    push_evm_point_words(&vk.beta_g2, &mut out);
+    push_evm_point_words(&vk.gamma_g2, &mut out);
+    push_evm_point_words(&vk.delta_g2, &mut out);
+    for g in vk.gamma_abc_g1.iter() {
+        push_evm_point_words(g, &mut out);
+    }
+    out
+}
+
+/// Flatten the proof into 32-byte big-endian words as `a, b, c` with the encoding of `export_vk_for_evm`
+pub fn export_proof_for_evm<E: Pairing>(proof: &Proof<E>) -> Vec<[u8; 32]> {
+    let mut out = Vec::new();
+    push_evm_point_words(&proof.a, &mut out);
+    push_evm_point_words(&proof.b, &mut out);
+    push_evm_point_words(&proof.c, &mut out);
+    out
+}
+
+/// Flatten the ciphertext into the public inputs of the proof as `X_r, enc_chunks[0], enc_chunks[1], ...`
+/// with the encoding of `export_vk_for_evm`. The commitment isn't part of the proof's statement and is
+/// not included. The verifier computes `d = gamma_abc_g1[0] + X_r + enc_chunks[0] + enc_chunks[1] + ...`,
+/// like `calculate_d`, and checks `e(a, b) == e(alpha_g1, beta_g2) * e(d, gamma_g2) * e(c, delta_g2)`.
+pub fn export_ciphertext_for_evm<E: Pairing>(ciphertext: &Ciphertext<E>) -> Vec<[u8; 32]> {
+    let mut out = Vec::new();
+    push_evm_point_words(&ciphertext.X_r, &mut out);
+    for c in ciphertext.enc_chunks.iter() {
+        push_evm_point_words(c, &mut out);
+    }
+    out
+}
+
+/// Inverse of `export_vk_for_evm`. Points are checked to be on the curve and in the correct subgroup.
+pub fn import_vk_from_evm<E, P1, P2>(words: &[[u8; 32]]) -> Result<VerifyingKey<E>, SaverError>
+where
+    E: Pairing<G1Affine = SWAffine<P1>, G2Affine = SWAffine<P2>>,
+    P1: SWCurveConfig,
+    P2: SWCurveConfig,
+{
+    let mut words = words;
+    let alpha_g1 = read_evm_point_words::<P1>(&mut words)?;
+    let beta_g2 = read_evm_point_words::<P2>(&mut words)?;
+    let gamma_g2 = read_evm_point_words::<P2>(&mut words)?;
+    let delta_g2 = read_evm_point_words::<P2>(&mut words)?;
+    let mut gamma_abc_g1 = Vec::new();
+    while !words.is_empty() {
+        gamma_abc_g1.push(read_evm_point_words::<P1>(&mut words)?);
+    }
+    if gamma_abc_g1.is_empty() {
+        return Err(SaverError::InvalidEvmEncoding);
+    }
+    Ok(VerifyingKey {
+        alpha_g1,
+        beta_g2,
+        gamma_g2,
+        delta_g2,
+        gamma_abc_g1,
+    })
+}
+
+/// Inverse of `export_proof_for_evm`. Points are checked to be on the curve and in the correct subgroup.
+pub fn import_proof_from_evm<E, P1, P2>(words: &[[u8; 32]]) -> Result<Proof<E>, SaverError>
+where
+    E: Pairing<G1Affine = SWAffine<P1>, G2Affine = SWAffine<P2>>,
+    P1: SWCurveConfig,
+    P2: SWCurveConfig,
+{
+    let mut words = words;
+    let a = read_evm_point_words::<P1>(&mut words)?;
+    let b = read_evm_point_words::<P2>(&mut words)?;
+    let c = read_evm_point_words::<P1>(&mut words)?;
+    if !words.is_empty() {
+        return Err(SaverError::InvalidEvmEncoding);
+    }
+    Ok(Proof { a, b, c })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        circuit::BitsizeCheckCircuit,
+        circuit::{BitDecompositionRangeCheck, RangeCheckGadget},
         encryption::{tests::gen_messages, Encryption},
         keygen::keygen,
         setup::setup_for_groth16,
@@ -298,7 +811,7 @@ mod tests {
             let n = chunks_count::<Fr>(chunk_bit_size);
             // Get random numbers that are of chunk_bit_size at most
             let msgs = gen_messages(&mut rng, n as usize, chunk_bit_size);
-            let msgs_as_field_elems = msgs.iter().map(|m| Fr::from(*m as u64)).collect::<Vec<_>>();
+            let msgs_as_field_elems = msgs.iter().map(|m| Fr::from(*m)).collect::<Vec<_>>();
 
             let circuit = BitsizeCheckCircuit::new(chunk_bit_size, Some(n), None, true);
             let snark_srs = generate_srs::<Bls12_381, _, _>(circuit, &gens, &mut rng).unwrap();
@@ -377,6 +890,364 @@ mod tests {
         check(16);
     }
 
+    #[test]
+    fn proof_with_range_check_gadget() {
+        fn check<G: RangeCheckGadget<Fr> + Clone>(chunk_bit_size: u8, range_check: G) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+            let n = chunks_count::<Fr>(chunk_bit_size);
+
+            let circuit = BitsizeCheckCircuit::<Fr, G>::new_with_range_check(
+                chunk_bit_size,
+                Some(n),
+                None,
+                true,
+                range_check.clone(),
+            );
+            let snark_srs = generate_srs::<Bls12_381, _, _>(circuit, &gens, &mut rng).unwrap();
+            let g_i = get_gs_for_encryption(&snark_srs.pk.vk);
+            let (_, ek, _) = keygen(
+                &mut rng,
+                chunk_bit_size,
+                &gens,
+                g_i,
+                &snark_srs.pk.delta_g1,
+                &snark_srs.gamma_g1,
+            )
+            .unwrap();
+
+            let m = Fr::rand(&mut rng);
+            let (ct, r) = Encryption::encrypt(&mut rng, &m, &ek, g_i, chunk_bit_size).unwrap();
+            let msgs_as_field_elems = crate::utils::decompose(&m, chunk_bit_size)
+                .unwrap()
+                .into_iter()
+                .map(Fr::from)
+                .collect::<Vec<_>>();
+            let circuit = BitsizeCheckCircuit::new_with_range_check(
+                chunk_bit_size,
+                Some(n),
+                Some(msgs_as_field_elems),
+                true,
+                range_check,
+            );
+            let proof = create_proof(circuit, &r, &snark_srs, &ek, &mut rng).unwrap();
+            let pvk = prepare_verifying_key::<Bls12_381>(&snark_srs.pk.vk);
+            ct.verify_commitment_and_proof(&proof, &pvk, ek, gens)
+                .unwrap();
+        }
+        check(4, BitDecompositionRangeCheck);
+        check(8, BitDecompositionRangeCheck);
+    }
+
+    #[test]
+    fn proof_from_assignment() {
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+            let (snark_srs, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+            let n = chunks_count::<Fr>(chunk_bit_size) as usize;
+            let msgs = gen_messages(&mut rng, n, chunk_bit_size);
+            let msgs_as_field_elems = msgs.iter().map(|m| Fr::from(*m)).collect::<Vec<_>>();
+
+            let g_i = get_gs_for_encryption(&snark_srs.pk.vk);
+            let (ct, r) = Encryption::encrypt_decomposed_message(&mut rng, msgs, &ek, g_i).unwrap();
+            let ct = Ciphertext {
+                X_r: ct[0],
+                enc_chunks: ct[1..n + 1].to_vec(),
+                commitment: ct[n + 1],
+            };
+            let pvk = prepare_verifying_key::<Bls12_381>(&snark_srs.pk.vk);
+
+            // Both paths produce the same proof when given the same randomness
+            let mut rng_1 = rng.clone();
+            let circuit = BitsizeCheckCircuit::new(
+                chunk_bit_size,
+                None,
+                Some(msgs_as_field_elems.clone()),
+                true,
+            );
+            let proof_1 = create_proof(circuit, &r, &snark_srs, &ek, &mut rng_1).unwrap();
+            let proof_2 =
+                create_proof_from_assignment(&msgs_as_field_elems, &r, &snark_srs, &ek, &mut rng)
+                    .unwrap();
+            assert_eq!(proof_1, proof_2);
+            verify_proof(&pvk, &proof_1, &ct).unwrap();
+            verify_proof(&pvk, &proof_2, &ct).unwrap();
+
+            // A chunk bigger than allowed by the chunk bit size is reported before proving
+            let mut oversized = msgs_as_field_elems.clone();
+            oversized[3] = Fr::from(1u64 << chunk_bit_size);
+            oversized[5] = Fr::from(1u64 << chunk_bit_size);
+            let max = (1u64 << chunk_bit_size) - 1;
+            match create_proof_from_assignment(&oversized, &r, &snark_srs, &ek, &mut rng) {
+                Err(SaverError::ChunkOutOfRange {
+                    index: 3,
+                    value,
+                    max: m,
+                }) => {
+                    assert_eq!(value, max + 1);
+                    assert_eq!(m, max);
+                }
+                _ => panic!("expected ChunkOutOfRange for chunk 3"),
+            }
+            let circuit = BitsizeCheckCircuit::new(chunk_bit_size, None, Some(oversized), true);
+            assert!(matches!(
+                create_proof(circuit, &r, &snark_srs, &ek, &mut rng),
+                Err(SaverError::ChunkOutOfRange { index: 3, .. })
+            ));
+
+            // Number of chunks must match the proving key
+            assert!(create_proof_from_assignment(
+                &msgs_as_field_elems[1..],
+                &r,
+                &snark_srs,
+                &ek,
+                &mut rng
+            )
+            .is_err());
+        }
+        check(4);
+        check(8);
+    }
+
+    #[test]
+    fn verify_proof_against_multiple_vks() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let setups = (0..3)
+            .map(|_| setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap())
+            .collect::<Vec<_>>();
+        let pvks = setups
+            .iter()
+            .map(|(snark_pk, _, _, _)| prepare_verifying_key::<Bls12_381>(&snark_pk.pk.vk))
+            .collect::<Vec<_>>();
+
+        // Encrypt and prove using the 2nd setup
+        let (snark_pk, _, ek, _) = &setups[1];
+        let msg = Fr::rand(&mut rng);
+        let (ct, _, proof) =
+            Encryption::encrypt_with_proof(&mut rng, &msg, ek, snark_pk, chunk_bit_size).unwrap();
+
+        assert_eq!(verify_proof_any(&pvks, &proof, &ct).unwrap(), Some(1));
+        assert_eq!(
+            verify_proof_any(&[pvks[2].clone(), pvks[1].clone()], &proof, &ct).unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            verify_proof_any(&[pvks[0].clone(), pvks[2].clone()], &proof, &ct).unwrap(),
+            None
+        );
+        assert_eq!(verify_proof_any(&[], &proof, &ct).unwrap(), None);
+
+        // Verification key for a different chunk bit size is skipped
+        let (snark_pk_4, _, _, _) = setup_for_groth16(&mut rng, 4, &gens).unwrap();
+        let pvk_4 = prepare_verifying_key::<Bls12_381>(&snark_pk_4.pk.vk);
+        assert_eq!(
+            verify_proof_any(&[pvk_4, pvks[1].clone()], &proof, &ct).unwrap(),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn reused_prepared_vk() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        let snark_pk = CachedProvingKey::new(snark_pk);
+        let pvk = snark_pk.prepared_vk();
+        assert_eq!(*pvk, prepare_verifying_key(&snark_pk.pk.vk));
+        // Prepared only once
+        assert!(core::ptr::eq(pvk, snark_pk.prepared_vk()));
+
+        let mut cts = vec![];
+        let mut proofs = vec![];
+        for _ in 0..3 {
+            let msg = Fr::rand(&mut rng);
+            let (ct, _, proof) =
+                Encryption::encrypt_with_proof(&mut rng, &msg, &ek, &snark_pk, chunk_bit_size)
+                    .unwrap();
+            cts.push(ct);
+            proofs.push(proof);
+        }
+        // The last ciphertext gets the proof of another
+        proofs[2] = proofs[0].clone();
+        for (ct, proof) in cts.iter().zip(proofs.iter()) {
+            let fresh = prepare_verifying_key(&snark_pk.pk.vk);
+            assert_eq!(
+                verify_proof(pvk, proof, ct).is_ok(),
+                verify_proof(&fresh, proof, ct).is_ok()
+            );
+        }
+        assert!(verify_proof(pvk, &proofs[0], &cts[0]).is_ok());
+        assert!(verify_proof(pvk, &proofs[2], &cts[2]).is_err());
+    }
+
+    #[test]
+    fn verify_proof_detailed_outcomes() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        let pvk = prepare_verifying_key::<Bls12_381>(&snark_pk.pk.vk);
+
+        let msg = Fr::rand(&mut rng);
+        let (ct, _, proof) =
+            Encryption::encrypt_with_proof(&mut rng, &msg, &ek, &snark_pk, chunk_bit_size).unwrap();
+        let outcome = verify_proof_detailed(&pvk, &proof, &ct, ek.clone(), gens.clone()).unwrap();
+        assert!(outcome.is_valid());
+
+        // Proof for another ciphertext
+        let (_, _, other_proof) =
+            Encryption::encrypt_with_proof(&mut rng, &msg, &ek, &snark_pk, chunk_bit_size).unwrap();
+        assert!(matches!(
+            verify_proof_detailed(&pvk, &other_proof, &ct, ek.clone(), gens.clone()).unwrap(),
+            VerifyOutcome::InvalidProof
+        ));
+
+        // Commitment not matching the encrypted chunks
+        let mut bad_ct = ct.clone();
+        bad_ct.commitment = (bad_ct.commitment + gens.G).into_affine();
+        assert!(matches!(
+            verify_proof_detailed(&pvk, &proof, &bad_ct, ek.clone(), gens.clone()).unwrap(),
+            VerifyOutcome::CommitmentMismatch
+        ));
+
+        // Ciphertext with a chunk missing
+        let mut short_ct = ct.clone();
+        short_ct.enc_chunks.pop();
+        assert!(matches!(
+            verify_proof_detailed(&pvk, &proof, &short_ct, ek.clone(), gens.clone()).unwrap(),
+            VerifyOutcome::MalformedInput(SaverError::UnequalSizeOfSequence(_, _))
+        ));
+
+        // Encryption key from a setup with different generators
+        let other_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (_, _, other_ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &other_gens).unwrap();
+        assert!(matches!(
+            verify_proof_detailed(&pvk, &proof, &ct, other_ek, gens.clone()).unwrap(),
+            VerifyOutcome::MalformedInput(_)
+        ));
+
+        // Verifying key without any public input base
+        let mut empty_vk = snark_pk.pk.vk.clone();
+        empty_vk.gamma_abc_g1.clear();
+        assert!(matches!(
+            verify_proof_detailed(&prepare_verifying_key(&empty_vk), &proof, &ct, ek, gens),
+            Err(SaverError::EmptyVerifyingKey)
+        ));
+    }
+
+    #[test]
+    fn prepared_inputs_for_groth16_verifier() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 16;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        let vk = &snark_pk.pk.vk;
+        let pvk = prepare_verifying_key(vk);
+
+        let msg = Fr::rand(&mut rng);
+        let (ct, _, proof) =
+            Encryption::encrypt_with_proof(&mut rng, &msg, &ek, &snark_pk, chunk_bit_size).unwrap();
+        let inputs = prepared_inputs_from_ciphertext(&ct, vk).unwrap();
+        assert_eq!(inputs.into_affine(), calculate_d(&pvk, &ct).unwrap());
+        assert!(
+            Groth16::<Bls12_381>::verify_proof_with_prepared_inputs(&pvk, &proof, &inputs).unwrap()
+        );
+
+        // Tampered ciphertext
+        let mut bad_ct = ct.clone();
+        bad_ct.enc_chunks[0] = (bad_ct.enc_chunks[0] + gens.G).into_affine();
+        let inputs = prepared_inputs_from_ciphertext(&bad_ct, vk).unwrap();
+        assert!(
+            !Groth16::<Bls12_381>::verify_proof_with_prepared_inputs(&pvk, &proof, &inputs)
+                .unwrap()
+        );
+
+        let mut short_ct = ct.clone();
+        short_ct.enc_chunks.pop();
+        assert!(matches!(
+            prepared_inputs_from_ciphertext(&short_ct, vk),
+            Err(SaverError::UnequalSizeOfSequence(15, 16))
+        ));
+
+        // Verifying key without any public input base, eg. a deserialized one
+        let mut empty_vk = vk.clone();
+        empty_vk.gamma_abc_g1.clear();
+        assert!(matches!(
+            prepared_inputs_from_ciphertext(&ct, &empty_vk),
+            Err(SaverError::EmptyVerifyingKey)
+        ));
+        assert!(matches!(
+            calculate_d(&prepare_verifying_key(&empty_vk), &ct),
+            Err(SaverError::EmptyVerifyingKey)
+        ));
+    }
+
+    #[test]
+    fn evm_export() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 16;
+        let n = chunks_count::<Fr>(chunk_bit_size) as usize;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, _, ek, _) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        let vk = &snark_pk.pk.vk;
+
+        let msg = Fr::rand(&mut rng);
+        let (ct, _, proof) =
+            Encryption::encrypt_with_proof(&mut rng, &msg, &ek, &snark_pk, chunk_bit_size).unwrap();
+
+        // A G1 point takes 4 words and a G2 point 8 words on BLS12-381
+        let vk_words = export_vk_for_evm(vk);
+        assert_eq!(vk_words.len(), 4 + 3 * 8 + 4 * (n + 1));
+        let proof_words = export_proof_for_evm(&proof);
+        assert_eq!(proof_words.len(), 4 + 8 + 4);
+        let ct_words = export_ciphertext_for_evm(&ct);
+        assert_eq!(ct_words.len(), 4 * (n + 1));
+
+        // The 48-byte coordinates leave the top 16 bytes of their first word empty
+        assert!(vk_words.iter().step_by(2).all(|w| w[..16] == [0; 16]));
+        assert_eq!(
+            vk_words[0][16..],
+            vk.alpha_g1.x.into_bigint().to_bytes_be()[..16]
+        );
+
+        let imported_vk = import_vk_from_evm::<Bls12_381, _, _>(&vk_words).unwrap();
+        assert_eq!(&imported_vk, vk);
+        let imported_proof = import_proof_from_evm::<Bls12_381, _, _>(&proof_words).unwrap();
+        assert_eq!(imported_proof, proof);
+        verify_proof(&prepare_verifying_key(&imported_vk), &imported_proof, &ct).unwrap();
+
+        // The ciphertext words are the points summed by the verifier
+        let mut words = ct_words.as_slice();
+        let mut d = vk.gamma_abc_g1[0].into_group();
+        while !words.is_empty() {
+            d += read_evm_point_words::<ark_bls12_381::g1::Config>(&mut words).unwrap();
+        }
+        assert_eq!(
+            d.into_affine(),
+            calculate_d(&prepare_verifying_key(vk), &ct).unwrap()
+        );
+
+        // Truncated, extended and non-canonical words are rejected
+        assert!(matches!(
+            import_proof_from_evm::<Bls12_381, _, _>(&proof_words[1..]),
+            Err(SaverError::InvalidEvmEncoding)
+        ));
+        let mut extended = proof_words.clone();
+        extended.push([0; 32]);
+        assert!(import_proof_from_evm::<Bls12_381, _, _>(&extended).is_err());
+        let mut non_canonical = proof_words.clone();
+        non_canonical[0] = [0xff; 32];
+        assert!(import_proof_from_evm::<Bls12_381, _, _>(&non_canonical).is_err());
+        let mut off_curve = proof_words;
+        off_curve[3][31] ^= 1;
+        assert!(import_proof_from_evm::<Bls12_381, _, _>(&off_curve).is_err());
+        assert!(import_vk_from_evm::<Bls12_381, _, _>(&vk_words[..28]).is_err());
+    }
+
     #[test]
     fn rerandomize_encryption() {
         fn check(chunk_bit_size: u8) {
@@ -536,4 +1407,83 @@ mod tests {
         )
         .expect("error in verification");
     }
+
+    #[test]
+    fn verifier_context_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<VerifierContext<Bls12_381>>();
+
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, sk, ek, dk) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+
+        let ctx = std::sync::Arc::new(
+            VerifierContext::new(
+                chunk_bit_size,
+                &snark_pk.pk.vk,
+                ek.clone(),
+                dk.clone(),
+                gens.clone(),
+            )
+            .unwrap(),
+        );
+
+        let items = (0..4)
+            .map(|_| {
+                let m = Fr::rand(&mut rng);
+                let (ct, _, proof) =
+                    Encryption::encrypt_with_proof(&mut rng, &m, &ek, &snark_pk, chunk_bit_size)
+                        .unwrap();
+                let (m_, nu) = ct
+                    .decrypt_given_groth16_vk(&sk, dk.clone(), &snark_pk.pk.vk, chunk_bit_size)
+                    .unwrap();
+                assert_eq!(m_, m);
+                (ct, proof, m, nu)
+            })
+            .collect::<Vec<_>>();
+
+        let handles = items
+            .into_iter()
+            .enumerate()
+            .map(|(i, (ct, proof, m, nu))| {
+                let ctx = ctx.clone();
+                std::thread::spawn(move || {
+                    ctx.verify(&ct, &proof).unwrap();
+                    ctx.verify_decryption(&ct, &m, &nu).unwrap();
+                    // Wrong message
+                    assert!(ctx
+                        .verify_decryption(&ct, &(m + Fr::from(i as u64 + 1)), &nu)
+                        .is_err());
+                    // Proof for another ciphertext
+                    let mut other_ct = ct.clone();
+                    other_ct.X_r = (other_ct.X_r + ctx.ek.X_0).into_affine();
+                    assert!(ctx.verify(&other_ct, &proof).is_err());
+                })
+            })
+            .collect::<Vec<_>>();
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        // Keys for a different chunk bit size
+        assert!(matches!(
+            VerifierContext::new(4, &snark_pk.pk.vk, ek.clone(), dk.clone(), gens.clone()),
+            Err(SaverError::IncompatibleEncryptionKey(32, 64))
+        ));
+        // Encryption key created with other generators
+        let other_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let mut ek_with_fingerprint = ek;
+        ek_with_fingerprint.gens_fingerprint = Some(gens.fingerprint());
+        assert!(matches!(
+            VerifierContext::new(
+                chunk_bit_size,
+                &snark_pk.pk.vk,
+                ek_with_fingerprint,
+                dk,
+                other_gens
+            ),
+            Err(SaverError::MismatchedEncryptionGens)
+        ));
+    }
 }
@@ -108,6 +108,7 @@ mod protocol_1 {
     }
 
     #[allow(dead_code)]
+    #[must_use = "a failed verification is only reported through the returned value"]
     pub fn verify_proof<E: Pairing>(
         pvk: &PreparedVerifyingKey<E>,
         proof: &Proof<E>,
@@ -166,6 +167,7 @@ mod protocol_2 {
     }
 
     #[allow(dead_code)]
+    #[must_use = "a failed verification is only reported through the returned value"]
     pub fn verify_proof<E: Pairing>(
         pvk: &PreparedVerifyingKey<E>,
         proof: &Proof<E>,
@@ -210,7 +212,7 @@ mod tests {
 
             // Get random numbers that are of chunk_bit_size at most
             let msgs = gen_messages(&mut rng, n as usize, chunk_bit_size);
-            let msgs_as_field_elems = msgs.iter().map(|m| Fr::from(*m as u64)).collect::<Vec<_>>();
+            let msgs_as_field_elems = msgs.iter().map(|m| Fr::from(*m)).collect::<Vec<_>>();
 
             let circuit = BitsizeCheckCircuit::new(chunk_bit_size, Some(n), None, false);
             let snark_srs = generate_srs::<Bls12_381, _, _>(circuit, &gens, n, &mut rng).unwrap();
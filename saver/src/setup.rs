@@ -1,11 +1,15 @@
 use crate::{
     circuit::BitsizeCheckCircuit,
+    error::SaverError,
     keygen::{keygen, DecryptionKey, EncryptionKey, SecretKey},
     saver_groth16,
+    utils::{check_chunk_bit_size, chunks_count},
 };
 use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::{PrimeField, Zero};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{rand::RngCore, vec::Vec, UniformRand};
+use ark_std::{ops::Neg, rand::RngCore, vec::Vec, UniformRand};
+use blake2::Blake2s256;
 use digest::Digest;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
@@ -15,6 +19,10 @@ use dock_crypto_utils::{
 };
 
 /// Create "G" and "H" from the paper.
+///
+/// The Groth16 SRS must be created with these generators as `saver_groth16::generate_srs` does, i.e. `G`
+/// and `H` are the generators of its groups. The SRS doesn't contain `G` and `H` so they can't be taken from
+/// it, but `Self::check_matches_srs` checks that given generators are the SRS's.
 #[serde_as]
 #[derive(
     Clone, PartialEq, Eq, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize,
@@ -36,6 +44,8 @@ pub struct PreparedEncryptionGens<E: Pairing> {
     pub G: E::G1Prepared,
     #[serde_as(as = "ArkObjectBytes")]
     pub H: E::G2Prepared,
+    /// Fingerprint of the generators before they were prepared. See `EncryptionGens::fingerprint`
+    pub fingerprint: [u8; 32],
 }
 
 impl<E: Pairing> EncryptionGens<E> {
@@ -52,13 +62,50 @@ impl<E: Pairing> EncryptionGens<E> {
         let H = E::G2Affine::rand(rng);
         Self { G, H }
     }
+
+    /// Check that the SRS was created with these generators, as ciphertexts, keys and proofs created with
+    /// mismatched generators don't verify. The SRS has `delta` and `gamma` in both groups so this checks
+    /// `e(delta*G, H) = e(G, delta*H)` and `e(-gamma*G, H) = e(-G, gamma*H)`. Returns
+    /// `SaverError::MismatchedEncryptionGens` if they don't hold.
+    pub fn check_matches_srs(&self, snark_pk: &saver_groth16::ProvingKey<E>) -> crate::Result<()> {
+        let neg_g = self.G.into_group().neg().into_affine();
+        let delta = E::multi_pairing(
+            [snark_pk.pk.delta_g1, neg_g],
+            [self.H, snark_pk.pk.vk.delta_g2],
+        );
+        let gamma = E::multi_pairing(
+            [snark_pk.gamma_g1, self.G],
+            [self.H, snark_pk.pk.vk.gamma_g2],
+        );
+        if delta.is_zero() && gamma.is_zero() {
+            Ok(())
+        } else {
+            Err(SaverError::MismatchedEncryptionGens)
+        }
+    }
+
+    /// A short digest of the generators. The encryption key created with these generators stores it so that
+    /// verifying with different generators fails with `SaverError::MismatchedEncryptionGens` rather than with
+    /// a failed pairing check.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut bytes = Vec::new();
+        self.G.serialize_compressed(&mut bytes).unwrap();
+        self.H.serialize_compressed(&mut bytes).unwrap();
+        Blake2s256::new()
+            .chain_update(b"SAVER encryption generators")
+            .chain_update(&bytes)
+            .finalize()
+            .into()
+    }
 }
 
 impl<E: Pairing> From<EncryptionGens<E>> for PreparedEncryptionGens<E> {
     fn from(ek: EncryptionGens<E>) -> Self {
+        let fingerprint = ek.fingerprint();
         Self {
             G: E::G1Prepared::from(ek.G),
             H: E::G2Prepared::from(ek.H),
+            fingerprint,
         }
     }
 }
@@ -117,10 +164,179 @@ pub fn setup_for_groth16<E: Pairing, R: RngCore>(
     Ok((proving_key, sk, ek, dk))
 }
 
+/// Everything needed to use SAVER with Groth16 in one place: the generators for encryption and for the
+/// chunked commitment, the SNARK proving key and the secret, encryption and decryption keys created for it.
+///
+/// Deserializing with validation runs `Self::validate` so a deserialized setup is consistent.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct SaverSetup<E: Pairing> {
+    pub chunk_bit_size: u8,
+    pub enc_gens: EncryptionGens<E>,
+    pub comm_gens: ChunkedCommitmentGens<E::G1Affine>,
+    pub snark_pk: saver_groth16::ProvingKey<E>,
+    pub sk: SecretKey<E::ScalarField>,
+    pub ek: EncryptionKey<E>,
+    pub dk: DecryptionKey<E>,
+}
+
+impl<E: Pairing> SaverSetup<E> {
+    /// Create random generators and then do `setup_for_groth16` with them
+    pub fn new_for_groth16<R: RngCore>(rng: &mut R, chunk_bit_size: u8) -> crate::Result<Self> {
+        let enc_gens = EncryptionGens::new_using_rng(rng);
+        let comm_gens = ChunkedCommitmentGens::new_using_rng(rng);
+        Self::new_for_groth16_given_gens(rng, chunk_bit_size, enc_gens, comm_gens)
+    }
+
+    /// Do `setup_for_groth16` with the given generators
+    pub fn new_for_groth16_given_gens<R: RngCore>(
+        rng: &mut R,
+        chunk_bit_size: u8,
+        enc_gens: EncryptionGens<E>,
+        comm_gens: ChunkedCommitmentGens<E::G1Affine>,
+    ) -> crate::Result<Self> {
+        let (snark_pk, sk, ek, dk) = setup_for_groth16(rng, chunk_bit_size, &enc_gens)?;
+        let setup = Self {
+            chunk_bit_size,
+            enc_gens,
+            comm_gens,
+            snark_pk,
+            sk,
+            ek,
+            dk,
+        };
+        setup.validate()?;
+        Ok(setup)
+    }
+
+    /// Check that the keys are of the expected size for the chunk bit size and were created for each other,
+    /// the SNARK SRS and the generators. Returns `SaverError::InconsistentSetup` if they weren't.
+    #[must_use = "invalid parameters are only reported through the returned value"]
+    pub fn validate(&self) -> crate::Result<()> {
+        check_chunk_bit_size(self.chunk_bit_size)?;
+        let n = chunks_count::<E::ScalarField>(self.chunk_bit_size) as usize;
+        let ek_n = self.ek.supported_chunks_count()? as usize;
+        if ek_n != n {
+            return Err(SaverError::IncompatibleEncryptionKey(ek_n, n));
+        }
+        let dk_n = self.dk.supported_chunks_count()? as usize;
+        if dk_n != n {
+            return Err(SaverError::IncompatibleDecryptionKey(dk_n, n));
+        }
+        let g_i = saver_groth16::get_gs_for_encryption(&self.snark_pk.pk.vk);
+        if g_i.len() != n {
+            return Err(SaverError::VectorShorterThanExpected(g_i.len(), n));
+        }
+        self.ek
+            .check_gens_fingerprint(&self.enc_gens.fingerprint())?;
+
+        // X_0 = G*delta and V_0 = H*rho
+        if self.ek.X_0 != self.snark_pk.pk.delta_g1
+            || self.dk.V_0
+                != self
+                    .enc_gens
+                    .H
+                    .mul_bigint(self.sk.0.into_bigint())
+                    .into_affine()
+        {
+            return Err(SaverError::InconsistentSetup);
+        }
+
+        let rho_x_0 = self
+            .ek
+            .X_0
+            .mul_bigint(self.sk.0.into_bigint())
+            .into_affine();
+        for (i, g) in g_i.iter().enumerate() {
+            // e(Y_i, H) = e(G_i, Z_{i+1}) as Y_i = G_i * t_{i+1} and Z_{i+1} = H * t_{i+1}
+            let ek_check = E::multi_pairing(
+                [self.ek.Y[i], g.into_group().neg().into_affine()],
+                [self.enc_gens.H, self.ek.Z[i + 1]],
+            );
+            // e(X_i, V_2_i) = e(X_0 * rho, V_1_i) as X_i = G*delta*s_i, V_1_i = H*s_i*v_i and V_2_i = H*rho*v_i
+            let dk_check = E::multi_pairing(
+                [self.ek.X[i], rho_x_0.into_group().neg().into_affine()],
+                [self.dk.V_2[i], self.dk.V_1[i]],
+            );
+            if !ek_check.is_zero() || !dk_check.is_zero() {
+                return Err(SaverError::InconsistentSetup);
+            }
+        }
+        Ok(())
+    }
+}
+
+mod serialization {
+    use super::*;
+    use ark_serialize::{Compress, Read, SerializationError, Valid, Validate};
+
+    impl<E: Pairing> Valid for SaverSetup<E> {
+        fn check(&self) -> Result<(), SerializationError> {
+            self.enc_gens.check()?;
+            self.comm_gens.check()?;
+            self.snark_pk.check()?;
+            self.sk.check()?;
+            self.ek.check()?;
+            self.dk.check()?;
+            self.validate().map_err(|_| SerializationError::InvalidData)
+        }
+    }
+
+    impl<E: Pairing> CanonicalDeserialize for SaverSetup<E> {
+        fn deserialize_with_mode<R: Read>(
+            mut reader: R,
+            compress: Compress,
+            validate: Validate,
+        ) -> Result<Self, SerializationError> {
+            let setup = Self {
+                chunk_bit_size: CanonicalDeserialize::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    Validate::No,
+                )?,
+                enc_gens: CanonicalDeserialize::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    Validate::No,
+                )?,
+                comm_gens: CanonicalDeserialize::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    Validate::No,
+                )?,
+                snark_pk: CanonicalDeserialize::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    Validate::No,
+                )?,
+                sk: CanonicalDeserialize::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    Validate::No,
+                )?,
+                ek: CanonicalDeserialize::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    Validate::No,
+                )?,
+                dk: CanonicalDeserialize::deserialize_with_mode(
+                    &mut reader,
+                    compress,
+                    Validate::No,
+                )?,
+            };
+            if let Validate::Yes = validate {
+                setup.check()?;
+            }
+            Ok(setup)
+        }
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
-    use crate::{saver_groth16::ProvingKey, test_serialization};
+    use crate::saver_groth16::ProvingKey;
     use ark_bls12_381::Bls12_381;
     use ark_std::rand::{prelude::StdRng, SeedableRng};
     use blake2::Blake2b512;
@@ -188,4 +404,212 @@ pub(crate) mod tests {
         check(8);
         check(16);
     }
+
+    #[test]
+    fn gens_match_srs() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 16;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let other_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, sk, ek, dk) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        gens.check_matches_srs(&snark_pk).unwrap();
+        assert!(matches!(
+            other_gens.check_matches_srs(&snark_pk),
+            Err(SaverError::MismatchedEncryptionGens)
+        ));
+        // Only one of the generators differs
+        let half = EncryptionGens {
+            G: gens.G,
+            H: other_gens.H,
+        };
+        assert!(half.check_matches_srs(&snark_pk).is_err());
+
+        let msg = Fr::rand(&mut rng);
+        let (ct, _, _) = crate::encryption::Encryption::encrypt_with_proof(
+            &mut rng,
+            &msg,
+            &ek,
+            &snark_pk,
+            chunk_bit_size,
+        )
+        .unwrap();
+        let g_i = saver_groth16::get_gs_for_encryption(&snark_pk.pk.vk);
+        let (m, nu) = ct.decrypt(&sk, dk.clone(), g_i, chunk_bit_size).unwrap();
+        assert_eq!(m, msg);
+        ct.verify_decryption_given_groth16_vk(
+            &m,
+            &nu,
+            chunk_bit_size,
+            dk.clone(),
+            &snark_pk.pk.vk,
+            gens.clone(),
+        )
+        .unwrap();
+        assert!(ct
+            .verify_decryption_given_groth16_vk(
+                &m,
+                &nu,
+                chunk_bit_size,
+                dk,
+                &snark_pk.pk.vk,
+                other_gens.clone()
+            )
+            .is_err());
+        ct.verify_commitment(ek.clone(), gens).unwrap();
+        assert!(ct.verify_commitment(ek, other_gens).is_err());
+    }
+
+    #[test]
+    fn setup_with_shared_generators() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let enc_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        // SRS for the smallest chunk size has the most bases so they can be used for larger chunk sizes as well
+        let (snark_pk, _, _, _) = setup_for_groth16(&mut rng, 4, &enc_gens).unwrap();
+        let g_i = saver_groth16::get_gs_for_encryption(&snark_pk.pk.vk);
+
+        let mut eks = vec![];
+        for chunk_bit_size in [4, 8] {
+            let chunk_count = crate::utils::chunks_count::<Fr>(chunk_bit_size) as usize;
+            let (sk, ek, dk) = keygen(
+                &mut rng,
+                chunk_bit_size,
+                &enc_gens,
+                g_i,
+                &snark_pk.pk.delta_g1,
+                &snark_pk.gamma_g1,
+            )
+            .unwrap();
+            ek.validate().unwrap();
+            dk.validate().unwrap();
+            assert_eq!(ek.supported_chunks_count().unwrap(), chunk_count as u8);
+            assert_eq!(ek.X_0, snark_pk.pk.delta_g1);
+
+            let m = Fr::rand(&mut rng);
+            let (ct, _) =
+                crate::encryption::Encryption::encrypt(&mut rng, &m, &ek, g_i, chunk_bit_size)
+                    .unwrap();
+            let (m_, _) = ct.decrypt(&sk, dk, g_i, chunk_bit_size).unwrap();
+            assert_eq!(m_, m);
+            eks.push(ek);
+        }
+        assert_ne!(eks[0], eks[1]);
+
+        // Not enough bases for a smaller chunk size
+        let (snark_pk_8, _, _, _) = setup_for_groth16(&mut rng, 8, &enc_gens).unwrap();
+        assert!(keygen(
+            &mut rng,
+            4,
+            &enc_gens,
+            saver_groth16::get_gs_for_encryption(&snark_pk_8.pk.vk),
+            &snark_pk_8.pk.delta_g1,
+            &snark_pk_8.gamma_g1,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn saver_setup_round_trip() {
+        use crate::{
+            commitment::ChunkedCommitment, encryption::Encryption,
+            saver_groth16::prepare_verifying_key,
+        };
+
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let setup = SaverSetup::<Bls12_381>::new_for_groth16(&mut rng, chunk_bit_size).unwrap();
+            test_serialization!(SaverSetup<Bls12_381>, setup);
+
+            let msg = Fr::rand(&mut rng);
+            let (ct, _, proof) = Encryption::encrypt_with_proof(
+                &mut rng,
+                &msg,
+                &setup.ek,
+                &setup.snark_pk,
+                chunk_bit_size,
+            )
+            .unwrap();
+            let pvk = prepare_verifying_key::<Bls12_381>(&setup.snark_pk.pk.vk);
+            ct.verify_commitment_and_proof(&proof, &pvk, setup.ek.clone(), setup.enc_gens.clone())
+                .unwrap();
+
+            let blinding = Fr::rand(&mut rng);
+            let chunked_comm = ChunkedCommitment::<<Bls12_381 as Pairing>::G1Affine>::new(
+                &msg,
+                &blinding,
+                chunk_bit_size,
+                &setup.comm_gens,
+            )
+            .unwrap();
+            let (m, nu) = ct
+                .decrypt_and_check_commitment(
+                    &setup.sk,
+                    setup.dk.clone(),
+                    &setup.snark_pk.pk.vk,
+                    chunk_bit_size,
+                    &chunked_comm,
+                    &blinding,
+                )
+                .unwrap();
+            assert_eq!(m, msg);
+            ct.verify_decryption_given_groth16_vk(
+                &m,
+                &nu,
+                chunk_bit_size,
+                setup.dk.clone(),
+                &setup.snark_pk.pk.vk,
+                setup.enc_gens.clone(),
+            )
+            .unwrap();
+
+            // Keys from another setup are detected
+            let other = SaverSetup::<Bls12_381>::new_for_groth16_given_gens(
+                &mut rng,
+                chunk_bit_size,
+                setup.enc_gens.clone(),
+                setup.comm_gens.clone(),
+            )
+            .unwrap();
+            let mut mixed = setup.clone();
+            mixed.ek = other.ek.clone();
+            assert!(matches!(
+                mixed.validate(),
+                Err(SaverError::InconsistentSetup)
+            ));
+            let mut mixed = setup.clone();
+            mixed.dk = other.dk.clone();
+            assert!(matches!(
+                mixed.validate(),
+                Err(SaverError::InconsistentSetup)
+            ));
+            let mut mixed = setup.clone();
+            mixed.sk = other.sk.clone();
+            assert!(matches!(
+                mixed.validate(),
+                Err(SaverError::InconsistentSetup)
+            ));
+            let mut mixed = setup.clone();
+            mixed.snark_pk = other.snark_pk.clone();
+            assert!(matches!(
+                mixed.validate(),
+                Err(SaverError::InconsistentSetup)
+            ));
+            let mut mixed = setup.clone();
+            mixed.chunk_bit_size = 16;
+            assert!(mixed.validate().is_err());
+
+            // A setup with invalid chunk bit size is an error and not a panic, also when deserializing
+            let mut mixed = setup.clone();
+            mixed.chunk_bit_size = 0;
+            assert!(mixed.validate().is_err());
+            let mut bytes = vec![];
+            mixed.serialize_compressed(&mut bytes).unwrap();
+            assert!(SaverSetup::<Bls12_381>::deserialize_compressed(&bytes[..]).is_err());
+            assert_eq!(
+                SaverSetup::<Bls12_381>::deserialize_compressed_unchecked(&bytes[..]).unwrap(),
+                mixed
+            );
+        }
+
+        check(8);
+    }
 }
@@ -0,0 +1,710 @@
+//! Proving correct encryption with sigma protocols only.
+//!
+//! The encryptor usually proves with Groth16 that each chunk has at most `b` bits (`chunk_bit_size`), which
+//! is too expensive for some clients. [`encrypt_with_sigma_proof`] instead creates a [`SigmaEncryptionProof`]
+//! which is a lot larger but needs no SNARK proving key. For the randomness `r` and chunks `m_1, .., m_n`,
+//! the ciphertext is
+//!
+//! ```text
+//! c_0 = r*X_0, c_i = r*X_i + m_i*g_i for each chunk i and psi = m_1*Y_1 + m_2*Y_2 + ... + m_n*Y_n + r*P_1
+//! ```
+//!
+//! Each chunk is decomposed in bits `m_i = m_{i,0} + 2*m_{i,1} + .. + 2^{b-1}*m_{i,b-1}` and each bit is
+//! committed as `C_{i,j} = m_{i,j}*G + s_{i,j}*H` using the generators of the chunked commitment. An OR
+//! proof shows that each `C_{i,j}` commits to 0 or 1 so `D_i = C_{i,0} + 2*C_{i,1} + .. + 2^{b-1}*C_{i,b-1}`
+//! commits to a chunk of at most `b` bits. A Schnorr protocol then proves knowledge of `r`, `m_i` and `s_i`
+//! such that the equations above hold and `D_i = m_i*G + s_i*H`, thus binding the ciphertext to chunks in
+//! range. The proof has `n*b` bit commitments and OR proofs which is about the bit size of the field
+//! whatever the chunk bit size. The ciphertext is the same as the one created by `Encryption::encrypt`
+//! and is decrypted the same way.
+//!
+//! `G` and `H` must be such that their discrete log relation is not known, eg. created with
+//! `ChunkedCommitmentGens::new`.
+
+use crate::{
+    encryption::{Ciphertext, Encryption},
+    error::SaverError,
+    keygen::EncryptionKey,
+    setup::ChunkedCommitmentGens,
+    utils::{self, chunks_count},
+};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::{PrimeField, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{rand::RngCore, vec::Vec, UniformRand};
+use digest::Digest;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+use dock_crypto_utils::{hashing_utils::field_elem_from_try_and_incr, serde_utils::*};
+
+/// Hashed first in the challenge of `SigmaEncryptionProof`
+const SIGMA_ENCRYPTION_LABEL: &[u8] = b"SAVER-SIGMA-ENCRYPTION";
+/// Hashed first in the challenge of `RerandomizationProof`
+const RERANDOMIZATION_LABEL: &[u8] = b"SAVER-RERANDOMIZATION";
+
+/// Proof that a bit commitment `C = b*G + s*H` commits to `b` = 0 or 1, i.e. knowledge of `s` such that
+/// `C = s*H` or `C - G = s*H`. The challenge of the 2nd branch is the proof's challenge minus `challenge_0`.
+#[serde_as]
+#[derive(
+    Clone, PartialEq, Eq, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize,
+)]
+pub struct BitProof<E: Pairing> {
+    #[serde_as(as = "ArkObjectBytes")]
+    pub t_0: E::G1Affine,
+    #[serde_as(as = "ArkObjectBytes")]
+    pub t_1: E::G1Affine,
+    #[serde_as(as = "ArkObjectBytes")]
+    pub challenge_0: E::ScalarField,
+    #[serde_as(as = "ArkObjectBytes")]
+    pub resp_0: E::ScalarField,
+    #[serde_as(as = "ArkObjectBytes")]
+    pub resp_1: E::ScalarField,
+}
+
+/// Proof that a ciphertext encrypts chunks of at most `chunk_bit_size` bits, created without Groth16
+#[serde_as]
+#[derive(
+    Clone, PartialEq, Eq, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize,
+)]
+#[serde(bound = "")]
+pub struct SigmaEncryptionProof<E: Pairing> {
+    /// `C_{i,j} = m_{i,j}*G + s_{i,j}*H` for bit `j` of chunk `i`. The bits of the 1st chunk come first,
+    /// least significant bit first.
+    #[serde_as(as = "Vec<ArkObjectBytes>")]
+    pub bit_comms: Vec<E::G1Affine>,
+    /// A proof for each bit commitment in `bit_comms`
+    pub bit_proofs: Vec<BitProof<E>>,
+    /// `k_r*X_0` for random `k_r`
+    #[serde_as(as = "ArkObjectBytes")]
+    pub t_X_r: E::G1Affine,
+    /// `k_r*X_i + k_i*g_i` for random `k_i` of each chunk
+    #[serde_as(as = "Vec<ArkObjectBytes>")]
+    pub t_enc_chunks: Vec<E::G1Affine>,
+    /// `k_1*Y_1 + k_2*Y_2 + ... + k_n*Y_n + k_r*P_1`
+    #[serde_as(as = "ArkObjectBytes")]
+    pub t_commitment: E::G1Affine,
+    /// `k_i*G + l_i*H` for random `l_i` of each chunk
+    #[serde_as(as = "Vec<ArkObjectBytes>")]
+    pub t_chunk_comms: Vec<E::G1Affine>,
+    /// `k_r + challenge * r`
+    #[serde_as(as = "ArkObjectBytes")]
+    pub resp_r: E::ScalarField,
+    /// `k_i + challenge * m_i` for each chunk
+    #[serde_as(as = "Vec<ArkObjectBytes>")]
+    pub resp_chunks: Vec<E::ScalarField>,
+    /// `l_i + challenge * s_i` for each chunk where `s_i = s_{i,0} + 2*s_{i,1} + .. + 2^{b-1}*s_{i,b-1}`
+    #[serde_as(as = "Vec<ArkObjectBytes>")]
+    pub resp_chunk_blindings: Vec<E::ScalarField>,
+}
+
+/// Encrypt the message like `Encryption::encrypt` and create a proof that the ciphertext is well-formed using
+/// only sigma protocols. `comm_gens` are the generators used for committing to the bits of the chunks. Returns
+/// the ciphertext, the randomness used in encryption and the proof.
+pub fn encrypt_with_sigma_proof<R: RngCore, E: Pairing, D: Digest>(
+    rng: &mut R,
+    message: &E::ScalarField,
+    ek: &EncryptionKey<E>,
+    g_i: &[E::G1Affine],
+    chunk_bit_size: u8,
+    comm_gens: &ChunkedCommitmentGens<E::G1Affine>,
+) -> crate::Result<(Ciphertext<E>, E::ScalarField, SigmaEncryptionProof<E>)> {
+    let (ct, r) = Encryption::encrypt(rng, message, ek, g_i, chunk_bit_size)?;
+    let chunks = utils::decompose(message, chunk_bit_size)?;
+    let n = chunks.len();
+    let b = chunk_bit_size as usize;
+    let powers = powers_of_2::<E::ScalarField>(chunk_bit_size);
+
+    // Commit to each bit and create the first message of its OR proof. For the branch of the bit's value,
+    // keep the randomness. The other branch is simulated with a random challenge and response.
+    let mut bit_comms = Vec::with_capacity(n * b);
+    let mut bit_states = Vec::with_capacity(n * b);
+    let mut t_bits = Vec::with_capacity(n * b);
+    let mut chunk_blindings = Vec::with_capacity(n);
+    let g = comm_gens.G.into_group();
+    for chunk in &chunks {
+        let mut s_i = E::ScalarField::zero();
+        for (j, power) in powers.iter().enumerate() {
+            let bit = (chunk >> j) & 1 == 1;
+            let s = E::ScalarField::rand(rng);
+            s_i += *power * s;
+            let mut c = comm_gens.H * s;
+            if bit {
+                c += g;
+            }
+            let k = E::ScalarField::rand(rng);
+            let sim_challenge = E::ScalarField::rand(rng);
+            let sim_resp = E::ScalarField::rand(rng);
+            let t_real = comm_gens.H * k;
+            // For the simulated branch, `t = resp*H - challenge*Y` where `Y` is `C - G` for bit 1 and `C` for bit 0
+            let t_sim = if bit {
+                comm_gens.H * sim_resp - c * sim_challenge
+            } else {
+                comm_gens.H * sim_resp - (c - g) * sim_challenge
+            };
+            let (t_0, t_1) = if bit {
+                (t_sim, t_real)
+            } else {
+                (t_real, t_sim)
+            };
+            bit_comms.push(c);
+            t_bits.push(t_0);
+            t_bits.push(t_1);
+            bit_states.push((bit, s, k, sim_challenge, sim_resp));
+        }
+        chunk_blindings.push(s_i);
+    }
+    let bit_comms = E::G1::normalize_batch(&bit_comms);
+    let t_bits = E::G1::normalize_batch(&t_bits);
+
+    let k_r = E::ScalarField::rand(rng);
+    let k_chunks = (0..n)
+        .map(|_| E::ScalarField::rand(rng))
+        .collect::<Vec<_>>();
+    let l_chunks = (0..n)
+        .map(|_| E::ScalarField::rand(rng))
+        .collect::<Vec<_>>();
+    let t_X_r = (ek.X_0 * k_r).into_affine();
+    let t_enc_chunks = E::G1::normalize_batch(
+        &(0..n)
+            .map(|i| ek.X[i] * k_r + g_i[i] * k_chunks[i])
+            .collect::<Vec<_>>(),
+    );
+    let mut k_comm = k_chunks.clone();
+    k_comm.push(k_r);
+    let t_commitment =
+        E::G1::msm_unchecked(&Ciphertext::commitment_bases(ek), &k_comm).into_affine();
+    let t_chunk_comms = E::G1::normalize_batch(
+        &(0..n)
+            .map(|i| g * k_chunks[i] + comm_gens.H * l_chunks[i])
+            .collect::<Vec<_>>(),
+    );
+
+    let challenge = compute_challenge::<E, D>(
+        public_bytes(&ct, ek, &g_i[..n], comm_gens),
+        &bit_comms,
+        &t_bits,
+        &t_X_r,
+        &t_enc_chunks,
+        &t_commitment,
+        &t_chunk_comms,
+    );
+
+    let bit_proofs = bit_states
+        .into_iter()
+        .zip(t_bits.chunks(2))
+        .map(|((bit, s, k, sim_challenge, sim_resp), t)| {
+            let real_challenge = challenge - sim_challenge;
+            let real_resp = k + real_challenge * s;
+            let (challenge_0, resp_0, resp_1) = if bit {
+                (sim_challenge, sim_resp, real_resp)
+            } else {
+                (real_challenge, real_resp, sim_resp)
+            };
+            BitProof {
+                t_0: t[0],
+                t_1: t[1],
+                challenge_0,
+                resp_0,
+                resp_1,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let resp_chunks = k_chunks
+        .into_iter()
+        .zip(chunks)
+        .map(|(k, m)| k + challenge * E::ScalarField::from(m))
+        .collect::<Vec<_>>();
+    let resp_chunk_blindings = l_chunks
+        .into_iter()
+        .zip(chunk_blindings)
+        .map(|(l, s)| l + challenge * s)
+        .collect::<Vec<_>>();
+    let proof = SigmaEncryptionProof {
+        bit_comms,
+        bit_proofs,
+        t_X_r,
+        t_enc_chunks,
+        t_commitment,
+        t_chunk_comms,
+        resp_r: k_r + challenge * r,
+        resp_chunks,
+        resp_chunk_blindings,
+    };
+    Ok((ct, r, proof))
+}
+
+/// Verify the proof created by `encrypt_with_sigma_proof` that the ciphertext is well-formed, i.e. it's the
+/// encryption of chunks of at most `chunk_bit_size` bits and its commitment is to the same chunks.
+#[must_use = "a failed verification is only reported through the returned value"]
+pub fn verify_sigma_proof<E: Pairing, D: Digest>(
+    proof: &SigmaEncryptionProof<E>,
+    ciphertext: &Ciphertext<E>,
+    chunk_bit_size: u8,
+    ek: &EncryptionKey<E>,
+    g_i: &[E::G1Affine],
+    comm_gens: &ChunkedCommitmentGens<E::G1Affine>,
+) -> crate::Result<()> {
+    utils::check_chunk_bit_size(chunk_bit_size)?;
+    let n = chunks_count::<E::ScalarField>(chunk_bit_size) as usize;
+    let expected = ek.supported_chunks_count()? as usize;
+    if n != expected {
+        return Err(SaverError::IncompatibleEncryptionKey(n, expected));
+    }
+    if ciphertext.enc_chunks.len() != n {
+        return Err(SaverError::IncompatibleEncryptionKey(
+            ciphertext.enc_chunks.len(),
+            n,
+        ));
+    }
+    if n > g_i.len() {
+        return Err(SaverError::InsufficientGenerators {
+            have: g_i.len(),
+            need: n,
+            source: "g_i",
+        });
+    }
+    let b = chunk_bit_size as usize;
+    for (l, e) in [
+        (proof.bit_comms.len(), n * b),
+        (proof.bit_proofs.len(), n * b),
+        (proof.t_enc_chunks.len(), n),
+        (proof.t_chunk_comms.len(), n),
+        (proof.resp_chunks.len(), n),
+        (proof.resp_chunk_blindings.len(), n),
+    ] {
+        if l != e {
+            return Err(SaverError::VectorShorterThanExpected(l, e));
+        }
+    }
+
+    let t_bits = proof
+        .bit_proofs
+        .iter()
+        .flat_map(|p| [p.t_0, p.t_1])
+        .collect::<Vec<_>>();
+    let challenge = compute_challenge::<E, D>(
+        public_bytes(ciphertext, ek, &g_i[..n], comm_gens),
+        &proof.bit_comms,
+        &t_bits,
+        &proof.t_X_r,
+        &proof.t_enc_chunks,
+        &proof.t_commitment,
+        &proof.t_chunk_comms,
+    );
+
+    // For each bit, resp_0*H = t_0 + challenge_0*C and resp_1*H = t_1 + challenge_1*(C - G)
+    let g = comm_gens.G.into_group();
+    for (c, p) in proof.bit_comms.iter().zip(proof.bit_proofs.iter()) {
+        let challenge_1 = challenge - p.challenge_0;
+        if comm_gens.H * p.resp_0 != p.t_0 + *c * p.challenge_0
+            || comm_gens.H * p.resp_1 != p.t_1 + (c.into_group() - g) * challenge_1
+        {
+            return Err(SaverError::InvalidProof);
+        }
+    }
+
+    // resp_r*X_0 = t_X_r + challenge*c_0
+    if ek.X_0 * proof.resp_r != proof.t_X_r + ciphertext.X_r * challenge {
+        return Err(SaverError::InvalidProof);
+    }
+    let powers = powers_of_2::<E::ScalarField>(chunk_bit_size);
+    for ((((((x_i, g_i), c_i), t_i), resp_i), (resp_blinding_i, t_chunk_comm_i)), bit_comms_i) in
+        ek.X.iter()
+            .zip(g_i.iter())
+            .zip(ciphertext.enc_chunks.iter())
+            .zip(proof.t_enc_chunks.iter())
+            .zip(proof.resp_chunks.iter())
+            .zip(
+                proof
+                    .resp_chunk_blindings
+                    .iter()
+                    .zip(proof.t_chunk_comms.iter()),
+            )
+            .zip(proof.bit_comms.chunks(b))
+    {
+        // resp_r*X_i + resp_i*g_i = t_i + challenge*c_i
+        if *x_i * proof.resp_r + *g_i * resp_i != *t_i + *c_i * challenge {
+            return Err(SaverError::InvalidProof);
+        }
+        // resp_i*G + resp_blinding_i*H = t_chunk_comm_i + challenge*D_i
+        let d_i = E::G1::msm_unchecked(bit_comms_i, &powers);
+        if g * resp_i + comm_gens.H * resp_blinding_i != *t_chunk_comm_i + d_i * challenge {
+            return Err(SaverError::InvalidProof);
+        }
+    }
+    // resp_1*Y_1 + .. + resp_n*Y_n + resp_r*P_1 = t_commitment + challenge*psi
+    let mut responses = proof.resp_chunks.clone();
+    responses.push(proof.resp_r);
+    if E::G1::msm_unchecked(&Ciphertext::commitment_bases(ek), &responses)
+        != proof.t_commitment + ciphertext.commitment * challenge
+    {
+        return Err(SaverError::InvalidCommitment);
+    }
+    Ok(())
+}
+
+/// Proof that a ciphertext `ct2` is a rerandomization of `ct1` with the same encryption key, i.e. knowledge of
+/// `delta` such that `ct2.X_r - ct1.X_r = delta*X_0`, `ct2.c_i - ct1.c_i = delta*X_i` for each chunk and
+/// `ct2.psi - ct1.psi = delta*P_1`, as `Encryption::rerandomize_ciphertext_and_proof` creates. The first
+/// message of the Schnorr protocol is recomputed by the verifier so only the challenge and response are kept.
+#[serde_as]
+#[derive(
+    Clone, PartialEq, Eq, Debug, CanonicalSerialize, CanonicalDeserialize, Serialize, Deserialize,
+)]
+pub struct RerandomizationProof<E: Pairing> {
+    #[serde_as(as = "ArkObjectBytes")]
+    pub challenge: E::ScalarField,
+    /// `k + challenge * delta` for random `k`
+    #[serde_as(as = "ArkObjectBytes")]
+    pub response: E::ScalarField,
+}
+
+/// Prove that `ct2` is the rerandomization of `ct1` with `delta`, eg. for a mix-net to show how its outputs
+/// link to its inputs to an auditor. Returns `SaverError::NotARerandomization` if `ct2` wasn't created from
+/// `ct1` with `delta`.
+pub fn prove_rerandomization<R: RngCore, E: Pairing, D: Digest>(
+    rng: &mut R,
+    ct1: &Ciphertext<E>,
+    ct2: &Ciphertext<E>,
+    delta: &E::ScalarField,
+    ek: &EncryptionKey<E>,
+) -> crate::Result<RerandomizationProof<E>> {
+    let bases = rerandomization_bases(ct1, ct2, ek)?;
+    let diffs = rerandomization_differences(ct1, ct2);
+    if bases
+        .iter()
+        .zip(diffs.iter())
+        .any(|(b, d)| *b * delta != *d)
+    {
+        return Err(SaverError::NotARerandomization);
+    }
+    let k = E::ScalarField::rand(rng);
+    let t = E::G1::normalize_batch(&bases.iter().map(|b| *b * k).collect::<Vec<_>>());
+    let challenge = compute_rerandomization_challenge::<E, D>(&bases, ct1, ct2, &t);
+    Ok(RerandomizationProof {
+        challenge,
+        response: k + challenge * delta,
+    })
+}
+
+/// Verify the proof created by `prove_rerandomization` that `ct2` is a rerandomization of `ct1`
+#[must_use = "a failed verification is only reported through the returned value"]
+pub fn verify_rerandomization<E: Pairing, D: Digest>(
+    proof: &RerandomizationProof<E>,
+    ct1: &Ciphertext<E>,
+    ct2: &Ciphertext<E>,
+    ek: &EncryptionKey<E>,
+) -> crate::Result<()> {
+    let bases = rerandomization_bases(ct1, ct2, ek)?;
+    let diffs = rerandomization_differences(ct1, ct2);
+    // t_j = response*B_j - challenge*(ct2_j - ct1_j)
+    let t = E::G1::normalize_batch(
+        &bases
+            .iter()
+            .zip(diffs.iter())
+            .map(|(b, d)| *b * proof.response - *d * proof.challenge)
+            .collect::<Vec<_>>(),
+    );
+    if compute_rerandomization_challenge::<E, D>(&bases, ct1, ct2, &t) != proof.challenge {
+        return Err(SaverError::InvalidProof);
+    }
+    Ok(())
+}
+
+/// `X_0, X_1, .., X_n, P_1`, the bases used for rerandomizing each element of the ciphertext
+fn rerandomization_bases<E: Pairing>(
+    ct1: &Ciphertext<E>,
+    ct2: &Ciphertext<E>,
+    ek: &EncryptionKey<E>,
+) -> crate::Result<Vec<E::G1Affine>> {
+    for l in [ct1.enc_chunks.len(), ct2.enc_chunks.len()] {
+        if l != ek.X.len() {
+            return Err(SaverError::IncompatibleEncryptionKey(l, ek.X.len()));
+        }
+    }
+    let mut bases = Vec::with_capacity(ek.X.len() + 2);
+    bases.push(ek.X_0);
+    bases.extend_from_slice(&ek.X);
+    bases.push(ek.P_1);
+    Ok(bases)
+}
+
+/// `ct2 - ct1` in the order of `rerandomization_bases`
+fn rerandomization_differences<E: Pairing>(ct1: &Ciphertext<E>, ct2: &Ciphertext<E>) -> Vec<E::G1> {
+    let mut diffs = Vec::with_capacity(ct1.enc_chunks.len() + 2);
+    diffs.push(ct2.X_r.into_group() - ct1.X_r);
+    diffs.extend(
+        ct2.enc_chunks
+            .iter()
+            .zip(ct1.enc_chunks.iter())
+            .map(|(c2, c1)| c2.into_group() - c1),
+    );
+    diffs.push(ct2.commitment.into_group() - ct1.commitment);
+    diffs
+}
+
+/// Hashes the `bases` from `rerandomization_bases` before the ciphertexts so that the proof is bound to the
+/// encryption key
+fn compute_rerandomization_challenge<E: Pairing, D: Digest>(
+    bases: &[E::G1Affine],
+    ct1: &Ciphertext<E>,
+    ct2: &Ciphertext<E>,
+    t: &[E::G1Affine],
+) -> E::ScalarField {
+    let mut bytes = RERANDOMIZATION_LABEL.to_vec();
+    bases.serialize_compressed(&mut bytes).unwrap();
+    ct1.serialize_compressed(&mut bytes).unwrap();
+    ct2.serialize_compressed(&mut bytes).unwrap();
+    for t in t {
+        t.serialize_compressed(&mut bytes).unwrap();
+    }
+    field_elem_from_try_and_incr::<E::ScalarField, D>(&bytes)
+}
+
+/// `1, 2, 4, .., 2^{chunk_bit_size-1}`
+fn powers_of_2<F: PrimeField>(chunk_bit_size: u8) -> Vec<F> {
+    (0..chunk_bit_size).map(|j| F::from(1u64 << j)).collect()
+}
+
+/// Serialized public parameters and ciphertext, hashed first in the challenge so that the proof can't be
+/// used with other keys or generators. Only the elements of the encryption key are hashed and not its
+/// `gens_fingerprint`, which is optional.
+fn public_bytes<E: Pairing>(
+    ciphertext: &Ciphertext<E>,
+    ek: &EncryptionKey<E>,
+    g_i: &[E::G1Affine],
+    comm_gens: &ChunkedCommitmentGens<E::G1Affine>,
+) -> Vec<u8> {
+    let mut bytes = SIGMA_ENCRYPTION_LABEL.to_vec();
+    ek.X_0.serialize_compressed(&mut bytes).unwrap();
+    ek.X.serialize_compressed(&mut bytes).unwrap();
+    ek.Y.serialize_compressed(&mut bytes).unwrap();
+    ek.Z.serialize_compressed(&mut bytes).unwrap();
+    ek.P_1.serialize_compressed(&mut bytes).unwrap();
+    ek.P_2.serialize_compressed(&mut bytes).unwrap();
+    g_i.serialize_compressed(&mut bytes).unwrap();
+    comm_gens.serialize_compressed(&mut bytes).unwrap();
+    ciphertext.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+/// `bytes` are the public parameters and ciphertext from `public_bytes`
+fn compute_challenge<E: Pairing, D: Digest>(
+    mut bytes: Vec<u8>,
+    bit_comms: &[E::G1Affine],
+    t_bits: &[E::G1Affine],
+    t_X_r: &E::G1Affine,
+    t_enc_chunks: &[E::G1Affine],
+    t_commitment: &E::G1Affine,
+    t_chunk_comms: &[E::G1Affine],
+) -> E::ScalarField {
+    for c in bit_comms.iter().chain(t_bits) {
+        c.serialize_compressed(&mut bytes).unwrap();
+    }
+    t_X_r.serialize_compressed(&mut bytes).unwrap();
+    for t in t_enc_chunks {
+        t.serialize_compressed(&mut bytes).unwrap();
+    }
+    t_commitment.serialize_compressed(&mut bytes).unwrap();
+    for t in t_chunk_comms {
+        t.serialize_compressed(&mut bytes).unwrap();
+    }
+    field_elem_from_try_and_incr::<E::ScalarField, D>(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{keygen::keygen, setup::EncryptionGens};
+    use ark_bls12_381::Bls12_381;
+    use ark_ff::One;
+    use ark_std::rand::{prelude::StdRng, SeedableRng};
+    use blake2::Blake2b512;
+
+    type Fr = <Bls12_381 as Pairing>::ScalarField;
+    type G1 = <Bls12_381 as Pairing>::G1;
+
+    #[test]
+    fn encrypt_and_verify_with_sigma_proof() {
+        fn check(chunk_bit_size: u8) {
+            let mut rng = StdRng::seed_from_u64(0u64);
+            let enc_gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+            let comm_gens = ChunkedCommitmentGens::<<Bls12_381 as Pairing>::G1Affine>::new::<
+                Blake2b512,
+            >(b"test");
+            // No SNARK setup, only the generators for the chunks
+            let n = chunks_count::<Fr>(chunk_bit_size) as usize;
+            let g_i = (0..n)
+                .map(|_| G1::rand(&mut rng).into_affine())
+                .collect::<Vec<_>>();
+            let delta_g = G1::rand(&mut rng).into_affine();
+            let gamma_g = G1::rand(&mut rng).into_affine();
+            let (sk, ek, dk) = keygen(
+                &mut rng,
+                chunk_bit_size,
+                &enc_gens,
+                &g_i,
+                &delta_g,
+                &gamma_g,
+            )
+            .unwrap();
+
+            let m = Fr::rand(&mut rng);
+            let (ct, _, proof) = encrypt_with_sigma_proof::<_, _, Blake2b512>(
+                &mut rng,
+                &m,
+                &ek,
+                &g_i,
+                chunk_bit_size,
+                &comm_gens,
+            )
+            .unwrap();
+            assert_eq!(proof.bit_comms.len(), n * chunk_bit_size as usize);
+            verify_sigma_proof::<_, Blake2b512>(&proof, &ct, chunk_bit_size, &ek, &g_i, &comm_gens)
+                .unwrap();
+            test_serialization!(SigmaEncryptionProof<Bls12_381>, proof);
+
+            // Decrypted like any other ciphertext
+            let (m_, nu) = ct.decrypt(&sk, dk.clone(), &g_i, chunk_bit_size).unwrap();
+            assert_eq!(m_, m);
+            ct.verify_decryption(&m_, &nu, chunk_bit_size, dk, &g_i, enc_gens)
+                .unwrap();
+
+            // Proof doesn't verify for another ciphertext
+            let (other_ct, _) =
+                Encryption::encrypt(&mut rng, &m, &ek, &g_i, chunk_bit_size).unwrap();
+            assert!(verify_sigma_proof::<_, Blake2b512>(
+                &proof,
+                &other_ct,
+                chunk_bit_size,
+                &ek,
+                &g_i,
+                &comm_gens
+            )
+            .is_err());
+
+            // Chunk changed in the ciphertext
+            let mut tampered_ct = ct.clone();
+            tampered_ct.enc_chunks[2] = (tampered_ct.enc_chunks[2] + g_i[2]).into_affine();
+            assert!(verify_sigma_proof::<_, Blake2b512>(
+                &proof,
+                &tampered_ct,
+                chunk_bit_size,
+                &ek,
+                &g_i,
+                &comm_gens
+            )
+            .is_err());
+
+            // Bit commitment replaced with a commitment to 2
+            let mut tampered_proof = proof.clone();
+            tampered_proof.bit_comms[0] =
+                (comm_gens.G * Fr::from(2u64) + comm_gens.H * Fr::one()).into_affine();
+            assert!(matches!(
+                verify_sigma_proof::<_, Blake2b512>(
+                    &tampered_proof,
+                    &ct,
+                    chunk_bit_size,
+                    &ek,
+                    &g_i,
+                    &comm_gens
+                ),
+                Err(SaverError::InvalidProof)
+            ));
+
+            // Proof for another chunk bit size
+            let mut short_proof = proof.clone();
+            short_proof.bit_proofs.pop();
+            assert!(matches!(
+                verify_sigma_proof::<_, Blake2b512>(
+                    &short_proof,
+                    &ct,
+                    chunk_bit_size,
+                    &ek,
+                    &g_i,
+                    &comm_gens
+                ),
+                Err(SaverError::VectorShorterThanExpected(..))
+            ));
+
+            // Proof is bound to the generators it was created with
+            let other_comm_gens = ChunkedCommitmentGens::<<Bls12_381 as Pairing>::G1Affine>::new::<
+                Blake2b512,
+            >(b"other");
+            assert!(verify_sigma_proof::<_, Blake2b512>(
+                &proof,
+                &ct,
+                chunk_bit_size,
+                &ek,
+                &g_i,
+                &other_comm_gens
+            )
+            .is_err());
+        }
+        check(4);
+        check(8);
+    }
+
+    #[test]
+    fn rerandomization_link_proof() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 16;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, _, ek, _) =
+            crate::setup::setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        let msg = Fr::rand(&mut rng);
+        let (ct1, _, proof) =
+            Encryption::encrypt_with_proof(&mut rng, &msg, &ek, &snark_pk, chunk_bit_size).unwrap();
+        let (ct2, delta, _) = Encryption::rerandomize_ciphertext_and_proof(
+            ct1.clone(),
+            proof,
+            &snark_pk.pk.vk,
+            &ek,
+            &mut rng,
+        )
+        .unwrap();
+
+        let link =
+            prove_rerandomization::<_, _, Blake2b512>(&mut rng, &ct1, &ct2, &delta, &ek).unwrap();
+        verify_rerandomization::<_, Blake2b512>(&link, &ct1, &ct2, &ek).unwrap();
+        test_serialization!(RerandomizationProof<Bls12_381>, link);
+
+        // An unrelated encryption of the same message
+        let (ct3, _) = Encryption::encrypt_given_snark_vk(
+            &mut rng,
+            &msg,
+            &ek,
+            &snark_pk.pk.vk,
+            chunk_bit_size,
+        )
+        .unwrap();
+        assert!(matches!(
+            prove_rerandomization::<_, _, Blake2b512>(&mut rng, &ct1, &ct3, &delta, &ek),
+            Err(SaverError::NotARerandomization)
+        ));
+        assert!(matches!(
+            verify_rerandomization::<_, Blake2b512>(&link, &ct1, &ct3, &ek),
+            Err(SaverError::InvalidProof)
+        ));
+        // The link is directional
+        assert!(verify_rerandomization::<_, Blake2b512>(&link, &ct2, &ct1, &ek).is_err());
+        // The link is bound to the encryption key
+        let (_, _, ek_other, _) =
+            crate::setup::setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        assert!(matches!(
+            verify_rerandomization::<_, Blake2b512>(&link, &ct1, &ct2, &ek_other),
+            Err(SaverError::InvalidProof)
+        ));
+        // The wrong delta
+        assert!(prove_rerandomization::<_, _, Blake2b512>(
+            &mut rng,
+            &ct1,
+            &ct2,
+            &(delta + Fr::one()),
+            &ek
+        )
+        .is_err());
+    }
+}
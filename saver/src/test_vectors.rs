@@ -0,0 +1,160 @@
+//! Regression test against the keys, ciphertext and decryption in `test_vectors.json` at the root of the
+//! crate. These were created by `generate_test_vectors` from `StdRng` seeded with `SEED`, so a change in
+//! key generation, encryption or serialization that changes their output fails `check_test_vectors`. When
+//! such a change is intended, regenerate the file with
+//!
+//! `cargo test --release -p saver generate_test_vectors -- --ignored`
+
+use crate::{
+    encryption::{Ciphertext, Encryption},
+    keygen::{keygen, DecryptionKey, EncryptionKey, PreparedDecryptionKey, SecretKey},
+    setup::EncryptionGens,
+};
+use ark_bls12_381::{Bls12_381, G1Affine};
+use ark_ec::{pairing::Pairing, CurveGroup};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{
+    rand::{prelude::StdRng, SeedableRng},
+    UniformRand,
+};
+use serde::{Deserialize, Serialize};
+
+type Fr = <Bls12_381 as Pairing>::ScalarField;
+
+const SEED: u64 = 0;
+const CHUNK_BIT_SIZE: u8 = 8;
+const VECTORS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_vectors.json");
+
+/// Each object as the hex of its compressed serialization
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct TestVectors {
+    seed: u64,
+    chunk_bit_size: u8,
+    encryption_gens: String,
+    g_i: String,
+    secret_key: String,
+    encryption_key: String,
+    decryption_key: String,
+    message: String,
+    randomness: String,
+    ciphertext: String,
+}
+
+fn to_hex<T: CanonicalSerialize>(obj: &T) -> String {
+    let mut bytes = vec![];
+    obj.serialize_compressed(&mut bytes).unwrap();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex<T: CanonicalDeserialize>(hex: &str) -> T {
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect::<Vec<_>>();
+    T::deserialize_compressed(&bytes[..]).unwrap()
+}
+
+fn create_test_vectors() -> TestVectors {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let n = crate::utils::chunks_count::<Fr>(CHUNK_BIT_SIZE) as usize;
+    let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+    let g_i = (0..n).map(|_| G1Affine::rand(&mut rng)).collect::<Vec<_>>();
+    let delta = Fr::rand(&mut rng);
+    let gamma = Fr::rand(&mut rng);
+    let g_delta = (gens.G * delta).into_affine();
+    let g_gamma = (gens.G * gamma).into_affine();
+    let (sk, ek, dk) = keygen(&mut rng, CHUNK_BIT_SIZE, &gens, &g_i, &g_delta, &g_gamma).unwrap();
+    let message = Fr::rand(&mut rng);
+    let (ct, r) = Encryption::encrypt(&mut rng, &message, &ek, &g_i, CHUNK_BIT_SIZE).unwrap();
+    TestVectors {
+        seed: SEED,
+        chunk_bit_size: CHUNK_BIT_SIZE,
+        encryption_gens: to_hex(&gens),
+        g_i: to_hex(&g_i),
+        secret_key: to_hex(&sk),
+        encryption_key: to_hex(&ek),
+        decryption_key: to_hex(&dk),
+        message: to_hex(&message),
+        randomness: to_hex(&r),
+        ciphertext: to_hex(&ct),
+    }
+}
+
+#[test]
+#[ignore]
+fn generate_test_vectors() {
+    let vectors = create_test_vectors();
+    std::fs::write(
+        VECTORS_PATH,
+        serde_json::to_string_pretty(&vectors).unwrap() + "\n",
+    )
+    .unwrap();
+}
+
+#[test]
+fn check_test_vectors() {
+    let vectors: TestVectors =
+        serde_json::from_str(&std::fs::read_to_string(VECTORS_PATH).unwrap()).unwrap();
+    assert_eq!(vectors.seed, SEED);
+    assert_eq!(vectors.chunk_bit_size, CHUNK_BIT_SIZE);
+
+    // The stored objects can be deserialized and decrypting the ciphertext gives the message
+    let gens: EncryptionGens<Bls12_381> = from_hex(&vectors.encryption_gens);
+    let g_i: Vec<G1Affine> = from_hex(&vectors.g_i);
+    let sk: SecretKey<Fr> = from_hex(&vectors.secret_key);
+    let ek: EncryptionKey<Bls12_381> = from_hex(&vectors.encryption_key);
+    let dk: DecryptionKey<Bls12_381> = from_hex(&vectors.decryption_key);
+    let message: Fr = from_hex(&vectors.message);
+    let r: Fr = from_hex(&vectors.randomness);
+    let ct: Ciphertext<Bls12_381> = from_hex(&vectors.ciphertext);
+
+    ct.verify_commitment(ek.clone(), gens.clone()).unwrap();
+    ct.verify_opening(&message, &r, &ek, &g_i, CHUNK_BIT_SIZE)
+        .unwrap();
+    let (decrypted, nu) = ct.decrypt(&sk, dk.clone(), &g_i, CHUNK_BIT_SIZE).unwrap();
+    assert_eq!(decrypted, message);
+    ct.verify_decryption(&decrypted, &nu, CHUNK_BIT_SIZE, dk, &g_i, gens)
+        .unwrap();
+
+    // Creating them again from the seed gives the same bytes
+    assert_eq!(create_test_vectors(), vectors);
+}
+
+#[test]
+fn parallel_loops_match_sequential_computation() {
+    // The loops made parallel with the `parallel` feature give the same output as computing each chunk
+    // one after another. Together with `check_test_vectors`, run with and without the feature, this shows
+    // that the output does not depend on it
+    let vectors: TestVectors =
+        serde_json::from_str(&std::fs::read_to_string(VECTORS_PATH).unwrap()).unwrap();
+    let g_i: Vec<G1Affine> = from_hex(&vectors.g_i);
+    let sk: SecretKey<Fr> = from_hex(&vectors.secret_key);
+    let ek: EncryptionKey<Bls12_381> = from_hex(&vectors.encryption_key);
+    let dk: DecryptionKey<Bls12_381> = from_hex(&vectors.decryption_key);
+    let message: Fr = from_hex(&vectors.message);
+    let r: Fr = from_hex(&vectors.randomness);
+    let ct: Ciphertext<Bls12_381> = from_hex(&vectors.ciphertext);
+
+    let chunks = crate::utils::decompose(&message, CHUNK_BIT_SIZE).unwrap();
+    assert_eq!(ct.X_r, (ek.X_0 * r).into_affine());
+    for i in 0..chunks.len() {
+        let expected = ek.X[i] * r + g_i[i] * Fr::from(chunks[i]);
+        assert_eq!(ct.enc_chunks[i], expected.into_affine());
+    }
+
+    let dk = PreparedDecryptionKey::from(dk);
+    let powers = dk.pairing_powers(CHUNK_BIT_SIZE, &g_i).unwrap();
+    for i in 0..chunks.len() {
+        let g_i_v_i = Bls12_381::pairing(g_i[i], dk.V_2[i].clone());
+        assert_eq!(powers[i][0], g_i_v_i);
+        assert_eq!(powers[i][254], g_i_v_i * Fr::from(255u64));
+    }
+
+    let (decrypted, nu) = ct.decrypt(&sk, dk.clone(), &g_i, CHUNK_BIT_SIZE).unwrap();
+    assert_eq!(decrypted, message);
+    assert_eq!(
+        ct.decrypt_given_pairing_powers(&sk, dk, &g_i, CHUNK_BIT_SIZE, &powers)
+            .unwrap(),
+        (decrypted, nu)
+    );
+}
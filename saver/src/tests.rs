@@ -1,11 +1,11 @@
 use crate::{
     circuit::BitsizeCheckCircuit,
     commitment::ChunkedCommitment,
-    encryption::Encryption,
+    encryption::{Ciphertext, Encryption},
     keygen::{PreparedDecryptionKey, PreparedEncryptionKey},
     saver_groth16::{create_proof, verify_proof},
     setup::{setup_for_groth16, ChunkedCommitmentGens, EncryptionGens, PreparedEncryptionGens},
-    utils::decompose,
+    utils::{chunked_commitment_witness, chunks_count, ciphertext_commitment_witness, decompose},
 };
 use ark_bls12_381::{Bls12_381, G1Affine};
 use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
@@ -20,6 +20,7 @@ use bbs_plus::{
     signature::SignatureG1,
 };
 use blake2::Blake2b512;
+use digest::Digest;
 use proof_system::{
     prelude::{
         EqualWitnesses, MetaStatement, MetaStatements, Proof, ProofSpec, Statements, Witness,
@@ -27,16 +28,26 @@ use proof_system::{
     },
     statement::{
         bbs_plus::PoKBBSSignatureG1 as PoKSignatureBBSG1Stmt,
-        ped_comm::PedersenCommitment as PedersenCommitmentStmt,
+        ped_comm::PedersenCommitment as PedersenCommitmentStmt, saver::link_commitment_chunks,
     },
     witness::PoKBBSSignatureG1 as PoKSignatureBBSG1Wit,
 };
 use std::{
     collections::{BTreeMap, BTreeSet},
     ops::Add,
-    time::Instant,
 };
 
+/// Evaluate the expression in a span named `$name` when the `tracing` feature is on so that a subscriber can
+/// record how long it takes. The operations instrumented in the crate, like creating and verifying the
+/// Groth16 proof, already have their own spans.
+macro_rules! in_span {
+    ($name:literal, $e:expr) => {{
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!($name).entered();
+        $e
+    }};
+}
+
 type Fr = <Bls12_381 as Pairing>::ScalarField;
 type ProofG1 = Proof<Bls12_381, G1Affine>;
 
@@ -110,14 +121,12 @@ fn bbs_plus_verifiably_encrypt_message() {
         let decomposed_message = decompose(&messages[user_id_idx], chunk_bit_size)
             .unwrap()
             .into_iter()
-            .map(|m| Fr::from(m as u64))
+            .map(Fr::from)
             .collect::<Vec<_>>();
 
         let circuit =
             BitsizeCheckCircuit::new(chunk_bit_size, None, Some(decomposed_message.clone()), true);
 
-        let start = Instant::now();
-
         let blinding = Fr::rand(&mut rng);
         let comm_single = chunked_comm_gens
             .G
@@ -132,17 +141,15 @@ fn bbs_plus_verifiably_encrypt_message() {
         .unwrap()
         .0;
 
-        let bases_comm_chunks =
-            ChunkedCommitment::<<Bls12_381 as Pairing>::G1Affine>::commitment_key(
-                &chunked_comm_gens,
-                chunk_bit_size,
-            );
-        let mut wit_comm_chunks = decomposed_message.clone();
-        wit_comm_chunks.push(blinding.clone());
+        let bases_comm_chunks = ChunkedCommitment::<<Bls12_381 as Pairing>::G1Affine>::bases(
+            &chunked_comm_gens,
+            chunk_bit_size,
+        )
+        .unwrap();
+        let wit_comm_chunks = chunked_commitment_witness(&decomposed_message, blinding);
 
-        let bases_comm_ct = ek.commitment_key();
-        let mut wit_comm_ct = decomposed_message.clone();
-        wit_comm_ct.push(r.clone());
+        let bases_comm_ct = Ciphertext::commitment_bases(&ek);
+        let wit_comm_ct = ciphertext_commitment_witness(&decomposed_message, r);
 
         let mut statements = Statements::new();
         statements.add(PoKSignatureBBSG1Stmt::new_statement_from_params(
@@ -177,7 +184,7 @@ fn bbs_plus_verifiably_encrypt_message() {
             )));
         }
 
-        let proof_spec = ProofSpec::new(statements.clone(), meta_statements.clone(), vec![], None);
+        let proof_spec = ProofSpec::new(statements, meta_statements, vec![], None);
 
         let mut witnesses = Witnesses::new();
         witnesses.add(PoKSignatureBBSG1Wit::new_as_witness(
@@ -196,49 +203,42 @@ fn bbs_plus_verifiably_encrypt_message() {
         witnesses.add(Witness::PedersenCommitment(wit_comm_chunks));
         witnesses.add(Witness::PedersenCommitment(wit_comm_ct));
 
-        println!("Timing for {}-bit chunks", chunk_bit_size);
-        let proof = ProofG1::new::<StdRng, Blake2b512>(
-            &mut rng,
-            proof_spec.clone(),
-            witnesses.clone(),
-            None,
-            Default::default(),
-        )
-        .unwrap()
-        .0;
-        println!("Time taken to create proof {:?}", start.elapsed());
+        let proof = in_span!(
+            "create_composite_proof",
+            ProofG1::new_with_spec_ref::<StdRng, Blake2b512>(
+                &mut rng,
+                &proof_spec,
+                witnesses,
+                None,
+                Default::default(),
+            )
+            .unwrap()
+            .0
+        );
 
         // Verifies the proof
         assert_eq!(comm_chunks, comm_single);
-        let start = Instant::now();
-        proof
-            .verify::<StdRng, Blake2b512>(&mut rng, proof_spec, None, Default::default())
-            .unwrap();
-        println!("Time taken to verify proof {:?}", start.elapsed());
+        in_span!(
+            "verify_composite_proof",
+            proof
+                .verify::<StdRng, Blake2b512>(&mut rng, proof_spec, None, Default::default())
+                .unwrap()
+        );
 
-        let start = Instant::now();
         let proof = create_proof(circuit, &r, &snark_srs, &ek, &mut rng).unwrap();
-        println!("Time taken to create Groth16 proof {:?}", start.elapsed());
 
-        let start = Instant::now();
-        ct.verify_commitment(ek.clone(), enc_gens.clone()).unwrap();
-        println!(
-            "Time taken to verify ciphertext commitment {:?}",
-            start.elapsed()
+        in_span!(
+            "verify_ciphertext_commitment",
+            ct.verify_commitment(ek.clone(), enc_gens.clone()).unwrap()
         );
-
-        let start = Instant::now();
-        ct.verify_commitment(prepared_ek.clone(), prepared_gens.clone())
-            .unwrap();
-        println!(
-            "Time taken to verify ciphertext commitment using prepared {:?}",
-            start.elapsed()
+        in_span!(
+            "verify_ciphertext_commitment_prepared",
+            ct.verify_commitment(prepared_ek.clone(), prepared_gens.clone())
+                .unwrap()
         );
 
-        let start = Instant::now();
         let pvk = prepare_verifying_key::<Bls12_381>(&snark_srs.pk.vk);
         verify_proof(&pvk, &proof, &ct).unwrap();
-        println!("Time taken to verify Groth16 proof {:?}", start.elapsed());
 
         // Decryptor decrypts
         let (decrypted_message, nu) = ct
@@ -294,6 +294,25 @@ fn bbs_plus_verifiably_encrypt_message() {
     check(16);
 }
 
+#[test]
+fn link_commitment_chunks_matches_manual_wiring() {
+    // The helper should create the same witness equalities as the loop in
+    // `bbs_plus_verifiably_encrypt_message` that links the chunks in statements 2 and 3
+    for chunk_bit_size in [1, 2, 4, 8, 16] {
+        let count = chunks_count::<Fr>(chunk_bit_size) as usize;
+        let mut manual = vec![];
+        for i in 0..count {
+            manual.push(MetaStatement::WitnessEquality(EqualWitnesses(
+                vec![(2, i), (3, i)]
+                    .into_iter()
+                    .collect::<BTreeSet<WitnessRef>>(),
+            )));
+        }
+        assert_eq!(link_commitment_chunks(2, 3, count), manual);
+    }
+    assert!(link_commitment_chunks(2, 3, 0).is_empty());
+}
+
 #[test]
 fn bbs_plus_verifiably_encrypt_many_messages() {
     fn check(chunk_bit_size: u8) {
@@ -356,7 +375,7 @@ fn bbs_plus_verifiably_encrypt_many_messages() {
         let decomposed_message_1 = decompose(&messages[m_idx_1], chunk_bit_size)
             .unwrap()
             .into_iter()
-            .map(|m| Fr::from(m as u64))
+            .map(Fr::from)
             .collect::<Vec<_>>();
         let blinding_1 = Fr::rand(&mut rng);
         let comm_single_1 = chunked_comm_gens
@@ -375,7 +394,7 @@ fn bbs_plus_verifiably_encrypt_many_messages() {
         let decomposed_message_2 = decompose(&messages[m_idx_2], chunk_bit_size)
             .unwrap()
             .into_iter()
-            .map(|m| Fr::from(m as u64))
+            .map(Fr::from)
             .collect::<Vec<_>>();
         let blinding_2 = Fr::rand(&mut rng);
         let comm_single_2 = chunked_comm_gens
@@ -394,7 +413,7 @@ fn bbs_plus_verifiably_encrypt_many_messages() {
         let decomposed_message_3 = decompose(&messages[m_idx_3], chunk_bit_size)
             .unwrap()
             .into_iter()
-            .map(|m| Fr::from(m as u64))
+            .map(Fr::from)
             .collect::<Vec<_>>();
         let blinding_3 = Fr::rand(&mut rng);
         let comm_single_3 = chunked_comm_gens
@@ -410,27 +429,21 @@ fn bbs_plus_verifiably_encrypt_many_messages() {
         .unwrap()
         .0;
 
-        let bases_comm_chunks =
-            ChunkedCommitment::<<Bls12_381 as Pairing>::G1Affine>::commitment_key(
-                &chunked_comm_gens,
-                chunk_bit_size,
-            );
-        let bases_comm_ct = ek.commitment_key();
+        let bases_comm_chunks = ChunkedCommitment::<<Bls12_381 as Pairing>::G1Affine>::bases(
+            &chunked_comm_gens,
+            chunk_bit_size,
+        )
+        .unwrap();
+        let bases_comm_ct = Ciphertext::commitment_bases(&ek);
 
-        let mut wit_comm_chunks_1 = decomposed_message_1.clone();
-        wit_comm_chunks_1.push(blinding_1.clone());
-        let mut wit_comm_ct_1 = decomposed_message_1.clone();
-        wit_comm_ct_1.push(r_1.clone());
+        let wit_comm_chunks_1 = chunked_commitment_witness(&decomposed_message_1, blinding_1);
+        let wit_comm_ct_1 = ciphertext_commitment_witness(&decomposed_message_1, r_1);
 
-        let mut wit_comm_chunks_2 = decomposed_message_2.clone();
-        wit_comm_chunks_2.push(blinding_2.clone());
-        let mut wit_comm_ct_2 = decomposed_message_2.clone();
-        wit_comm_ct_2.push(r_2.clone());
+        let wit_comm_chunks_2 = chunked_commitment_witness(&decomposed_message_2, blinding_2);
+        let wit_comm_ct_2 = ciphertext_commitment_witness(&decomposed_message_2, r_2);
 
-        let mut wit_comm_chunks_3 = decomposed_message_3.clone();
-        wit_comm_chunks_3.push(blinding_3.clone());
-        let mut wit_comm_ct_3 = decomposed_message_3.clone();
-        wit_comm_ct_3.push(r_3.clone());
+        let wit_comm_chunks_3 = chunked_commitment_witness(&decomposed_message_3, blinding_3);
+        let wit_comm_ct_3 = ciphertext_commitment_witness(&decomposed_message_3, r_3);
 
         let mut statements = Statements::new();
         statements.add(PoKSignatureBBSG1Stmt::new_statement_from_params(
@@ -513,7 +526,7 @@ fn bbs_plus_verifiably_encrypt_many_messages() {
             )));
         }
 
-        let proof_spec = ProofSpec::new(statements.clone(), meta_statements.clone(), vec![], None);
+        let proof_spec = ProofSpec::new(statements, meta_statements, vec![], None);
 
         let mut witnesses = Witnesses::new();
         witnesses.add(PoKSignatureBBSG1Wit::new_as_witness(
@@ -546,10 +559,10 @@ fn bbs_plus_verifiably_encrypt_many_messages() {
         witnesses.add(Witness::PedersenCommitment(wit_comm_chunks_3));
         witnesses.add(Witness::PedersenCommitment(wit_comm_ct_3));
 
-        let proof = ProofG1::new::<StdRng, Blake2b512>(
+        let proof = ProofG1::new_with_spec_ref::<StdRng, Blake2b512>(
             &mut rng,
-            proof_spec.clone(),
-            witnesses.clone(),
+            &proof_spec,
+            witnesses,
             None,
             Default::default(),
         )
@@ -703,14 +716,14 @@ fn bbs_plus_verifiably_encrypt_message_from_2_sigs() {
         let decomposed_message_1 = decompose(&messages_1[user_id_idx], chunk_bit_size)
             .unwrap()
             .into_iter()
-            .map(|m| Fr::from(m as u64))
+            .map(Fr::from)
             .collect::<Vec<_>>();
 
         // User creates proof
         let decomposed_message_2 = decompose(&messages_2[user_id_idx], chunk_bit_size)
             .unwrap()
             .into_iter()
-            .map(|m| Fr::from(m as u64))
+            .map(Fr::from)
             .collect::<Vec<_>>();
 
         let circuit_1 = BitsizeCheckCircuit::new(
@@ -726,7 +739,6 @@ fn bbs_plus_verifiably_encrypt_message_from_2_sigs() {
             true,
         );
 
-        let start = Instant::now();
         let blinding_1 = Fr::rand(&mut rng);
         let blinding_2 = Fr::rand(&mut rng);
 
@@ -756,25 +768,21 @@ fn bbs_plus_verifiably_encrypt_message_from_2_sigs() {
         .unwrap()
         .0;
 
-        let bases_comm_chunks =
-            ChunkedCommitment::<<Bls12_381 as Pairing>::G1Affine>::commitment_key(
-                &chunked_comm_gens,
-                chunk_bit_size,
-            );
+        let bases_comm_chunks = ChunkedCommitment::<<Bls12_381 as Pairing>::G1Affine>::bases(
+            &chunked_comm_gens,
+            chunk_bit_size,
+        )
+        .unwrap();
 
-        let mut wit_comm_chunks_1 = decomposed_message_1.clone();
-        wit_comm_chunks_1.push(blinding_1.clone());
+        let wit_comm_chunks_1 = chunked_commitment_witness(&decomposed_message_1, blinding_1);
 
-        let mut wit_comm_chunks_2 = decomposed_message_2.clone();
-        wit_comm_chunks_2.push(blinding_2.clone());
+        let wit_comm_chunks_2 = chunked_commitment_witness(&decomposed_message_2, blinding_2);
 
-        let bases_comm_ct = ek.commitment_key();
+        let bases_comm_ct = Ciphertext::commitment_bases(&ek);
 
-        let mut wit_comm_ct_1 = decomposed_message_1.clone();
-        wit_comm_ct_1.push(r_1.clone());
+        let wit_comm_ct_1 = ciphertext_commitment_witness(&decomposed_message_1, r_1);
 
-        let mut wit_comm_ct_2 = decomposed_message_2.clone();
-        wit_comm_ct_2.push(r_2.clone());
+        let wit_comm_ct_2 = ciphertext_commitment_witness(&decomposed_message_2, r_2);
 
         let mut statements = Statements::new();
         // For 1st sig
@@ -843,7 +851,7 @@ fn bbs_plus_verifiably_encrypt_message_from_2_sigs() {
             )));
         }
 
-        let proof_spec = ProofSpec::new(statements.clone(), meta_statements.clone(), vec![], None);
+        let proof_spec = ProofSpec::new(statements, meta_statements, vec![], None);
 
         let mut witnesses = Witnesses::new();
         witnesses.add(PoKSignatureBBSG1Wit::new_as_witness(
@@ -878,53 +886,43 @@ fn bbs_plus_verifiably_encrypt_message_from_2_sigs() {
         witnesses.add(Witness::PedersenCommitment(wit_comm_chunks_2));
         witnesses.add(Witness::PedersenCommitment(wit_comm_ct_2));
 
-        let proof = ProofG1::new::<StdRng, Blake2b512>(
-            &mut rng,
-            proof_spec.clone(),
-            witnesses.clone(),
-            None,
-            Default::default(),
-        )
-        .unwrap()
-        .0;
-        println!("Time taken to create proof {:?}", start.elapsed());
+        let proof = in_span!(
+            "create_composite_proof",
+            ProofG1::new_with_spec_ref::<StdRng, Blake2b512>(
+                &mut rng,
+                &proof_spec,
+                witnesses,
+                None,
+                Default::default(),
+            )
+            .unwrap()
+            .0
+        );
 
         // Verifies the proof
         assert_eq!(comm_chunks_1, comm_single_1);
         assert_eq!(comm_chunks_2, comm_single_2);
-        let start = Instant::now();
-        proof
-            .verify::<StdRng, Blake2b512>(&mut rng, proof_spec, None, Default::default())
-            .unwrap();
-        println!("Time taken to verify proof {:?}", start.elapsed());
+        in_span!(
+            "verify_composite_proof",
+            proof
+                .verify::<StdRng, Blake2b512>(&mut rng, proof_spec, None, Default::default())
+                .unwrap()
+        );
 
-        let start = Instant::now();
         let proof_1 = create_proof(circuit_1, &r_1, &snark_srs, &ek, &mut rng).unwrap();
         let proof_2 = create_proof(circuit_2, &r_2, &snark_srs, &ek, &mut rng).unwrap();
-        println!(
-            "Time taken to create 2 Groth16 proofs {:?}",
-            start.elapsed()
-        );
 
         let pvk = prepare_verifying_key::<Bls12_381>(&snark_srs.pk.vk);
 
-        let start = Instant::now();
-        ct_1.verify_commitment(ek.clone(), enc_gens.clone())
-            .unwrap();
-        ct_2.verify_commitment(ek.clone(), enc_gens.clone())
-            .unwrap();
-        println!(
-            "Time taken to verify ciphertext commitment {:?}",
-            start.elapsed()
-        );
+        in_span!("verify_ciphertext_commitment", {
+            ct_1.verify_commitment(ek.clone(), enc_gens.clone())
+                .unwrap();
+            ct_2.verify_commitment(ek.clone(), enc_gens.clone())
+                .unwrap();
+        });
 
-        let start = Instant::now();
         verify_proof(&pvk, &proof_1, &ct_1).unwrap();
         verify_proof(&pvk, &proof_2, &ct_2).unwrap();
-        println!(
-            "Time taken to verify 2 Groth16 proofs {:?}",
-            start.elapsed()
-        );
 
         // Decryptor decrypts
         let (decrypted_message_1, nu_1) = ct_1
@@ -962,3 +960,181 @@ fn bbs_plus_verifiably_encrypt_message_from_2_sigs() {
     check(8);
     check(16);
 }
+
+#[test]
+fn verifiably_encrypt_message_with_different_hash_functions() {
+    // The hash function used for generating parameters and the Fiat-Shamir challenge is only a type
+    // parameter so anything implementing `Digest` works
+    fn check<D: Digest>(chunk_bit_size: u8) {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let enc_gens = EncryptionGens::<Bls12_381>::new::<D>(b"test");
+        let chunked_comm_gens =
+            ChunkedCommitmentGens::<<Bls12_381 as Pairing>::G1Affine>::new::<D>(b"test");
+
+        let (snark_srs, sk, ek, dk) =
+            setup_for_groth16(&mut rng, chunk_bit_size, &enc_gens).unwrap();
+        let chunks_count = ek.supported_chunks_count().unwrap();
+
+        let message = Fr::rand(&mut rng);
+        let (ct, r, snark_proof) =
+            Encryption::encrypt_with_proof(&mut rng, &message, &ek, &snark_srs, chunk_bit_size)
+                .unwrap();
+
+        let decomposed_message = decompose(&message, chunk_bit_size)
+            .unwrap()
+            .into_iter()
+            .map(Fr::from)
+            .collect::<Vec<_>>();
+        let blinding = Fr::rand(&mut rng);
+        let comm_chunks = ChunkedCommitment::<<Bls12_381 as Pairing>::G1Affine>::new(
+            &message,
+            &blinding,
+            chunk_bit_size,
+            &chunked_comm_gens,
+        )
+        .unwrap()
+        .0;
+
+        let wit_comm_chunks = chunked_commitment_witness(&decomposed_message, blinding);
+        let wit_comm_ct = ciphertext_commitment_witness(&decomposed_message, r);
+
+        let mut statements = Statements::new();
+        statements.add(PedersenCommitmentStmt::new_statement_from_params(
+            ChunkedCommitment::<<Bls12_381 as Pairing>::G1Affine>::bases(
+                &chunked_comm_gens,
+                chunk_bit_size,
+            )
+            .unwrap(),
+            comm_chunks,
+        ));
+        statements.add(PedersenCommitmentStmt::new_statement_from_params(
+            Ciphertext::commitment_bases(&ek),
+            ct.commitment,
+        ));
+
+        let mut meta_statements = MetaStatements::new();
+        for i in 0..chunks_count as usize {
+            meta_statements.add(MetaStatement::WitnessEquality(EqualWitnesses(
+                vec![(0, i), (1, i)]
+                    .into_iter()
+                    .collect::<BTreeSet<WitnessRef>>(),
+            )));
+        }
+        let proof_spec = ProofSpec::new(statements, meta_statements, vec![], None);
+
+        let mut witnesses = Witnesses::new();
+        witnesses.add(Witness::PedersenCommitment(wit_comm_chunks));
+        witnesses.add(Witness::PedersenCommitment(wit_comm_ct));
+
+        let proof = ProofG1::new::<StdRng, D>(
+            &mut rng,
+            proof_spec.clone(),
+            witnesses,
+            None,
+            Default::default(),
+        )
+        .unwrap()
+        .0;
+        proof
+            .clone()
+            .verify::<StdRng, D>(&mut rng, proof_spec.clone(), None, Default::default())
+            .unwrap();
+        // Verifier using a different hash function than the prover fails
+        assert!(proof
+            .verify::<StdRng, Blake2b512>(&mut rng, proof_spec, None, Default::default())
+            .is_err());
+
+        let pvk = prepare_verifying_key::<Bls12_381>(&snark_srs.pk.vk);
+        ct.verify_commitment_and_proof(&snark_proof, &pvk, ek, enc_gens.clone())
+            .unwrap();
+
+        let (decrypted_message, nu) = ct
+            .decrypt_given_groth16_vk(&sk, dk.clone(), &snark_srs.pk.vk, chunk_bit_size)
+            .unwrap();
+        assert_eq!(decrypted_message, message);
+        ct.verify_decryption_given_groth16_vk(
+            &decrypted_message,
+            &nu,
+            chunk_bit_size,
+            dk,
+            &snark_srs.pk.vk,
+            enc_gens,
+        )
+        .unwrap();
+    }
+
+    check::<sha2::Sha256>(8);
+    check::<blake3::Hasher>(8);
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing_spans_for_expensive_operations() {
+    use std::sync::{Arc, Mutex};
+    use tracing::{
+        span::{Attributes, Id, Record},
+        Event, Metadata, Subscriber,
+    };
+
+    /// Name and field names of a span
+    type SpanInfo = (String, Vec<String>);
+
+    /// Records each created span
+    #[derive(Clone, Default)]
+    struct SpanRecorder(Arc<Mutex<Vec<SpanInfo>>>);
+
+    impl Subscriber for SpanRecorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut spans = self.0.lock().unwrap();
+            spans.push((
+                span.metadata().name().to_string(),
+                span.metadata()
+                    .fields()
+                    .iter()
+                    .map(|f| f.name().to_string())
+                    .collect(),
+            ));
+            Id::from_u64(spans.len() as u64)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let recorder = SpanRecorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let chunk_bit_size = 8;
+        let gens = EncryptionGens::<Bls12_381>::new_using_rng(&mut rng);
+        let (snark_pk, sk, ek, dk) = setup_for_groth16(&mut rng, chunk_bit_size, &gens).unwrap();
+        let msg = Fr::rand(&mut rng);
+        let (ct, _, proof) =
+            Encryption::encrypt_with_proof(&mut rng, &msg, &ek, &snark_pk, chunk_bit_size).unwrap();
+        let pvk = prepare_verifying_key::<Bls12_381>(&snark_pk.pk.vk);
+        verify_proof(&pvk, &proof, &ct).unwrap();
+        let (m, _) = ct
+            .decrypt_given_groth16_vk(&sk, dk, &snark_pk.pk.vk, chunk_bit_size)
+            .unwrap();
+        assert_eq!(m, msg);
+    });
+
+    let spans = recorder.0.lock().unwrap();
+    for name in [
+        "keygen",
+        "create_proof",
+        "verify_proof",
+        "decrypt_given_groth16_vk",
+    ] {
+        let (_, fields) = spans
+            .iter()
+            .find(|(n, _)| n == name)
+            .unwrap_or_else(|| panic!("no span named {}", name));
+        assert!(fields.contains(&"chunks_count".to_string()));
+        assert!(fields.contains(&"chunk_bit_size".to_string()));
+    }
+}
@@ -1,23 +1,115 @@
 use crate::error::SaverError;
-use ark_ff::{BigInteger, PrimeField};
-use ark_std::vec::Vec;
+use ark_ec::{pairing::Pairing, AffineRepr};
+use ark_ff::{
+    field_hashers::{DefaultFieldHasher, HashToField},
+    BigInteger, PrimeField,
+};
+use ark_serialize::{CanonicalSerialize, Read, SerializationError, Write};
+use ark_std::{
+    fmt,
+    io::ErrorKind,
+    rand::{rngs::StdRng, SeedableRng},
+    vec::Vec,
+};
+use blake2::{Blake2b512, Blake2s256};
+use digest::Digest;
+use num_bigint::BigUint;
 
 /// Return number of chunks given the bit size of chunk. Considers the size of the field.
+/// Panics if the count doesn't fit in a `u8`, eg. for 1-bit chunks of a field bigger than 255 bits.
 pub fn chunks_count<F: PrimeField>(chunk_bit_size: u8) -> u8 {
     let scalar_size = F::MODULUS_BIT_SIZE as usize;
     let bit_size = chunk_bit_size as usize;
     // ceil(scalar_size / bit_size)
-    ((scalar_size + bit_size - 1) / bit_size) as u8
+    let count = scalar_size.div_ceil(bit_size);
+    u8::try_from(count).expect("number of chunks should fit in a u8")
 }
 
+/// Type of a chunk of a decomposed message. Holds chunks of up to 16 bits, the biggest supported chunk bit
+/// size, so chunks are never truncated.
 pub type CHUNK_TYPE = u16;
 
+/// Minimum number of chunks handled by each task when the per-chunk work of encryption and key generation runs
+/// in parallel with the `parallel` feature. Such work is a couple of scalar multiplications per chunk, so
+/// splitting a few chunks between threads costs more than it saves. With fewer than twice this many chunks,
+/// like the 16 chunks of 16-bit chunk size, the work is done by a single task, i.e. serially. The output
+/// doesn't depend on it.
+pub const PARALLEL_MIN_CHUNKS: usize = 16;
+
+/// Return the chunk bit size for which the field has `count` chunks, the inverse of `chunks_count`.
+/// Only the chunk bit sizes supported by `decompose` are considered.
+pub fn chunk_bit_size_for_chunks_count<F: PrimeField>(count: usize) -> Option<u8> {
+    [1, 2, 4, 8, 16]
+        .into_iter()
+        .find(|b| chunks_count::<F>(*b) as usize == count)
+}
+
+/// Return the integer in `[0, modulus)` that `elem` represents. For a message that spans many chunks this
+/// is its numeric value, i.e. the chunks from `decompose` read as big-endian digits in base `2^chunk_bit_size`
+pub fn field_element_to_biguint<F: PrimeField>(elem: &F) -> BigUint {
+    elem.into_bigint().into()
+}
+
+/// Return the number of chunks of field `F` if `chunk_bit_size` is a sensible choice for it, i.e. it's
+/// supported by `decompose`, it splits an element of `F` into more than one chunk and the count fits in a
+/// `u8`. Unlike `chunks_count`, this doesn't panic so it can be used to validate a configured chunk bit size.
+pub fn valid_chunks_count<F: PrimeField>(chunk_bit_size: u8) -> Option<u8> {
+    if check_chunk_bit_size(chunk_bit_size).is_err() {
+        return None;
+    }
+    let count = (F::MODULUS_BIT_SIZE as usize).div_ceil(chunk_bit_size as usize);
+    if count < 2 {
+        return None;
+    }
+    u8::try_from(count).ok()
+}
+
+/// Whether `chunk_bit_size` can be used with field `F`. See `valid_chunks_count`
+pub fn is_valid_chunk_bit_size_for_field<F: PrimeField>(chunk_bit_size: u8) -> bool {
+    valid_chunks_count::<F>(chunk_bit_size).is_some()
+}
+
+/// Check that `chunk_bit_size` is one of the sizes supported by `decompose`, i.e. 1, 2, 4, 8 or 16
+pub fn check_chunk_bit_size(chunk_bit_size: u8) -> crate::Result<()> {
+    if matches!(chunk_bit_size, 1 | 2 | 4 | 8 | 16) {
+        Ok(())
+    } else {
+        Err(SaverError::UnexpectedBase(chunk_bit_size))
+    }
+}
+
+/// Check that each chunk fits in `chunk_bit_size` bits. `CHUNK_TYPE` can hold the biggest supported chunk
+/// but for smaller chunk bit sizes, a chunk not created by `decompose` can be out of range.
+pub fn check_chunks_range(chunks: &[CHUNK_TYPE], chunk_bit_size: u8) -> crate::Result<()> {
+    if chunk_bit_size >= CHUNK_TYPE::BITS as u8 {
+        return Ok(());
+    }
+    let max = (1 << chunk_bit_size) - 1;
+    match chunks.iter().position(|c| *c > max) {
+        Some(index) => Err(SaverError::ChunkOutOfRange {
+            index,
+            value: chunks[index] as u64,
+            max: max as u64,
+        }),
+        None => Ok(()),
+    }
+}
+
 /// Given an element `F`, break it into chunks where each chunk is of `chunk_bit_size` bits. This is
 /// essentially an n-ary representation where n is `chunk_bit_size`. Returns big-endian representation.
+/// Supported chunk bit sizes are 1, 2, 4, 8 and 16.
 pub fn decompose<F: PrimeField>(message: &F, chunk_bit_size: u8) -> crate::Result<Vec<CHUNK_TYPE>> {
     let bytes = message.into_bigint().to_bytes_be();
     let mut decomposition = Vec::<CHUNK_TYPE>::new();
     match chunk_bit_size {
+        1 | 2 => {
+            let mask = (1 << chunk_bit_size) - 1;
+            for b in bytes {
+                for i in (0..8 / chunk_bit_size).rev() {
+                    decomposition.push(((b >> (i * chunk_bit_size)) & mask) as CHUNK_TYPE);
+                }
+            }
+        }
         4 => {
             for b in bytes {
                 decomposition.push((b >> 4) as CHUNK_TYPE);
@@ -30,36 +122,67 @@ pub fn decompose<F: PrimeField>(message: &F, chunk_bit_size: u8) -> crate::Resul
             }
         }
         16 => {
-            // Process 2 bytes at a time
-            for bytes_2 in bytes.chunks(2) {
-                let mut b = (bytes_2[0] as CHUNK_TYPE) << (8 as CHUNK_TYPE);
-                if bytes_2.len() > 1 {
-                    b += bytes_2[1] as CHUNK_TYPE;
-                }
-                decomposition.push(b);
+            // Prefix with a 0 byte if needed so that every chunk is made of 2 bytes. Otherwise, the last
+            // byte would become the high byte of the last chunk
+            let padding = bytes.len() % 2;
+            let mut bytes = ark_std::iter::repeat_n(0, padding).chain(bytes);
+            while let (Some(hi), Some(lo)) = (bytes.next(), bytes.next()) {
+                decomposition.push(((hi as CHUNK_TYPE) << 8) | lo as CHUNK_TYPE);
             }
         }
         b => return Err(SaverError::UnexpectedBase(b)),
     }
+    // The byte representation can have more bits than the modulus and these leading chunks are
+    // always 0 so remove them to have as many chunks as `chunks_count`
+    let extra = decomposition.len() - chunks_count::<F>(chunk_bit_size) as usize;
+    decomposition.drain(0..extra);
     Ok(decomposition)
 }
 
 /// Recreate a field element back from output of `decompose`. Assumes big-endian representation in `decomposed`
 pub fn compose<F: PrimeField>(decomposed: &[CHUNK_TYPE], chunk_bit_size: u8) -> crate::Result<F> {
     match chunk_bit_size {
-        4 => {
-            if (decomposed.len() % 2) == 1 {
-                return Err(SaverError::InvalidDecomposition);
+        1 | 2 => {
+            let per_byte = (8 / chunk_bit_size) as usize;
+            // Prefix with 0 chunks so that the chunks fill complete bytes
+            let padding = (per_byte - decomposed.len() % per_byte) % per_byte;
+            let mut bytes = Vec::<u8>::with_capacity((decomposed.len() + padding) / per_byte);
+            let mut cur = 0u8;
+            for (i, c) in ark_std::iter::repeat_n(&0, padding)
+                .chain(decomposed.iter())
+                .enumerate()
+            {
+                if *c >> chunk_bit_size != 0 {
+                    return Err(SaverError::InvalidDecomposition);
+                }
+                cur = (cur << chunk_bit_size) | (*c as u8);
+                if (i + 1) % per_byte == 0 {
+                    bytes.push(cur);
+                    cur = 0;
+                }
             }
-            let mut bytes = Vec::<u8>::with_capacity(decomposed.len() / 2);
-            for nibbles in decomposed.chunks(2) {
-                bytes.push(((nibbles[0] << 4) + nibbles[1]) as u8);
+            Ok(F::from_be_bytes_mod_order(&bytes))
+        }
+        4 => {
+            // Prefix with a 0 chunk if needed so that the chunks fill complete bytes
+            let padding = decomposed.len() % 2;
+            let mut bytes = Vec::<u8>::with_capacity((decomposed.len() + padding) / 2);
+            let mut nibbles = ark_std::iter::repeat_n(&0, padding).chain(decomposed.iter());
+            while let (Some(hi), Some(lo)) = (nibbles.next(), nibbles.next()) {
+                if (hi | lo) >> 4 != 0 {
+                    return Err(SaverError::InvalidDecomposition);
+                }
+                bytes.push(((hi << 4) | lo) as u8);
             }
             Ok(F::from_be_bytes_mod_order(&bytes))
         }
-        8 => Ok(F::from_be_bytes_mod_order(
-            &decomposed.iter().map(|b| *b as u8).collect::<Vec<u8>>(),
-        )),
+        8 => {
+            let bytes = decomposed
+                .iter()
+                .map(|c| u8::try_from(*c).map_err(|_| SaverError::InvalidDecomposition))
+                .collect::<crate::Result<Vec<u8>>>()?;
+            Ok(F::from_be_bytes_mod_order(&bytes))
+        }
         16 => {
             let mut bytes = Vec::<u8>::with_capacity(decomposed.len() * 2);
             for byte_2 in decomposed {
@@ -72,6 +195,268 @@ pub fn compose<F: PrimeField>(decomposed: &[CHUNK_TYPE], chunk_bit_size: u8) ->
     }
 }
 
+/// Witness for the Pedersen commitment to the message chunks whose commitment key is `ChunkedCommitment::commitment_key`,
+/// i.e. the decomposed message (as returned by `decompose`, converted to field elements) followed by the `blinding`
+pub fn chunked_commitment_witness<F: PrimeField>(decomposed: &[F], blinding: F) -> Vec<F> {
+    let mut witness = Vec::with_capacity(decomposed.len() + 1);
+    witness.extend_from_slice(decomposed);
+    witness.push(blinding);
+    witness
+}
+
+/// Witness for the Pedersen commitment in the ciphertext whose commitment key is `EncryptionKey::commitment_key`,
+/// i.e. the decomposed message (as returned by `decompose`, converted to field elements) followed by the
+/// randomness `r` used during encryption
+pub fn ciphertext_commitment_witness<F: PrimeField>(decomposed: &[F], r: F) -> Vec<F> {
+    let mut witness = Vec::with_capacity(decomposed.len() + 1);
+    witness.extend_from_slice(decomposed);
+    witness.push(r);
+    witness
+}
+
+/// Number of bytes that can be encoded in a single field element such that the encoding is always less than the modulus
+pub fn bytes_per_field_element<F: PrimeField>() -> usize {
+    (F::MODULUS_BIT_SIZE as usize - 1) / 8
+}
+
+/// Encode arbitrary bytes as field elements. The bytes are prefixed with their length as a 4-byte big-endian
+/// integer so that trailing zeros aren't ambiguous, and then split into pieces of `bytes_per_field_element`
+/// bytes, each converted to a field element (big-endian). The last piece is padded with zeros.
+pub fn bytes_to_field_elements<F: PrimeField>(data: &[u8]) -> crate::Result<Vec<F>> {
+    let len = u32::try_from(data.len()).map_err(|_| SaverError::InvalidByteEncoding)?;
+    let size = bytes_per_field_element::<F>();
+    let mut bytes = Vec::with_capacity(data.len() + 4);
+    bytes.extend_from_slice(&len.to_be_bytes());
+    bytes.extend_from_slice(data);
+    Ok(bytes
+        .chunks(size)
+        .map(|b| {
+            let mut piece = b.to_vec();
+            piece.resize(size, 0);
+            F::from_be_bytes_mod_order(&piece)
+        })
+        .collect())
+}
+
+/// Recover the bytes encoded with `bytes_to_field_elements`
+pub fn field_elements_to_bytes<F: PrimeField>(elems: &[F]) -> crate::Result<Vec<u8>> {
+    let size = bytes_per_field_element::<F>();
+    let mut bytes = Vec::with_capacity(elems.len() * size);
+    for e in elems {
+        let b = e.into_bigint().to_bytes_be();
+        let (high, low) = b.split_at(b.len() - size);
+        // An element created by `bytes_to_field_elements` does not need more than `size` bytes
+        if high.iter().any(|b| *b != 0) {
+            return Err(SaverError::InvalidByteEncoding);
+        }
+        bytes.extend_from_slice(low);
+    }
+    if bytes.len() < 4 {
+        return Err(SaverError::InvalidByteEncoding);
+    }
+    let len = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    if len > bytes.len() - 4 || bytes[4 + len..].iter().any(|b| *b != 0) {
+        return Err(SaverError::InvalidByteEncoding);
+    }
+    Ok(bytes[4..4 + len].to_vec())
+}
+
+/// Returns 1 if both byte slices are equal and 0 otherwise. The running time depends only on the length
+/// of the slices, not their contents.
+pub fn ct_eq_bytes(a: &[u8], b: &[u8]) -> u8 {
+    if a.len() != b.len() {
+        return 0;
+    }
+    let mut diff = 0u8;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff |= x ^ y;
+    }
+    // `diff - 1` underflows, setting the high bits, only when `diff` is 0
+    (((diff as u16).wrapping_sub(1) >> 8) & 1) as u8
+}
+
+/// RNG for the item at position `index` of a batch, derived from the batch's `seed`. Each item gets an
+/// independent RNG so items can be processed in any order, or in parallel, with the same result.
+pub fn rng_for_batch_item(seed: &[u8; 32], index: usize) -> StdRng {
+    let item_seed = Blake2s256::new()
+        .chain_update(b"SAVER batch item")
+        .chain_update(seed)
+        .chain_update((index as u64).to_le_bytes())
+        .finalize();
+    StdRng::from_seed(item_seed.into())
+}
+
+/// Identifier of the pairing curve `E`, taken from a hash of its G1 and G2 generators. As it is a single
+/// byte, 2 curves can have the same identifier, so this catches accidental mixups and not malicious input.
+pub fn curve_id<E: Pairing>() -> u8 {
+    let mut bytes = Vec::new();
+    E::G1Affine::generator()
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    E::G2Affine::generator()
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    Blake2s256::new()
+        .chain_update(b"SAVER curve id")
+        .chain_update(&bytes)
+        .finalize()[0]
+}
+
+/// Short identifier of an object for logs and UIs: the first 8 bytes of the Blake2b-512 hash of its
+/// compressed serialization. Not collision resistant so it must not be used to check that 2 objects are the
+/// same.
+pub fn fingerprint<T: CanonicalSerialize>(obj: &T) -> [u8; 8] {
+    let mut bytes = Vec::new();
+    obj.serialize_compressed(&mut bytes).unwrap();
+    let mut f = [0u8; 8];
+    f.copy_from_slice(&Blake2b512::digest(&bytes)[..8]);
+    f
+}
+
+/// Write the `fingerprint` of `obj` as 16 lowercase hex characters
+pub(crate) fn write_fingerprint<T: CanonicalSerialize>(
+    obj: &T,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    for b in fingerprint(obj) {
+        write!(f, "{:02x}", b)?;
+    }
+    Ok(())
+}
+
+/// Maximum number of bytes of a `u64` encoded by `write_varint`
+const MAX_VARINT_LEN: usize = 10;
+
+/// Write `n` as an unsigned LEB128 varint, i.e. 7 bits per byte starting with the least significant, with
+/// the high bit set on all bytes but the last
+pub fn write_varint<W: Write>(mut n: u64, mut writer: W) -> Result<(), SerializationError> {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            writer.write_all(&[byte])?;
+            return Ok(());
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+/// Read a varint written by `write_varint`. Returns `None` if the reader has ended before the first byte
+/// so that the end of a stream of varint prefixed items can be told apart from a truncated item.
+pub fn read_varint<R: Read>(mut reader: R) -> Result<Option<u64>, SerializationError> {
+    let mut n = 0u64;
+    for i in 0..MAX_VARINT_LEN {
+        let mut byte = [0u8; 1];
+        if i == 0 {
+            loop {
+                match reader.read(&mut byte) {
+                    Ok(0) => return Ok(None),
+                    Ok(_) => break,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        } else {
+            reader.read_exact(&mut byte)?;
+        }
+        let bits = (byte[0] & 0x7f) as u64;
+        // The 10th byte can only hold the most significant bit of a `u64`
+        if i == MAX_VARINT_LEN - 1 && bits > 1 {
+            return Err(SerializationError::InvalidData);
+        }
+        n |= bits << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            // Reject encodings with trailing zero bytes so that each number has a single encoding
+            if i > 0 && bits == 0 {
+                return Err(SerializationError::InvalidData);
+            }
+            return Ok(Some(n));
+        }
+    }
+    Err(SerializationError::InvalidData)
+}
+
+/// Map arbitrary bytes, like a user id, to the message to be encrypted. Uses the hash-to-field of the
+/// IETF hash-to-curve draft (`expand_message_xmd` with Blake2b-512) with a SAVER specific domain separation
+/// tag so that everyone encrypting identifiers gets the same message for the same bytes. The message can't be
+/// recovered from the decryption, only compared with the hash of a known identifier.
+pub fn message_from_bytes<F: PrimeField>(data: &[u8]) -> F {
+    let hasher =
+        <DefaultFieldHasher<Blake2b512> as HashToField<F>>::new(b"SAVER-MESSAGE-FROM-BYTES");
+    hasher.hash_to_field(data, 1).pop().unwrap()
+}
+
+/// Create the message for a small integer like an age or a code, checking that it can be decrypted from
+/// `chunks_count` chunks of `chunk_bit_size` bits, i.e. that it's less than `2^{chunk_bit_size * chunks_count}`.
+/// `chunks_count` is the number of chunks of the field, `chunks_count::<F>(chunk_bit_size)`, when decrypting
+/// all the chunks, or fewer when decrypting with `Ciphertext::decrypt_last_chunks`, and
+/// `SaverError::TooManyChunks` is returned if it's more.
+pub fn message_from_u64<F: PrimeField>(
+    v: u64,
+    chunk_bit_size: u8,
+    chunks_count: u8,
+) -> crate::Result<F> {
+    check_chunk_bit_size(chunk_bit_size)?;
+    let field_chunks = self::chunks_count::<F>(chunk_bit_size);
+    if chunks_count > field_chunks {
+        return Err(SaverError::TooManyChunks {
+            chunk_bit_size,
+            chunks_count,
+            max: field_chunks,
+        });
+    }
+    let bits = chunk_bit_size as u32 * chunks_count as u32;
+    if bits < u64::BITS && v >> bits != 0 {
+        return Err(SaverError::MessageOutOfRange {
+            value: v,
+            max: (1 << bits) - 1,
+        });
+    }
+    Ok(F::from(v))
+}
+
+/// Encode a signed integer, like a balance delta, as the message `v + offset` so that negative values are
+/// mapped to a non-negative range before being decomposed into chunks. Returns an error if `v + offset` is
+/// negative, so `offset` must be at least the magnitude of the smallest value to encrypt. Decode the decrypted
+/// message with `decode_signed` and the same offset.
+///
+/// The encoded message is at most `offset + v_max` and the chunks must be able to hold it, like with
+/// `message_from_u64`: for values in `[-2^{k-1}, 2^{k-1})` with offset `2^{k-1}`, the message is less than
+/// `2^k` so it is decrypted from the last `ceil(k / chunk_bit_size)` chunks. Since the offset is part of the
+/// message, the sum of `n` ciphertexts (`Ciphertext::linear_combination` or `Sum`) decrypts to the sum of
+/// the values plus `n * offset`, and so must be decoded with `n * offset`.
+pub fn encode_signed<F: PrimeField>(v: i64, offset: u64) -> crate::Result<F> {
+    let encoded = v as i128 + offset as i128;
+    if encoded < 0 {
+        return Err(SaverError::SignedValueBelowOffset { value: v, offset });
+    }
+    Ok(F::from(encoded as u128))
+}
+
+/// Decode a message created by `encode_signed` with the same `offset`, i.e. return `message - offset`. Returns
+/// an error if the result doesn't fit in an `i64`, eg. if the message was encoded with a different offset.
+pub fn decode_signed<F: PrimeField>(message: &F, offset: u64) -> crate::Result<i64> {
+    let encoded = u128::try_from(&field_element_to_biguint(message))
+        .map_err(|_| SaverError::InvalidSignedEncoding)?;
+    i128::try_from(encoded)
+        .ok()
+        .and_then(|e| i64::try_from(e - offset as i128).ok())
+        .ok_or(SaverError::InvalidSignedEncoding)
+}
+
+/// Derive encryption randomness from a secret shared by the encryptor and another party, so that both can
+/// compute the same ciphertext. Uses HKDF-SHA256 with `context` as the info, and the 64 bytes of output key
+/// material are reduced to a field element so that the bias is negligible. Use a different `context` for
+/// each encryption as the same randomness must never be used twice.
+#[cfg(feature = "derived-randomness")]
+pub fn derive_randomness<F: PrimeField>(shared_secret: &[u8], context: &[u8]) -> F {
+    let hk = hkdf::Hkdf::<sha2::Sha256>::new(Some(b"SAVER encryption randomness"), shared_secret);
+    let mut okm = [0u8; 64];
+    // 64 bytes is well below the maximum of 255 * 32 bytes for SHA-256
+    hk.expand(context, &mut okm).unwrap();
+    F::from_le_bytes_mod_order(&okm)
+}
+
 #[cfg(test)]
 #[macro_export]
 macro_rules! test_serialization {
@@ -103,13 +488,38 @@ pub(crate) mod tests {
     use super::*;
     use ark_bls12_381::Bls12_381;
     use ark_ec::pairing::Pairing;
+    use ark_ff::Field;
     use ark_std::{
-        rand::{prelude::StdRng, SeedableRng},
+        rand::{prelude::StdRng, RngCore, SeedableRng},
         UniformRand,
     };
+    use proptest::prelude::*;
 
     type Fr = <Bls12_381 as Pairing>::ScalarField;
 
+    #[test]
+    fn varint() {
+        for n in [0, 1, 127, 128, 300, 16383, 16384, u32::MAX as u64, u64::MAX] {
+            let mut bytes = vec![];
+            write_varint(n, &mut bytes).unwrap();
+            assert_eq!(
+                bytes.len(),
+                core::cmp::max(1, (64 - n.leading_zeros() as usize).div_ceil(7))
+            );
+            assert_eq!(read_varint(&bytes[..]).unwrap(), Some(n));
+            // Truncated
+            if bytes.len() > 1 {
+                assert!(read_varint(&bytes[..bytes.len() - 1]).is_err());
+            }
+        }
+        assert_eq!(read_varint(&[][..]).unwrap(), None);
+        // Trailing zero byte and overflow
+        assert!(read_varint(&[0x80, 0x00][..]).is_err());
+        assert!(
+            read_varint(&[0xff; 9].iter().chain(&[0x02]).copied().collect::<Vec<_>>()[..]).is_err()
+        );
+    }
+
     #[test]
     fn compose_decompose() {
         let n1 = Fr::from(53u64);
@@ -203,10 +613,282 @@ pub(crate) mod tests {
         let mut rng = StdRng::seed_from_u64(0u64);
         for _ in 0..1000 {
             let n = Fr::rand(&mut rng);
-            for b in [4, 8, 16] {
+            for b in [1, 2, 4, 8, 16] {
                 let decomposed = decompose(&n, b).unwrap();
                 assert_eq!(n, compose(&decomposed, b).unwrap());
             }
         }
+
+        // 1 and 2 bit chunks
+        let n = Fr::from(53u64);
+        let decomposed = decompose(&n, 1).unwrap();
+        assert_eq!(decomposed.len(), 255);
+        assert!(decomposed[..249].iter().all(|c| *c == 0));
+        assert_eq!(decomposed[249..], vec![1, 1, 0, 1, 0, 1][..]);
+        let decomposed = decompose(&n, 2).unwrap();
+        assert_eq!(decomposed.len(), 128);
+        assert!(decomposed[..125].iter().all(|c| *c == 0));
+        assert_eq!(decomposed[125..], vec![3, 1, 1][..]);
+        assert!(compose::<Fr>(&[0, 1, 4], 2).is_err());
+        assert!(decompose(&n, 3).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn compose_inverts_decompose(
+            bytes in any::<[u8; 32]>(),
+            chunk_bit_size in prop::sample::select(vec![1u8, 2, 4, 8, 16])
+        ) {
+            let n = Fr::from_le_bytes_mod_order(&bytes);
+            let decomposed = decompose(&n, chunk_bit_size).unwrap();
+            prop_assert_eq!(decomposed.len(), chunks_count::<Fr>(chunk_bit_size) as usize);
+            prop_assert!(check_chunks_range(&decomposed, chunk_bit_size).is_ok());
+            prop_assert_eq!(compose::<Fr>(&decomposed, chunk_bit_size).unwrap(), n);
+        }
+
+        #[test]
+        fn compose_inverts_decompose_for_small_values(
+            n in any::<u64>(),
+            chunk_bit_size in prop::sample::select(vec![1u8, 2, 4, 8, 16])
+        ) {
+            for n in [Fr::from(n), -Fr::from(n)] {
+                let decomposed = decompose(&n, chunk_bit_size).unwrap();
+                prop_assert!(check_chunks_range(&decomposed, chunk_bit_size).is_ok());
+                prop_assert_eq!(compose::<Fr>(&decomposed, chunk_bit_size).unwrap(), n);
+            }
+        }
+    }
+
+    /// Prime field of modulus `2^31 - 1` whose byte representation has many more bits than the modulus
+    pub struct SmallFieldConfig;
+    type SmallField = ark_ff::Fp64<ark_ff::MontBackend<SmallFieldConfig, 1>>;
+
+    impl ark_ff::MontConfig<1> for SmallFieldConfig {
+        const MODULUS: ark_ff::BigInt<1> = ark_ff::BigInt([2147483647]);
+        const GENERATOR: SmallField = ark_ff::MontFp!("7");
+        // Two-adicity is 1 so the root of unity is -1
+        const TWO_ADIC_ROOT_OF_UNITY: SmallField = ark_ff::MontFp!("2147483646");
+    }
+
+    #[test]
+    fn decompose_16_bit_chunks_without_truncation() {
+        // The largest field element has chunks bigger than a byte
+        let m = -Fr::from(1u64);
+        let decomposed = decompose(&m, 16).unwrap();
+        assert_eq!(decomposed.len(), chunks_count::<Fr>(16) as usize);
+        let bytes = m.into_bigint().to_bytes_be();
+        for (c, b) in decomposed.iter().zip(bytes.chunks(2)) {
+            assert_eq!(*c, u16::from_be_bytes([b[0], b[1]]));
+        }
+        assert!(decomposed.iter().any(|c| *c > u8::MAX as u16));
+        assert_eq!(compose::<Fr>(&decomposed, 16).unwrap(), m);
+
+        // Chunks that don't fit in the chunk bit size are rejected rather than truncated
+        let mut decomposed = decompose(&Fr::from(53u64), 8).unwrap();
+        decomposed[0] = 256;
+        assert!(compose::<Fr>(&decomposed, 8).is_err());
+        assert!(matches!(
+            decompose(&m, 32),
+            Err(SaverError::UnexpectedBase(32))
+        ));
+    }
+
+    #[test]
+    fn chunk_bit_size_validity_for_field() {
+        for chunk_bit_size in [1, 2, 4, 8, 16] {
+            assert!(is_valid_chunk_bit_size_for_field::<Fr>(chunk_bit_size));
+            assert_eq!(
+                valid_chunks_count::<Fr>(chunk_bit_size),
+                Some(chunks_count::<Fr>(chunk_bit_size))
+            );
+            assert!(is_valid_chunk_bit_size_for_field::<SmallField>(
+                chunk_bit_size
+            ));
+        }
+        assert_eq!(valid_chunks_count::<Fr>(8), Some(32));
+
+        // Not supported by `decompose`, including sizes as big as the field which would give a single chunk
+        for chunk_bit_size in [0, 3, 32, 255, u8::MAX] {
+            assert!(!is_valid_chunk_bit_size_for_field::<Fr>(chunk_bit_size));
+            assert_eq!(valid_chunks_count::<Fr>(chunk_bit_size), None);
+        }
+    }
+
+    #[test]
+    fn chunks_count_for_field_sizes() {
+        // BLS12-381 scalar field is of 255 bits
+        for (chunk_bit_size, count) in [(1, 255), (2, 128), (4, 64), (8, 32), (16, 16)] {
+            assert_eq!(chunks_count::<Fr>(chunk_bit_size), count);
+            assert_eq!(
+                chunk_bit_size_for_chunks_count::<Fr>(count as usize),
+                Some(chunk_bit_size)
+            );
+        }
+
+        // 31 bits in 8 bytes
+        for (chunk_bit_size, count) in [(1, 31), (2, 16), (4, 8), (8, 4), (16, 2)] {
+            assert_eq!(chunks_count::<SmallField>(chunk_bit_size), count);
+            for n in [
+                SmallField::from(0u64),
+                SmallField::from(1u64),
+                SmallField::from(1234567u64),
+                -SmallField::from(1u64),
+            ] {
+                let decomposed = decompose(&n, chunk_bit_size).unwrap();
+                assert_eq!(decomposed.len(), count as usize);
+                assert!(check_chunks_range(&decomposed, chunk_bit_size).is_ok());
+                assert_eq!(
+                    compose::<SmallField>(&decomposed, chunk_bit_size).unwrap(),
+                    n
+                );
+            }
+        }
+        assert_eq!(
+            decompose(&SmallField::from(0x12345u64), 4).unwrap(),
+            vec![0, 0, 0, 1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            decompose(&SmallField::from(0x12345u64), 16).unwrap(),
+            vec![0x1, 0x2345]
+        );
+    }
+
+    #[test]
+    fn message_from_small_integer() {
+        // With all the chunks, any u64 can be decrypted
+        for b in [1, 2, 4, 8, 16] {
+            let n = chunks_count::<Fr>(b);
+            assert_eq!(
+                message_from_u64::<Fr>(u64::MAX, b, n).unwrap(),
+                Fr::from(u64::MAX)
+            );
+        }
+        // 2 chunks of 8 bits
+        assert_eq!(
+            message_from_u64::<Fr>(65535, 8, 2).unwrap(),
+            Fr::from(65535u64)
+        );
+        assert!(matches!(
+            message_from_u64::<Fr>(65536, 8, 2),
+            Err(SaverError::MessageOutOfRange {
+                value: 65536,
+                max: 65535
+            })
+        ));
+        assert!(message_from_u64::<Fr>(16, 4, 1).is_err());
+        assert_eq!(message_from_u64::<Fr>(0, 4, 0).unwrap(), Fr::from(0u64));
+        assert!(matches!(
+            message_from_u64::<Fr>(1, 8, 33),
+            Err(SaverError::TooManyChunks {
+                chunk_bit_size: 8,
+                chunks_count: 33,
+                max: 32
+            })
+        ));
+        assert!(matches!(
+            message_from_u64::<Fr>(1, 3, 2),
+            Err(SaverError::UnexpectedBase(3))
+        ));
+    }
+
+    #[test]
+    fn chunks_range() {
+        assert!(check_chunks_range(&[0, 1, 1, 0], 1).is_ok());
+        assert!(check_chunks_range(&[3, 15, 0], 4).is_ok());
+        assert!(check_chunks_range(&[255, 0], 8).is_ok());
+        assert!(check_chunks_range(&[u16::MAX, 0], 16).is_ok());
+        assert!(matches!(
+            check_chunks_range(&[0, 1, 2], 1),
+            Err(SaverError::ChunkOutOfRange {
+                index: 2,
+                value: 2,
+                max: 1
+            })
+        ));
+        assert!(matches!(
+            check_chunks_range(&[15, 16], 4),
+            Err(SaverError::ChunkOutOfRange {
+                index: 1,
+                value: 16,
+                max: 15
+            })
+        ));
+        assert!(matches!(
+            check_chunks_range(&[256], 8),
+            Err(SaverError::ChunkOutOfRange {
+                index: 0,
+                value: 256,
+                max: 255
+            })
+        ));
+    }
+
+    #[test]
+    fn constant_time_byte_equality() {
+        assert_eq!(ct_eq_bytes(&[], &[]), 1);
+        assert_eq!(ct_eq_bytes(&[1, 2, 3], &[1, 2, 3]), 1);
+        assert_eq!(ct_eq_bytes(&[1, 2, 3], &[1, 2, 4]), 0);
+        assert_eq!(ct_eq_bytes(&[0, 2, 3], &[128, 2, 3]), 0);
+        assert_eq!(ct_eq_bytes(&[1, 2, 3], &[1, 2]), 0);
+    }
+
+    #[test]
+    fn commitment_witnesses() {
+        let mut rng = StdRng::seed_from_u64(0u64);
+        let m = Fr::rand(&mut rng);
+        let blinding = Fr::rand(&mut rng);
+        let r = Fr::rand(&mut rng);
+        let decomposed = decompose(&m, 8)
+            .unwrap()
+            .into_iter()
+            .map(Fr::from)
+            .collect::<Vec<_>>();
+
+        let mut expected_chunks = decomposed.clone();
+        expected_chunks.push(blinding);
+        assert_eq!(
+            chunked_commitment_witness(&decomposed, blinding),
+            expected_chunks
+        );
+
+        let mut expected_ct = decomposed.clone();
+        expected_ct.push(r);
+        assert_eq!(ciphertext_commitment_witness(&decomposed, r), expected_ct);
+    }
+
+    #[test]
+    fn message_from_bytes_is_deterministic() {
+        let m = message_from_bytes::<Fr>(b"user-1234");
+        assert_eq!(message_from_bytes::<Fr>(b"user-1234"), m);
+        assert_ne!(message_from_bytes::<Fr>(b"user-1235"), m);
+        assert_ne!(message_from_bytes::<Fr>(b""), m);
+        // Not simply the bytes interpreted as a number
+        assert_ne!(m, Fr::from_le_bytes_mod_order(b"user-1234"));
+    }
+
+    #[test]
+    fn bytes_to_field_elements_and_back() {
+        assert_eq!(bytes_per_field_element::<Fr>(), 31);
+        let mut rng = StdRng::seed_from_u64(0u64);
+        for len in [0, 1, 26, 27, 31, 40, 58, 59, 100] {
+            let mut data = vec![0u8; len];
+            rng.fill_bytes(&mut data);
+            let elems = bytes_to_field_elements::<Fr>(&data).unwrap();
+            assert_eq!(elems.len(), (len + 4).div_ceil(31));
+            assert_eq!(field_elements_to_bytes(&elems).unwrap(), data);
+        }
+
+        // Trailing zeros are preserved
+        let data = vec![1, 2, 0, 0, 0];
+        let elems = bytes_to_field_elements::<Fr>(&data).unwrap();
+        assert_eq!(field_elements_to_bytes(&elems).unwrap(), data);
+
+        // Length prefix larger than the encoded bytes
+        let elems = bytes_to_field_elements::<Fr>(&[1, 2, 3]).unwrap();
+        let mut tampered = elems.clone();
+        tampered[0] += Fr::from(1u64) * Fr::from(2u64).pow([8 * 30]);
+        assert!(field_elements_to_bytes(&tampered).is_err());
+        assert!(field_elements_to_bytes::<Fr>(&[]).is_err());
+        assert!(field_elements_to_bytes(&[-Fr::from(1u64)]).is_err());
     }
 }